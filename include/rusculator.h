@@ -0,0 +1,42 @@
+/* The C interface of rusculator, built with
+   `cargo rustc --release --lib --features ffi --crate-type cdylib`. */
+
+#ifndef RUSCULATOR_H
+#define RUSCULATOR_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RUSC_OK 0
+/* A formula was well formed but could not be evaluated, or did not evaluate to a number. */
+#define RUSC_EVALUATION_ERROR 1
+/* A pointer was null or a string was not UTF-8. */
+#define RUSC_INVALID_ARGUMENT 2
+#define RUSC_PARSE_ERROR 3
+
+typedef struct RuscContext RuscContext;
+
+/* A new context, to be freed with rusc_ctx_free. */
+RuscContext *rusc_ctx_new(void);
+void rusc_ctx_free(RuscContext *ctx);
+
+int rusc_ctx_set_var(RuscContext *ctx, const char *name, double value);
+
+/* Evaluates the formula, writing its value to result if it is a number. */
+int rusc_eval(RuscContext *ctx, const char *formula, double *result);
+
+/* The value of the formula of any type written out, or NULL on errors, to be freed with
+ * rusc_string_free. */
+char *rusc_eval_string(RuscContext *ctx, const char *formula);
+void rusc_string_free(char *string);
+
+/* The message of the last error of the context, or NULL if its last call succeeded. It
+ * stays valid until the next call taking the context. */
+const char *rusc_last_error(const RuscContext *ctx);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
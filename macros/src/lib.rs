@@ -0,0 +1,165 @@
+//! `calc!`, evaluating formulas of rusculator when compiling.
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+use rusculator::{Context, Error, Value};
+
+/// Evaluates a formula written as a string literal when compiling, with the builtin
+/// functions, constants and units, into an `f64` literal. Formulas which fail, or do not
+/// evaluate to a finite number, are compile errors marking where they fail.
+///
+/// ```
+/// use rusculator_macros::calc;
+///
+/// const EQUATOR: f64 = calc!("2 * pi * 6371");
+/// assert!((EQUATOR - 40030.17).abs() < 0.01);
+/// assert_eq!(calc!("-(2^10)"), -1024.0);
+/// ```
+///
+/// ```compile_fail
+/// // Unknown variable 'y', marked under the formula.
+/// let x = rusculator_macros::calc!("2 * (y + 1)");
+/// ```
+#[proc_macro]
+pub fn calc(input: TokenStream) -> TokenStream {
+    let mut tokens = input.into_iter();
+    let literal = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(literal)), None) => literal,
+        (Some(token), _) => return error("calc! expects a string literal", token.span()),
+        (None, _) => return error("calc! expects a string literal", Span::call_site()),
+    };
+    let Some(source) = unquote(&literal.to_string()) else {
+        return error("calc! expects a string literal", literal.span());
+    };
+    match Context::new().eval(&source) {
+        Ok(Value::Number(value)) if value.is_finite() => number(value, literal.span()),
+        Ok(Value::Number(value)) => error(
+            &format!("calc! expects a finite number, found {}", value),
+            literal.span(),
+        ),
+        Ok(value) => error(
+            &format!("calc! expects a number, found {}", value.type_name()),
+            literal.span(),
+        ),
+        Err(error) => self::error(&report(&source, &error), literal.span()),
+    }
+}
+
+/// The literal of `value`, parenthesized with a minus sign if negative.
+fn number(value: f64, span: Span) -> TokenStream {
+    let mut literal = Literal::f64_suffixed(value.abs());
+    literal.set_span(span);
+    if value.is_sign_positive() {
+        return TokenTree::Literal(literal).into();
+    }
+    let mut minus = Punct::new('-', Spacing::Alone);
+    minus.set_span(span);
+    let negated = [TokenTree::Punct(minus), TokenTree::Literal(literal)];
+    let mut group = Group::new(Delimiter::Parenthesis, negated.into_iter().collect());
+    group.set_span(span);
+    TokenTree::Group(group).into()
+}
+
+/// `compile_error!` with `message`, pointing at `span`.
+fn error(message: &str, span: Span) -> TokenStream {
+    let mut literal = Literal::string(message);
+    literal.set_span(span);
+    let mut group = Group::new(Delimiter::Parenthesis, TokenTree::Literal(literal).into());
+    group.set_span(span);
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(span);
+    [
+        TokenTree::Ident(Ident::new("compile_error", span)),
+        TokenTree::Punct(bang),
+        TokenTree::Group(group),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// The message of `error` with the formula and a caret under where it fails, as the
+/// spans of string literals cannot be split outside of nightly compilers.
+fn report(source: &str, error: &Error) -> String {
+    let span = match error {
+        Error::Parser(error) => Some(error.span),
+        Error::Evaluator(error) => error.span,
+    };
+    let Some(span) = span else {
+        return error.to_string();
+    };
+    let start = source[..span.start.min(source.len())].chars().count();
+    let width = source[span.start.min(source.len())..span.end.min(source.len())]
+        .chars()
+        .count()
+        .max(1);
+    format!(
+        "{}\n  {}\n  {}{}",
+        error,
+        source,
+        " ".repeat(start),
+        "^".repeat(width)
+    )
+}
+
+/// The contents of a string literal as written in the source, `"..."` with escapes or
+/// raw as `r#"..."#`.
+fn unquote(literal: &str) -> Option<String> {
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let raw = &raw[hashes..raw.len().checked_sub(hashes)?];
+        return Some(raw.strip_prefix('"')?.strip_suffix('"')?.to_string());
+    }
+    let inner = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::new();
+    let mut characters = inner.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            text.push(character);
+            continue;
+        }
+        match characters.next()? {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            'r' => text.push('\r'),
+            '0' => text.push('\0'),
+            '\\' => text.push('\\'),
+            '"' => text.push('"'),
+            '\'' => text.push('\''),
+            // A backslash at the end of a line continues the string on the next one.
+            '\n' => {
+                let rest = characters.as_str().trim_start();
+                characters = rest.chars();
+            }
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusculator::Parser;
+
+    #[test]
+    fn read_literals_and_report_errors() {
+        assert_eq!(unquote("\"2 * pi\""), Some(String::from("2 * pi")));
+        assert_eq!(
+            unquote("\"\\\"a\\\" \\\\\""),
+            Some(String::from("\"a\" \\"))
+        );
+        assert_eq!(
+            unquote("r#\"say \"hi\"\"#"),
+            Some(String::from("say \"hi\""))
+        );
+        assert_eq!(unquote("\"1 + \\\n    2\""), Some(String::from("1 + 2")));
+        assert_eq!(unquote("12"), None);
+        let error = Error::from(Parser::new("2 * (3").parse().unwrap_err());
+        assert!(report("2 * (3", &error).ends_with("\n  2 * (3\n        ^"));
+        let error = Context::new().eval("1 + y").unwrap_err();
+        assert_eq!(
+            report("1 + y", &error),
+            "Unknown variable 'y'\n  1 + y\n      ^"
+        );
+    }
+}
@@ -0,0 +1,609 @@
+//! The command line arguments.
+
+use rusculator::{Context, Diagnostic, DisplayOptions, Notation};
+
+const USAGE: &str = "\
+Usage: rusculator [options] [-e <formula>]...
+       rusculator run [options] [-q] [--stream] <script>
+       rusculator convert [options] [<quantity> <unit>]
+       rusculator watch [options] <script>
+       rusculator serve [options] [--port <port>]
+       rusculator lsp
+       rusculator tui [options]
+       rusculator explain <code>
+
+Without arguments, reads formulas from a terminal in a read-eval-print loop, or line by
+line from a pipe. `run` runs a script of assignments, function definitions such as
+`f(x) = x^2` and formulas, printing their values, from the standard input for `-`. With
+`--stream` it runs each line as it is read, in bounded memory, and reports the errors of
+all the statements. `convert` converts a quantity to a unit, as `convert 5 mi km`, or
+each quantity and unit read when given none. `watch` runs a script again whenever it
+changes. `serve` evaluates the formulas of JSON requests posted to `/eval`, as
+`{\"expression\": \"2 * x\", \"variables\": {\"x\": 3}}`, with limits on their time and
+size. `lsp` serves the language server protocol for scripts on the standard input and
+output, and `tui` runs the calculator full screen. `explain` describes the error of a
+code such as E0201, given in the error reports.";
+
+const EXIT_STATUSES: &str = "\
+Exit status:
+  0  Success
+  1  A formula could not be evaluated
+  2  Invalid arguments or commands
+  3  A formula could not be parsed";
+
+/// An option of the command line, from which the help and the shell completions are
+/// written.
+pub struct Flag {
+    pub short: Option<char>,
+    pub long: &'static str,
+    /// The name of the value the option takes, if any.
+    pub value: Option<&'static str>,
+    /// The values the option accepts, if only these.
+    pub choices: &'static [&'static str],
+    pub help: &'static str,
+}
+
+pub const FLAGS: &[Flag] = &[
+    Flag {
+        short: Some('e'),
+        long: "expr",
+        value: Some("formula"),
+        choices: &[],
+        help: "Prints the value of the formula and exits, may be repeated",
+    },
+    Flag {
+        short: Some('q'),
+        long: "quiet",
+        value: None,
+        choices: &[],
+        help: "Runs the script without printing the values of its formulas",
+    },
+    Flag {
+        short: None,
+        long: "stream",
+        value: None,
+        choices: &[],
+        help: "Runs the script a line at a time as it is read, for large files",
+    },
+    Flag {
+        short: None,
+        long: "json",
+        value: None,
+        choices: &[],
+        help: "Prints each result or error as a JSON object on its own line",
+    },
+    Flag {
+        short: None,
+        long: "error-format",
+        value: Some("format"),
+        choices: &["human", "json"],
+        help: "Reports errors as human text or JSON objects on the standard error",
+    },
+    Flag {
+        short: None,
+        long: "precision",
+        value: Some("digits"),
+        choices: &[],
+        help: "Rounds results to this many significant digits",
+    },
+    Flag {
+        short: None,
+        long: "format",
+        value: Some("format"),
+        choices: &["sci", "eng", "fixed"],
+        help: "Writes numbers as sci, eng or fixed",
+    },
+    Flag {
+        short: None,
+        long: "ast",
+        value: None,
+        choices: &[],
+        help: "Prints the syntax tree of each formula of -e instead of its value",
+    },
+    Flag {
+        short: None,
+        long: "tokens",
+        value: None,
+        choices: &[],
+        help: "Prints the tokens of each formula of -e instead of its value",
+    },
+    Flag {
+        short: None,
+        long: "port",
+        value: Some("port"),
+        choices: &[],
+        help: "Serves on this port, 8080 by default",
+    },
+    Flag {
+        short: None,
+        long: "host",
+        value: Some("address"),
+        choices: &[],
+        help: "Serves on this address, 127.0.0.1 by default",
+    },
+    Flag {
+        short: Some('h'),
+        long: "help",
+        value: None,
+        choices: &[],
+        help: "Prints this help",
+    },
+];
+
+/// The subcommands and what they do, leaving out `completions`, which writes the shell
+/// completions from these tables when packaging.
+pub const SUBCOMMANDS: &[(&str, &str)] = &[
+    ("run", "Runs a script"),
+    ("convert", "Converts a quantity to a unit"),
+    ("watch", "Runs a script again whenever it changes"),
+    ("serve", "Evaluates formulas posted over HTTP"),
+    ("lsp", "Serves the language server protocol for scripts"),
+    ("tui", "Runs the full-screen calculator"),
+    ("explain", "Describes an error code"),
+];
+
+/// The shells `completions` writes for.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// The help of `--help`.
+pub fn usage() -> String {
+    let mut lines = vec![String::from(USAGE), String::new(), String::from("Options:")];
+    for flag in FLAGS {
+        let short = match flag.short {
+            Some(short) => format!("-{}, ", short),
+            None => String::from("    "),
+        };
+        let value = flag
+            .value
+            .map_or(String::new(), |value| format!(" <{}>", value));
+        let name = format!("{}--{}{}", short, flag.long, value);
+        lines.push(format!("  {:<27} {}", name, flag.help));
+    }
+    lines.push(String::new());
+    lines.push(String::from(EXIT_STATUSES));
+    lines.join("\n")
+}
+
+/// What the program was asked to do.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Interactive,
+    /// Evaluates the formulas in order, each result available to the next as `ans`.
+    Evaluate(Vec<String>),
+    /// Prints the syntax trees of the formulas without evaluating them.
+    Ast(Vec<String>),
+    /// Prints the tokens of the formulas without parsing them.
+    Tokens(Vec<String>),
+    Run {
+        path: String,
+        quiet: bool,
+        /// Runs each line as it is read rather than loading the script.
+        stream: bool,
+    },
+    /// Runs the script now and whenever it changes, until interrupted.
+    Watch(String),
+    /// Evaluates the formulas posted to `/eval` on the address, until interrupted.
+    Serve {
+        host: String,
+        port: u16,
+    },
+    /// The full-screen calculator, with the history, variables and modes in panes.
+    Tui,
+    /// Serves the language server protocol on the standard input and output.
+    Lsp,
+    /// Prints the description of an error code.
+    Explain(String),
+    /// Converts the quantity of the words to the unit of the last, or those read if none.
+    Convert(Vec<String>),
+    /// Writes the completions of the arguments for a shell.
+    Completions(String),
+    Help,
+}
+
+/// How numbers are written, set by `--format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Always in scientific notation, as `4.7e3`.
+    Scientific,
+    Engineering,
+    /// Never in scientific notation, as `0.0000123`.
+    Fixed,
+}
+
+impl Format {
+    /// The format of `--format`, as `sci`.
+    pub fn from_name(name: &str) -> Result<Format, String> {
+        match name {
+            "sci" => Ok(Format::Scientific),
+            "eng" => Ok(Format::Engineering),
+            "fixed" => Ok(Format::Fixed),
+            _ => Err(format!(
+                "Unknown format '{}', expected sci, eng or fixed",
+                name
+            )),
+        }
+    }
+}
+
+/// How errors are reported on the standard error, set by `--error-format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ErrorFormat {
+    /// Quoting the formula and underlining the span at fault.
+    #[default]
+    Human,
+    /// The JSON objects of `Diagnostic::to_json`, one per line, for editors and CI tools.
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_name(name: &str) -> Result<ErrorFormat, String> {
+        match name {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "Unknown error format '{}', expected human or json",
+                name
+            )),
+        }
+    }
+
+    /// The report of `diagnostic` in `source`, as `Diagnostic::render` takes them.
+    pub fn report(
+        self,
+        diagnostic: &Diagnostic,
+        source: &str,
+        origin: &str,
+        first_line: usize,
+        colors: bool,
+    ) -> String {
+        match self {
+            ErrorFormat::Human => diagnostic.render(source, origin, first_line, colors),
+            ErrorFormat::Json => diagnostic.to_json(source, origin, first_line),
+        }
+    }
+}
+
+/// How results are written.
+#[derive(Debug, Default, PartialEq)]
+pub struct Output {
+    pub json: bool,
+    pub error_format: ErrorFormat,
+    pub precision: Option<usize>,
+    pub format: Option<Format>,
+}
+
+impl Output {
+    /// Applies the precision and format to the display options of `context`.
+    pub fn configure(&self, context: &mut Context) {
+        let mut options = *context.display_options();
+        if self.precision.is_some() {
+            options.precision = self.precision;
+        }
+        match self.format {
+            Some(Format::Scientific) => {
+                options.notation = Notation::Plain;
+                options.scientific_above = Some(0.0);
+            }
+            Some(Format::Engineering) => options.notation = Notation::Engineering,
+            Some(Format::Fixed) => {
+                options = DisplayOptions {
+                    notation: Notation::Plain,
+                    scientific_above: None,
+                    scientific_below: None,
+                    ..options
+                }
+            }
+            None => {}
+        }
+        context.set_display_options(options);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    pub command: Command,
+    pub output: Output,
+}
+
+/// The arguments, without the program name, or the error to report.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter().peekable();
+    if args.next_if(|arg| arg == "completions").is_some() {
+        return match (args.next(), args.next()) {
+            (Some(shell), None) if SHELLS.contains(&shell.as_str()) => Ok(Args {
+                command: Command::Completions(shell),
+                output: Output::default(),
+            }),
+            _ => Err(format!("Expected a shell among {}", SHELLS.join(", "))),
+        };
+    }
+    if args.next_if(|arg| arg == "lsp").is_some() {
+        return match args.next() {
+            None => Ok(Args {
+                command: Command::Lsp,
+                output: Output::default(),
+            }),
+            Some(arg) => Err(format!("Unexpected argument '{}'", arg)),
+        };
+    }
+    if args.next_if(|arg| arg == "explain").is_some() {
+        return match (args.next(), args.next()) {
+            (Some(code), None) => Ok(Args {
+                command: Command::Explain(code),
+                output: Output::default(),
+            }),
+            _ => Err(String::from("Expected an error code, as explain E0201")),
+        };
+    }
+    let subcommand =
+        args.next_if(|arg| ["run", "convert", "watch", "serve", "tui"].contains(&arg.as_str()));
+    let run = subcommand.as_deref() == Some("run");
+    let watch = subcommand.as_deref() == Some("watch");
+    let convert = subcommand.as_deref() == Some("convert");
+    let serve = subcommand.as_deref() == Some("serve");
+    let tui = subcommand.as_deref() == Some("tui");
+    let mut output = Output::default();
+    let mut formulas = vec![];
+    let mut words = vec![];
+    let mut path = None;
+    let mut quiet = false;
+    let mut stream = false;
+    let mut ast = false;
+    let mut tokens = false;
+    let mut host = String::from("127.0.0.1");
+    let mut port = 8080;
+    while let Some(arg) = args.next() {
+        // Long options also take their values as `--precision=12`.
+        let (flag, mut inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .take()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing the value of '{}'", flag))
+        };
+        let known = FLAGS.iter().find(|known| {
+            flag.strip_prefix("--") == Some(known.long)
+                || known
+                    .short
+                    .is_some_and(|short| flag == format!("-{}", short))
+        });
+        match known.map(|known| known.long).unwrap_or_default() {
+            "help" => {
+                return Ok(Args {
+                    command: Command::Help,
+                    output,
+                })
+            }
+            "expr" if subcommand.is_none() => formulas.push(value()?),
+            "quiet" if run => quiet = true,
+            "stream" if run => stream = true,
+            "ast" if subcommand.is_none() => ast = true,
+            "tokens" if subcommand.is_none() => tokens = true,
+            "json" => output.json = true,
+            "error-format" => output.error_format = ErrorFormat::from_name(&value()?)?,
+            "precision" => {
+                let digits = value()?;
+                match digits.parse() {
+                    Ok(digits) if digits > 0 => output.precision = Some(digits),
+                    _ => return Err(format!("Invalid precision '{}'", digits)),
+                }
+            }
+            "format" => output.format = Some(Format::from_name(&value()?)?),
+            "port" if serve => {
+                let number = value()?;
+                match number.parse() {
+                    Ok(number) => port = number,
+                    _ => return Err(format!("Invalid port '{}'", number)),
+                }
+            }
+            "host" if serve => host = value()?,
+            _ if (run || watch) && path.is_none() && (arg == "-" || !arg.starts_with('-')) => {
+                path = Some(arg.clone())
+            }
+            _ if convert && !is_option(&arg) => words.push(arg.clone()),
+            _ => return Err(format!("Unexpected argument '{}'", arg)),
+        }
+        if inline.is_some() {
+            return Err(format!("Unexpected value of '{}'", flag));
+        }
+    }
+    let command = match path {
+        _ if convert => Command::Convert(words),
+        _ if serve => Command::Serve { host, port },
+        _ if tui => Command::Tui,
+        Some(path) if watch => Command::Watch(path),
+        None if watch => return Err(String::from("Missing the script to watch")),
+        Some(path) => Command::Run {
+            path,
+            quiet,
+            stream,
+        },
+        None if run => return Err(String::from("Missing the script to run")),
+        None if (ast || tokens) && formulas.is_empty() => {
+            let flag = if ast { "--ast" } else { "--tokens" };
+            return Err(format!(
+                "Expected formulas for {0}, as {0} -e '1 + 2'",
+                flag
+            ));
+        }
+        None if ast => Command::Ast(formulas),
+        None if tokens => Command::Tokens(formulas),
+        None if formulas.is_empty() => Command::Interactive,
+        None => Command::Evaluate(formulas),
+    };
+    Ok(Args { command, output })
+}
+
+/// Whether `arg` is an option rather than a negative number such as `-5`.
+fn is_option(arg: &str) -> bool {
+    arg.strip_prefix('-')
+        .is_some_and(|rest| !rest.starts_with(|next: char| next.is_ascii_digit() || next == '.'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_all(args: &[&str]) -> Result<Args, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn command(args: &[&str]) -> Result<Command, String> {
+        parse_all(args).map(|args| args.command)
+    }
+
+    #[test]
+    fn parse_arguments() {
+        assert_eq!(command(&[]), Ok(Command::Interactive));
+        assert_eq!(
+            command(&["-e", "2*(3+4)", "--expr=ans + 1"]),
+            Ok(Command::Evaluate(vec![
+                String::from("2*(3+4)"),
+                String::from("ans + 1")
+            ]))
+        );
+        assert_eq!(command(&["-e", "1", "--help"]), Ok(Command::Help));
+        assert_eq!(
+            command(&["--ast", "-e", "1"]),
+            Ok(Command::Ast(vec![String::from("1")]))
+        );
+        assert!(command(&["--ast"]).is_err());
+        assert_eq!(
+            command(&["--tokens"]),
+            Err(String::from(
+                "Expected formulas for --tokens, as --tokens -e '1 + 2'"
+            ))
+        );
+        assert_eq!(
+            command(&["--expr"]),
+            Err(String::from("Missing the value of '--expr'"))
+        );
+        assert_eq!(
+            command(&["-x"]),
+            Err(String::from("Unexpected argument '-x'"))
+        );
+        assert_eq!(
+            command(&["run", "script.calc", "--quiet"]),
+            Ok(Command::Run {
+                path: String::from("script.calc"),
+                quiet: true,
+                stream: false
+            })
+        );
+        assert_eq!(
+            command(&["run", "--stream", "-"]),
+            Ok(Command::Run {
+                path: String::from("-"),
+                quiet: false,
+                stream: true
+            })
+        );
+        assert_eq!(
+            command(&["run"]),
+            Err(String::from("Missing the script to run"))
+        );
+        assert_eq!(
+            command(&["watch", "script.calc", "--json"]),
+            Ok(Command::Watch(String::from("script.calc")))
+        );
+        assert_eq!(
+            command(&["watch", "script.calc", "-q"]),
+            Err(String::from("Unexpected argument '-q'"))
+        );
+        assert_eq!(
+            command(&["convert", "-5.5", "mi", "--precision", "3", "km"]),
+            Ok(Command::Convert(vec![
+                String::from("-5.5"),
+                String::from("mi"),
+                String::from("km")
+            ]))
+        );
+        assert_eq!(
+            command(&["serve", "--port=9000"]),
+            Ok(Command::Serve {
+                host: String::from("127.0.0.1"),
+                port: 9000
+            })
+        );
+        assert_eq!(
+            command(&["serve", "--port", "http"]),
+            Err(String::from("Invalid port 'http'"))
+        );
+        assert_eq!(command(&["lsp"]), Ok(Command::Lsp));
+        assert_eq!(
+            command(&["explain", "E0201"]),
+            Ok(Command::Explain(String::from("E0201")))
+        );
+        assert_eq!(command(&["tui", "--precision", "4"]), Ok(Command::Tui));
+        assert_eq!(
+            command(&["--port", "80"]),
+            Err(String::from("Unexpected argument '--port'"))
+        );
+        assert_eq!(
+            command(&["convert", "-e", "1"]),
+            Err(String::from("Unexpected argument '-e'"))
+        );
+        assert_eq!(
+            command(&["completions", "fish"]),
+            Ok(Command::Completions(String::from("fish")))
+        );
+        assert_eq!(
+            command(&["completions", "pwsh"]),
+            Err(String::from("Expected a shell among bash, zsh, fish"))
+        );
+        assert_eq!(
+            command(&["--quiet"]),
+            Err(String::from("Unexpected argument '--quiet'"))
+        );
+    }
+
+    #[test]
+    fn parse_output_options() {
+        assert_eq!(
+            parse_all(&["--json", "--precision=3", "--format", "eng", "-e", "1"])
+                .map(|args| args.output),
+            Ok(Output {
+                json: true,
+                precision: Some(3),
+                format: Some(Format::Engineering),
+                ..Output::default()
+            })
+        );
+        assert_eq!(
+            parse_all(&["run", "script.calc", "--error-format=json"])
+                .map(|args| args.output.error_format),
+            Ok(ErrorFormat::Json)
+        );
+        assert_eq!(
+            command(&["--error-format", "xml"]),
+            Err(String::from(
+                "Unknown error format 'xml', expected human or json"
+            ))
+        );
+        assert_eq!(
+            command(&["--precision", "zero"]),
+            Err(String::from("Invalid precision 'zero'"))
+        );
+        assert_eq!(
+            command(&["--json=yes"]),
+            Err(String::from("Unexpected value of '--json'"))
+        );
+        let mut context = Context::new();
+        let output = Output {
+            format: Some(Format::Scientific),
+            precision: Some(2),
+            ..Output::default()
+        };
+        output.configure(&mut context);
+        assert_eq!(
+            context
+                .eval("4700")
+                .map(|value| context.format(&value))
+                .unwrap(),
+            "4.7e3"
+        );
+    }
+}
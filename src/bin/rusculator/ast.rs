@@ -0,0 +1,91 @@
+//! The syntax trees printed by `--ast` and `:ast`, showing how formulas are parsed.
+
+use rusculator::{Expr, ExprKind, UnaryOperator};
+
+/// The tree of `expr`, a node per line with its span, its operands indented below it.
+pub fn tree(expr: &Expr) -> String {
+    let mut lines = vec![];
+    write(expr, "", "", &mut lines);
+    lines.join("\n")
+}
+
+fn label(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Number(value) => value.to_string(),
+        ExprKind::String(string) => format!("\"{}\"", string),
+        ExprKind::Variable(name) => name.clone(),
+        ExprKind::Unary(UnaryOperator::Negate, _) => String::from("negate"),
+        ExprKind::Unary(UnaryOperator::Percent, _) => String::from("percent"),
+        ExprKind::Binary(operator, _, _) => operator.symbol().to_string(),
+        ExprKind::Call(name, _) => format!("call {}", name),
+        ExprKind::Conditional(_, _, _) => String::from("?:"),
+        ExprKind::Lambda(params, _) => format!("lambda ({})", params.join(", ")),
+        ExprKind::Tuple(_) => String::from("tuple"),
+        ExprKind::Value(value) => value.to_string(),
+    }
+}
+
+/// The operands of `expr`, in order.
+pub fn children(expr: &Expr) -> Vec<&Expr> {
+    match &expr.kind {
+        ExprKind::Unary(_, operand) => vec![operand],
+        ExprKind::Binary(_, lhs, rhs) => vec![lhs, rhs],
+        ExprKind::Call(_, items) | ExprKind::Tuple(items) => items.iter().collect(),
+        ExprKind::Conditional(condition, then, otherwise) => vec![condition, then, otherwise],
+        ExprKind::Lambda(_, body) => vec![body],
+        _ => vec![],
+    }
+}
+
+/// Writes the node after `branch`, and its children each after `indent`.
+fn write(expr: &Expr, branch: &str, indent: &str, lines: &mut Vec<String>) {
+    lines.push(format!(
+        "{}{}  {}..{}",
+        branch,
+        label(expr),
+        expr.span.start,
+        expr.span.end
+    ));
+    let children = children(expr);
+    for (index, child) in children.iter().enumerate() {
+        if index + 1 == children.len() {
+            write(
+                child,
+                &format!("{}└─ ", indent),
+                &format!("{}   ", indent),
+                lines,
+            );
+        } else {
+            write(
+                child,
+                &format!("{}├─ ", indent),
+                &format!("{}│  ", indent),
+                lines,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusculator::Parser;
+
+    #[test]
+    fn write_trees() {
+        let expr = Parser::new("-2^x + f(1, 3%)").parse().unwrap();
+        assert_eq!(
+            tree(&expr),
+            "\
++  0..15
+├─ negate  0..4
+│  └─ ^  1..4
+│     ├─ 2  1..2
+│     └─ x  3..4
+└─ call f  7..15
+   ├─ 1  9..10
+   └─ percent  12..14
+      └─ 3  12..13"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Shell completions of the arguments, written by `rusculator completions <shell>` from
+//! the tables of the argument parser so that they follow its options.
+
+use crate::args::{Flag, FLAGS, SUBCOMMANDS};
+
+/// The completion script of `shell`, one of `SHELLS`.
+pub fn script(shell: &str) -> String {
+    match shell {
+        "bash" => bash(),
+        "zsh" => zsh(),
+        _ => fish(),
+    }
+}
+
+fn names(flag: &Flag) -> Vec<String> {
+    let long = format!("--{}", flag.long);
+    match flag.short {
+        Some(short) => vec![format!("-{}", short), long],
+        None => vec![long],
+    }
+}
+
+fn bash() -> String {
+    let mut words: Vec<String> = FLAGS.iter().flat_map(names).collect();
+    let subcommands: Vec<&str> = SUBCOMMANDS.iter().map(|(name, _)| *name).collect();
+    let mut cases = String::new();
+    for flag in FLAGS.iter().filter(|flag| flag.value.is_some()) {
+        let reply = match flag.choices {
+            [] => String::from("COMPREPLY=()"),
+            choices => format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                choices.join(" ")
+            ),
+        };
+        cases += &format!(
+            "        {})\n            {}\n            return ;;\n",
+            names(flag).join("|"),
+            reply
+        );
+    }
+    words.sort();
+    format!(
+        "_rusculator() {{
+    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+    case \"$prev\" in
+{cases}    esac
+    if [[ \"$cur\" != -* && \"${{COMP_WORDS[1]}}\" =~ ^(run|watch)$ && $COMP_CWORD -gt 1 ]]; then
+        COMPREPLY=($(compgen -f -- \"$cur\"))
+    elif [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W \"{subcommands} {words}\" -- \"$cur\"))
+    else
+        COMPREPLY=($(compgen -W \"{words}\" -- \"$cur\"))
+    fi
+}}
+complete -F _rusculator rusculator
+",
+        cases = cases,
+        subcommands = subcommands.join(" "),
+        words = words.join(" "),
+    )
+}
+
+fn zsh() -> String {
+    let mut lines = vec![String::from("#compdef rusculator"), String::new()];
+    lines.push(String::from("_arguments \\"));
+    for flag in FLAGS {
+        let names = names(flag);
+        let spec = match names.len() {
+            1 => names[0].clone(),
+            _ => format!("'({})'{{{}}}", names.join(" "), names.join(",")),
+        };
+        let value = match (flag.value, flag.choices) {
+            (None, _) => String::new(),
+            (Some(value), []) => format!(":{}: ", value),
+            (Some(value), choices) => format!(":{}:({})", value, choices.join(" ")),
+        };
+        lines.push(format!("  {}'[{}]{}' \\", spec, flag.help, value));
+    }
+    let subcommands: Vec<String> = SUBCOMMANDS
+        .iter()
+        .map(|(name, help)| format!("{}\\:\"{}\"", name, help))
+        .collect();
+    lines.push(format!("  '1:command:(({}))' \\", subcommands.join(" ")));
+    lines.push(String::from("  '*:script:_files'"));
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn fish() -> String {
+    let mut lines = vec![];
+    for (name, help) in SUBCOMMANDS {
+        lines.push(format!(
+            "complete -c rusculator -n __fish_use_subcommand -f -a {} -d '{}'",
+            name, help
+        ));
+    }
+    for flag in FLAGS {
+        let mut line = String::from("complete -c rusculator");
+        if let Some(short) = flag.short {
+            line += &format!(" -s {}", short);
+        }
+        line += &format!(" -l {}", flag.long);
+        match (flag.value, flag.choices) {
+            (None, _) => {}
+            (Some(_), []) => line += " -r",
+            (Some(_), choices) => line += &format!(" -x -a '{}'", choices.join(" ")),
+        }
+        lines.push(format!("{} -d '{}'", line, flag.help));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_completions() {
+        let bash = script("bash");
+        assert!(bash.contains("        --format)\n            COMPREPLY=($(compgen -W \"sci eng fixed\" -- \"$cur\"))\n"));
+        assert!(bash.contains(
+            "compgen -W \"run convert watch serve lsp tui explain --ast --error-format --expr --format --help --host --json --port --precision --quiet --stream --tokens -e -h -q\""
+        ));
+        let zsh = script("zsh");
+        assert!(zsh.contains("  '(-e --expr)'{-e,--expr}'[Prints the value of the formula and exits, may be repeated]:formula: ' \\\n"));
+        assert!(zsh.contains(
+            "  --format'[Writes numbers as sci, eng or fixed]:format:(sci eng fixed)' \\\n"
+        ));
+        let fish = script("fish");
+        assert!(fish.contains("complete -c rusculator -s q -l quiet -d 'Runs the script without printing the values of its formulas'\n"));
+        assert!(fish.contains("complete -c rusculator -l format -x -a 'sci eng fixed' -d"));
+        // Help texts are quoted as they are.
+        for flag in FLAGS {
+            assert!(!flag.help.contains(['\'', '[', ']']));
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! The configuration file, `~/.config/rusculator/config.toml`, giving the defaults the
+//! command line arguments override:
+//!
+//! ```toml
+//! precision = 12
+//! angle = "deg"
+//! backend = "money 2"
+//! base = ["hex", "dec"]
+//! prompt = "calc> "
+//!
+//! [units]
+//! furlong = "201.168 m"
+//!
+//! [constants]
+//! g0 = "9.80665 m/s^2"
+//! ```
+//!
+//! Only this subset of TOML is read: tables, and keys with strings, numbers, booleans or
+//! arrays of them.
+
+use std::path::PathBuf;
+
+use rusculator::Context;
+
+use crate::settings::{self, Modes};
+
+/// What the configuration sets besides the context.
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub modes: Modes,
+    pub prompt: Option<String>,
+}
+
+/// The configuration file, in `$XDG_CONFIG_HOME` or else `~/.config`.
+pub fn path() -> Option<PathBuf> {
+    let directory = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(directory.join("rusculator").join("config.toml"))
+}
+
+/// Applies the configuration of `source` to `context`, returning the rest of it and the
+/// errors of the lines ignored.
+pub fn load(source: &str, context: &mut Context) -> (Config, Vec<String>) {
+    let mut config = Config::default();
+    let mut errors = vec![];
+    let mut table = String::new();
+    for (number, line) in source.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let result = match line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            Some(name) => {
+                table = name.trim().to_string();
+                match table.as_str() {
+                    "" | "units" | "constants" => Ok(()),
+                    _ => Err(format!("Unknown table '{}'", table)),
+                }
+            }
+            None => match line.split_once('=') {
+                Some((key, value)) => values(value.trim()).and_then(|values| {
+                    let key = key.trim().trim_matches('"');
+                    apply(&table, key, &values, context, &mut config)
+                }),
+                None => Err(String::from("Expected 'key = value'")),
+            },
+        };
+        if let Err(message) = result {
+            errors.push(format!("line {}: {}", number + 1, message));
+        }
+    }
+    (config, errors)
+}
+
+fn apply(
+    table: &str,
+    key: &str,
+    values: &[String],
+    context: &mut Context,
+    config: &mut Config,
+) -> Result<(), String> {
+    let [value] = values else {
+        if table.is_empty() && key == "base" {
+            let values: Vec<&str> = values.iter().map(String::as_str).collect();
+            return settings::set(context, &mut config.modes, key, &values);
+        }
+        return Err(format!("Expected a single value of '{}'", key));
+    };
+    match table {
+        "units" => {
+            let value = context.eval(value).map_err(|error| error.to_string())?;
+            context
+                .define_unit(key, &value)
+                .map_err(|error| error.to_string())
+        }
+        "constants" => {
+            let value = context.eval(value).map_err(|error| error.to_string())?;
+            context.set_variable(key, value);
+            Ok(())
+        }
+        _ if key == "prompt" => {
+            config.prompt = Some(value.clone());
+            Ok(())
+        }
+        _ => {
+            let values: Vec<&str> = value.split_whitespace().collect();
+            settings::set(context, &mut config.modes, key, &values)
+        }
+    }
+}
+
+/// The line without its comment, a `#` outside strings.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// The values of a string, number, boolean or array of them, as text.
+fn values(value: &str) -> Result<Vec<String>, String> {
+    match value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+    {
+        Some(items) => items
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(scalar)
+            .collect(),
+        None => scalar(value).map(|value| vec![value]),
+    }
+}
+
+fn scalar(value: &str) -> Result<String, String> {
+    let Some(quoted) = value.strip_prefix('"') else {
+        return match value {
+            "" => Err(String::from("Missing the value")),
+            _ if value.contains(['"', '[', ']', ' ']) => Err(format!("Invalid value '{}'", value)),
+            _ => Ok(value.to_string()),
+        };
+    };
+    let mut text = String::new();
+    let mut characters = quoted.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if characters.as_str().is_empty() => return Ok(text),
+            '\\' => match characters.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some(character @ ('"' | '\\')) => text.push(character),
+                _ => return Err(format!("Invalid escape in {}", value)),
+            },
+            character => text.push(character),
+        }
+    }
+    Err(format!("Unterminated string {}", value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::settings::Backend;
+    use rusculator::{AngleUnit, FixedFormat};
+
+    #[test]
+    fn load_configuration() {
+        let source = r#"
+            # Defaults
+            precision = 4
+            angle = "deg"   # for surveying
+            backend = "fixed Q15.16"
+            prompt = "calc# "
+
+            [units]
+            furlong = "201.168 m"
+
+            [constants]
+            "g0" = "9.80665"
+            half = [1, 2]
+
+            [colors]
+            speed = fast
+        "#;
+        let mut context = Context::new();
+        let (config, errors) = load(source, &mut context);
+        assert_eq!(
+            config,
+            Config {
+                modes: Modes {
+                    backend: Backend::Fixed(FixedFormat::new(15, 16).unwrap()),
+                    programmer: false,
+                },
+                prompt: Some(String::from("calc# ")),
+            }
+        );
+        assert_eq!(
+            errors[..2],
+            [
+                "line 13: Expected a single value of 'half'",
+                "line 15: Unknown table 'colors'",
+            ]
+        );
+        assert!(errors[2].starts_with("line 16: Unknown setting 'speed'"));
+        assert_eq!(context.angle_unit(), AngleUnit::Degrees);
+        let format = |context: &mut Context, source| {
+            let value = context.eval(source).unwrap();
+            context.format(&value)
+        };
+        assert_eq!(format(&mut context, "1 / 3"), "0.3333");
+        assert_eq!(format(&mut context, "2 furlong in m"), "402.3 m");
+        assert_eq!(format(&mut context, "g0 * 2"), "19.61");
+    }
+}
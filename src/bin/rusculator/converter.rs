@@ -0,0 +1,99 @@
+//! `rusculator convert`, converting quantities between units as `5 mi km`.
+
+use std::io::{self, Write};
+
+use rusculator::{Context, Error, Parser, Value};
+
+use crate::repl::Input;
+use crate::status::Status;
+
+/// The formula converting the quantity of the words to the unit of the last, which may
+/// follow `to` or `in` as in `5 mi to km`.
+pub fn formula(words: &[&str]) -> Result<String, String> {
+    let (target, mut quantity) = match words.split_last() {
+        Some((target, quantity)) if !quantity.is_empty() => (target, quantity),
+        _ => return Err(String::from("Expected a quantity and a unit, as '5 mi km'")),
+    };
+    if let [rest @ .., "to" | "in"] = quantity {
+        if !rest.is_empty() {
+            quantity = rest;
+        }
+    }
+    Ok(format!("({}) in {}", quantity.join(" "), target))
+}
+
+pub fn convert(formula: &str, context: &Context) -> Result<Value, Error> {
+    let expr = Parser::new(formula).parse()?;
+    Ok(context.evaluate(&expr)?)
+}
+
+/// Converts the quantities read line by line until the end of the input or `quit`,
+/// returning the status of the first conversion failing, if any. Units are completed.
+pub fn run(
+    input: &mut impl Input,
+    output: &mut impl Write,
+    prompt: &str,
+    context: &Context,
+) -> io::Result<Status> {
+    let mut status = Status::Success;
+    let complete = |prefix: &str| {
+        let mut symbols = context.units().symbols();
+        symbols.retain(|symbol| symbol.starts_with(prefix));
+        symbols
+    };
+    while let Some(line) = input.read_line(prompt, output, &complete)? {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words[..] {
+            [] => continue,
+            ["quit" | "exit"] => break,
+            _ => match formula(&words) {
+                Ok(formula) => convert(&formula, context)
+                    .map(|value| context.format(&value))
+                    .map_err(|error| (error.to_string(), Status::of(&error))),
+                Err(message) => Err((message, Status::UsageError)),
+            },
+        };
+        match result {
+            Ok(result) => writeln!(output, "{}", result)?,
+            Err((message, failure)) => {
+                if status == Status::Success {
+                    status = failure;
+                }
+                writeln!(output, "Error: {}", message)?
+            }
+        }
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::repl::Plain;
+
+    #[test]
+    fn convert_quantities() {
+        assert_eq!(
+            formula(&["5", "mi", "to", "km"]),
+            Ok(String::from("(5 mi) in km"))
+        );
+        assert_eq!(formula(&["to", "km"]), Ok(String::from("(to) in km")));
+        assert!(formula(&["km"]).is_err());
+        let mut output = vec![];
+        let input = "10 ft m\n\n3 m s\nkm\n2 km/h to m/s\nquit\n1 m cm\n";
+        let status = run(
+            &mut Plain(input.as_bytes()),
+            &mut output,
+            "",
+            &Context::new(),
+        );
+        assert_eq!(status.unwrap(), Status::EvaluationError);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "3.048 m\n\
+             Error: Cannot convert m (length) and s (time)\n\
+             Error: Expected a quantity and a unit, as '5 mi km'\n\
+             0.5555555555555556 m/s\n"
+        );
+    }
+}
@@ -0,0 +1,559 @@
+//! A line editor for terminals, with the keys of readline and a history kept in a file
+//! across sessions. The terminal is in raw mode only while reading a line.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use crate::highlight::highlight;
+use crate::repl::Input;
+
+/// Entries of the history kept, the oldest being dropped first.
+const MAX_HISTORY: usize = 1000;
+
+/// A key pressed, decoded from the bytes the terminal sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Left,
+    Right,
+    /// `Home` or `Ctrl-A`.
+    Home,
+    /// `End` or `Ctrl-E`.
+    End,
+    Up,
+    Down,
+    Backspace,
+    Delete,
+    /// `Ctrl-K`, deleting to the end of the line.
+    KillEnd,
+    /// `Ctrl-U`, deleting to the start of the line.
+    KillStart,
+    Enter,
+    /// `Ctrl-D`, ending the input on an empty line and deleting otherwise.
+    EndOfInput,
+    /// `Ctrl-C`, abandoning the line.
+    Interrupt,
+    /// Completes the identifier before the cursor.
+    Tab,
+    PageUp,
+    PageDown,
+    /// Keys without an action, as unknown escape sequences.
+    Ignored,
+}
+
+/// Decodes the next key from `bytes`, or returns `None` at their end.
+pub fn read_key(bytes: &mut impl Iterator<Item = u8>) -> Option<Key> {
+    let byte = bytes.next()?;
+    Some(match byte {
+        1 => Key::Home,
+        2 => Key::Left,
+        3 => Key::Interrupt,
+        4 => Key::EndOfInput,
+        5 => Key::End,
+        6 => Key::Right,
+        8 | 127 => Key::Backspace,
+        b'\t' => Key::Tab,
+        11 => Key::KillEnd,
+        14 => Key::Down,
+        16 => Key::Up,
+        21 => Key::KillStart,
+        b'\r' | b'\n' => Key::Enter,
+        0x1B => match (bytes.next(), bytes.next()) {
+            (Some(b'[' | b'O'), Some(b'A')) => Key::Up,
+            (Some(b'[' | b'O'), Some(b'B')) => Key::Down,
+            (Some(b'[' | b'O'), Some(b'C')) => Key::Right,
+            (Some(b'[' | b'O'), Some(b'D')) => Key::Left,
+            (Some(b'[' | b'O'), Some(b'H')) => Key::Home,
+            (Some(b'[' | b'O'), Some(b'F')) => Key::End,
+            (Some(b'['), Some(digit @ b'0'..=b'9')) => {
+                // `ESC [ n ~`, skipping any further parameters.
+                let last = bytes.find(|byte| !byte.is_ascii_digit() && *byte != b';');
+                match (digit, last) {
+                    (b'1' | b'7', Some(b'~')) => Key::Home,
+                    (b'4' | b'8', Some(b'~')) => Key::End,
+                    (b'3', Some(b'~')) => Key::Delete,
+                    (b'5', Some(b'~')) => Key::PageUp,
+                    (b'6', Some(b'~')) => Key::PageDown,
+                    _ => Key::Ignored,
+                }
+            }
+            _ => Key::Ignored,
+        },
+        byte if byte < 0x20 => Key::Ignored,
+        byte => {
+            // The continuation bytes of UTF-8 follow the first one.
+            let length = match byte {
+                0xF0.. => 4,
+                0xE0.. => 3,
+                0xC0.. => 2,
+                _ => 1,
+            };
+            let mut encoded = vec![byte];
+            encoded.extend(bytes.take(length - 1));
+            match std::str::from_utf8(&encoded)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(character) => Key::Char(character),
+                None => Key::Ignored,
+            }
+        }
+    })
+}
+
+/// Lines entered before, saved to a file as they are entered when it has one.
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// The history of the file at `path`, empty if it does not exist yet.
+    pub fn load(path: Option<PathBuf>) -> History {
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| contents.lines().map(String::from).collect())
+            .unwrap_or_default();
+        let mut history = History { entries, path };
+        history.truncate();
+        history
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Adds `line` unless it is blank or the same as the last entry. Failing to save it
+    /// only loses it for later sessions.
+    pub fn push(&mut self, line: &str) {
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.entries.push(line.to_string());
+        let full = self.entries.len() > MAX_HISTORY;
+        self.truncate();
+        let Some(path) = &self.path else {
+            return;
+        };
+        if full {
+            // Rewritten without the dropped entries.
+            let _ = fs::write(path, self.entries.join("\n") + "\n");
+        } else if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    fn truncate(&mut self) {
+        let excess = self.entries.len().saturating_sub(MAX_HISTORY);
+        self.entries.drain(..excess);
+    }
+}
+
+/// What a key did to the line being edited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Edited,
+    /// The names completing the identifier typed, which have no longer common prefix.
+    Candidates(Vec<String>),
+    Done(String),
+    EndOfInput,
+}
+
+/// The line being edited, and where it is in the history.
+#[derive(Default)]
+pub struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+    /// The entry of the history shown, and the line typed before recalling it.
+    recalled: Option<(usize, Vec<char>)>,
+}
+
+impl Line {
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn handle(
+        &mut self,
+        key: Key,
+        history: &History,
+        complete: &dyn Fn(&str) -> Vec<String>,
+    ) -> Action {
+        match key {
+            Key::Tab => return self.complete(complete),
+            Key::Char(character) => {
+                self.chars.insert(self.cursor, character);
+                self.cursor += 1;
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.chars.len(),
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            Key::Delete | Key::EndOfInput if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            Key::EndOfInput if self.chars.is_empty() => return Action::EndOfInput,
+            Key::KillEnd => self.chars.truncate(self.cursor),
+            Key::KillStart => {
+                self.chars.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::Up => self.recall(history, -1),
+            Key::Down => self.recall(history, 1),
+            Key::Enter => return Action::Done(self.text()),
+            Key::Interrupt => {
+                self.chars.clear();
+                self.cursor = 0;
+                return Action::Done(String::new());
+            }
+            _ => {}
+        }
+        Action::Edited
+    }
+
+    /// Extends the identifier before the cursor, which may be within a formula, to the
+    /// longest prefix its completions share.
+    fn complete(&mut self, complete: &dyn Fn(&str) -> Vec<String>) -> Action {
+        let start = self.chars[..self.cursor]
+            .iter()
+            .rposition(|char| !(char.is_alphanumeric() || *char == '_'))
+            .map_or(0, |position| position + 1);
+        let typed: String = self.chars[start..self.cursor].iter().collect();
+        if typed.is_empty() || typed.starts_with(|char: char| char.is_ascii_digit()) {
+            return Action::Edited;
+        }
+        let candidates = complete(&typed);
+        let Some(first) = candidates.first() else {
+            return Action::Edited;
+        };
+        let common = candidates.iter().fold(first.as_str(), |common, candidate| {
+            let length = common
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(common.len().min(candidate.len()), |((index, _), _)| index);
+            &common[..length]
+        });
+        if common.len() > typed.len() || candidates.len() == 1 {
+            for character in common[typed.len()..].chars() {
+                self.chars.insert(self.cursor, character);
+                self.cursor += 1;
+            }
+            return Action::Edited;
+        }
+        Action::Candidates(candidates)
+    }
+
+    /// Shows the entry of the history before or after the one shown, or back the line
+    /// typed after the last entry.
+    fn recall(&mut self, history: &History, step: isize) {
+        let entries = history.entries();
+        let current = self
+            .recalled
+            .as_ref()
+            .map_or(entries.len(), |(index, _)| *index);
+        let Some(index) = current.checked_add_signed(step) else {
+            return;
+        };
+        if index > entries.len() {
+            return;
+        }
+        let typed = match self.recalled.take() {
+            Some((_, typed)) => typed,
+            None => self.chars.clone(),
+        };
+        self.chars = match entries.get(index) {
+            Some(entry) => {
+                let chars = entry.chars().collect();
+                self.recalled = Some((index, typed));
+                chars
+            }
+            None => typed,
+        };
+        self.cursor = self.chars.len();
+    }
+}
+
+/// Reads lines from a terminal, editing them with the keys.
+pub struct Editor {
+    history: History,
+    /// Whether to color the tokens of the line.
+    colors: bool,
+}
+
+impl Editor {
+    pub fn new(history: History, colors: bool) -> Editor {
+        Editor { history, colors }
+    }
+
+    /// The default history file, in the home directory.
+    pub fn history_path() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".rusculator_history"))
+    }
+}
+
+fn render(output: &mut dyn Write, prompt: &str, line: &Line, colors: bool) -> io::Result<()> {
+    let text = if colors {
+        highlight(&line.text())
+    } else {
+        line.text()
+    };
+    // Back to the start of the terminal line, write it and erase what is left.
+    write!(output, "\r{}{}\x1b[K", prompt, text)?;
+    let after = line.text().chars().count() - line.cursor();
+    if after > 0 {
+        write!(output, "\x1b[{}D", after)?;
+    }
+    output.flush()
+}
+
+impl Input for Editor {
+    fn read_line(
+        &mut self,
+        prompt: &str,
+        output: &mut dyn Write,
+        complete: &dyn Fn(&str) -> Vec<String>,
+    ) -> io::Result<Option<String>> {
+        let _raw = terminal::RawMode::enable()?;
+        let mut bytes = io::stdin().lock().bytes().map_while(Result::ok);
+        let mut line = Line::default();
+        render(output, prompt, &line, self.colors)?;
+        loop {
+            let action = match read_key(&mut bytes) {
+                Some(key) => line.handle(key, &self.history, complete),
+                None => Action::EndOfInput,
+            };
+            match action {
+                Action::Edited => render(output, prompt, &line, self.colors)?,
+                Action::Candidates(candidates) => {
+                    write!(output, "\r\n{}\r\n", candidates.join("  "))?;
+                    render(output, prompt, &line, self.colors)?;
+                }
+                Action::Done(text) => {
+                    write!(output, "\r\n")?;
+                    self.history.push(&text);
+                    return Ok(Some(text));
+                }
+                Action::EndOfInput => {
+                    write!(output, "\r\n")?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+/// Raw mode of the terminal through termios, leaving the keys to the editor, and its size.
+pub mod terminal {
+    use std::io;
+
+    const STDIN: i32 = 0;
+    const TCSANOW: i32 = 0;
+    const ISIG: u32 = 0o1;
+    const ICANON: u32 = 0o2;
+    const ECHO: u32 = 0o10;
+    const IEXTEN: u32 = 0o100000;
+    const ICRNL: u32 = 0o400;
+    const IXON: u32 = 0o2000;
+    const VTIME: usize = 5;
+    const VMIN: usize = 6;
+
+    /// `struct termios` of Linux.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Termios {
+        c_iflag: u32,
+        c_oflag: u32,
+        c_cflag: u32,
+        c_lflag: u32,
+        c_line: u8,
+        c_cc: [u8; 32],
+        c_ispeed: u32,
+        c_ospeed: u32,
+    }
+
+    /// `struct winsize`.
+    #[repr(C)]
+    struct Winsize {
+        ws_row: u16,
+        ws_col: u16,
+        ws_xpixel: u16,
+        ws_ypixel: u16,
+    }
+
+    const STDOUT: i32 = 1;
+    const TIOCGWINSZ: u64 = 0x5413;
+
+    extern "C" {
+        fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        fn tcsetattr(fd: i32, actions: i32, termios: *const Termios) -> i32;
+        fn ioctl(fd: i32, request: u64, ...) -> i32;
+    }
+
+    /// The columns and rows of the terminal of the standard output.
+    pub fn size() -> Option<(usize, usize)> {
+        let mut size = Winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // SAFETY: `TIOCGWINSZ` writes a `struct winsize` to `size`.
+        if unsafe { ioctl(STDOUT, TIOCGWINSZ, &mut size as *mut Winsize) } != 0 || size.ws_col == 0
+        {
+            return None;
+        }
+        Some((usize::from(size.ws_col), usize::from(size.ws_row)))
+    }
+
+    /// Restores the previous mode when dropped.
+    pub struct RawMode {
+        original: Termios,
+    }
+
+    impl RawMode {
+        pub fn enable() -> io::Result<RawMode> {
+            let mut original = Termios {
+                c_iflag: 0,
+                c_oflag: 0,
+                c_cflag: 0,
+                c_lflag: 0,
+                c_line: 0,
+                c_cc: [0; 32],
+                c_ispeed: 0,
+                c_ospeed: 0,
+            };
+            // SAFETY: `original` is a valid `struct termios` to write to.
+            if unsafe { tcgetattr(STDIN, &mut original) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = original;
+            raw.c_lflag &= !(ECHO | ICANON | ISIG | IEXTEN);
+            raw.c_iflag &= !(IXON | ICRNL);
+            raw.c_cc[VMIN] = 1;
+            raw.c_cc[VTIME] = 0;
+            // SAFETY: `raw` is a valid `struct termios`.
+            if unsafe { tcsetattr(STDIN, TCSANOW, &raw) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(RawMode { original })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: `original` was read by `tcgetattr`.
+            unsafe { tcsetattr(STDIN, TCSANOW, &self.original) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(bytes: &[u8]) -> Vec<Key> {
+        let mut bytes = bytes.iter().copied();
+        std::iter::from_fn(|| read_key(&mut bytes)).collect()
+    }
+
+    fn complete(prefix: &str) -> Vec<String> {
+        let names = ["sin", "sinh", "sqrt", "sum", "x_1"];
+        names
+            .iter()
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    fn edit(history: &History, bytes: &[u8]) -> (String, usize) {
+        let mut line = Line::default();
+        for key in keys(bytes) {
+            line.handle(key, history, &complete);
+        }
+        (line.text(), line.cursor())
+    }
+
+    #[test]
+    fn decode_keys() {
+        assert_eq!(
+            keys(b"a\x1b[A\x1b[D\x1bOH\x1b[3~\x01\x05\x7f\r"),
+            [
+                Key::Char('a'),
+                Key::Up,
+                Key::Left,
+                Key::Home,
+                Key::Delete,
+                Key::Home,
+                Key::End,
+                Key::Backspace,
+                Key::Enter
+            ]
+        );
+        assert_eq!(keys("π".as_bytes()), [Key::Char('π')]);
+        assert_eq!(keys(b"\x1b[1;5C"), [Key::Ignored]);
+        assert_eq!(keys(b"\x1b[5~\x1b[6~"), [Key::PageUp, Key::PageDown]);
+    }
+
+    #[test]
+    fn edit_lines_with_history() {
+        let path = std::env::temp_dir().join(format!("rusculator-history-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut history = History::load(Some(path.clone()));
+        history.push("1 + 2");
+        history.push("sqrt(2)");
+        history.push("sqrt(2)");
+        history.push("  ");
+        let history = History::load(Some(path.clone()));
+        assert_eq!(history.entries(), ["1 + 2", "sqrt(2)"]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            edit(&history, b"2+3\x1b[D\x1b[D*"),
+            (String::from("2*+3"), 2)
+        );
+        assert_eq!(edit(&history, b"abc\x01x\x05y"), (String::from("xabcy"), 5));
+        assert_eq!(edit(&history, b"abc\x02\x02\x0b"), (String::from("a"), 1));
+        assert_eq!(edit(&history, b"x\x1b[A\x1b[A"), (String::from("1 + 2"), 5));
+        assert_eq!(
+            edit(&history, b"x\x1b[A\x1b[A\x1b[A\x1b[B"),
+            (String::from("sqrt(2)"), 7)
+        );
+        assert_eq!(
+            edit(&history, b"x\x1b[A\x1b[B\x1b[B"),
+            (String::from("x"), 1)
+        );
+        let mut line = Line::default();
+        assert_eq!(
+            line.handle(Key::EndOfInput, &history, &complete),
+            Action::EndOfInput
+        );
+    }
+
+    #[test]
+    fn complete_identifiers() {
+        let history = History::load(None);
+        assert_eq!(edit(&history, b"2*sq\t"), (String::from("2*sqrt"), 6));
+        assert_eq!(edit(&history, b"1+x\t+1"), (String::from("1+x_1+1"), 7));
+        assert_eq!(edit(&history, b"si\t"), (String::from("sin"), 3));
+        assert_eq!(edit(&history, b"(y)\x02\x02y\t"), (String::from("(yy)"), 2));
+        assert_eq!(edit(&history, b"12\t"), (String::from("12"), 2));
+        let mut line = Line::default();
+        for key in keys(b"s") {
+            line.handle(key, &history, &complete);
+        }
+        assert_eq!(
+            line.handle(Key::Tab, &history, &complete),
+            Action::Candidates(complete("s"))
+        );
+    }
+}
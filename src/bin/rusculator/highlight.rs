@@ -0,0 +1,80 @@
+//! Colors of the tokens of a line being typed, by the classification of the lexer.
+
+use rusculator::{Lexer, Token};
+
+const NUMBER: &str = "\x1b[36m";
+const OPERATOR: &str = "\x1b[33m";
+const IDENTIFIER: &str = "\x1b[32m";
+const STRING: &str = "\x1b[35m";
+/// Parentheses without a match, and input the lexer rejects.
+const MISMATCHED: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+/// `line` with the escape sequences coloring its tokens. The text is left as it is,
+/// so the cursor moves over it as over the line.
+pub fn highlight(line: &str) -> String {
+    let mut lexer = Lexer::new(line);
+    let mut tokens = vec![];
+    let mut rest = line.len();
+    loop {
+        match lexer.next_spanned_token() {
+            Ok(Some((token, span))) => tokens.push((token, span.start, span.end)),
+            Ok(None) => break,
+            Err(error) => {
+                rest = error.span.start.min(line.len());
+                break;
+            }
+        }
+    }
+    // The parentheses left once the matching pairs are removed.
+    let mut open = vec![];
+    let mut mismatched = vec![];
+    for (index, (token, _, _)) in tokens.iter().enumerate() {
+        match token {
+            Token::OpenParenthesis => open.push(index),
+            Token::ClosedParenthesis if open.pop().is_none() => mismatched.push(index),
+            _ => {}
+        }
+    }
+    mismatched.extend(open);
+    let mut highlighted = String::new();
+    let mut end = 0;
+    for (index, (token, start, token_end)) in tokens.iter().enumerate() {
+        highlighted.push_str(&line[end..*start]);
+        let color = match token {
+            _ if mismatched.contains(&index) => MISMATCHED,
+            Token::Number(_) => NUMBER,
+            Token::Identifier(_) => IDENTIFIER,
+            Token::String(_) => STRING,
+            Token::Operator(_) => OPERATOR,
+            Token::OpenParenthesis | Token::ClosedParenthesis | Token::Comma => "",
+        };
+        if color.is_empty() {
+            highlighted.push_str(&line[*start..*token_end]);
+        } else {
+            highlighted.push_str(&format!("{}{}{}", color, &line[*start..*token_end], RESET));
+        }
+        end = *token_end;
+    }
+    highlighted.push_str(&line[end..rest]);
+    if rest < line.len() {
+        highlighted.push_str(&format!("{}{}{}", MISMATCHED, &line[rest..], RESET));
+    }
+    highlighted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn highlight_tokens() {
+        assert_eq!(
+            highlight("2 * sin(x)"),
+            "\x1b[36m2\x1b[0m \x1b[33m*\x1b[0m \x1b[32msin\x1b[0m(\x1b[32mx\x1b[0m)"
+        );
+        assert_eq!(highlight("(1))"), "(\x1b[36m1\x1b[0m)\x1b[1;31m)\x1b[0m");
+        assert_eq!(highlight("(( )"), "\x1b[1;31m(\x1b[0m( )");
+        assert_eq!(highlight("1 # 2"), "\x1b[36m1\x1b[0m \x1b[1;31m# 2\x1b[0m");
+    }
+}
@@ -0,0 +1,333 @@
+//! The JSON objects written by `--json`, one per line, and those read by `serve`.
+
+use std::fmt::{self, Write};
+
+use rusculator::Error;
+
+/// A JSON object written field by field.
+#[derive(Debug, Default)]
+pub struct Object {
+    text: String,
+}
+
+impl Object {
+    pub fn new() -> Object {
+        Object::default()
+    }
+
+    pub fn string(self, key: &str, value: &str) -> Object {
+        self.raw(key, &string(value))
+    }
+
+    pub fn number(self, key: &str, value: usize) -> Object {
+        self.raw(key, &value.to_string())
+    }
+
+    pub fn object(self, key: &str, value: Object) -> Object {
+        self.raw(key, &value.finish())
+    }
+
+    /// Adds `value`, already written as JSON.
+    pub fn raw(mut self, key: &str, value: &str) -> Object {
+        self.text.push(if self.text.is_empty() { '{' } else { ',' });
+        write!(self.text, "{}:{}", string(key), value).unwrap();
+        self
+    }
+
+    pub fn finish(self) -> String {
+        if self.text.is_empty() {
+            return String::from("{}");
+        }
+        self.text + "}"
+    }
+}
+
+/// The array of `items`, each already written as JSON.
+pub fn array(items: impl IntoIterator<Item = String>) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(","))
+}
+
+/// The kind, code, message and span, in bytes, of `error`.
+pub fn error(error: &Error) -> Object {
+    let (kind, span) = match error {
+        Error::Parser(error) => ("parse", Some(error.span)),
+        Error::Evaluator(error) => ("evaluation", error.span),
+    };
+    let object = Object::new()
+        .string("kind", kind)
+        .string("code", error.code())
+        .string("message", &error.to_string());
+    match span {
+        Some(span) => object.number("start", span.start).number("end", span.end),
+        None => object,
+    }
+}
+
+/// `text` quoted, escaping quotes, backslashes and control characters.
+pub fn string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            character if character.is_control() => {
+                write!(quoted, "\\u{:04x}", character as u32).unwrap()
+            }
+            character => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A JSON value read from a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// The members in order, the last of a repeated key winning in `get`.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// The member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().rev().find(|(name, _)| name == key),
+            _ => None,
+        }
+        .map(|(_, value)| value)
+    }
+}
+
+impl fmt::Display for Json {
+    /// Writes the value back as JSON.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(text) => write!(f, "{}", string(text)),
+            Json::Array(values) => {
+                let values: Vec<String> = values.iter().map(Json::to_string).collect();
+                write!(f, "{}", array(values))
+            }
+            Json::Object(members) => {
+                let object = members.iter().fold(Object::new(), |object, (key, value)| {
+                    object.raw(key, &value.to_string())
+                });
+                write!(f, "{}", object.finish())
+            }
+        }
+    }
+}
+
+/// Bound on the nesting of arrays and objects read.
+const MAX_DEPTH: usize = 64;
+
+/// The value of `text`, or the error and the byte offset where reading it failed.
+pub fn parse(text: &str) -> Result<Json, (String, usize)> {
+    let mut reader = Reader { text, position: 0 };
+    let value = reader.value(0)?;
+    reader.skip_whitespace();
+    if reader.position < text.len() {
+        return Err(reader.unexpected());
+    }
+    Ok(value)
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn unexpected(&self) -> (String, usize) {
+        match self.rest().chars().next() {
+            Some(character) => (format!("Unexpected '{}'", character), self.position),
+            None => (String::from("Unexpected end of input"), self.position),
+        }
+    }
+
+    /// Consumes `token` if the input continues with it.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.position += token.len();
+        }
+        found
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, (String, usize)> {
+        if depth > MAX_DEPTH {
+            return Err((String::from("Too deeply nested"), self.position));
+        }
+        self.skip_whitespace();
+        if self.eat("null") {
+            return Ok(Json::Null);
+        } else if self.eat("true") {
+            return Ok(Json::Bool(true));
+        } else if self.eat("false") {
+            return Ok(Json::Bool(false));
+        } else if self.rest().starts_with('"') {
+            return self.string().map(Json::String);
+        } else if self.eat("[") {
+            let mut values = vec![];
+            if self.eat("]") {
+                return Ok(Json::Array(values));
+            }
+            loop {
+                values.push(self.value(depth + 1)?);
+                if self.eat("]") {
+                    return Ok(Json::Array(values));
+                } else if !self.eat(",") {
+                    return Err(self.unexpected());
+                }
+            }
+        } else if self.eat("{") {
+            let mut members = vec![];
+            if self.eat("}") {
+                return Ok(Json::Object(members));
+            }
+            loop {
+                self.skip_whitespace();
+                if !self.rest().starts_with('"') {
+                    return Err(self.unexpected());
+                }
+                let key = self.string()?;
+                if !self.eat(":") {
+                    return Err(self.unexpected());
+                }
+                members.push((key, self.value(depth + 1)?));
+                if self.eat("}") {
+                    return Ok(Json::Object(members));
+                } else if !self.eat(",") {
+                    return Err(self.unexpected());
+                }
+            }
+        }
+        let length = self
+            .rest()
+            .find(|character: char| !matches!(character, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(self.rest().len());
+        match self.rest()[..length].parse() {
+            Ok(number) if length > 0 => {
+                self.position += length;
+                Ok(Json::Number(number))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// The string starting at the current quote.
+    fn string(&mut self) -> Result<String, (String, usize)> {
+        let start = self.position;
+        let mut text = String::new();
+        let mut characters = self.rest()[1..].char_indices();
+        while let Some((offset, character)) = characters.next() {
+            match character {
+                '"' => {
+                    self.position += offset + 2;
+                    return Ok(text);
+                }
+                '\\' => {
+                    let escaped = match characters.next().map(|(_, escaped)| escaped) {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let digits: String = characters
+                                .by_ref()
+                                .take(4)
+                                .map(|(_, digit)| digit)
+                                .collect();
+                            // Surrogate pairs, for characters outside of the first plane,
+                            // are not read.
+                            let code = u32::from_str_radix(&digits, 16).ok();
+                            code.and_then(char::from_u32)
+                                .ok_or((String::from("Invalid escape"), start + offset + 1))?
+                        }
+                        _ => return Err((String::from("Invalid escape"), start + offset + 1)),
+                    };
+                    text.push(escaped);
+                }
+                character if character.is_control() => {
+                    return Err((
+                        String::from("Unescaped control character"),
+                        start + offset + 1,
+                    ))
+                }
+                character => text.push(character),
+            }
+        }
+        Err((String::from("Unclosed string"), start))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusculator::Context;
+
+    #[test]
+    fn write_objects() {
+        let error = Context::new().eval("1 + \"a").unwrap_err();
+        assert_eq!(
+            Object::new()
+                .string("input", "1 + \"a\u{1}\n")
+                .object("error", super::error(&error))
+                .finish(),
+            "{\"input\":\"1 + \\\"a\\u0001\\n\",\"error\":{\"kind\":\"parse\",\"code\":\"E0001\",\"message\":\"Unexpected character at position 4\",\"start\":4,\"end\":6}}"
+        );
+        assert_eq!(Object::new().finish(), "{}");
+    }
+
+    #[test]
+    fn read_values() {
+        assert_eq!(
+            parse(" {\"a\": [1, -2.5e1, true, null], \"b\": \"\\\"x\\u00e9\\n\"} "),
+            Ok(Json::Object(vec![
+                (
+                    String::from("a"),
+                    Json::Array(vec![
+                        Json::Number(1.0),
+                        Json::Number(-25.0),
+                        Json::Bool(true),
+                        Json::Null
+                    ])
+                ),
+                (String::from("b"), Json::String(String::from("\"x\u{e9}\n")))
+            ]))
+        );
+        let value = parse("{\"a\": 1, \"a\": {}}").unwrap();
+        assert_eq!(value.get("a"), Some(&Json::Object(vec![])));
+        assert_eq!(parse("[1,]"), Err((String::from("Unexpected ']'"), 3)));
+        assert_eq!(parse("{\"a\" 1}"), Err((String::from("Unexpected '1'"), 5)));
+        assert_eq!(parse("\"ab"), Err((String::from("Unclosed string"), 0)));
+        assert_eq!(parse("1 2"), Err((String::from("Unexpected '2'"), 2)));
+        assert!(parse(&"[".repeat(100)).is_err());
+        let text = "{\"id\":[1,\"a\",null,false]}";
+        assert_eq!(parse(text).unwrap().to_string(), text);
+    }
+}
@@ -0,0 +1,446 @@
+//! `rusculator lsp`, a language server for scripts over standard input and output, with
+//! diagnostics of the statements which fail, the values of subexpressions on hover, the
+//! completion of names and the definitions of variables and functions.
+//!
+//! Documents are synchronized whole, and positions are in UTF-16 code units as the
+//! protocol defaults to.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use rusculator::{Context, Error, Expr, ExprKind, Parser, Span, TokenClass};
+
+use crate::ast;
+use crate::json::{self, Json, Object};
+use crate::script;
+
+/// The error code of requests for methods the server does not have.
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// Serves the messages of `input` until `exit`, starting each document from `context`.
+pub fn run(mut input: impl BufRead, mut output: impl Write, context: &Context) -> io::Result<()> {
+    let mut documents: HashMap<String, String> = HashMap::new();
+    while let Some(message) = read(&mut input)? {
+        let Ok(message) = json::parse(&message) else {
+            continue;
+        };
+        let method = match message.get("method") {
+            Some(Json::String(method)) => method.as_str(),
+            _ => continue,
+        };
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let document = params.get("textDocument");
+        let uri = match document.and_then(|document| document.get("uri")) {
+            Some(Json::String(uri)) => uri.clone(),
+            _ => String::new(),
+        };
+        let offset = |source: &str| {
+            let position = params.get("position")?;
+            match (position.get("line"), position.get("character")) {
+                (Some(Json::Number(line)), Some(Json::Number(character))) => {
+                    Some(offset(source, *line as usize, *character as usize))
+                }
+                _ => None,
+            }
+        };
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => Some(String::from("null")),
+            "exit" => return Ok(()),
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                // The whole text is in the document when opened, in the last change after.
+                let change = match params.get("contentChanges") {
+                    Some(Json::Array(changes)) => changes.last(),
+                    _ => None,
+                };
+                let text = match change.or(document).and_then(|holder| holder.get("text")) {
+                    Some(Json::String(text)) => Some(text.clone()),
+                    _ => None,
+                };
+                if let Some(text) = text {
+                    let diagnostics = diagnostics(&text, context);
+                    documents.insert(uri.clone(), text);
+                    publish(&mut output, &uri, diagnostics)?;
+                }
+                None
+            }
+            "textDocument/didClose" => {
+                documents.remove(&uri);
+                publish(&mut output, &uri, String::from("[]"))?;
+                None
+            }
+            "textDocument/hover" => Some(
+                documents
+                    .get(&uri)
+                    .and_then(|source| hover(source, offset(source)?, context))
+                    .unwrap_or_else(|| String::from("null")),
+            ),
+            "textDocument/completion" => Some(
+                documents
+                    .get(&uri)
+                    .and_then(|source| Some(completions(source, offset(source)?, context)))
+                    .unwrap_or_else(|| String::from("[]")),
+            ),
+            "textDocument/definition" => Some(
+                documents
+                    .get(&uri)
+                    .and_then(|source| {
+                        let span = definition(source, offset(source)?)?;
+                        let location = Object::new()
+                            .string("uri", &uri)
+                            .object("range", range(source, span));
+                        Some(location.finish())
+                    })
+                    .unwrap_or_else(|| String::from("null")),
+            ),
+            _ => None,
+        };
+        // Notifications, without ids, get no response.
+        let Some(id) = message.get("id") else {
+            continue;
+        };
+        let response = Object::new()
+            .string("jsonrpc", "2.0")
+            .raw("id", &id.to_string());
+        let response = match result {
+            Some(result) => response.raw("result", &result),
+            None => {
+                let error = Object::new()
+                    .raw("code", &METHOD_NOT_FOUND.to_string())
+                    .string("message", &format!("Unknown method '{}'", method));
+                response.object("error", error)
+            }
+        };
+        write(&mut output, &response.finish())?;
+    }
+    Ok(())
+}
+
+/// The next message, after its headers, or `None` at the end of the input.
+fn read(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing the Content-Length of a message",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn write(output: &mut impl Write, message: &str) -> io::Result<()> {
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        message.len(),
+        message
+    )?;
+    output.flush()
+}
+
+fn publish(output: &mut impl Write, uri: &str, diagnostics: String) -> io::Result<()> {
+    let params = Object::new()
+        .string("uri", uri)
+        .raw("diagnostics", &diagnostics);
+    let notification = Object::new()
+        .string("jsonrpc", "2.0")
+        .string("method", "textDocument/publishDiagnostics")
+        .object("params", params);
+    write(output, &notification.finish())
+}
+
+fn capabilities() -> String {
+    let capabilities = Object::new()
+        // Whole documents on each change.
+        .number("textDocumentSync", 1)
+        .raw("hoverProvider", "true")
+        .object("completionProvider", Object::new())
+        .raw("definitionProvider", "true");
+    let info = Object::new()
+        .string("name", "rusculator")
+        .string("version", env!("CARGO_PKG_VERSION"));
+    Object::new()
+        .object("capabilities", capabilities)
+        .object("serverInfo", info)
+        .finish()
+}
+
+/// The byte offset of the position in `source`, clamped to its line.
+fn offset(source: &str, line: usize, character: usize) -> usize {
+    let start = source
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum::<usize>();
+    let text = source[start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (index, next) in text.char_indices() {
+        if units >= character {
+            return start + index;
+        }
+        units += next.len_utf16();
+    }
+    start + text.len()
+}
+
+fn position(source: &str, offset: usize) -> Object {
+    let offset = offset.min(source.len());
+    let line_start = source[..offset]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    Object::new()
+        .number("line", source[..offset].matches('\n').count())
+        .number(
+            "character",
+            source[line_start..offset].encode_utf16().count(),
+        )
+}
+
+fn range(source: &str, span: Span) -> Object {
+    Object::new()
+        .object("start", position(source, span.start))
+        .object("end", position(source, span.end))
+}
+
+/// Runs `statement`, at `offset` in its script, as `rusculator run` does.
+fn execute(statement: &str, offset: usize, context: &mut Context) -> Result<(), Error> {
+    let result = match script::define(statement, context, &HashMap::new()) {
+        Some(result) => result.map(|_| ()),
+        None => Parser::new(statement)
+            .parse()
+            .map_err(Error::from)
+            .and_then(|expr| Ok(context.evaluate(&expr).map(|_| ())?)),
+    };
+    result.map_err(|error| script::shift(error, offset))
+}
+
+/// The diagnostics of the statements of `source` which fail, each run in the variables of
+/// those before it which did not.
+fn diagnostics(source: &str, context: &Context) -> String {
+    let mut context = context.clone();
+    let diagnostics = script::statements(source)
+        .into_iter()
+        .filter_map(|(offset, statement)| {
+            let error = execute(statement, offset, &mut context).err()?;
+            let span = match &error {
+                Error::Parser(error) => error.span,
+                Error::Evaluator(error) => error.span.unwrap_or(Span {
+                    start: offset,
+                    end: offset + statement.len(),
+                }),
+            };
+            let diagnostic = Object::new()
+                .object("range", range(source, span))
+                // Errors, by the severities of the protocol.
+                .number("severity", 1)
+                .string("code", error.code())
+                .string("source", "rusculator")
+                .string("message", &error.to_string());
+            Some(diagnostic.finish())
+        });
+    json::array(diagnostics)
+}
+
+/// The smallest subexpression of `expr` whose span holds `offset`.
+fn innermost(expr: &Expr, offset: usize) -> Option<&Expr> {
+    if offset < expr.span.start || offset > expr.span.end {
+        return None;
+    }
+    let children = ast::children(expr);
+    let inner = children
+        .into_iter()
+        .find_map(|child| innermost(child, offset));
+    Some(inner.unwrap_or(expr))
+}
+
+/// The hover of `offset`, the value of the innermost subexpression there in the
+/// variables of the statements before it.
+fn hover(source: &str, offset: usize, context: &Context) -> Option<String> {
+    let mut context = context.clone();
+    for (start, statement) in script::statements(source) {
+        if offset > start + statement.len() {
+            // The errors of statements are the diagnostics, not hovers.
+            let _ = execute(statement, start, &mut context);
+            continue;
+        }
+        let formula = match script::assignment(statement) {
+            // The parameters of functions have no values until called.
+            Some(position) if statement[..position].contains('(') => return None,
+            Some(position) => position + 1,
+            None => 0,
+        };
+        let relative = offset.checked_sub(start + formula)?;
+        let expr = Parser::new(&statement[formula..]).parse().ok()?;
+        let expr = innermost(&expr, relative)?;
+        let value = context.evaluate(expr).ok()?;
+        let first = start + formula;
+        let span = Span {
+            start: first + expr.span.start,
+            end: first + expr.span.end,
+        };
+        let text = format!(
+            "{} = {}",
+            &source[span.start..span.end],
+            context.format(&value)
+        );
+        let contents = Object::new()
+            .string("kind", "plaintext")
+            .string("value", &text);
+        let hover = Object::new()
+            .object("contents", contents)
+            .object("range", range(source, span));
+        return Some(hover.finish());
+    }
+    None
+}
+
+/// The start and end of the identifier around `offset`.
+fn identifier(source: &str, offset: usize) -> (usize, usize) {
+    let is_name = |character: char| character.is_alphanumeric() || character == '_';
+    let start = source[..offset]
+        .rfind(|character| !is_name(character))
+        .map_or(0, |index| index + 1);
+    let end = source[offset..]
+        .find(|character| !is_name(character))
+        .map_or(source.len(), |index| offset + index);
+    (start, end)
+}
+
+/// The suggestions of `Context::suggest` at `offset`, among the names of `context` and
+/// those the script defines.
+fn completions(source: &str, offset: usize, context: &Context) -> String {
+    let mut context = context.clone();
+    for (start, statement) in script::statements(source) {
+        let _ = execute(statement, start, &mut context);
+    }
+    let items = context
+        .suggest(source, offset)
+        .into_iter()
+        .map(|suggestion| {
+            // Completion item kinds, by the numbers of the protocol.
+            let kind = match suggestion.kind {
+                TokenClass::Function => 3,
+                TokenClass::Unit => 11,
+                TokenClass::Punctuation => 24,
+                _ => 6,
+            };
+            Object::new()
+                .string("label", &suggestion.text)
+                .number("kind", kind)
+                .finish()
+        });
+    json::array(items)
+}
+
+/// The name each statement of `source` defines, with its span.
+fn definitions(source: &str) -> Vec<(String, Span)> {
+    script::statements(source)
+        .into_iter()
+        .filter_map(|(start, statement)| {
+            let position = script::assignment(statement)?;
+            let target = Parser::new(&statement[..position]).parse().ok()?;
+            let (ExprKind::Variable(name) | ExprKind::Call(name, _)) = &target.kind else {
+                return None;
+            };
+            let span = Span {
+                start: start + target.span.start,
+                end: start + target.span.start + name.len(),
+            };
+            Some((name.clone(), span))
+        })
+        .collect()
+}
+
+/// Where the name at `offset` is defined, last before it, or first after it for
+/// functions calling those defined later.
+fn definition(source: &str, offset: usize) -> Option<Span> {
+    let (start, end) = identifier(source, offset);
+    let name = &source[start..end];
+    let definitions = definitions(source);
+    let named = || definitions.iter().filter(|(defined, _)| defined == name);
+    named()
+        .rfind(|(_, span)| span.start <= start)
+        .or_else(|| named().next())
+        .map(|(_, span)| *span)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn analyze_scripts() {
+        let source = "r = 2\narea(r) = pi * r^2\narea(r) + x\nround(area(r))";
+        let context = Context::new();
+        assert_eq!(
+            diagnostics(source, &context),
+            "[{\"range\":{\"start\":{\"line\":2,\"character\":10},\"end\":{\"line\":2,\"character\":11}},\"severity\":1,\"code\":\"E0201\",\"source\":\"rusculator\",\"message\":\"Unknown variable 'x'\"}]"
+        );
+        let hovered = hover(source, offset(source, 3, 8), &context).unwrap();
+        assert!(hovered.contains("\"value\":\"area(r) = 12.566370614359172\""));
+        assert_eq!(hover(source, offset(source, 1, 16), &context), None);
+        let span = definition(source, offset(source, 3, 7)).unwrap();
+        assert_eq!(&source[span.start..span.end], "area");
+        assert_eq!(span.start, 6);
+        assert!(completions(source, offset(source, 3, 3), &context).contains("\"round\""));
+        // Positions count UTF-16 units.
+        assert_eq!(offset("é = 1\n𝑥 = 2", 1, 2), 11);
+        assert_eq!(
+            position("é = 1\n𝑥 = 2", 11).finish(),
+            "{\"line\":1,\"character\":2}"
+        );
+    }
+
+    #[test]
+    fn serve_messages() {
+        let messages = [
+            "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\",\"params\":{}}",
+            "{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/didOpen\",\"params\":{\"textDocument\":{\"uri\":\"file:///a.calc\",\"text\":\"x = 2\\nx * 3\"}}}",
+            "{\"jsonrpc\":\"2.0\",\"id\":\"h\",\"method\":\"textDocument/hover\",\"params\":{\"textDocument\":{\"uri\":\"file:///a.calc\"},\"position\":{\"line\":1,\"character\":0}}}",
+            "{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"workspace/symbol\",\"params\":{}}",
+            "{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}",
+        ];
+        let input: String = messages
+            .iter()
+            .map(|message| format!("Content-Length: {}\r\n\r\n{}", message.len(), message))
+            .collect();
+        let mut output = vec![];
+        run(input.as_bytes(), &mut output, &Context::new()).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let mut input = output.as_bytes();
+        let mut responses = vec![];
+        while let Some(message) = read(&mut input).unwrap() {
+            responses.push(message);
+        }
+        assert_eq!(responses.len(), 4);
+        assert!(
+            responses[0].starts_with("{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"capabilities\":")
+        );
+        assert_eq!(
+            responses[1],
+            "{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{\"uri\":\"file:///a.calc\",\"diagnostics\":[]}}"
+        );
+        assert!(responses[2].contains("\"id\":\"h\""));
+        assert!(responses[2].contains("\"value\":\"x = 2\""));
+        assert!(responses[3].contains("\"code\":-32601"));
+    }
+}
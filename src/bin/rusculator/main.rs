@@ -0,0 +1,430 @@
+//! The `rusculator` desktop calculator, a read-eval-print loop over the library.
+
+mod args;
+mod ast;
+mod completions;
+mod config;
+mod converter;
+#[cfg(target_os = "linux")]
+mod editor;
+#[cfg(target_os = "linux")]
+mod highlight;
+mod json;
+mod lsp;
+mod plot;
+mod programmer;
+mod repl;
+mod script;
+mod serve;
+mod session;
+mod settings;
+mod status;
+mod timing;
+mod tokens;
+#[cfg(target_os = "linux")]
+mod tui;
+mod watch;
+
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+
+use args::Command;
+use repl::{Plain, Repl};
+use rusculator::{Context, Diagnostic};
+use status::Status;
+
+fn main() -> io::Result<ExitCode> {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("Error: {}\n\n{}", message, args::usage());
+            return Ok(Status::UsageError.into());
+        }
+    };
+    let output = args.output;
+    let mut context = Context::new();
+    let mut config = config::Config::default();
+    if let Some(path) = config::path() {
+        if let Ok(source) = std::fs::read_to_string(&path) {
+            let errors;
+            (config, errors) = config::load(&source, &mut context);
+            for error in errors {
+                eprintln!("Warning: {}, {}", path.display(), error);
+            }
+        }
+    }
+    // The arguments override the configuration.
+    output.configure(&mut context);
+    // Colors are for terminals, unless the user opts out by convention.
+    let colors = std::env::var_os("NO_COLOR").is_none();
+    let error_colors = colors && io::stderr().is_terminal();
+    match args.command {
+        Command::Interactive => {}
+        Command::Evaluate(formulas) => {
+            let mut repl = configured(context, &config);
+            for formula in formulas {
+                let object = json::Object::new().string("input", &formula);
+                match repl.record(&formula) {
+                    Ok((result, kind)) if output.json => {
+                        let object = object.string("result", &result).string("type", kind);
+                        println!("{}", object.finish())
+                    }
+                    Ok((result, _)) => println!("{}", result),
+                    Err(error) => {
+                        if output.json {
+                            println!("{}", object.object("error", json::error(&error)).finish());
+                        } else {
+                            let diagnostic = output.error_format.report(
+                                &Diagnostic::from(&error),
+                                &formula,
+                                "<expr>",
+                                1,
+                                error_colors,
+                            );
+                            eprintln!("{}", diagnostic);
+                        }
+                        return Ok(Status::of(&error).into());
+                    }
+                }
+            }
+            return Ok(Status::Success.into());
+        }
+        Command::Ast(formulas) => {
+            for formula in formulas {
+                match rusculator::Parser::new(&formula).parse() {
+                    Ok(expr) => println!("{}", ast::tree(&expr)),
+                    Err(error) => {
+                        let error = error.into();
+                        let diagnostic = output.error_format.report(
+                            &Diagnostic::from(&error),
+                            &formula,
+                            "<expr>",
+                            1,
+                            error_colors,
+                        );
+                        eprintln!("{}", diagnostic);
+                        return Ok(Status::of(&error).into());
+                    }
+                }
+            }
+            return Ok(Status::Success.into());
+        }
+        Command::Tokens(formulas) => {
+            for formula in formulas {
+                let (list, error) = tokens::list(&formula);
+                if !list.is_empty() {
+                    println!("{}", list);
+                }
+                if let Some(error) = error {
+                    let span = error.span;
+                    let error = rusculator::Error::Parser(rusculator::ParserError {
+                        kind: rusculator::ParserErrorKind::Lexer(error),
+                        span,
+                    });
+                    let diagnostic = output.error_format.report(
+                        &Diagnostic::from(&error),
+                        &formula,
+                        "<expr>",
+                        1,
+                        error_colors,
+                    );
+                    eprintln!("{}", diagnostic);
+                    return Ok(Status::of(&error).into());
+                }
+            }
+            return Ok(Status::Success.into());
+        }
+        Command::Run {
+            path,
+            quiet,
+            stream: true,
+        } => {
+            let status = if path == "-" {
+                stream(
+                    &path,
+                    io::stdin().lock(),
+                    &mut context,
+                    &output,
+                    quiet,
+                    error_colors,
+                )?
+            } else {
+                match std::fs::File::open(&path) {
+                    Ok(file) => {
+                        let reader = io::BufReader::new(file);
+                        stream(&path, reader, &mut context, &output, quiet, error_colors)?
+                    }
+                    Err(error) => {
+                        eprintln!("Error: Cannot read '{}': {}", path, error);
+                        Status::UsageError
+                    }
+                }
+            };
+            return Ok(status.into());
+        }
+        Command::Run { path, quiet, .. } => {
+            let read = if path == "-" {
+                io::read_to_string(io::stdin())
+            } else {
+                std::fs::read_to_string(&path)
+            };
+            let source = match read {
+                Ok(source) => source,
+                Err(error) => {
+                    eprintln!("Error: Cannot read '{}': {}", path, error);
+                    return Ok(Status::UsageError.into());
+                }
+            };
+            return Ok(run(&path, &source, &mut context, &output, quiet, error_colors).into());
+        }
+        Command::Watch(path) => {
+            let mut watcher = watch::Watcher::new(&path);
+            let clear = io::stdout().is_terminal();
+            loop {
+                match watcher.poll() {
+                    Some(Ok(source)) => {
+                        // Each run starts from the configuration, not the previous run.
+                        let mut context = context.clone();
+                        if clear {
+                            print!("\x1b[2J\x1b[H");
+                        }
+                        run(&path, &source, &mut context, &output, false, error_colors);
+                    }
+                    Some(Err(error)) => eprintln!("Error: Cannot read '{}': {}", path, error),
+                    None => {}
+                }
+                std::thread::sleep(watch::INTERVAL);
+            }
+        }
+        Command::Serve { host, port } => {
+            let listener = match std::net::TcpListener::bind((host.as_str(), port)) {
+                Ok(listener) => listener,
+                Err(error) => {
+                    eprintln!("Error: Cannot listen on {}:{}: {}", host, port, error);
+                    return Ok(Status::UsageError.into());
+                }
+            };
+            eprintln!("Serving on http://{}:{}/eval", host, port);
+            serve::serve(listener, &context)?;
+            return Ok(Status::Success.into());
+        }
+        Command::Tui => {
+            #[cfg(target_os = "linux")]
+            if io::stdin().is_terminal() && io::stdout().is_terminal() {
+                let mut repl = configured(context, &config);
+                tui::run(&mut repl, colors)?;
+                return Ok(Status::Success.into());
+            }
+            eprintln!("Error: tui needs a terminal");
+            return Ok(Status::UsageError.into());
+        }
+        Command::Lsp => {
+            lsp::run(io::stdin().lock(), io::stdout().lock(), &context)?;
+            return Ok(Status::Success.into());
+        }
+        Command::Convert(words) => {
+            return Ok(convert(&words, &context, &output, error_colors)?.into())
+        }
+        Command::Explain(code) => {
+            let Some(explanation) = rusculator::explain(&code) else {
+                eprintln!("Error: Unknown error code '{}'", code);
+                return Ok(Status::UsageError.into());
+            };
+            println!("{}", explanation);
+            return Ok(Status::Success.into());
+        }
+        Command::Completions(shell) => {
+            print!("{}", completions::script(&shell));
+            return Ok(Status::Success.into());
+        }
+        Command::Help => {
+            println!("{}", args::usage());
+            return Ok(Status::Success.into());
+        }
+    }
+    let mut repl = configured(context, &config);
+    repl.set_json(output.json);
+    repl.set_colors(error_colors);
+    repl.set_error_format(output.error_format);
+    let mut stdout = io::stdout();
+    // Formulas piped in, as by `cat formulas.txt | rusculator`, are filtered without prompts.
+    if !io::stdin().is_terminal() {
+        let status = repl.filter(io::stdin().lock(), &mut stdout, &mut io::stderr())?;
+        return Ok(status.into());
+    }
+    #[cfg(target_os = "linux")]
+    if stdout.is_terminal() {
+        let history = editor::History::load(editor::Editor::history_path());
+        repl.run(&mut editor::Editor::new(history, colors), &mut stdout)?;
+        return Ok(Status::Success.into());
+    }
+    repl.run(&mut Plain(io::stdin().lock()), &mut stdout)?;
+    Ok(Status::Success.into())
+}
+
+/// Runs the script `source` read from `path`, printing the values of its formulas unless
+/// `quiet` and reporting its first error.
+fn run(
+    path: &str,
+    source: &str,
+    context: &mut Context,
+    output: &args::Output,
+    quiet: bool,
+    colors: bool,
+) -> Status {
+    let print = |result: String, kind| {
+        if !quiet {
+            print_result(output, &result, kind);
+        }
+    };
+    if let Err(error) = script::run(source, context, print) {
+        let location = (error.line, error.column);
+        report_script_error(&error.error, path, source, 1, location, output, colors);
+        return Status::of(&error.error);
+    }
+    Status::Success
+}
+
+/// Runs the script read from `reader` a line at a time, printing the values of its
+/// formulas unless `quiet` and reporting its errors. The status is that of the first
+/// error.
+fn stream(
+    path: &str,
+    reader: impl io::BufRead,
+    context: &mut Context,
+    output: &args::Output,
+    quiet: bool,
+    colors: bool,
+) -> io::Result<Status> {
+    let mut status = Status::Success;
+    let mut statements = context.stream(reader);
+    while let Some(evaluated) = statements.next() {
+        let evaluated = evaluated?;
+        match &evaluated.result {
+            Ok(value) if evaluated.assigned.is_none() && !quiet => {
+                let result = statements.context().format(value);
+                print_result(output, &result, value.type_name());
+            }
+            Ok(_) => {}
+            Err(error) => {
+                let start = match error {
+                    rusculator::Error::Parser(error) => error.span.start,
+                    rusculator::Error::Evaluator(error) => {
+                        error.span.unwrap_or(evaluated.span).start
+                    }
+                };
+                let column = evaluated.text[..start].chars().count() + 1;
+                let location = (evaluated.line, column);
+                let text = &evaluated.text;
+                report_script_error(error, path, text, evaluated.line, location, output, colors);
+                if status == Status::Success {
+                    status = Status::of(error);
+                }
+            }
+        }
+    }
+    Ok(status)
+}
+
+/// Prints the value of a formula of a script, as a JSON object with `--json`.
+fn print_result(output: &args::Output, result: &str, kind: &str) {
+    if output.json {
+        let object = json::Object::new()
+            .string("result", result)
+            .string("type", kind);
+        println!("{}", object.finish())
+    } else {
+        println!("{}", result)
+    }
+}
+
+/// Reports the error of a script read from `path`, in `source` starting on line
+/// `first_line`, at the line and column of `location`.
+fn report_script_error(
+    error: &rusculator::Error,
+    path: &str,
+    source: &str,
+    first_line: usize,
+    (line, column): (usize, usize),
+    output: &args::Output,
+    colors: bool,
+) {
+    if output.json {
+        let object = json::error(error)
+            .string("file", path)
+            .number("line", line)
+            .number("column", column);
+        println!("{}", json::Object::new().object("error", object).finish());
+    } else {
+        let diagnostic = Diagnostic::from(error);
+        let report = output
+            .error_format
+            .report(&diagnostic, source, path, first_line, colors);
+        eprintln!("{}", report);
+    }
+}
+
+/// Converts the quantity of `words`, or those read if it is empty.
+fn convert(
+    words: &[String],
+    context: &Context,
+    output: &args::Output,
+    colors: bool,
+) -> io::Result<Status> {
+    if words.is_empty() {
+        let mut stdout = io::stdout();
+        if !io::stdin().is_terminal() {
+            return converter::run(&mut Plain(io::stdin().lock()), &mut stdout, "", context);
+        }
+        #[cfg(target_os = "linux")]
+        if stdout.is_terminal() {
+            let mut editor = editor::Editor::new(editor::History::load(None), colors);
+            return converter::run(&mut editor, &mut stdout, "convert> ", context);
+        }
+        let mut input = Plain(io::stdin().lock());
+        return converter::run(&mut input, &mut stdout, "convert> ", context);
+    }
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let formula = match converter::formula(&words) {
+        Ok(formula) => formula,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            return Ok(Status::UsageError);
+        }
+    };
+    let object = json::Object::new().string("input", &words.join(" "));
+    match converter::convert(&formula, context) {
+        Ok(value) if output.json => {
+            let object = object
+                .string("result", &context.format(&value))
+                .string("type", value.type_name());
+            println!("{}", object.finish());
+        }
+        Ok(value) => println!("{}", context.format(&value)),
+        Err(error) => {
+            if output.json {
+                println!("{}", object.object("error", json::error(&error)).finish());
+            } else {
+                let diagnostic = Diagnostic::from(&error);
+                eprintln!(
+                    "{}",
+                    output
+                        .error_format
+                        .report(&diagnostic, &formula, "<convert>", 1, colors)
+                );
+            }
+            return Ok(Status::of(&error));
+        }
+    }
+    Ok(Status::Success)
+}
+
+/// A REPL with the modes and prompt of the configuration.
+fn configured(context: Context, config: &config::Config) -> Repl {
+    let mut repl = Repl::with_context(context);
+    repl.set_modes(config.modes);
+    if let Some(prompt) = &config.prompt {
+        repl.set_prompt(prompt);
+    }
+    repl
+}
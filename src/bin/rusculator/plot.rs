@@ -0,0 +1,281 @@
+//! The `:plot` command, drawing formulas over a range with braille characters.
+
+use std::collections::HashMap;
+
+use rusculator::{CompileOptions, Compiled, Context, Expr, ExprKind, Parser, Value};
+
+/// Characters across the plot, each two dots wide.
+const WIDTH: usize = 64;
+
+/// Lines of the plot, each four dots high.
+const HEIGHT: usize = 16;
+
+/// Colors of the formulas in order, reused past the last.
+const COLORS: &[&str] = &["\x1b[34m", "\x1b[31m", "\x1b[32m", "\x1b[33m", "\x1b[35m"];
+const RESET: &str = "\x1b[0m";
+
+/// User-defined functions with bodies of at most this many nodes are inlined.
+const INLINE_SIZE: usize = 64;
+
+/// The bits of the dots of a braille character, by column then row.
+const DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Dots drawn on a grid of braille characters, with the formula drawing each character
+/// last.
+struct Canvas {
+    cells: Vec<u8>,
+    owners: Vec<usize>,
+}
+
+impl Canvas {
+    fn new() -> Canvas {
+        Canvas {
+            cells: vec![0; WIDTH * HEIGHT],
+            owners: vec![0; WIDTH * HEIGHT],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, owner: usize) {
+        let cell = y / 4 * WIDTH + x / 2;
+        self.cells[cell] |= DOTS[x % 2][y % 4];
+        self.owners[cell] = owner;
+    }
+
+    fn line(&self, row: usize, colors: bool) -> String {
+        let mut line = String::new();
+        for cell in row * WIDTH..(row + 1) * WIDTH {
+            let character = char::from_u32(0x2800 + u32::from(self.cells[cell])).unwrap();
+            if colors && self.cells[cell] != 0 {
+                let color = COLORS[self.owners[cell] % COLORS.len()];
+                line.push_str(&format!("{}{}{}", color, character, RESET));
+            } else {
+                line.push(character);
+            }
+        }
+        line
+    }
+}
+
+/// Splits `text` at the commas outside of parentheses and brackets.
+fn split_arguments(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (index, character) in text.char_indices() {
+        match character {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(text[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts
+}
+
+fn bound(text: &str, context: &Context) -> Result<f64, String> {
+    let expr = Parser::new(text)
+        .parse()
+        .map_err(|error| error.to_string())?;
+    match context.evaluate(&expr).map_err(|error| error.to_string())? {
+        Value::Number(n) if n.is_finite() => Ok(n),
+        value => Err(format!(
+            "Expected a number as a bound, found {}",
+            value.type_name()
+        )),
+    }
+}
+
+/// The values of a compiled formula at `xs`, with its other inputs the variables of
+/// `context`.
+fn batch(
+    compiled: &Compiled,
+    variable: &str,
+    xs: &[f64],
+    context: &Context,
+) -> Result<Vec<f64>, String> {
+    let mut constants = vec![];
+    for input in compiled.program().inputs() {
+        if input == variable {
+            continue;
+        }
+        match context.variable(input) {
+            Some(Value::Number(n)) => constants.push((input.as_str(), vec![*n; xs.len()])),
+            _ => return Err(format!("Unknown variable '{}'", input)),
+        }
+    }
+    let mut columns: Vec<(&str, &[f64])> = vec![(variable, xs)];
+    columns.extend(
+        constants
+            .iter()
+            .map(|(name, values)| (*name, values.as_slice())),
+    );
+    compiled
+        .eval_batch(&columns)
+        .map_err(|error| error.to_string())
+}
+
+/// The values of a formula which cannot be compiled at `xs`, NaN where they are not
+/// numbers.
+fn walk(expr: &Expr, variable: &str, xs: &[f64], context: &Context) -> Vec<f64> {
+    xs.iter()
+        .map(|x| {
+            let value = Expr::new(ExprKind::Value(Value::Number(*x)), expr.span);
+            let expr = expr.substitute_all(&HashMap::from([(variable.to_string(), value)]));
+            match context.evaluate(&expr) {
+                Ok(Value::Number(y)) => y,
+                _ => f64::NAN,
+            }
+        })
+        .collect()
+}
+
+/// Plots the formulas of `sin(x), cos(x), x = -pi..pi` over the range of the variable,
+/// with the results of the REPL substituted and colors if `colors`. Formulas are
+/// compiled and evaluated in one batch where they can be.
+pub fn plot(
+    text: &str,
+    context: &Context,
+    results: &HashMap<String, Expr>,
+    colors: bool,
+) -> Result<String, String> {
+    let expected =
+        || String::from("Error: Expected formulas and a range, as ':plot sin(x), x = -pi..pi'");
+    let mut arguments = split_arguments(text);
+    let range = arguments
+        .pop()
+        .filter(|_| !arguments.is_empty())
+        .ok_or_else(expected)?;
+    let (variable, bounds) = range.split_once('=').ok_or_else(expected)?;
+    let (low, high) = bounds.split_once("..").ok_or_else(expected)?;
+    let variable = variable.trim();
+    let failed = |message: String| format!("Error: {}", message);
+    let (low, high) = (
+        bound(low, context).map_err(failed)?,
+        bound(high, context).map_err(failed)?,
+    );
+    if low >= high {
+        return Err(format!(
+            "Error: Expected a range from a lower to a higher bound, found {}..{}",
+            low, high
+        ));
+    }
+
+    let samples = WIDTH * 2;
+    let xs: Vec<f64> = (0..samples)
+        .map(|index| low + (high - low) * index as f64 / (samples - 1) as f64)
+        .collect();
+    let options = CompileOptions {
+        inline_size: Some(INLINE_SIZE),
+    };
+    let mut series = vec![];
+    for formula in &arguments {
+        let expr = Parser::new(formula)
+            .parse()
+            .map_err(|error| format!("Error: {}: {}", formula, error))?
+            .substitute_all(results);
+        let ys = match expr.compile_with(context, &options) {
+            Ok(program) => batch(&Compiled::from(program), variable, &xs, context)
+                .map_err(|message| format!("Error: {}: {}", formula, message))?,
+            Err(_) => walk(&expr, variable, &xs, context),
+        };
+        series.push(ys);
+    }
+
+    let finite = || series.iter().flatten().copied().filter(|y| y.is_finite());
+    let (Some(mut bottom), Some(mut top)) = (finite().reduce(f64::min), finite().reduce(f64::max))
+    else {
+        return Err(String::from("Error: No finite values over the range"));
+    };
+    if bottom == top {
+        bottom -= 1.0;
+        top += 1.0;
+    }
+    let rows = HEIGHT * 4;
+    let dot = |y: f64| ((top - y) / (top - bottom) * (rows - 1) as f64).round() as usize;
+    let mut canvas = Canvas::new();
+    for (owner, ys) in series.iter().enumerate() {
+        let mut previous: Option<usize> = None;
+        for (x, y) in ys.iter().enumerate() {
+            if !y.is_finite() {
+                previous = None;
+                continue;
+            }
+            let y = dot(*y);
+            let (from, to) = match previous {
+                Some(previous) if previous < y => (previous + 1, y),
+                Some(previous) if previous > y => (y, previous - 1),
+                _ => (y, y),
+            };
+            for y in from..=to {
+                canvas.set(x, y, owner);
+            }
+            previous = Some(y);
+        }
+    }
+
+    let (top, bottom) = (format!("{:.3}", top), format!("{:.3}", bottom));
+    let margin = top.len().max(bottom.len());
+    let mut lines = vec![];
+    for row in 0..HEIGHT {
+        let label = match row {
+            0 => &top,
+            row if row == HEIGHT - 1 => &bottom,
+            _ => "",
+        };
+        let tick = if label.is_empty() { '│' } else { '┤' };
+        lines.push(format!(
+            "{:>margin$} {}{}",
+            label,
+            tick,
+            canvas.line(row, colors)
+        ));
+    }
+    lines.push(format!("{:margin$} └{}", "", "─".repeat(WIDTH)));
+    let (low, high) = (format!("{:.3}", low), format!("{:.3}", high));
+    lines.push(format!(
+        "{:margin$}  {}{:>width$}",
+        "",
+        low,
+        high,
+        width = WIDTH.saturating_sub(low.len())
+    ));
+    if arguments.len() > 1 {
+        for (owner, formula) in arguments.iter().enumerate() {
+            let mark = if colors {
+                format!("{}⣿{}", COLORS[owner % COLORS.len()], RESET)
+            } else {
+                String::from("⣿")
+            };
+            lines.push(format!("{:margin$}  {} {}", "", mark, formula));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plot_formulas() {
+        let context = Context::new();
+        let results = HashMap::new();
+        let output = plot("x, x = 0..1", &context, &results, false).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), HEIGHT + 2);
+        assert!(lines[0].starts_with("1.000 ┤"));
+        assert!(lines[0].ends_with("⣀⠤⠒⠉"));
+        assert!(lines[HEIGHT - 1].starts_with("0.000 ┤⣀⠤⠒⠉⠀"));
+        assert!(lines[HEIGHT + 1].trim_start().starts_with("0.000"));
+        let output = plot("sin(x), max(x, 0), x = -pi..pi", &context, &results, true).unwrap();
+        assert!(output.contains("\x1b[31m"));
+        assert!(output.ends_with("max(x, 0)"));
+        assert_eq!(split_arguments("f(a, b), c"), ["f(a, b)", "c"]);
+        assert!(plot("x", &context, &results, false).is_err());
+        assert!(plot("x, x = 1..0", &context, &results, false).is_err());
+        assert!(plot("y, x = 0..1", &context, &results, false).is_err());
+    }
+}
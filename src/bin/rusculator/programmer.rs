@@ -0,0 +1,74 @@
+//! The programmer mode of the REPL, writing integer results in every radix along with
+//! their bits.
+
+/// Bits of negative numbers written without a word size.
+const DEFAULT_BITS: u32 = 64;
+
+/// The lines writing `number` in hexadecimal, decimal, octal and binary, then its bits in
+/// groups of four, or `None` if it is not an integer. Negative numbers are written in two's
+/// complement of `word_size` bits, or 64 without one, and the bits are padded to the word
+/// size or to whole bytes.
+pub fn write(number: f64, word_size: Option<u32>) -> Option<String> {
+    if number.fract() != 0.0 || number.abs() >= 2f64.powi(63) {
+        return None;
+    }
+    let n = number as i64;
+    let bits = match word_size {
+        Some(bits) => bits,
+        None if n < 0 => DEFAULT_BITS,
+        None => (64 - n.leading_zeros()).max(1).div_ceil(8) * 8,
+    };
+    let word = if bits >= 64 {
+        n as u64
+    } else {
+        n as u64 & ((1 << bits) - 1)
+    };
+    let binary = format!("{:01$b}", word, bits as usize);
+    let start = binary.len() % 4;
+    let mut groups: Vec<&str> = (start..binary.len())
+        .step_by(4)
+        .map(|index| &binary[index..index + 4])
+        .collect();
+    if start > 0 {
+        groups.insert(0, &binary[..start]);
+    }
+    Some(format!(
+        "hex 0x{:x}\ndec {}\noct 0o{:o}\nbin 0b{:b}\nbits {}",
+        word,
+        n,
+        word,
+        word,
+        groups.join(" ")
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_integers() {
+        assert_eq!(
+            write(255.0, None).as_deref(),
+            Some("hex 0xff\ndec 255\noct 0o377\nbin 0b11111111\nbits 1111 1111")
+        );
+        assert_eq!(
+            write(-1.0, Some(8)).as_deref(),
+            Some("hex 0xff\ndec -1\noct 0o377\nbin 0b11111111\nbits 1111 1111")
+        );
+        assert_eq!(
+            write(5.0, Some(10)).unwrap().lines().last(),
+            Some("bits 00 0000 0101")
+        );
+        assert_eq!(
+            write(0.0, None).unwrap().lines().last(),
+            Some("bits 0000 0000")
+        );
+        assert_eq!(
+            write(-2.0, None).unwrap().lines().next(),
+            Some("hex 0xfffffffffffffffe")
+        );
+        assert_eq!(write(1.5, None), None);
+        assert_eq!(write(f64::INFINITY, None), None);
+    }
+}
@@ -0,0 +1,885 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use rusculator::{
+    Context, Diagnostic, Error, EvaluatorError, EvaluatorErrorKind, Expr, ExprKind, Lexer, Parser,
+    Span, Token, Value,
+};
+
+use crate::args::ErrorFormat;
+use crate::ast;
+use crate::json;
+use crate::plot;
+use crate::programmer;
+use crate::script;
+use crate::session;
+use crate::settings::{self, Backend, Modes};
+use crate::status::Status;
+use crate::timing;
+use crate::tokens;
+
+/// The prompt unless the configuration sets another.
+const PROMPT: &str = "> ";
+
+const COMMANDS: &str =
+    ":set, :show, :history, :vars, :funcs, :clear, :clearall, :save, :load, :undo, :time, :plot, :ast and :tokens";
+
+/// Where the lines typed come from.
+pub trait Input {
+    /// The next line entered after `prompt`, or `None` at the end of the input. Inputs
+    /// completing identifiers get the names starting with a prefix from `complete`.
+    fn read_line(
+        &mut self,
+        prompt: &str,
+        output: &mut dyn Write,
+        complete: &dyn Fn(&str) -> Vec<String>,
+    ) -> io::Result<Option<String>>;
+}
+
+/// Lines read as they are, by pipes or terminals without line editing.
+pub struct Plain<R>(pub R);
+
+impl<R: BufRead> Input for Plain<R> {
+    fn read_line(
+        &mut self,
+        prompt: &str,
+        output: &mut dyn Write,
+        _: &dyn Fn(&str) -> Vec<String>,
+    ) -> io::Result<Option<String>> {
+        write!(output, "{}", prompt)?;
+        output.flush()?;
+        let mut line = String::new();
+        if self.0.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+/// A formula evaluated, numbered by its position in the history.
+struct Entry {
+    input: String,
+    output: String,
+    /// The result for later formulas, `None` if it cannot be written as one.
+    value: Option<Expr>,
+}
+
+/// Reads formulas line by line, printing the value of each or the error pointing at its
+/// location, until the end of the input or `quit`. Lines starting with `:` are commands.
+/// Results are numbered, later formulas referring to them as `$1`, `$2`, ... and to the
+/// last as `ans`.
+pub struct Repl {
+    context: Context,
+    modes: Modes,
+    history: Vec<Entry>,
+    /// The variables each definition or `:clear` changed, with the values they had
+    /// before.
+    undo: Vec<Vec<(String, Option<Value>)>>,
+    json: bool,
+    colors: bool,
+    error_format: ErrorFormat,
+    prompt: String,
+}
+
+impl Repl {
+    pub fn with_context(context: Context) -> Repl {
+        Repl {
+            context,
+            modes: Modes::default(),
+            history: vec![],
+            undo: vec![],
+            json: false,
+            colors: false,
+            error_format: ErrorFormat::Human,
+            prompt: String::from(PROMPT),
+        }
+    }
+
+    /// Colors the errors `filter` reports.
+    pub fn set_colors(&mut self, colors: bool) {
+        self.colors = colors;
+    }
+
+    /// Sets how `filter` reports errors on the standard error, unless it writes JSON.
+    pub fn set_error_format(&mut self, error_format: ErrorFormat) {
+        self.error_format = error_format;
+    }
+
+    pub fn set_modes(&mut self, modes: Modes) {
+        self.modes = modes;
+    }
+
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+    }
+
+    /// Makes `filter` write JSON objects, errors included, instead of lines of text.
+    pub fn set_json(&mut self, json: bool) {
+        self.json = json;
+    }
+
+    pub fn run(&mut self, input: &mut impl Input, output: &mut impl Write) -> io::Result<()> {
+        loop {
+            let complete = |prefix: &str| self.context.completions(prefix);
+            let width = self.prompt.chars().count();
+            // The prompt of the lines continuing a formula, as wide as the first.
+            let continuation = format!("{:<width$}", ".", width = width.max(1));
+            let Some(mut text) = input.read_line(&self.prompt, output, &complete)? else {
+                break;
+            };
+            // An empty line abandons a formula being continued.
+            while incomplete(&text) {
+                match input.read_line(&continuation, output, &complete)? {
+                    Some(line) if line.trim().is_empty() => text.clear(),
+                    Some(line) => {
+                        text.push('\n');
+                        text.push_str(&line);
+                    }
+                    None => break,
+                }
+            }
+            match self.respond(&text) {
+                Some(response) if response.is_empty() => {}
+                Some(response) => writeln!(output, "{}", response)?,
+                None => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    /// What `run` writes for the line `text` entered, a command or a formula, empty for
+    /// blank lines and `None` for `quit`.
+    pub fn respond(&mut self, text: &str) -> Option<String> {
+        match text.trim() {
+            "" => Some(String::new()),
+            "quit" | "exit" => None,
+            command if command.starts_with(':') => {
+                Some(self.command(&command[1..]).unwrap_or_else(|error| error))
+            }
+            _ => Some(self.execute(text)),
+        }
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn modes(&self) -> &Modes {
+        &self.modes
+    }
+
+    /// Evaluates formulas line by line without prompting, as a filter in a pipeline: each
+    /// result is printed on its own line, and errors are reported to `errors` quoting their
+    /// line and skipped. Returns the status of the first line failing, if any.
+    pub fn filter(
+        &mut self,
+        input: impl BufRead,
+        output: &mut impl Write,
+        errors: &mut impl Write,
+    ) -> io::Result<Status> {
+        let mut status = Status::Success;
+        for (number, line) in input.lines().enumerate() {
+            let line = line?;
+            let object = json::Object::new().string("input", &line);
+            let (result, object) = match line.trim() {
+                "" => continue,
+                command if command.starts_with(':') => match self.command(&command[1..]) {
+                    Ok(result) => {
+                        let object = object.string("result", &result).string("type", "command");
+                        (Ok(result), object)
+                    }
+                    Err(message) => {
+                        let error = json::Object::new()
+                            .string("kind", "command")
+                            .string("message", message.trim_start_matches("Error: "));
+                        let message = format!("line {}: {}", number + 1, message);
+                        let failure = (message, Status::UsageError);
+                        (Err(failure), object.object("error", error))
+                    }
+                },
+                _ => match self.record(&line) {
+                    Ok((result, kind)) => {
+                        let object = object.string("result", &result).string("type", kind);
+                        (Ok(result), object)
+                    }
+                    Err(error) => {
+                        let message = self.error_format.report(
+                            &Diagnostic::from(&error),
+                            &line,
+                            "<stdin>",
+                            number + 1,
+                            self.colors,
+                        );
+                        (
+                            Err((message, Status::of(&error))),
+                            object.object("error", json::error(&error)),
+                        )
+                    }
+                },
+            };
+            if let (Err((_, failure)), Status::Success) = (&result, status) {
+                status = *failure;
+            }
+            match result {
+                _ if self.json => writeln!(output, "{}", object.finish())?,
+                Ok(result) => writeln!(output, "{}", result)?,
+                Err((message, _)) => writeln!(errors, "{}", message)?,
+            }
+        }
+        Ok(status)
+    }
+
+    /// The numbered value of the formula of `line` with the backend, or its error.
+    fn execute(&mut self, line: &str) -> String {
+        match self.record(line) {
+            Ok((output, "definition")) => output,
+            Ok((output, _)) => {
+                let output = format!("${} = {}", self.history.len(), output);
+                match self.programmer() {
+                    Some(block) => format!("{}\n{}", output, block),
+                    None => output,
+                }
+            }
+            Err(error) => report(line, &error, self.prompt.chars().count()),
+        }
+    }
+
+    /// The last result in every radix in programmer mode, if it is an integer.
+    fn programmer(&self) -> Option<String> {
+        if !self.modes.programmer {
+            return None;
+        }
+        match &self.history.last()?.value.as_ref()?.kind {
+            ExprKind::Value(Value::Number(n)) => programmer::write(*n, self.context.word_size()),
+            _ => None,
+        }
+    }
+
+    /// The value of the formula of `line` written out and its type, adding it to the
+    /// history. Assignments such as `x = 2` and `f(x) = x^2` instead define variables,
+    /// written out with the type `definition`.
+    pub fn record(&mut self, line: &str) -> Result<(String, &'static str), Error> {
+        if let Some((span, number)) = references(line)
+            .into_iter()
+            .find(|(_, number)| !(1..=self.history.len()).contains(number))
+        {
+            let name = format!("${}", number);
+            return Err(
+                EvaluatorError::new(EvaluatorErrorKind::UnknownVariable(name))
+                    .or_span(span)
+                    .into(),
+            );
+        }
+        let results = self.results();
+        if let Some(name) = script::define(&recalled(line), &mut self.context, &results) {
+            let (name, previous) = name?;
+            self.undo.push(vec![(name.clone(), previous)]);
+            let value = self.context.variable(&name).expect("defined");
+            return Ok((
+                script::definition(&name, value, |value| self.context.format(value)),
+                "definition",
+            ));
+        }
+        let (output, kind, value) = self.evaluate(line)?;
+        self.history.push(Entry {
+            input: line.to_string(),
+            output: output.clone(),
+            value,
+        });
+        Ok((output, kind))
+    }
+
+    /// The result written out, its type, and the result as a formula.
+    fn evaluate(&self, line: &str) -> Result<(String, &'static str, Option<Expr>), Error> {
+        let expr = Parser::new(&recalled(line)).parse()?;
+        let expr = expr.substitute_all(&self.results());
+        let (output, kind) = match self.modes.backend {
+            Backend::Float => {
+                let value = self.context.evaluate(&expr)?;
+                let output = self.context.format(&value);
+                let kind = value.type_name();
+                let value = Expr::new(ExprKind::Value(value), expr.span);
+                return Ok((output, kind, Some(value)));
+            }
+            Backend::Fixed(format) => (
+                expr.evaluate_fixed_in(&self.context, format)?.to_string(),
+                "fixed",
+            ),
+            Backend::Money(digits) => (
+                expr.evaluate_money_in(&self.context, digits)?.to_string(),
+                "money",
+            ),
+        };
+        let value = Parser::new(&output).parse().ok();
+        Ok((output, kind, value))
+    }
+
+    /// The formulas of the results for the variables referring to them.
+    fn results(&self) -> HashMap<String, Expr> {
+        let mut results = HashMap::new();
+        for (index, entry) in self.history.iter().enumerate() {
+            if let Some(value) = &entry.value {
+                results.insert(format!("_{}", index + 1), value.clone());
+                results.insert(String::from("ans"), value.clone());
+            }
+        }
+        results
+    }
+
+    /// Runs a command such as `set precision 12`, returning what it prints.
+    fn command(&mut self, command: &str) -> Result<String, String> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words[..] {
+            ["set", name, ref values @ ..] => {
+                match settings::set(&mut self.context, &mut self.modes, name, values) {
+                    Ok(()) => Ok(settings::show(&self.context, &self.modes)
+                        .lines()
+                        .find(|line| line.split(' ').next() == Some(name))
+                        .unwrap_or_default()
+                        .to_string()),
+                    Err(message) => Err(format!("Error: {}", message)),
+                }
+            }
+            ["show"] => Ok(settings::show(&self.context, &self.modes)),
+            ["history"] => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| {
+                    let input = entry.input.split_whitespace().collect::<Vec<_>>().join(" ");
+                    format!("${} = {}  ({})", index + 1, entry.output, input)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ["vars"] => {
+                let variables: Vec<String> = self
+                    .context
+                    .variables()
+                    .into_iter()
+                    .filter(|(_, value)| !matches!(value, Value::Function(_)))
+                    .map(|(name, value)| {
+                        let definition =
+                            script::definition(name, value, |value| self.context.format(value));
+                        format!("{}  ({})", definition, value.type_name())
+                    })
+                    .collect();
+                Ok(listed(variables, "No variables"))
+            }
+            ["funcs"] => {
+                let functions: Vec<String> = self
+                    .context
+                    .variables()
+                    .into_iter()
+                    .filter(|(_, value)| matches!(value, Value::Function(_)))
+                    .map(|(name, value)| {
+                        script::definition(name, value, |value| self.context.format(value))
+                    })
+                    .collect();
+                Ok(listed(functions, "No functions"))
+            }
+            ["clear", ref names @ ..] if !names.is_empty() => {
+                if let Some(name) = names
+                    .iter()
+                    .find(|name| self.context.variable(name).is_none())
+                {
+                    return Err(format!("Error: Unknown variable '{}'", name));
+                }
+                let removed = names
+                    .iter()
+                    .map(|name| (name.to_string(), self.context.remove_variable(name)))
+                    .collect();
+                self.undo.push(removed);
+                Ok(format!("Cleared {}", names.join(", ")))
+            }
+            ["save", _, ..] => {
+                let path = command.trim_start()["save".len()..].trim();
+                let contents = session::contents(&self.context, &self.modes);
+                match std::fs::write(path, contents) {
+                    Ok(()) => Ok(format!("Saved the session to {}", path)),
+                    Err(error) => Err(format!("Error: Cannot write '{}': {}", path, error)),
+                }
+            }
+            ["load", _, ..] => self.load(command.trim_start()["load".len()..].trim()),
+            ["ast", _, ..] => {
+                let formula = command.trim_start()["ast".len()..].trim();
+                match Parser::new(formula).parse() {
+                    Ok(expr) => Ok(ast::tree(&expr)),
+                    Err(error) => Err(format!("Error: {}", error)),
+                }
+            }
+            ["tokens", _, ..] => {
+                let formula = command.trim_start()["tokens".len()..].trim();
+                match tokens::list(formula) {
+                    (list, None) => Ok(list),
+                    (list, Some(error)) if list.is_empty() => Err(format!("Error: {}", error)),
+                    (list, Some(error)) => Err(format!("{}\nError: {}", list, error)),
+                }
+            }
+            ["plot", ..] => {
+                let formulas = recalled(&command.trim_start()["plot".len()..]);
+                plot::plot(&formulas, &self.context, &self.results(), self.colors)
+            }
+            ["time", ..] => {
+                let formula = recalled(&command.trim_start()["time".len()..]);
+                timing::time(&formula, &self.context, &self.results())
+            }
+            ["clearall"] => {
+                let names: Vec<String> = self
+                    .context
+                    .variables()
+                    .into_iter()
+                    .map(|(name, _)| name.to_string())
+                    .collect();
+                let count = names.len();
+                let removed = names
+                    .into_iter()
+                    .map(|name| {
+                        let value = self.context.remove_variable(&name);
+                        (name, value)
+                    })
+                    .collect();
+                self.undo.push(removed);
+                Ok(format!("Cleared {} variables and functions", count))
+            }
+            ["undo"] => {
+                let changes = self
+                    .undo
+                    .pop()
+                    .ok_or_else(|| String::from("Error: Nothing to undo"))?;
+                let mut lines = vec![];
+                for (name, previous) in changes.into_iter().rev() {
+                    match previous {
+                        Some(value) => {
+                            let definition = script::definition(&name, &value, |value| {
+                                self.context.format(value)
+                            });
+                            lines.push(format!("Restored {}", definition));
+                            self.context.set_variable(&name, value);
+                        }
+                        None => {
+                            self.context.remove_variable(&name);
+                            lines.push(format!("Removed {}", name));
+                        }
+                    }
+                }
+                lines.reverse();
+                Ok(lines.join("\n"))
+            }
+            _ => Err(format!(
+                "Error: Unknown command ':{}', expected one of {}",
+                command, COMMANDS
+            )),
+        }
+    }
+
+    /// Runs the settings and definitions of a session file, stopping at the first line
+    /// failing.
+    fn load(&mut self, path: &str) -> Result<String, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| format!("Error: Cannot read '{}': {}", path, error))?;
+        for (number, line) in session::statements(&source) {
+            let result = match line.strip_prefix(':') {
+                Some(command) if command.starts_with("set ") => self.command(command).map(|_| ()),
+                Some(_) => Err(String::from("Error: Sessions may only run :set commands")),
+                None => self
+                    .record(line)
+                    .map(|_| ())
+                    .map_err(|error| format!("Error: {}", error)),
+            };
+            result.map_err(|message| {
+                let message = message.trim_start_matches("Error: ");
+                format!("Error: {}, line {}: {}", path, number, message)
+            })?;
+        }
+        Ok(format!("Loaded the session from {}", path))
+    }
+}
+
+fn listed(lines: Vec<String>, empty: &str) -> String {
+    if lines.is_empty() {
+        return empty.to_string();
+    }
+    lines.join("\n")
+}
+
+/// Whether the formula continues on the next line: it has unclosed parentheses, or ends
+/// with an operator other than `%` or with a comma.
+fn incomplete(text: &str) -> bool {
+    let mut lexer = Lexer::new(text);
+    let mut depth = 0i32;
+    let mut last = None;
+    while let Ok(Some(token)) = lexer.next_token() {
+        match token {
+            Token::OpenParenthesis => depth += 1,
+            Token::ClosedParenthesis => depth -= 1,
+            _ => {}
+        }
+        last = Some(token);
+    }
+    if !lexer.eof() {
+        return false;
+    }
+    depth > 0
+        || match last {
+            Some(Token::Operator(operator)) => operator != b"%",
+            Some(Token::Comma) => true,
+            _ => false,
+        }
+}
+
+/// The spans and numbers of the results referred to, `$` and digits outside strings.
+fn references(text: &str) -> Vec<(Span, usize)> {
+    let mut references = vec![];
+    let mut quoted = false;
+    for (start, char) in text.char_indices() {
+        quoted ^= char == '"';
+        if char != '$' || quoted {
+            continue;
+        }
+        let digits = text[start + 1..]
+            .find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(text.len() - start - 1);
+        if let Ok(number) = text[start + 1..start + 1 + digits].parse() {
+            let end = start + 1 + digits;
+            references.push((Span { start, end }, number));
+        }
+    }
+    references
+}
+
+/// The text with `$1`, `$2`, ... as the variables `_1`, `_2`, ... results are named by,
+/// keeping the spans of the formula.
+fn recalled(text: &str) -> String {
+    let mut text = text.to_string();
+    for (span, _) in references(&text) {
+        text.replace_range(span.start..span.start + 1, "_");
+    }
+    text
+}
+
+/// Marks the span of `error` under its line, after the prompt `indent` characters wide.
+/// The line of a formula continued on several lines is written again above the mark.
+fn report(text: &str, error: &Error, indent: usize) -> String {
+    let span = match error {
+        Error::Parser(error) => Some(error.span),
+        Error::Evaluator(error) => error.span,
+    };
+    let message = format!("Error: {}", error);
+    let Some(Span { start, end }) = span else {
+        return message;
+    };
+    let start = start.min(text.len());
+    let line_start = text[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = text[start..]
+        .find('\n')
+        .map_or(text.len(), |newline| start + newline);
+    let end = end.clamp(start, line_end);
+    let offset = indent + text[line_start..start].chars().count();
+    let width = text[start..end].chars().count().max(1);
+    let mark = format!("{}{}\n{}", " ".repeat(offset), "^".repeat(width), message);
+    if !text.contains('\n') {
+        return mark;
+    }
+    format!(
+        "{}{}\n{}",
+        " ".repeat(indent),
+        &text[line_start..line_end],
+        mark
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session(input: &str) -> String {
+        let mut output = vec![];
+        Repl::with_context(Context::new())
+            .run(&mut Plain(input.as_bytes()), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn evaluate_lines() {
+        assert_eq!(
+            session("1 + 2\n\n2 * pi\n"),
+            "> $1 = 3\n> > $2 = 6.283185307179586\n> \n"
+        );
+        // Typed lines are echoed by the terminal, so marks start below the prompt.
+        let output = session("1 + y\nsqrt(4)\nquit\n1\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [">       ^", "Error: Unknown variable 'y'", "> $1 = 2", "> "]
+        );
+        let output = session("2 * (3");
+        assert!(output.ends_with("^\nError: Unexpected end of input, expected ')'\n> \n"));
+    }
+
+    #[test]
+    fn change_settings() {
+        let output = session(":set precision 3\n2/3\n:set angle deg\nsin(30)\n:show\n:set backend money 2\n1/3\n:reset\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "> precision 3",
+                "> $1 = 0.667",
+                "> angle deg",
+                "> $2 = 0.5",
+                "> precision 3",
+                "notation plain",
+                "base dec",
+                "angle deg",
+                "backend float",
+                "programmer off",
+                "> backend money 2",
+                "> $3 = 0.33",
+                "> Error: Unknown command ':reset', expected one of :set, :show, :history, :vars, :funcs, :clear, :clearall, :save, :load, :undo, :time, :plot, :ast and :tokens",
+                "> "
+            ]
+        );
+    }
+
+    #[test]
+    fn see_variables_in_exact_backends() {
+        let output =
+            session("x = 2.5\n:set backend money 2\nx / 3\n:set backend fixed Q15.16\nx * 2\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "> x = 2.5",
+                "> backend money 2",
+                "> $1 = 0.83",
+                "> backend fixed Q15.16",
+                "> $2 = 5",
+                "> "
+            ]
+        );
+    }
+
+    #[test]
+    fn write_in_programmer_mode() {
+        let output = session(":set programmer on\n12\n0.5\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "> programmer on",
+                "> $1 = 12",
+                "hex 0xc",
+                "dec 12",
+                "oct 0o14",
+                "bin 0b1100",
+                "bits 0000 1100",
+                "> $2 = 0.5",
+                "> "
+            ]
+        );
+    }
+
+    #[test]
+    fn recall_results() {
+        let output = session("1 + 2\nans * 2\n$1 + $2\n$4\n:history\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "> $1 = 3",
+                "> $2 = 6",
+                "> $3 = 9",
+                ">   ^^",
+                "Error: Unknown variable '$4'",
+                "> $1 = 3  (1 + 2)",
+                "$2 = 6  (ans * 2)",
+                "$3 = 9  ($1 + $2)",
+                "> "
+            ]
+        );
+        let output = session(":set backend fixed Q7.8\n1 / 4\nans * 3\n");
+        assert!(output.ends_with("> $1 = 0.25\n> $2 = 0.75\n> \n"));
+    }
+
+    #[test]
+    fn filter_lines() {
+        let (mut output, mut errors) = (vec![], vec![]);
+        let input = "1 + 2\n\n2 * y\nans / 2\n:set precision 2\n1 / 3\n";
+        let status = Repl::with_context(Context::new())
+            .filter(input.as_bytes(), &mut output, &mut errors)
+            .unwrap();
+        assert_eq!(status, Status::EvaluationError);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "3\n1.5\nprecision 2\n0.33\n"
+        );
+        assert_eq!(
+            String::from_utf8(errors).unwrap(),
+            "error[E0201]: Unknown variable 'y'\n --> <stdin>:3:5\n  |\n3 | 2 * y\n  |     ^\n  = help: assign it first, as y = 2\n"
+        );
+    }
+
+    #[test]
+    fn filter_lines_as_json() {
+        let mut output = vec![];
+        let mut repl = Repl::with_context(Context::new());
+        repl.set_json(true);
+        let input = "6 * 7\nsqrt(\n:set speed 2\n";
+        let status = repl.filter(input.as_bytes(), &mut output, &mut io::sink());
+        assert_eq!(status.unwrap(), Status::ParseError);
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            r#"{"input":"6 * 7","result":"42","type":"number"}"#
+        );
+        assert!(lines[1].starts_with(r#"{"input":"sqrt(","error":{"kind":"parse","code":"E0102","#));
+        assert!(lines[2].starts_with(
+            r#"{"input":":set speed 2","error":{"kind":"command","message":"Unknown setting"#
+        ));
+    }
+
+    #[test]
+    fn configure_prompt() {
+        let mut repl = Repl::with_context(Context::new());
+        repl.set_prompt("calc> ");
+        let mut output = vec![];
+        let input = "(1 +\n2) * y\n";
+        repl.run(&mut Plain(input.as_bytes()), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "calc> .           2) * y",
+                "           ^",
+                "Error: Unknown variable 'y'",
+                "calc> "
+            ]
+        );
+    }
+
+    #[test]
+    fn define_and_list_variables() {
+        let input = "\
+            :vars\n\
+            r = 2 m\n\
+            area(r) = pi * r^2\n\
+            k = $1\n\
+            10\n\
+            k = ans / 5\n\
+            :vars\n\
+            :funcs\n\
+            :clear r k\n\
+            :clear r\n\
+            :clearall\n\
+            :funcs\n";
+        let output = session(input);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "> No variables",
+                "> r = 2 m",
+                "> area(r) = pi * r^2",
+                ">       ^^",
+                "Error: Unknown variable '$1'",
+                "> $1 = 10",
+                "> k = 2",
+                "> k = 2  (number)",
+                "r = 2 m  (quantity)",
+                "> area(r) = pi * r^2",
+                "> Cleared r, k",
+                "> Error: Unknown variable 'r'",
+                "> Cleared 1 variables and functions",
+                "> No functions",
+                "> "
+            ]
+        );
+    }
+
+    #[test]
+    fn undo_definitions() {
+        let input = "\
+            x = 2\n\
+            x = 0\n\
+            :undo\n\
+            x\n\
+            f(t) = t + x\n\
+            :clearall\n\
+            :undo\n\
+            :undo\n\
+            :undo\n\
+            :undo\n\
+            :vars\n";
+        let output = session(input);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "> x = 2",
+                "> x = 0",
+                "> Restored x = 2",
+                "> $1 = 2",
+                "> f(t) = t + x",
+                "> Cleared 2 variables and functions",
+                "> Restored f(t) = t + x",
+                "Restored x = 2",
+                "> Removed f",
+                "> Removed x",
+                "> Error: Nothing to undo",
+                "> No variables",
+                "> "
+            ]
+        );
+    }
+
+    #[test]
+    fn save_and_load_sessions() {
+        let path = std::env::temp_dir().join(format!("rusculator-{}.calc", std::process::id()));
+        let path = path.to_str().unwrap();
+        let input = format!(
+            ":set angle deg\nhalf(x) = x / 2\nr = half(5)\n:save {}\n",
+            path
+        );
+        session(&input);
+        let output = session(&format!(":load {}\nhalf(r)\nsin(90)\n", path));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                format!("> Loaded the session from {}", path).as_str(),
+                "> $1 = 1.25",
+                "> $2 = 1",
+                "> "
+            ]
+        );
+        std::fs::write(path, "r = 1\n:clearall\n").unwrap();
+        let output = session(&format!(":load {}\n", path));
+        assert!(output.ends_with(", line 2: Sessions may only run :set commands\n> \n"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn continue_formulas() {
+        assert_eq!(session("2 *\n(3 +\n4)\n"), "> . . $1 = 14\n> \n");
+        assert_eq!(
+            session("max(1,\n2, 3)\n5%\n"),
+            "> . $1 = 3\n> $2 = 0.05\n> \n"
+        );
+        assert_eq!(session("(1 +\n\n7\n"), "> . > $1 = 7\n> \n");
+        let output = session("1 +\n(2 * y)\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "> .   (2 * y)",
+                "       ^",
+                "Error: Unknown variable 'y'",
+                "> "
+            ]
+        );
+    }
+}
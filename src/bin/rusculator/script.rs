@@ -0,0 +1,234 @@
+//! Scripts of statements run by `rusculator run`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use rusculator::{
+    Context, Error, Expr, ExprKind, Function, Parser, ParserError, ParserErrorKind, Span, Value,
+};
+
+/// The first statement of a script that failed, and where it failed.
+#[derive(Debug)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub error: Error,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: Error: {}", self.line, self.column, self.error)
+    }
+}
+
+/// Runs the statements of `source`, separated by `;` or new lines, in `context`: `x = 2`
+/// assigns a variable, `f(x, y) = x * y` defines a function, and other statements are
+/// formulas whose values are passed to `print` with their types. Text after `#` is a comment. Stops at the
+/// first statement that fails.
+pub fn run(
+    source: &str,
+    context: &mut Context,
+    mut print: impl FnMut(String, &'static str),
+) -> Result<(), ScriptError> {
+    for (offset, statement) in statements(source) {
+        execute(statement, context, &mut print).map_err(|error| {
+            let error = shift(error, offset);
+            let start = match &error {
+                Error::Parser(error) => error.span.start,
+                Error::Evaluator(error) => error.span.map_or(offset, |span| span.start),
+            };
+            let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+            ScriptError {
+                line: source[..start].matches('\n').count() + 1,
+                column: source[line_start..start].chars().count() + 1,
+                error,
+            }
+        })?;
+    }
+    Ok(())
+}
+
+fn execute(
+    statement: &str,
+    context: &mut Context,
+    print: &mut impl FnMut(String, &'static str),
+) -> Result<(), Error> {
+    if let Some(result) = define(statement, context, &HashMap::new()) {
+        return result.map(|_| ());
+    }
+    let value = context.evaluate(&Parser::new(statement).parse()?)?;
+    print(context.format(&value), value.type_name());
+    Ok(())
+}
+
+/// Runs `statement` if it assigns a variable or defines a function, returning its name
+/// and the value it replaced, if any. The formula assigned has `replacements` substituted
+/// for its variables.
+pub fn define(
+    statement: &str,
+    context: &mut Context,
+    replacements: &HashMap<String, Expr>,
+) -> Option<Result<(String, Option<Value>), Error>> {
+    let position = assignment(statement)?;
+    Some(assign(statement, position, context, replacements))
+}
+
+fn assign(
+    statement: &str,
+    position: usize,
+    context: &mut Context,
+    replacements: &HashMap<String, Expr>,
+) -> Result<(String, Option<Value>), Error> {
+    let target = Parser::new(&statement[..position]).parse()?;
+    let start = position + 1;
+    let formula = Parser::new(&statement[start..])
+        .parse()
+        .map_err(|error| shift(error.into(), start))?
+        .substitute_all(replacements);
+    let (name, formula) = match &target.kind {
+        ExprKind::Variable(name) => (name.clone(), formula),
+        ExprKind::Call(name, args) => {
+            let params: Option<Vec<String>> = args
+                .iter()
+                .map(|arg| match &arg.kind {
+                    ExprKind::Variable(param) => Some(param.clone()),
+                    _ => None,
+                })
+                .collect();
+            let Some(params) = params else {
+                return Err(not_a_name(target.span));
+            };
+            let span = formula.span;
+            (
+                name.clone(),
+                Expr::new(ExprKind::Lambda(params, Box::new(formula)), span),
+            )
+        }
+        _ => return Err(not_a_name(target.span)),
+    };
+    let value = context
+        .evaluate(&formula)
+        .map_err(|error| shift(error.into(), start))?;
+    let previous = context.variable(&name).cloned();
+    context.set_variable(&name, value);
+    Ok((name, previous))
+}
+
+/// The definition of a variable as a statement, `f(x) = x^2` for functions, writing
+/// other values with `format`.
+pub fn definition(name: &str, value: &Value, format: impl Fn(&Value) -> String) -> String {
+    match value {
+        Value::Function(Function::Lambda(params, body)) => {
+            format!("{}({}) = {}", name, params.join(", "), body)
+        }
+        value => format!("{} = {}", name, format(value)),
+    }
+}
+
+fn not_a_name(span: Span) -> Error {
+    Error::Parser(ParserError {
+        kind: ParserErrorKind::UnexpectedToken {
+            found: String::from("="),
+            expected: "a name or a function such as f(x)",
+        },
+        span,
+    })
+}
+
+/// The statements with their offsets in `source`, without comments and blank ones.
+pub fn statements(source: &str) -> Vec<(usize, &str)> {
+    let mut statements = vec![];
+    let mut start = 0;
+    let mut end = None;
+    let mut quoted = false;
+    for (index, character) in source.char_indices() {
+        match character {
+            '"' if end.is_none() => quoted = !quoted,
+            '#' if !quoted && end.is_none() => end = Some(index),
+            ';' | '\n' if !quoted && (end.is_none() || character == '\n') => {
+                statements.push((start, &source[start..end.unwrap_or(index)]));
+                start = index + 1;
+                end = None;
+            }
+            _ => {}
+        }
+    }
+    statements.push((start, &source[start..end.unwrap_or(source.len())]));
+    statements.retain(|(_, statement)| !statement.trim().is_empty());
+    statements
+}
+
+/// The position of the `=` of an assignment, not part of a comparison.
+pub fn assignment(statement: &str) -> Option<usize> {
+    let bytes = statement.as_bytes();
+    (0..bytes.len()).find(|&index| {
+        bytes[index] == b'='
+            && !matches!(
+                index.checked_sub(1).map(|before| bytes[before]),
+                Some(b'=' | b'<' | b'>' | b'!')
+            )
+            && bytes.get(index + 1) != Some(&b'=')
+    })
+}
+
+/// The error with its span moved `offset` bytes on.
+pub fn shift(error: Error, offset: usize) -> Error {
+    let shift = |span: Span| Span {
+        start: span.start + offset,
+        end: span.end + offset,
+    };
+    match error {
+        Error::Parser(mut error) => {
+            error.span = shift(error.span);
+            Error::Parser(error)
+        }
+        Error::Evaluator(mut error) => {
+            error.span = error.span.map(shift);
+            Error::Evaluator(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run_script(source: &str) -> (Vec<String>, Option<String>) {
+        let mut printed = vec![];
+        let result = run(source, &mut Context::new(), |value, _| printed.push(value));
+        (printed, result.err().map(|error| error.to_string()))
+    }
+
+    #[test]
+    fn run_scripts() {
+        let source = "# Areas\nr = 2; area(r) = pi * r^2\narea(1) == pi\nround(area(r))\n";
+        assert_eq!(
+            run_script(source),
+            (vec![String::from("true"), String::from("13")], None)
+        );
+        let source = "fact(n) = n <= 1 ? 1 : n * fact(n - 1)\nfact(5)\nx = \"#;\"; x";
+        assert_eq!(
+            run_script(source),
+            (vec![String::from("120"), String::from("#;")], None)
+        );
+        assert_eq!(
+            run_script("1\nx = 2 *\n3"),
+            (
+                vec![String::from("1")],
+                Some(String::from(
+                    "2:8: Error: Unexpected end of input, expected an expression"
+                ))
+            )
+        );
+        assert_eq!(
+            run_script("a = 1\n  f(a + 1) = 2").1,
+            Some(String::from(
+                "2:3: Error: Unexpected '=', expected a name or a function such as f(x)"
+            ))
+        );
+        assert_eq!(
+            run_script("y = 1; z = y + w").1,
+            Some(String::from("1:16: Error: Unknown variable 'w'"))
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! `rusculator serve`, evaluating the formulas of JSON requests posted over HTTP.
+//!
+//! `POST /eval` takes an object with the `expression`, optional `variables`, numbers or
+//! formulas evaluated in order, and optional `options`, the `precision` and `format` of
+//! the command line. It answers `{"result": ..., "type": ...}`, or `{"error": ...}` with
+//! the error of `--json` and the `variable` it came from, if any.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rusculator::{Context, Error, EvaluatorErrorKind, Limit, Limits, Value};
+
+use crate::args::{Format, Output};
+use crate::json::{self, Json, Object};
+
+/// The limits of each request, so that no formula can hold up the server. The timeout is
+/// shared by the formulas of the variables and the expression.
+pub const LIMITS: Limits = Limits {
+    timeout: Some(Duration::from_secs(1)),
+    max_depth: Some(256),
+    max_digits: Some(10_000),
+    max_cost: Some(1_000_000),
+};
+
+/// Bound on the size of the head of a request, its request line and headers.
+const MAX_HEAD: usize = 8 * 1024;
+
+/// Bound on the size of the body of a request.
+const MAX_BODY: usize = 64 * 1024;
+
+/// How long reading a request or writing its response may take.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Bound on the connections handled at once, past which clients are answered 503.
+const MAX_CONNECTIONS: usize = 64;
+
+/// Serves requests on `listener`, each in its own thread with a copy of `context`.
+pub fn serve(listener: TcpListener, context: &Context) -> io::Result<()> {
+    serve_up_to(listener, context, MAX_CONNECTIONS)
+}
+
+fn serve_up_to(listener: TcpListener, context: &Context, connections: usize) -> io::Result<()> {
+    let mut context = context.clone();
+    context.set_limits(LIMITS);
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        // Clients going away are not errors of the server.
+        if active.fetch_add(1, Ordering::SeqCst) >= connections {
+            active.fetch_sub(1, Ordering::SeqCst);
+            let _ = stream
+                .set_write_timeout(Some(IO_TIMEOUT))
+                .and_then(|()| write(stream, 503, &failure("Too many requests, try again later")));
+            continue;
+        }
+        let connection = Connection(Arc::clone(&active));
+        let context = context.clone();
+        thread::spawn(move || {
+            let _connection = connection;
+            let _ = handle(stream, &context);
+        });
+    }
+    Ok(())
+}
+
+/// A connection being handled, counted in the connections active until dropped.
+struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn handle(stream: TcpStream, context: &Context) -> io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?).take(MAX_HEAD as u64);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return write(stream, 400, &failure("Incomplete request"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(usize::MAX);
+            }
+        }
+    }
+    let mut words = request_line.split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("POST"), Some("/eval")) if length > MAX_BODY => {
+            (413, failure("The request is too large"))
+        }
+        (Some("POST"), Some("/eval")) => {
+            let mut body = vec![0; length];
+            reader.set_limit(length as u64);
+            reader.read_exact(&mut body)?;
+            match String::from_utf8(body) {
+                Ok(body) => respond(&body, context),
+                Err(_) => (400, failure("The request is not UTF-8")),
+            }
+        }
+        (Some(_), Some("/eval")) => (405, failure("Expected POST")),
+        _ => (404, failure("Not found, expected POST /eval")),
+    };
+    write(stream, status, &body)
+}
+
+fn write(mut stream: TcpStream, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        503 => "Service Unavailable",
+        _ => "Unprocessable Content",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// The error of a request which is not one to evaluate.
+fn failure(message: &str) -> String {
+    let error = Object::new()
+        .string("kind", "request")
+        .string("message", message);
+    Object::new().object("error", error).finish()
+}
+
+/// The status and body of the response to the JSON request `body`.
+fn respond(body: &str, context: &Context) -> (u16, String) {
+    let request = match json::parse(body) {
+        Ok(request @ Json::Object(_)) => request,
+        Ok(_) => return (400, failure("Expected an object")),
+        Err((message, position)) => {
+            return (
+                400,
+                failure(&format!(
+                    "{} at position {} of the request",
+                    message, position
+                )),
+            )
+        }
+    };
+    let Some(Json::String(expression)) = request.get("expression") else {
+        return (400, failure("Expected the expression as a string"));
+    };
+    let mut context = context.clone();
+    let mut output = Output::default();
+    match request.get("options") {
+        Some(options @ Json::Object(_)) => {
+            match options.get("precision") {
+                Some(Json::Number(digits)) if digits.fract() == 0.0 && *digits >= 1.0 => {
+                    output.precision = Some(*digits as usize)
+                }
+                None => {}
+                _ => return (400, failure("Expected the precision as a positive integer")),
+            }
+            match options.get("format") {
+                Some(Json::String(name)) => match Format::from_name(name) {
+                    Ok(format) => output.format = Some(format),
+                    Err(message) => return (400, failure(&message)),
+                },
+                None => {}
+                _ => return (400, failure("Expected the format as a string")),
+            }
+        }
+        None => {}
+        _ => return (400, failure("Expected the options as an object")),
+    }
+    output.configure(&mut context);
+    let deadline = LIMITS.timeout.map(|timeout| Instant::now() + timeout);
+    match request.get("variables") {
+        Some(Json::Object(variables)) => {
+            for (name, value) in variables {
+                let value = match value {
+                    Json::Number(number) => Value::Number(*number),
+                    Json::String(formula) => match eval_until(&mut context, formula, deadline) {
+                        Ok(value) => value,
+                        Err(error) => {
+                            let error = json::error(&error).string("variable", name);
+                            return (422, Object::new().object("error", error).finish());
+                        }
+                    },
+                    _ => {
+                        let message = format!("Expected '{}' as a number or a formula", name);
+                        return (400, failure(&message));
+                    }
+                };
+                context.set_variable(name, value);
+            }
+        }
+        None => {}
+        _ => return (400, failure("Expected the variables as an object")),
+    }
+    match eval_until(&mut context, expression, deadline) {
+        Ok(value) => {
+            let object = Object::new()
+                .string("result", &context.format(&value))
+                .string("type", value.type_name());
+            (200, object.finish())
+        }
+        Err(error) => (
+            422,
+            Object::new().object("error", json::error(&error)).finish(),
+        ),
+    }
+}
+
+/// Evaluates `source` with the time left until `deadline`, reporting the timeout of the
+/// request if it runs out.
+fn eval_until(
+    context: &mut Context,
+    source: &str,
+    deadline: Option<Instant>,
+) -> Result<Value, Error> {
+    let mut limits = *context.limits();
+    limits.timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+    context.set_limits(limits);
+    let mut result = context.eval(source);
+    if let Err(Error::Evaluator(error)) = &mut result {
+        if let EvaluatorErrorKind::LimitExceeded(Limit::Time(timeout)) = &mut error.kind {
+            *timeout = LIMITS.timeout.unwrap_or(*timeout);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Shutdown;
+
+    #[test]
+    fn respond_to_requests() {
+        let mut context = Context::new();
+        context.set_limits(LIMITS);
+        let respond = |body| respond(body, &context);
+        assert_eq!(
+            respond(r#"{"expression": "2 * x + y", "variables": {"x": 3, "y": "x km / 1 km"}}"#),
+            (200, String::from(r#"{"result":"9","type":"number"}"#))
+        );
+        assert_eq!(
+            respond(r#"{"expression": "4700", "options": {"format": "sci", "precision": 2}}"#),
+            (200, String::from(r#"{"result":"4.7e3","type":"number"}"#))
+        );
+        assert_eq!(
+            respond(r#"{"expression": "1 + z"}"#),
+            (
+                422,
+                String::from(
+                    r#"{"error":{"kind":"evaluation","code":"E0201","message":"Unknown variable 'z'","start":4,"end":5}}"#
+                )
+            )
+        );
+        assert_eq!(
+            respond(r#"{"expression": "1", "variables": {"a": "("}}"#).0,
+            422
+        );
+        assert_eq!(
+            respond(r#"{"expression": 1}"#),
+            (400, failure("Expected the expression as a string"))
+        );
+        assert_eq!(respond("{").0, 400);
+        assert_eq!(
+            respond(r#"{"expression": "1", "options": {"format": "hex"}}"#).0,
+            400
+        );
+        // The limits hold for the formulas of requests.
+        assert_eq!(respond(r#"{"expression": "tobase(10^20000, 2)"}"#).0, 422);
+        let mut context = context.clone();
+        let deadline = Instant::now();
+        thread::sleep(Duration::from_millis(2));
+        let error = eval_until(&mut context, "integrate(sin(x), x, 0, 1)", Some(deadline))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Evaluation took longer than 1s");
+    }
+
+    #[test]
+    fn serve_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, &Context::new()));
+        let request = |text: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(text.as_bytes()).unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let body = r#"{"expression": "1 + 2"}"#;
+        let response = request(&format!(
+            "POST /eval HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n{\"result\":\"3\",\"type\":\"number\"}"));
+        let response = request("GET /eval HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 "));
+        // Past the connections handled at once, clients are turned away.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve_up_to(listener, &Context::new(), 1));
+        let _waiting = TcpStream::connect(address).unwrap();
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+    }
+}
@@ -0,0 +1,71 @@
+//! Sessions saved by `:save` and restored by `:load`: the settings as `:set` commands,
+//! then the variables and functions as definitions.
+
+use rusculator::{Context, DisplayOptions};
+
+use crate::script;
+use crate::settings::{self, Modes};
+
+const HEADER: &str = "# rusculator session";
+
+/// The session file of `context` and `modes`.
+pub fn contents(context: &Context, modes: &Modes) -> String {
+    let mut lines = vec![String::from(HEADER)];
+    for setting in settings::show(context, modes).lines() {
+        lines.push(format!(":set {}", setting));
+    }
+    // Numbers are written exactly, whatever the display precision.
+    let exact = DisplayOptions::default();
+    for (name, value) in context.variables() {
+        lines.push(script::definition(name, value, |value| {
+            value.format(&exact)
+        }));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// The lines of a session file to run, without comments and blank lines.
+pub fn statements(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::settings::Backend;
+    use rusculator::Value;
+
+    #[test]
+    fn write_sessions() {
+        let mut context = Context::new();
+        context.set_variable("third", Value::Number(1.0 / 3.0));
+        let square = context.eval("x -> x^2").unwrap();
+        context.set_variable("square", square);
+        let mut options = *context.display_options();
+        options.precision = Some(3);
+        context.set_display_options(options);
+        let modes = Modes {
+            backend: Backend::Money(2),
+            programmer: false,
+        };
+        let contents = contents(&context, &modes);
+        assert_eq!(
+            contents,
+            "# rusculator session\n\
+             :set precision 3\n\
+             :set notation plain\n\
+             :set base dec\n\
+             :set angle rad\n\
+             :set backend money 2\n\
+             :set programmer off\n\
+             square(x) = x^2\n\
+             third = 0.3333333333333333\n"
+        );
+        assert_eq!(statements(&contents).nth(6), Some((8, "square(x) = x^2")));
+    }
+}
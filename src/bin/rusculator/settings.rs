@@ -0,0 +1,192 @@
+//! The `:set` and `:show` commands, changing how the REPL evaluates and writes results.
+
+use rusculator::{AngleUnit, Context, FixedFormat, Notation, Radix, MAX_SCALE};
+
+/// The number type formulas are evaluated with. There is no rational backend: numerators
+/// and denominators grow without bound over long formulas, and `sqrt` and the constants
+/// have no rational value, so exact results are left to fixed point and money.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Float,
+    Fixed(FixedFormat),
+    /// Exact decimals rounded to this many fractional digits.
+    Money(u32),
+}
+
+/// The settings of the REPL rather than of the context.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Modes {
+    pub backend: Backend,
+    /// Writes integers in every radix with their bits, as programmer calculators do.
+    pub programmer: bool,
+}
+
+impl Default for Modes {
+    fn default() -> Modes {
+        Modes {
+            backend: Backend::Float,
+            programmer: false,
+        }
+    }
+}
+
+const RADIXES: &[(&str, Radix)] = &[
+    ("bin", Radix::Binary),
+    ("oct", Radix::Octal),
+    ("dec", Radix::Decimal),
+    ("hex", Radix::Hexadecimal),
+];
+
+const NOTATIONS: &[(&str, Notation)] = &[
+    ("plain", Notation::Plain),
+    ("engineering", Notation::Engineering),
+    ("si", Notation::SiPrefix),
+];
+
+pub const USAGE: &str = "Settings are precision <digits|off>, notation <plain|engineering|si>, \
+     base <bin|oct|dec|hex>..., angle <rad|deg>, backend <float|fixed Q<m>.<n>|money <digits>> \
+     and programmer <on|off>";
+
+/// Changes the setting `name` to `values`.
+pub fn set(
+    context: &mut Context,
+    modes: &mut Modes,
+    name: &str,
+    values: &[&str],
+) -> Result<(), String> {
+    let invalid = || format!("Invalid value '{}' of '{}'", values.join(" "), name);
+    match (name, values) {
+        ("precision", ["off"]) => {
+            let mut options = *context.display_options();
+            options.precision = None;
+            context.set_display_options(options);
+        }
+        ("precision", [digits]) => {
+            let digits = digits
+                .parse()
+                .ok()
+                .filter(|digits| *digits > 0)
+                .ok_or_else(invalid)?;
+            let mut options = *context.display_options();
+            options.precision = Some(digits);
+            context.set_display_options(options);
+        }
+        ("notation", [notation]) => {
+            context.set_notation(named(NOTATIONS, notation).ok_or_else(invalid)?)
+        }
+        ("base", radixes) if !radixes.is_empty() => {
+            let radixes: Option<Vec<Radix>> =
+                radixes.iter().map(|radix| named(RADIXES, radix)).collect();
+            context.set_display_radixes(&radixes.ok_or_else(invalid)?);
+        }
+        ("angle", ["rad"]) => context.set_angle_unit(AngleUnit::Radians),
+        ("angle", ["deg"]) => context.set_angle_unit(AngleUnit::Degrees),
+        ("backend", ["float"]) => modes.backend = Backend::Float,
+        ("backend", ["fixed", format]) => {
+            modes.backend = Backend::Fixed(fixed_format(format).ok_or_else(invalid)?)
+        }
+        ("backend", ["rational"]) => {
+            return Err(String::from(
+                "There is no rational backend, use fixed or money for exact results",
+            ))
+        }
+        ("backend", ["money", digits]) => {
+            let digits = digits.parse().ok().filter(|digits| *digits <= MAX_SCALE);
+            modes.backend = Backend::Money(digits.ok_or_else(invalid)?);
+        }
+        ("programmer", ["on"]) => modes.programmer = true,
+        ("programmer", ["off"]) => modes.programmer = false,
+        ("precision" | "notation" | "base" | "angle" | "backend" | "programmer", _) => {
+            return Err(invalid())
+        }
+        _ => return Err(format!("Unknown setting '{}'. {}", name, USAGE)),
+    }
+    Ok(())
+}
+
+/// The current settings, one per line.
+pub fn show(context: &Context, modes: &Modes) -> String {
+    let precision = match context.display_options().precision {
+        Some(digits) => digits.to_string(),
+        None => String::from("off"),
+    };
+    let notation = NOTATIONS
+        .iter()
+        .find(|(_, notation)| *notation == context.notation())
+        .map_or("plain", |(name, _)| name);
+    let radixes: Vec<&str> = context
+        .display_radixes()
+        .iter()
+        .filter_map(|radix| RADIXES.iter().find(|(_, other)| other == radix))
+        .map(|(name, _)| *name)
+        .collect();
+    let base = if radixes.is_empty() {
+        String::from("dec")
+    } else {
+        radixes.join(" ")
+    };
+    let angle = match context.angle_unit() {
+        AngleUnit::Radians => "rad",
+        AngleUnit::Degrees => "deg",
+    };
+    let backend = match modes.backend {
+        Backend::Float => String::from("float"),
+        Backend::Fixed(format) => format!("fixed {}", format),
+        Backend::Money(digits) => format!("money {}", digits),
+    };
+    format!(
+        "precision {}\nnotation {}\nbase {}\nangle {}\nbackend {}\nprogrammer {}",
+        precision,
+        notation,
+        base,
+        angle,
+        backend,
+        if modes.programmer { "on" } else { "off" }
+    )
+}
+
+fn named<T: Copy>(names: &[(&str, T)], name: &str) -> Option<T> {
+    names
+        .iter()
+        .find(|(other, _)| *other == name)
+        .map(|(_, value)| *value)
+}
+
+/// A format written as by its `Display`, `Q<integer bits>.<fraction bits>`.
+fn fixed_format(text: &str) -> Option<FixedFormat> {
+    let (integer, fraction) = text.strip_prefix('Q')?.split_once('.')?;
+    FixedFormat::new(integer.parse().ok()?, fraction.parse().ok()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_and_show() {
+        let mut context = Context::new();
+        let mut modes = Modes::default();
+        let mut set = |name, values: &[&str]| set(&mut context, &mut modes, name, values);
+        assert_eq!(set("precision", &["12"]), Ok(()));
+        assert_eq!(set("base", &["hex", "dec"]), Ok(()));
+        assert_eq!(set("angle", &["deg"]), Ok(()));
+        assert_eq!(set("notation", &["si"]), Ok(()));
+        assert_eq!(set("backend", &["fixed", "Q15.16"]), Ok(()));
+        assert_eq!(set("programmer", &["on"]), Ok(()));
+        assert_eq!(
+            set("precision", &["-1"]),
+            Err(String::from("Invalid value '-1' of 'precision'"))
+        );
+        assert!(set("backend", &["fixed", "Q60.10"]).is_err());
+        assert!(set("backend", &["rational"])
+            .unwrap_err()
+            .starts_with("There is no rational backend"));
+        assert!(set("speed", &["fast"])
+            .unwrap_err()
+            .starts_with("Unknown setting 'speed'"));
+        assert_eq!(
+            show(&context, &modes),
+            "precision 12\nnotation si\nbase hex dec\nangle deg\nbackend fixed Q15.16\nprogrammer on"
+        );
+    }
+}
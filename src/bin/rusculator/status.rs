@@ -0,0 +1,46 @@
+//! The exit statuses, telling scripts calling rusculator what went wrong.
+
+use std::process::ExitCode;
+
+use rusculator::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success = 0,
+    /// A formula was well formed but could not be evaluated.
+    EvaluationError = 1,
+    /// The arguments or a command were invalid, or the script could not be read.
+    UsageError = 2,
+    /// A formula could not be lexed or parsed.
+    ParseError = 3,
+}
+
+impl Status {
+    pub fn of(error: &Error) -> Status {
+        match error {
+            Error::Parser(_) => Status::ParseError,
+            Error::Evaluator(_) => Status::EvaluationError,
+        }
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> ExitCode {
+        ExitCode::from(status as u8)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusculator::Context;
+
+    #[test]
+    fn classify_errors() {
+        let mut context = Context::new();
+        let status = |context: &mut Context, source| Status::of(&context.eval(source).unwrap_err());
+        assert_eq!(status(&mut context, "1 +"), Status::ParseError);
+        assert_eq!(status(&mut context, "1 + @"), Status::ParseError);
+        assert_eq!(status(&mut context, "sqrt(x)"), Status::EvaluationError);
+    }
+}
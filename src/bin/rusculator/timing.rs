@@ -0,0 +1,201 @@
+//! The `:time` command, benchmarking the parsing, compilation and evaluation of a formula.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rusculator::{CompileOptions, Compiled, Context, Error, Expr, Parser, Value};
+
+/// Runs of a formula timed without a count or duration.
+const DEFAULT_RUNS: u64 = 1000;
+
+/// Runs between looks at the clock when timing for a duration.
+const BATCH: u64 = 64;
+
+/// Calls of user-defined functions with bodies of at most this many nodes are inlined so
+/// that formulas calling them still compile.
+const INLINE_SIZE: usize = 64;
+
+/// How long a formula is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Budget {
+    Runs(u64),
+    For(Duration),
+}
+
+/// Splits `1000x sin(2)` or `2s sin(2)` into how long to evaluate and the formula.
+fn budget(text: &str) -> Result<(Budget, &str), String> {
+    let text = text.trim();
+    let (word, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let parsed = if let Some(runs) = word.strip_suffix('x') {
+        runs.parse().ok().filter(|runs| *runs > 0).map(Budget::Runs)
+    } else if let Some(millis) = word.strip_suffix("ms") {
+        millis
+            .parse()
+            .ok()
+            .map(|millis| Budget::For(Duration::from_millis(millis)))
+    } else if let Some(seconds) = word.strip_suffix('s') {
+        seconds
+            .parse()
+            .ok()
+            .map(|seconds| Budget::For(Duration::from_secs(seconds)))
+    } else {
+        None
+    };
+    match parsed {
+        Some(_) if rest.trim().is_empty() => Err(String::from("Expected a formula to time")),
+        Some(budget) => Ok((budget, rest.trim())),
+        None if text.is_empty() => Err(String::from("Expected a formula to time")),
+        None => Ok((Budget::Runs(DEFAULT_RUNS), text)),
+    }
+}
+
+/// Times the formula of `text`, optionally after a count of runs as `1000x` or a duration
+/// as `2s` or `500ms`, with the results of the REPL substituted. Numeric formulas are
+/// compiled, and others are timed with the tree-walking evaluator.
+pub fn time(
+    text: &str,
+    context: &Context,
+    results: &HashMap<String, Expr>,
+) -> Result<String, String> {
+    let (budget, formula) = budget(text).map_err(|message| format!("Error: {}", message))?;
+    let failed = |error: Error| format!("Error: {}", error);
+    let start = Instant::now();
+    let expr = Parser::new(formula)
+        .parse()
+        .map_err(|error| failed(error.into()))?;
+    let parse = start.elapsed();
+    let expr = expr.substitute_all(results);
+    let mut lines = vec![format!("parse    {}", duration(parse))];
+
+    let start = Instant::now();
+    let options = CompileOptions {
+        inline_size: Some(INLINE_SIZE),
+    };
+    let compiled = expr.compile_with(context, &options).map(Compiled::from);
+    let compile = start.elapsed();
+    let inputs = compiled.as_ref().ok().and_then(|compiled| {
+        let names = compiled.program().inputs();
+        names
+            .iter()
+            .map(|name| match context.variable(name) {
+                Some(Value::Number(n)) => Some(*n),
+                _ => None,
+            })
+            .collect::<Option<Vec<f64>>>()
+    });
+
+    let (runs, elapsed, result, path) = match (&compiled, inputs) {
+        (Ok(compiled), Some(inputs)) => {
+            lines.push(format!("compile  {}", duration(compile)));
+            let result = compiled
+                .eval(&inputs)
+                .map_err(|error| failed(error.into()))?;
+            let (runs, elapsed) = repeat(budget, || {
+                black_box(compiled.eval(black_box(&inputs)).ok());
+            });
+            let path = if compiled.is_native() {
+                "native"
+            } else {
+                "bytecode"
+            };
+            (runs, elapsed, context.format(&Value::Number(result)), path)
+        }
+        (compiled, _) => {
+            let reason = match compiled {
+                Err(error) => error.to_string(),
+                Ok(_) => String::from("Inputs other than numbers"),
+            };
+            lines.push(format!("compile  n/a: {}", reason));
+            let result = context
+                .evaluate(&expr)
+                .map_err(|error| failed(error.into()))?;
+            let (runs, elapsed) = repeat(budget, || {
+                black_box(context.evaluate(black_box(&expr)).ok());
+            });
+            (runs, elapsed, context.format(&result), "tree-walking")
+        }
+    };
+    lines.push(format!(
+        "evaluate {} each, {} runs ({})",
+        duration(elapsed.div_f64(runs as f64)),
+        runs,
+        path
+    ));
+    lines.push(format!("result   {}", result));
+    Ok(lines.join("\n"))
+}
+
+/// Runs `evaluate` within `budget`, returning how many times and for how long.
+fn repeat(budget: Budget, mut evaluate: impl FnMut()) -> (u64, Duration) {
+    let start = Instant::now();
+    let mut runs = 0;
+    match budget {
+        Budget::Runs(count) => {
+            while runs < count {
+                evaluate();
+                runs += 1;
+            }
+        }
+        Budget::For(limit) => {
+            while runs == 0 || start.elapsed() < limit {
+                for _ in 0..BATCH {
+                    evaluate();
+                }
+                runs += BATCH;
+            }
+        }
+    }
+    (runs, start.elapsed())
+}
+
+/// The duration with the unit keeping it between 1 and 1000.
+fn duration(duration: Duration) -> String {
+    let nanos = duration.as_secs_f64() * 1e9;
+    if nanos < 1e3 {
+        format!("{:.0} ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.1} µs", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.1} ms", nanos / 1e6)
+    } else {
+        format!("{:.2} s", nanos / 1e9)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_budgets() {
+        assert_eq!(budget("sin(2)"), Ok((Budget::Runs(DEFAULT_RUNS), "sin(2)")));
+        assert_eq!(budget("10x  1 + 2"), Ok((Budget::Runs(10), "1 + 2")));
+        let half = Duration::from_millis(500);
+        assert_eq!(budget("500ms x^2"), Ok((Budget::For(half), "x^2")));
+        assert_eq!(
+            budget("2s x"),
+            Ok((Budget::For(Duration::from_secs(2)), "x"))
+        );
+        assert_eq!(budget("x"), Ok((Budget::Runs(DEFAULT_RUNS), "x")));
+        assert!(budget("10x").is_err());
+        assert!(budget(" ").is_err());
+        assert_eq!(duration(Duration::from_nanos(85)), "85 ns");
+        assert_eq!(duration(Duration::from_micros(1500)), "1.5 ms");
+    }
+
+    #[test]
+    fn time_formulas() {
+        let mut context = Context::new();
+        context.set_variable("y", Value::Number(3.0));
+        let output = time("10x y * 2", &context, &HashMap::new()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("parse "));
+        assert!(lines[1].starts_with("compile ") && !lines[1].contains("n/a"));
+        assert!(lines[2].contains("each, 10 runs ("));
+        assert_eq!(lines[3], "result   6");
+        let output = time("10x \"a\"", &context, &HashMap::new()).unwrap();
+        assert!(output.contains("(tree-walking)"));
+        assert!(time("1 / z", &context, &HashMap::new()).is_err());
+    }
+}
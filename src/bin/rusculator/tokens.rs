@@ -0,0 +1,60 @@
+//! The tokens printed by `--tokens` and `:tokens`, showing how formulas are lexed.
+
+use rusculator::{Lexer, LexerError, Token};
+
+/// The tokens of `source`, one per line with its kind and span, up to the error of the
+/// first character which cannot start a token, if any.
+pub fn list(source: &str) -> (String, Option<LexerError>) {
+    let mut lexer = Lexer::new(source);
+    let mut lines = vec![];
+    loop {
+        match lexer.next_spanned_token() {
+            Ok(Some((token, span))) => {
+                let kind = match token {
+                    Token::Number(_) => "number",
+                    Token::Identifier(_) => "identifier",
+                    Token::String(_) => "string",
+                    Token::Operator(_) => "operator",
+                    Token::OpenParenthesis | Token::ClosedParenthesis => "parenthesis",
+                    Token::Comma => "comma",
+                };
+                let text = token.to_string();
+                lines.push(format!(
+                    "{:<11} {:<8} {}..{}",
+                    kind, text, span.start, span.end
+                ));
+            }
+            Ok(None) => return (lines.join("\n"), None),
+            Err(error) => return (lines.join("\n"), Some(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn list_tokens() {
+        assert_eq!(
+            list("f(2.5, \"a\") >= x"),
+            (
+                String::from(
+                    "\
+identifier  f        0..1
+parenthesis (        1..2
+number      2.5      2..5
+comma       ,        5..6
+string      \"a\"      7..10
+parenthesis )        10..11
+operator    >=       12..14
+identifier  x        15..16"
+                ),
+                None
+            )
+        );
+        let (tokens, error) = list("1 + #");
+        assert_eq!(tokens.lines().count(), 2);
+        assert_eq!(error.map(|error| error.span.start), Some(4));
+    }
+}
@@ -0,0 +1,194 @@
+//! `rusculator tui`, a full-screen calculator: the modes on top, the formulas entered and
+//! their results, scrolled with `PageUp` and `PageDown`, the variables beside them and the
+//! formula being typed at the bottom, edited with the keys of the REPL.
+
+use std::io::{self, Read, Write};
+
+use crate::editor::{self, read_key, terminal, Action, History, Key, Line};
+use crate::repl::Repl;
+use crate::script;
+use crate::settings;
+
+const PROMPT: &str = "> ";
+
+/// Columns of the panel of the variables, at most.
+const PANEL: usize = 32;
+
+/// Switches to the alternate screen of the terminal, and back when dropped.
+struct Screen;
+
+impl Screen {
+    fn enter(output: &mut impl Write) -> io::Result<Screen> {
+        write!(output, "\x1b[?1049h")?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let mut output = io::stdout();
+        let _ = write!(output, "\x1b[?1049l");
+        let _ = output.flush();
+    }
+}
+
+/// What is shown besides the formula being typed.
+#[derive(Default)]
+struct View {
+    /// The formulas entered and what they wrote, line by line.
+    transcript: Vec<String>,
+    /// Lines scrolled back from the end of the transcript.
+    scroll: usize,
+}
+
+/// `text` cut or padded with spaces to `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().take(width).collect();
+    let count = fitted.chars().count();
+    fitted.extend(std::iter::repeat_n(' ', width - count));
+    fitted
+}
+
+/// The rows of the screen of `width` by `height` as plain text, and the column of the
+/// cursor on the last.
+fn layout(
+    view: &View,
+    modes: &str,
+    variables: &[String],
+    line: &Line,
+    width: usize,
+    height: usize,
+) -> (Vec<String>, usize) {
+    let mut rows = vec![fit(&format!(" rusculator  {}", modes), width)];
+    let panel = PANEL.min(width / 3);
+    let pane = width.saturating_sub(panel + 1);
+    let body = height.saturating_sub(2);
+    let end = view.transcript.len().saturating_sub(view.scroll);
+    let start = end.saturating_sub(body);
+    let shown = &view.transcript[start..end];
+    let mut panel_lines = vec![String::from("Variables")];
+    panel_lines.extend(variables.iter().cloned());
+    for index in 0..body {
+        // The transcript ends at the bottom of its pane, as in a terminal.
+        let offset = index + shown.len();
+        let left = offset
+            .checked_sub(body)
+            .map_or("", |index| shown[index].as_str());
+        let right = panel_lines.get(index).map_or("", String::as_str);
+        rows.push(format!("{}│{}", fit(left, pane), fit(right, panel)));
+    }
+    // Long formulas scroll to keep the cursor in sight.
+    let room = width.saturating_sub(PROMPT.len() + 1).max(1);
+    let skipped = line.cursor().saturating_sub(room);
+    let visible: String = line.text().chars().skip(skipped).collect();
+    rows.push(fit(&format!("{}{}", PROMPT, visible), width));
+    rows.truncate(height);
+    (rows, PROMPT.len() + line.cursor() - skipped)
+}
+
+fn draw(output: &mut impl Write, rows: &[String], cursor: usize, colors: bool) -> io::Result<()> {
+    write!(output, "\x1b[H")?;
+    for (index, row) in rows.iter().enumerate() {
+        if index > 0 {
+            write!(output, "\r\n")?;
+        }
+        match index {
+            0 if colors => write!(output, "\x1b[7m{}\x1b[0m", row)?,
+            _ => write!(output, "{}", row)?,
+        }
+    }
+    write!(output, "\x1b[{};{}H", rows.len(), cursor + 1)?;
+    output.flush()
+}
+
+/// Runs the full-screen calculator on the terminal until `quit` or `Ctrl-D`.
+pub fn run(repl: &mut Repl, colors: bool) -> io::Result<()> {
+    let mut output = io::stdout();
+    let _raw = terminal::RawMode::enable()?;
+    let _screen = Screen::enter(&mut output)?;
+    let mut bytes = io::stdin().lock().bytes().map_while(Result::ok);
+    let mut history = History::load(editor::Editor::history_path());
+    let mut view = View::default();
+    let mut line = Line::default();
+    loop {
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+        let modes = settings::show(repl.context(), repl.modes()).replace('\n', " | ");
+        let variables: Vec<String> = repl
+            .context()
+            .variables()
+            .into_iter()
+            .map(|(name, value)| {
+                script::definition(name, value, |value| repl.context().format(value))
+            })
+            .collect();
+        let (rows, cursor) = layout(&view, &modes, &variables, &line, width, height);
+        draw(&mut output, &rows, cursor, colors)?;
+        let page = height.saturating_sub(3).max(1);
+        let action = match read_key(&mut bytes) {
+            Some(Key::PageUp) => {
+                let most = view.transcript.len().saturating_sub(1);
+                view.scroll = (view.scroll + page).min(most);
+                continue;
+            }
+            Some(Key::PageDown) => {
+                view.scroll = view.scroll.saturating_sub(page);
+                continue;
+            }
+            Some(key) => {
+                let complete = |prefix: &str| repl.context().completions(prefix);
+                line.handle(key, &history, &complete)
+            }
+            None => Action::EndOfInput,
+        };
+        match action {
+            Action::Edited => {}
+            Action::Candidates(candidates) => view.transcript.push(candidates.join("  ")),
+            Action::Done(text) => {
+                line = Line::default();
+                if text.trim().is_empty() {
+                    continue;
+                }
+                history.push(&text);
+                let Some(response) = repl.respond(&text) else {
+                    return Ok(());
+                };
+                view.transcript.push(format!("{}{}", PROMPT, text));
+                view.transcript.extend(response.lines().map(String::from));
+                view.scroll = 0;
+            }
+            Action::EndOfInput => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lay_out_the_screen() {
+        let view = View {
+            transcript: (1..=5).map(|n| format!("${} = {}", n, n * n)).collect(),
+            scroll: 1,
+        };
+        let mut line = Line::default();
+        let history = History::load(None);
+        for key in [Key::Char('x'), Key::Char('+'), Key::Char('1'), Key::Left] {
+            line.handle(key, &history, &|_| vec![]);
+        }
+        let variables = [String::from("x = 2"), String::from("f(x) = x^2")];
+        let (rows, cursor) = layout(&view, "angle rad", &variables, &line, 30, 6);
+        assert_eq!(
+            rows,
+            [
+                " rusculator  angle rad        ",
+                "$1 = 1             │Variables ",
+                "$2 = 4             │x = 2     ",
+                "$3 = 9             │f(x) = x^2",
+                "$4 = 16            │          ",
+                "> x+1                         ",
+            ]
+        );
+        assert_eq!(cursor, 4);
+    }
+}
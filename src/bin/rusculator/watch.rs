@@ -0,0 +1,70 @@
+//! `rusculator watch`, running a script again whenever it changes.
+
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+/// How often the script is looked at.
+pub const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Notices changes of a file by its modification time and length, which editors saving
+/// within the resolution of the clock still change most of the time.
+pub struct Watcher {
+    path: String,
+    stamp: Option<(SystemTime, u64)>,
+    /// Whether the file could not be read when last looked at.
+    missing: bool,
+}
+
+impl Watcher {
+    pub fn new(path: &str) -> Watcher {
+        Watcher {
+            path: path.to_string(),
+            stamp: None,
+            missing: false,
+        }
+    }
+
+    /// The contents of the file the first time and whenever it changed since, or the error
+    /// when it can no longer be read.
+    pub fn poll(&mut self) -> Option<io::Result<String>> {
+        let stamp = fs::metadata(&self.path).and_then(|metadata| {
+            let modified = metadata.modified()?;
+            Ok((modified, metadata.len()))
+        });
+        match stamp {
+            Ok(stamp) if self.stamp == Some(stamp) => None,
+            Ok(stamp) => {
+                self.stamp = Some(stamp);
+                self.missing = false;
+                Some(fs::read_to_string(&self.path))
+            }
+            Err(_) if self.missing => None,
+            Err(error) => {
+                self.stamp = None;
+                self.missing = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn poll_changes() {
+        let path = std::env::temp_dir().join(format!("rusculator-watch-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "x = 1\n").unwrap();
+        let mut watcher = Watcher::new(path);
+        assert_eq!(watcher.poll().unwrap().unwrap(), "x = 1\n");
+        assert!(watcher.poll().is_none());
+        fs::write(path, "x = 1\nx + 1\n").unwrap();
+        assert_eq!(watcher.poll().unwrap().unwrap(), "x = 1\nx + 1\n");
+        fs::remove_file(path).unwrap();
+        assert!(watcher.poll().unwrap().is_err());
+        assert!(watcher.poll().is_none());
+    }
+}
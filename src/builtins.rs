@@ -0,0 +1,1365 @@
+use std::f64::consts;
+
+use crate::date::Date;
+use crate::evaluator::{Evaluator, EvaluatorError, EvaluatorErrorKind, MAX_WORD_SIZE};
+use crate::integer;
+use crate::limit::Approach;
+use crate::numeric;
+use crate::parser::{BinaryOperator, Expr, ExprKind};
+use crate::partial;
+use crate::radix;
+use crate::rounding::{self, RoundingMode};
+use crate::units::Quantity;
+use crate::value::{Function, Value};
+
+pub(crate) type ValuesFunction = fn(&[Value]) -> Result<Value, EvaluatorError>;
+pub(crate) type SpecialFunction = fn(&mut Evaluator, &[Expr]) -> Result<Value, EvaluatorError>;
+
+pub(crate) enum BuiltinKind {
+    /// Plain `f64 -> f64` function of one argument.
+    Numeric(fn(f64) -> f64),
+    /// Function of already evaluated arguments.
+    Values(ValuesFunction),
+    /// Function receiving its arguments unevaluated, for bound variables and laziness.
+    Special(SpecialFunction),
+}
+
+pub(crate) struct Builtin {
+    pub name: &'static str,
+    pub min_args: usize,
+    pub max_args: Option<usize>,
+    pub kind: BuiltinKind,
+}
+
+impl Builtin {
+    const fn numeric(name: &'static str, function: fn(f64) -> f64) -> Builtin {
+        Builtin {
+            name,
+            min_args: 1,
+            max_args: Some(1),
+            kind: BuiltinKind::Numeric(function),
+        }
+    }
+
+    const fn values(
+        name: &'static str,
+        min_args: usize,
+        max_args: Option<usize>,
+        function: ValuesFunction,
+    ) -> Builtin {
+        Builtin {
+            name,
+            min_args,
+            max_args,
+            kind: BuiltinKind::Values(function),
+        }
+    }
+
+    const fn special(
+        name: &'static str,
+        min_args: usize,
+        max_args: Option<usize>,
+        function: SpecialFunction,
+    ) -> Builtin {
+        Builtin {
+            name,
+            min_args,
+            max_args,
+            kind: BuiltinKind::Special(function),
+        }
+    }
+
+    pub fn check_arity(&self, found: usize) -> Result<(), EvaluatorError> {
+        if found < self.min_args || self.max_args.is_some_and(|max| found > max) {
+            return Err(argument_count(
+                self.name,
+                self.min_args,
+                self.max_args,
+                found,
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn call(&self, args: &[Value]) -> Result<Value, EvaluatorError> {
+        match self.kind {
+            BuiltinKind::Numeric(function) => match &args[0] {
+                Value::Uncertain(uncertain) => Ok(Value::Uncertain(uncertain.map(function))),
+                arg => Ok(Value::Number(function(arg.as_number()?))),
+            },
+            BuiltinKind::Values(function) => function(args),
+            BuiltinKind::Special(_) => {
+                Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                    format!("'{}' cannot be called indirectly", self.name),
+                )))
+            }
+        }
+    }
+}
+
+fn argument_count(name: &str, min: usize, max: Option<usize>, found: usize) -> EvaluatorError {
+    EvaluatorError::new(EvaluatorErrorKind::ArgumentCount {
+        function: name.to_string(),
+        min,
+        max,
+        found,
+    })
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin::numeric("sqrt", f64::sqrt),
+    Builtin::numeric("abs", f64::abs),
+    Builtin::numeric("sin", f64::sin),
+    Builtin::numeric("cos", f64::cos),
+    Builtin::numeric("tan", f64::tan),
+    Builtin::numeric("asin", f64::asin),
+    Builtin::numeric("acos", f64::acos),
+    Builtin::numeric("atan", f64::atan),
+    Builtin::values("atan2", 2, Some(2), atan2),
+    Builtin::numeric("sinh", f64::sinh),
+    Builtin::numeric("cosh", f64::cosh),
+    Builtin::numeric("tanh", f64::tanh),
+    Builtin::numeric("asinh", f64::asinh),
+    Builtin::numeric("acosh", f64::acosh),
+    Builtin::numeric("atanh", f64::atanh),
+    Builtin::numeric("floor", f64::floor),
+    Builtin::numeric("ceil", f64::ceil),
+    Builtin::numeric("trunc", f64::trunc),
+    Builtin::numeric("frac", f64::fract),
+    Builtin::values("round", 1, Some(3), round),
+    Builtin::values("roundsig", 2, Some(3), round_significant),
+    Builtin::numeric("exp", f64::exp),
+    Builtin::numeric("expm1", f64::exp_m1),
+    Builtin::numeric("ln", f64::ln),
+    Builtin::numeric("ln1p", f64::ln_1p),
+    Builtin::numeric("log10", f64::log10),
+    Builtin::numeric("log2", f64::log2),
+    Builtin::values("log", 1, Some(2), log),
+    Builtin::special("convert", 2, Some(2), convert),
+    Builtin::special("tobase", 2, Some(2), to_base),
+    Builtin::values("date", 1, Some(3), date),
+    Builtin::values("frombase", 2, Some(2), from_base),
+    Builtin::special("integrate", 3, Some(5), integrate),
+    Builtin::special("diff", 2, Some(5), diff),
+    Builtin::special("solve", 1, Some(4), solve),
+    Builtin::special("solve_sym", 1, Some(2), solve_symbolic),
+    Builtin::special("derive", 1, Some(2), derive),
+    Builtin::special("integrate_sym", 1, Some(2), integrate_symbolic),
+    Builtin::special("series", 3, Some(4), series),
+    Builtin::special("limit", 2, Some(4), limit),
+    Builtin::special("expand", 1, Some(1), expand),
+    Builtin::special("factor", 1, Some(1), factor),
+    Builtin::special("piecewise", 1, None, piecewise),
+    Builtin::values("min", 1, None, min),
+    Builtin::values("max", 1, None, max),
+    Builtin::values("clamp", 3, Some(3), clamp),
+    Builtin::values("sum", 1, None, sum),
+    Builtin::values("avg", 1, None, avg),
+    Builtin::values("isprime", 1, Some(1), is_prime),
+    Builtin::values("nextprime", 1, Some(1), next_prime),
+    Builtin::values("prevprime", 1, Some(1), prev_prime),
+    Builtin::values("factorint", 1, Some(1), factor_int),
+    Builtin::values("powmod", 3, Some(3), pow_mod),
+    Builtin::values("invmod", 2, Some(2), inv_mod),
+    Builtin::special("popcount", 1, Some(1), pop_count),
+    Builtin::special("bitlength", 1, Some(1), bit_length),
+    Builtin::special("bit", 2, Some(2), bit),
+    Builtin::special("setbit", 2, Some(2), set_bit),
+    Builtin::special("clearbit", 2, Some(2), clear_bit),
+    Builtin::special("rotl", 2, Some(2), rotate_left),
+    Builtin::special("rotr", 2, Some(2), rotate_right),
+    Builtin::values("not", 1, Some(1), not),
+    Builtin::values("and", 1, None, and),
+    Builtin::values("or", 1, None, or),
+    Builtin::values("xor", 1, None, xor),
+];
+
+const CONSTANTS: &[(&str, f64)] = &[("pi", consts::PI), ("e", consts::E), ("inf", f64::INFINITY)];
+
+pub(crate) fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// The names of the builtin functions and constants.
+pub(crate) fn names() -> impl Iterator<Item = &'static str> {
+    let constants = CONSTANTS.iter().map(|(name, _)| *name);
+    let functions = BUILTINS.iter().map(|builtin| builtin.name);
+    functions.chain(constants).chain(["true", "false"])
+}
+
+pub(crate) fn boolean(name: &str) -> Option<bool> {
+    match name {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+pub(crate) fn constant(name: &str) -> Option<f64> {
+    CONSTANTS
+        .iter()
+        .find(|(constant, _)| *constant == name)
+        .map(|(_, value)| *value)
+}
+
+/// A real function of one variable given as an argument: either a function value
+/// (`x -> x^2`, `sin`) or an expression together with its bound variable (`x^2, x`).
+enum UnaryFunction<'e> {
+    Function(Function),
+    Bound(&'e Expr, String),
+}
+
+impl UnaryFunction<'_> {
+    /// Splits `args` into the function and the remaining arguments.
+    fn from_args<'e>(
+        evaluator: &mut Evaluator,
+        name: &str,
+        args: &'e [Expr],
+    ) -> Result<(UnaryFunction<'e>, &'e [Expr]), EvaluatorError> {
+        if let Some(function) = evaluator.function_argument(&args[0])? {
+            return Ok((UnaryFunction::Function(function), &args[1..]));
+        }
+        match args.get(1).map(|arg| &arg.kind) {
+            Some(ExprKind::Variable(variable)) => {
+                Ok((UnaryFunction::Bound(&args[0], variable.clone()), &args[2..]))
+            }
+            _ => Err(
+                EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+                    "'{}' expects a function or an expression followed by its variable",
+                    name
+                )))
+                .or_span(args[0].span),
+            ),
+        }
+    }
+
+    fn call(&self, evaluator: &mut Evaluator, x: f64) -> Result<f64, EvaluatorError> {
+        match self {
+            UnaryFunction::Function(function) => {
+                evaluator.call(function, &[Value::Number(x)])?.as_number()
+            }
+            UnaryFunction::Bound(expr, variable) => evaluator
+                .with_locals(vec![(variable.clone(), Value::Number(x))], |evaluator| {
+                    evaluator.evaluate(expr)
+                })?
+                .as_number(),
+        }
+    }
+}
+
+fn number_argument(evaluator: &mut Evaluator, arg: &Expr) -> Result<f64, EvaluatorError> {
+    evaluator
+        .evaluate(arg)?
+        .as_number()
+        .map_err(|error| error.or_span(arg.span))
+}
+
+fn invalid_argument(message: String) -> EvaluatorError {
+    EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(message))
+}
+
+/// Checks the number of arguments following a `UnaryFunction`.
+fn check_remaining(
+    name: &str,
+    args: &[Expr],
+    rest: &[Expr],
+    min: usize,
+    max: usize,
+) -> Result<(), EvaluatorError> {
+    if rest.len() < min || rest.len() > max {
+        let bound = args.len() - rest.len();
+        return Err(argument_count(
+            name,
+            bound + min,
+            Some(bound + max),
+            args.len(),
+        ));
+    }
+    Ok(())
+}
+
+/// `convert(quantity, unit)`, also written `quantity in unit`. Amounts of money are
+/// exchanged using the rates of the context.
+fn convert(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let value = evaluator.evaluate(&args[0])?;
+    let target = evaluator.evaluate(&args[1])?;
+    let target = match &target {
+        Value::Quantity(target) if target.value == 1.0 => target,
+        _ => {
+            return Err(
+                invalid_argument(format!("Cannot convert to {}, expected a unit", target))
+                    .or_span(args[1].span),
+            )
+        }
+    };
+    let quantity = match evaluator.exchange(value, &target.unit)? {
+        Value::Quantity(quantity) => quantity,
+        value => Quantity::dimensionless(value.as_number()?),
+    };
+    Ok(Value::Quantity(quantity.convert(&target.unit)?))
+}
+
+fn base_argument(value: &Value) -> Result<u32, EvaluatorError> {
+    let base = value.as_number()?;
+    if base.fract() != 0.0 || !(radix::MIN_BASE as f64..=radix::MAX_BASE as f64).contains(&base) {
+        return Err(invalid_argument(format!(
+            "The base must be an integer from {} to {}, found {}",
+            radix::MIN_BASE,
+            radix::MAX_BASE,
+            base
+        )));
+    }
+    Ok(base as u32)
+}
+
+/// `tobase(value, base)`: the digits of an integer in `base`, as a string.
+fn to_base(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let value = number_argument(evaluator, &args[0])?;
+    let base = evaluator.evaluate(&args[1])?;
+    let base = base_argument(&base).map_err(|error| error.or_span(args[1].span))?;
+    match radix::to_base(value, base) {
+        Some(digits) => {
+            evaluator.check_digits(digits.trim_start_matches('-').len())?;
+            Ok(Value::String(digits))
+        }
+        None => Err(invalid_argument(format!(
+            "Cannot write {} in base {}, expected an integer",
+            value, base
+        ))),
+    }
+}
+
+/// `frombase("ff", base)`: the integer written by a string of digits in `base`.
+fn from_base(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let Value::String(digits) = &args[0] else {
+        return Err(EvaluatorError::new(EvaluatorErrorKind::TypeMismatch {
+            expected: "string",
+            found: args[0].type_name(),
+        }));
+    };
+    let base = base_argument(&args[1])?;
+    match radix::from_base(digits, base) {
+        Some(value) => Ok(Value::Number(value)),
+        None => Err(invalid_argument(format!(
+            "'{}' is not an integer in base {}",
+            digits, base
+        ))),
+    }
+}
+
+/// `piecewise((condition, value), ..., otherwise)`: the value of the first case whose
+/// condition is true, or the optional last argument. Conditions are evaluated from left to
+/// right and only the chosen value is.
+fn piecewise(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    for (index, arg) in args.iter().enumerate() {
+        match &arg.kind {
+            ExprKind::Tuple(case) if case.len() == 2 => {
+                if evaluator
+                    .evaluate(&case[0])?
+                    .is_true()
+                    .map_err(|error| error.or_span(case[0].span))?
+                {
+                    return evaluator.evaluate(&case[1]);
+                }
+            }
+            _ if index == args.len() - 1 => return evaluator.evaluate(arg),
+            _ => {
+                return Err(invalid_argument(String::from(
+                    "The cases of 'piecewise' must be pairs (condition, value)",
+                ))
+                .or_span(arg.span))
+            }
+        }
+    }
+    Err(invalid_argument(String::from(
+        "No case of 'piecewise' applies",
+    )))
+}
+
+/// The rounding mode named by an optional string argument, rounding half up by default.
+fn rounding_mode(arg: Option<&Value>) -> Result<RoundingMode, EvaluatorError> {
+    match arg {
+        None => Ok(RoundingMode::HalfUp),
+        Some(Value::String(name)) => RoundingMode::from_name(name),
+        Some(arg) => Err(EvaluatorError::new(EvaluatorErrorKind::TypeMismatch {
+            expected: "string",
+            found: arg.type_name(),
+        })),
+    }
+}
+
+/// `round(x, ndigits, mode)`: `x` rounded to `ndigits` decimals, none by default, or to
+/// tens, hundreds... for negative `ndigits`.
+fn round(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let x = args[0].as_number()?;
+    let decimals = match args.get(1) {
+        Some(arg) => integer_argument("round", arg)?,
+        None => 0,
+    };
+    let mode = rounding_mode(args.get(2))?;
+    let last = -decimals.clamp(-400, 400) as i32;
+    Ok(Value::Number(rounding::round_at(x, mode, |_| last)))
+}
+
+/// `roundsig(x, sigfigs, mode)`: `x` rounded to `sigfigs` significant digits.
+fn round_significant(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let x = args[0].as_number()?;
+    let digits = integer_argument("roundsig", &args[1])?;
+    if digits < 1 {
+        return Err(invalid_argument(String::from(
+            "'roundsig' keeps at least one significant digit",
+        )));
+    }
+    let mode = rounding_mode(args.get(2))?;
+    let digits = digits.min(400) as i32;
+    Ok(Value::Number(rounding::round_at(x, mode, |exponent| {
+        exponent + 1 - digits
+    })))
+}
+
+/// `log(x, base)`, the base being 10 if omitted.
+fn log(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let x = args[0].as_number()?;
+    Ok(Value::Number(match args.get(1) {
+        Some(base) => x.log(base.as_number()?),
+        None => x.log10(),
+    }))
+}
+
+/// The angle in radians of the point `(x, y)`, from -pi to pi.
+fn atan2(args: &[Value]) -> Result<Value, EvaluatorError> {
+    Ok(Value::Number(
+        args[0].as_number()?.atan2(args[1].as_number()?),
+    ))
+}
+
+/// Bound of the integers which numbers represent exactly.
+const MAX_SAFE_INTEGER: f64 = 9007199254740992.0;
+
+/// The argument as an integer, of magnitude at most 2^53.
+fn integer_argument(name: &str, arg: &Value) -> Result<i64, EvaluatorError> {
+    let value = arg.as_number()?;
+    if value.fract() != 0.0 || value.abs() > MAX_SAFE_INTEGER {
+        return Err(invalid_argument(format!(
+            "'{}' expects integers up to 2^53, found {}",
+            name, value
+        )));
+    }
+    Ok(value as i64)
+}
+
+/// Bound of the integers of the number theory functions, those of 64 bits.
+const WIDE_INTEGER_BOUND: f64 = 18446744073709551616.0;
+
+/// The argument as an integer, of magnitude below 2^64. Beyond 2^53 it is the number the
+/// argument rounded to: `2^61 - 1` is 2^61.
+fn wide_integer_argument(name: &str, arg: &Value) -> Result<i128, EvaluatorError> {
+    let value = arg.as_number()?;
+    if value.fract() != 0.0 || value.abs() >= WIDE_INTEGER_BOUND {
+        return Err(invalid_argument(format!(
+            "'{}' expects integers below 2^64, found {}",
+            name, value
+        )));
+    }
+    Ok(value as i128)
+}
+
+/// Whether the argument, below 2^64, is prime. Numbers from 2^53 on are even, so none of
+/// them is.
+fn is_prime(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let n = wide_integer_argument("isprime", &args[0])?;
+    Ok(Value::Bool(n > 0 && integer::is_prime(n as u64)))
+}
+
+/// The smallest prime above the argument, up to 2^53 beyond which numbers are not exact:
+/// `nextprime(2^53 - 1)` fails, the next prime being larger.
+fn next_prime(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let n = wide_integer_argument("nextprime", &args[0])?.max(1);
+    match integer::next_prime(n as u64) {
+        Some(prime) if prime as f64 <= MAX_SAFE_INTEGER => Ok(Value::Number(prime as f64)),
+        _ => Err(invalid_argument(format!(
+            "The prime after {} is beyond 2^53",
+            n
+        ))),
+    }
+}
+
+/// The largest prime below the argument, if it is at most 2^53: `prevprime(2^60)` fails, the
+/// prime being beyond.
+fn prev_prime(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let n = wide_integer_argument("prevprime", &args[0])?.max(0);
+    match integer::prev_prime(n as u64) {
+        Some(prime) if prime as f64 <= MAX_SAFE_INTEGER => Ok(Value::Number(prime as f64)),
+        Some(_) => Err(invalid_argument(format!(
+            "The prime below {} is beyond 2^53",
+            n
+        ))),
+        None => Err(invalid_argument(format!("There is no prime below {}", n))),
+    }
+}
+
+/// The list of prime factors, repeated by multiplicity: `factorint(12)` is `[2, 2, 3]`. The
+/// argument is below 2^64, and a number that large is a power of two times an integer
+/// below 2^53, so the factors are exact.
+fn factor_int(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let n = wide_integer_argument("factorint", &args[0])?;
+    if n < 1 {
+        return Err(invalid_argument(String::from(
+            "'factorint' expects a positive integer",
+        )));
+    }
+    let factors = integer::factorize(n as u64);
+    Ok(Value::List(
+        factors
+            .into_iter()
+            .map(|p| Value::Number(p as f64))
+            .collect(),
+    ))
+}
+
+/// The modulus argument of `name`, which must be positive.
+fn modulus_argument(name: &str, arg: &Value) -> Result<u64, EvaluatorError> {
+    let m = wide_integer_argument(name, arg)?;
+    if m < 1 {
+        return Err(invalid_argument(format!(
+            "The modulus of '{}' must be positive",
+            name
+        )));
+    }
+    Ok(m as u64)
+}
+
+/// `a` modulo `m`, from 0 to `m - 1`.
+fn residue(a: i128, m: u64) -> u64 {
+    a.rem_euclid(i128::from(m)) as u64
+}
+
+/// A residue of `name` as a number, failing if it is beyond 2^53 and not exact.
+fn residue_number(name: &str, r: u64) -> Result<Value, EvaluatorError> {
+    let value = r as f64;
+    if value as u64 != r {
+        return Err(invalid_argument(format!(
+            "'{}': the result {} is beyond 2^53",
+            name, r
+        )));
+    }
+    Ok(Value::Number(value))
+}
+
+/// `powmod(a, b, m)`, `a^b mod m` from 0 to `m - 1`. Negative exponents are powers of the
+/// inverse of `a`. The arguments are below 2^64 and the products are kept in 128 bits, so
+/// `powmod(2^60, 2, 7)` is exact; results beyond 2^53 fail.
+fn pow_mod(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let a = wide_integer_argument("powmod", &args[0])?;
+    let b = wide_integer_argument("powmod", &args[1])?;
+    let m = modulus_argument("powmod", &args[2])?;
+    let mut base = residue(a, m);
+    if b < 0 {
+        base = inverse("powmod", a, m)?;
+    }
+    let power = integer::pow_mod(base, b.unsigned_abs() as u64, m);
+    residue_number("powmod", power)
+}
+
+/// `invmod(a, m)`, the `x` from 0 to `m - 1` such that `a x mod m` is 1. As with `powmod`,
+/// the arguments are below 2^64 and results beyond 2^53 fail.
+fn inv_mod(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let a = wide_integer_argument("invmod", &args[0])?;
+    let m = modulus_argument("invmod", &args[1])?;
+    residue_number("invmod", inverse("invmod", a, m)?)
+}
+
+fn inverse(name: &str, a: i128, m: u64) -> Result<u64, EvaluatorError> {
+    integer::inv_mod(residue(a, m), m)
+        .ok_or_else(|| invalid_argument(format!("'{}': {} has no inverse modulo {}", name, a, m)))
+}
+
+fn word_mask(bits: u32) -> u64 {
+    (1 << bits) - 1
+}
+
+/// An argument of the bitwise functions, wrapped around to the word size of the context.
+fn word_argument(evaluator: &mut Evaluator, name: &str, arg: &Expr) -> Result<u64, EvaluatorError> {
+    let value = evaluator.evaluate(arg)?;
+    let n = integer_argument(name, &value).map_err(|error| error.or_span(arg.span))?;
+    match evaluator.context().word_size() {
+        Some(bits) => Ok(n as u64 & word_mask(bits)),
+        None if n < 0 => Err(invalid_argument(format!(
+            "'{}' of a negative number needs a word size",
+            name
+        ))
+        .or_span(arg.span)),
+        None => Ok(n as u64),
+    }
+}
+
+/// The index of a bit of a word, counting from the least significant bit.
+fn bit_index(evaluator: &mut Evaluator, name: &str, arg: &Expr) -> Result<u32, EvaluatorError> {
+    let value = evaluator.evaluate(arg)?;
+    let index = integer_argument(name, &value).map_err(|error| error.or_span(arg.span))?;
+    let bits = evaluator.context().word_size().unwrap_or(MAX_WORD_SIZE);
+    if !(0..i64::from(bits)).contains(&index) {
+        return Err(invalid_argument(format!(
+            "'{}' expects a bit index from 0 to {}, found {}",
+            name,
+            bits - 1,
+            index
+        ))
+        .or_span(arg.span));
+    }
+    Ok(index as u32)
+}
+
+fn word_value(word: u64) -> Result<Value, EvaluatorError> {
+    Ok(Value::Number(word as f64))
+}
+
+fn pop_count(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    word_value(
+        word_argument(evaluator, "popcount", &args[0])?
+            .count_ones()
+            .into(),
+    )
+}
+
+/// The number of bits needed to write the argument, 0 for 0.
+fn bit_length(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let word = word_argument(evaluator, "bitlength", &args[0])?;
+    word_value((u64::BITS - word.leading_zeros()).into())
+}
+
+fn bit(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let word = word_argument(evaluator, "bit", &args[0])?;
+    let index = bit_index(evaluator, "bit", &args[1])?;
+    word_value(word >> index & 1)
+}
+
+fn set_bit(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let word = word_argument(evaluator, "setbit", &args[0])?;
+    let index = bit_index(evaluator, "setbit", &args[1])?;
+    word_value(word | 1 << index)
+}
+
+fn clear_bit(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let word = word_argument(evaluator, "clearbit", &args[0])?;
+    let index = bit_index(evaluator, "clearbit", &args[1])?;
+    word_value(word & !(1 << index))
+}
+
+/// Rotates the word left by `shift` bits, or right by a negative shift.
+fn rotate(
+    evaluator: &mut Evaluator,
+    name: &str,
+    args: &[Expr],
+    direction: i64,
+) -> Result<Value, EvaluatorError> {
+    let bits = evaluator.context().word_size().ok_or_else(|| {
+        invalid_argument(format!("'{}' needs a word size to rotate within", name))
+    })?;
+    let word = word_argument(evaluator, name, &args[0])?;
+    let shift = integer_argument(name, &evaluator.evaluate(&args[1])?)
+        .map_err(|error| error.or_span(args[1].span))?;
+    let shift = (direction * shift).rem_euclid(i64::from(bits)) as u32;
+    word_value((word << shift | word >> (bits - shift)) & word_mask(bits))
+}
+
+fn rotate_left(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    rotate(evaluator, "rotl", args, 1)
+}
+
+fn rotate_right(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    rotate(evaluator, "rotr", args, -1)
+}
+
+/// The arguments, the items of lists taking the place of the lists.
+fn flatten<'a>(name: &str, args: &'a [Value]) -> Result<Vec<&'a Value>, EvaluatorError> {
+    let mut items = vec![];
+    for arg in args {
+        match arg {
+            Value::List(list) => items.extend(list),
+            arg => items.push(arg),
+        }
+    }
+    if items.is_empty() {
+        return Err(invalid_argument(format!("'{}' of an empty list", name)));
+    }
+    Ok(items)
+}
+
+/// The extremum of the arguments by `operator`, which is `<` for the minimum.
+fn extremum(name: &str, operator: BinaryOperator, args: &[Value]) -> Result<Value, EvaluatorError> {
+    let items = flatten(name, args)?;
+    let mut best = items[0];
+    for item in &items[1..] {
+        if Value::binary(operator, item, best)?.is_true()? {
+            best = item;
+        }
+    }
+    Ok(best.clone())
+}
+
+fn min(args: &[Value]) -> Result<Value, EvaluatorError> {
+    extremum("min", BinaryOperator::Less, args)
+}
+
+fn max(args: &[Value]) -> Result<Value, EvaluatorError> {
+    extremum("max", BinaryOperator::Greater, args)
+}
+
+fn clamp(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let (value, lo, hi) = (&args[0], &args[1], &args[2]);
+    if Value::binary(BinaryOperator::Greater, lo, hi)?.is_true()? {
+        return Err(invalid_argument(String::from(
+            "The lower bound of 'clamp' is above the upper bound",
+        )));
+    }
+    max(&[min(&[value.clone(), hi.clone()])?, lo.clone()])
+}
+
+fn sum(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let items = flatten("sum", args)?;
+    items[1..].iter().try_fold(items[0].clone(), |sum, item| {
+        Value::binary(BinaryOperator::Add, &sum, item)
+    })
+}
+
+fn avg(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let count = flatten("avg", args)?.len();
+    Value::binary(
+        BinaryOperator::Divide,
+        &sum(args)?,
+        &Value::Number(count as f64),
+    )
+}
+
+fn truths(args: &[Value]) -> Result<Vec<bool>, EvaluatorError> {
+    args.iter().map(Value::is_true).collect()
+}
+
+fn not(args: &[Value]) -> Result<Value, EvaluatorError> {
+    Ok(Value::Bool(!args[0].is_true()?))
+}
+
+fn and(args: &[Value]) -> Result<Value, EvaluatorError> {
+    Ok(Value::Bool(truths(args)?.into_iter().all(|truth| truth)))
+}
+
+fn or(args: &[Value]) -> Result<Value, EvaluatorError> {
+    Ok(Value::Bool(truths(args)?.into_iter().any(|truth| truth)))
+}
+
+/// True when an odd number of arguments are.
+fn xor(args: &[Value]) -> Result<Value, EvaluatorError> {
+    Ok(Value::Bool(
+        truths(args)?.into_iter().filter(|truth| *truth).count() % 2 == 1,
+    ))
+}
+
+/// `date(year, month, day)` or `date("YYYY-MM-DD")`.
+fn date(args: &[Value]) -> Result<Value, EvaluatorError> {
+    let date = match args {
+        [Value::String(text)] => Date::parse(text)
+            .ok_or_else(|| invalid_argument(format!("'{}' is not a date (YYYY-MM-DD)", text)))?,
+        [year, month, day] => {
+            let (year, month, day) = (year.as_number()?, month.as_number()?, day.as_number()?);
+            let whole = [year, month, day].iter().all(|part| part.fract() == 0.0);
+            whole
+                .then(|| Date::from_ymd(year as i64, month as u32, day as u32))
+                .flatten()
+                .ok_or_else(|| {
+                    invalid_argument(format!("{}-{}-{} is not a date", year, month, day))
+                })?
+        }
+        _ => {
+            return Err(invalid_argument(String::from(
+                "'date' expects a year, a month and a day, or a \"YYYY-MM-DD\" string",
+            )))
+        }
+    };
+    Ok(Value::Date(date))
+}
+
+/// `integrate(f, a, b[, tolerance])` or `integrate(expr, x, a, b[, tolerance])`.
+fn integrate(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let (function, rest) = UnaryFunction::from_args(evaluator, "integrate", args)?;
+    check_remaining("integrate", args, rest, 2, 3)?;
+    let a = number_argument(evaluator, &rest[0])?;
+    let b = number_argument(evaluator, &rest[1])?;
+    let tolerance = match rest.get(2) {
+        Some(arg) => number_argument(evaluator, arg)?,
+        None => numeric::DEFAULT_TOLERANCE,
+    };
+    if tolerance.is_nan() || tolerance <= 0.0 {
+        return Err(invalid_argument(String::from(
+            "The tolerance of 'integrate' must be positive",
+        )));
+    }
+    let quadrature = numeric::integrate(|x| function.call(evaluator, x), a, b, tolerance)?;
+    if !quadrature.converged {
+        return Err(EvaluatorError::new(EvaluatorErrorKind::NotConverged(
+            format!(
+                "'integrate' did not reach the requested tolerance (error estimate {:e})",
+                quadrature.error
+            ),
+        )));
+    }
+    Ok(Value::Number(quadrature.value))
+}
+
+/// `diff(f, x0[, order[, step]])` or `diff(expr, x, x0[, order[, step]])`.
+fn diff(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let (function, rest) = UnaryFunction::from_args(evaluator, "diff", args)?;
+    check_remaining("diff", args, rest, 1, 3)?;
+    let x0 = number_argument(evaluator, &rest[0])?;
+    let order = match rest.get(1) {
+        Some(arg) => number_argument(evaluator, arg)?,
+        None => 1.0,
+    };
+    if order.fract() != 0.0 || !(1.0..=numeric::MAX_DERIVATIVE_ORDER as f64).contains(&order) {
+        return Err(invalid_argument(format!(
+            "The order of 'diff' must be an integer between 1 and {}",
+            numeric::MAX_DERIVATIVE_ORDER
+        ))
+        .or_span(rest[1].span));
+    }
+    let step = match rest.get(2) {
+        Some(arg) => {
+            let step = number_argument(evaluator, arg)?;
+            if step.is_nan() || step <= 0.0 {
+                return Err(
+                    invalid_argument(String::from("The step of 'diff' must be positive"))
+                        .or_span(arg.span),
+                );
+            }
+            step
+        }
+        None => numeric::default_step(x0),
+    };
+    let derivative =
+        numeric::differentiate(|x| function.call(evaluator, x), x0, order as u32, step)?;
+    Ok(Value::Number(derivative.value))
+}
+
+/// The formula and variable of the argument of a symbolic function such as `derive`: a
+/// lambda of one parameter, a builtin function of `x`, or an expression and its variable.
+fn symbolic_argument<'e>(
+    evaluator: &mut Evaluator,
+    name: &str,
+    args: &'e [Expr],
+    min: usize,
+    max: usize,
+) -> Result<(Expr, String, &'e [Expr]), EvaluatorError> {
+    let (function, rest) = UnaryFunction::from_args(evaluator, name, args)?;
+    check_remaining(name, args, rest, min, max)?;
+    let (body, variable) = match function {
+        UnaryFunction::Bound(expr, variable) => (expr.clone(), variable),
+        UnaryFunction::Function(Function::Lambda(params, body)) if params.len() == 1 => {
+            (*body, params[0].clone())
+        }
+        UnaryFunction::Function(Function::Builtin(name)) => {
+            let variable = String::from("x");
+            let argument = Expr::new(ExprKind::Variable(variable.clone()), args[0].span);
+            let call = Expr::new(
+                ExprKind::Call(name.to_string(), vec![argument]),
+                args[0].span,
+            );
+            (call, variable)
+        }
+        UnaryFunction::Function(function) => {
+            return Err(invalid_argument(format!(
+                "'{}' expects a function of one variable, found {}",
+                name, function
+            ))
+            .or_span(args[0].span))
+        }
+    };
+    Ok((body, variable, rest))
+}
+
+/// `derive(f)` or `derive(expr, x)`: the symbolic derivative, as a function of the variable.
+fn derive(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let (body, variable, _) = symbolic_argument(evaluator, "derive", args, 0, 0)?;
+    let derivative = body.derive(&variable)?;
+    Ok(Value::Function(Function::Lambda(
+        vec![variable],
+        Box::new(derivative),
+    )))
+}
+
+/// `integrate_sym(f)` or `integrate_sym(expr, x)`: an antiderivative, as a function of the
+/// variable.
+fn integrate_symbolic(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let (body, variable, _) = symbolic_argument(evaluator, "integrate_sym", args, 0, 0)?;
+    let integral = body.integrate_symbolic(&variable)?;
+    Ok(Value::Function(Function::Lambda(
+        vec![variable],
+        Box::new(integral),
+    )))
+}
+
+/// `solve_sym(equation, x)` or `solve_sym(f)`: the list of the exact solutions of a linear
+/// or quadratic equation, each a number or, with other variables, a function of them.
+fn solve_symbolic(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let (body, variable, _) = symbolic_argument(evaluator, "solve_sym", args, 0, 0)?;
+    let mut solutions = vec![];
+    for root in body.solve_symbolic(&variable)? {
+        let params: Vec<String> = partial::free_variables(&root).into_iter().collect();
+        solutions.push(if params.is_empty() {
+            evaluator.evaluate(&root)?
+        } else {
+            Value::Function(Function::Lambda(params, Box::new(root)))
+        });
+    }
+    Ok(Value::List(solutions))
+}
+
+/// `series(expr, x, x0, order)` or `series(f, x0, order)`: the Taylor expansion, as a
+/// function of the variable.
+fn series(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let (body, variable, rest) = symbolic_argument(evaluator, "series", args, 2, 2)?;
+    let at = number_argument(evaluator, &rest[0])?;
+    let order = evaluator.evaluate(&rest[1])?;
+    let order = usize::try_from(integer_argument("series", &order)?).map_err(|_| {
+        invalid_argument(String::from("'series' expects a nonnegative order")).or_span(rest[1].span)
+    })?;
+    let series = body.series(evaluator.context(), &variable, at, order)?;
+    Ok(Value::Function(Function::Lambda(
+        vec![variable],
+        Box::new(series),
+    )))
+}
+
+/// `limit(expr, x, a[, side])` or `limit(f, a[, side])`, `side` being "left" or "right" for
+/// a one-sided limit and `a` possibly `inf` or `-inf`.
+fn limit(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let (body, variable, rest) = symbolic_argument(evaluator, "limit", args, 1, 2)?;
+    let at = number_argument(evaluator, &rest[0])?;
+    let approach = match rest.get(1).map(|arg| evaluator.evaluate(arg)).transpose()? {
+        None => Approach::Both,
+        Some(Value::String(side)) if side == "left" => Approach::Left,
+        Some(Value::String(side)) if side == "right" => Approach::Right,
+        Some(_) => {
+            return Err(invalid_argument(String::from(
+                "'limit' expects the side \"left\" or \"right\"",
+            ))
+            .or_span(rest[1].span))
+        }
+    };
+    let limit = body.limit(evaluator.context(), &variable, at, approach)?;
+    Ok(Value::Number(limit))
+}
+
+/// `expand(expr)` or `expand(f)`: the expanded polynomial, as a function of its variables.
+fn expand(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    polynomial(evaluator, "expand", args, Expr::expand)
+}
+
+/// `factor(expr)` or `factor(f)`: the factored polynomial, as a function of its variables.
+fn factor(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    polynomial(evaluator, "factor", args, Expr::factor)
+}
+
+/// Rewrites the body of a function, or an expression whose names are all variables. The
+/// result is a function of the parameters, or of the variables in alphabetical order, and a
+/// number if there are none.
+fn polynomial(
+    evaluator: &mut Evaluator,
+    name: &str,
+    args: &[Expr],
+    rewrite: fn(&Expr) -> Result<Expr, EvaluatorError>,
+) -> Result<Value, EvaluatorError> {
+    let (body, mut params) = match evaluator.function_argument(&args[0])? {
+        Some(Function::Lambda(params, body)) => (*body, params),
+        Some(function) => {
+            return Err(invalid_argument(format!(
+                "'{}' expects a polynomial, found {}",
+                name, function
+            ))
+            .or_span(args[0].span))
+        }
+        None => (args[0].clone(), vec![]),
+    };
+    let result = rewrite(&body).map_err(|error| error.or_span(args[0].span))?;
+    if params.is_empty() {
+        params = partial::free_variables(&result).into_iter().collect();
+    }
+    if params.is_empty() {
+        return evaluator.evaluate(&result);
+    }
+    Ok(Value::Function(Function::Lambda(params, Box::new(result))))
+}
+
+/// `solve(equation, x[, lo, hi])` or `solve(f[, lo, hi])`: all the roots found in the
+/// interval, which defaults to `numeric::DEFAULT_ROOT_INTERVAL`. An equation `a == b` is
+/// solved as `a - b == 0`, any other expression as `expr == 0`.
+fn solve(evaluator: &mut Evaluator, args: &[Expr]) -> Result<Value, EvaluatorError> {
+    let (function, rest) = UnaryFunction::from_args(evaluator, "solve", args)?;
+    if rest.len() == 1 {
+        return Err(argument_count(
+            "solve",
+            args.len() + 1,
+            Some(args.len() + 1),
+            args.len(),
+        ));
+    }
+    check_remaining("solve", args, rest, 0, 2)?;
+    let difference;
+    let function = match function {
+        UnaryFunction::Bound(expr, variable) => match &expr.kind {
+            ExprKind::Binary(BinaryOperator::Equal, lhs, rhs) => {
+                difference = Expr::new(
+                    ExprKind::Binary(BinaryOperator::Subtract, lhs.clone(), rhs.clone()),
+                    expr.span,
+                );
+                UnaryFunction::Bound(&difference, variable)
+            }
+            _ => UnaryFunction::Bound(expr, variable),
+        },
+        function => function,
+    };
+    let (lo, hi) = match rest {
+        [lo, hi] => (
+            number_argument(evaluator, lo)?,
+            number_argument(evaluator, hi)?,
+        ),
+        _ => numeric::DEFAULT_ROOT_INTERVAL,
+    };
+    if !(lo < hi && lo.is_finite() && hi.is_finite()) {
+        return Err(invalid_argument(String::from(
+            "The interval of 'solve' must be finite and non-empty",
+        )));
+    }
+    let roots = numeric::find_roots(|x| function.call(evaluator, x), lo, hi)?;
+    Ok(Value::List(roots.into_iter().map(Value::Number).collect()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::Context;
+    use crate::parser::Parser;
+
+    fn evaluate(source: &str) -> Result<f64, EvaluatorErrorKind> {
+        let context = Context::new();
+        match context.evaluate(&Parser::new(source).parse().unwrap()) {
+            Ok(value) => Ok(value.as_number().unwrap()),
+            Err(error) => Err(error.kind),
+        }
+    }
+
+    #[test]
+    fn integrate_functions_and_expressions() {
+        assert!((evaluate("integrate(x -> x^2, 0, 3)").unwrap() - 9.0).abs() < 1e-12);
+        assert!((evaluate("integrate(sin, 0, pi)").unwrap() - 2.0).abs() < 1e-12);
+        assert!((evaluate("integrate(t * exp(t), t, 0, 1)").unwrap() - 1.0).abs() < 1e-12);
+        let gaussian = evaluate("integrate(exp(-x^2), x, -inf, inf)").unwrap();
+        assert!((gaussian - consts::PI.sqrt()).abs() < 1e-10);
+        let value = evaluate("integrate(x -> 1 / sqrt(x), 0, 1, 1e-6)").unwrap();
+        assert!((value - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn differentiate_functions_and_expressions() {
+        assert!((evaluate("diff(sin, 0)").unwrap() - 1.0).abs() < 1e-10);
+        assert!((evaluate("diff(x -> x^3, 2)").unwrap() - 12.0).abs() < 1e-9);
+        assert!((evaluate("diff(x^3, x, 2, 2)").unwrap() - 12.0).abs() < 1e-6);
+        assert!((evaluate("diff(exp(t), t, 1, 3, 0.5)").unwrap() - consts::E).abs() < 1e-5);
+        assert!(matches!(
+            evaluate("diff(sin, 0, 1.5)"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn solve_equations_and_functions() {
+        let context = Context::new();
+        let solve = |source: &str| context.evaluate(&Parser::new(source).parse().unwrap());
+        assert_eq!(
+            solve("solve(x^2 - 4 == 0, x)"),
+            Ok(Value::List(vec![Value::Number(-2.0), Value::Number(2.0)]))
+        );
+        assert_eq!(
+            solve("solve(x^2 == 4, x, 0, 10)"),
+            Ok(Value::List(vec![Value::Number(2.0)]))
+        );
+        let Ok(Value::List(roots)) = solve("solve(sin, -1, 7)") else {
+            panic!("solve did not return a list");
+        };
+        let expected = [0.0, consts::PI, 2.0 * consts::PI];
+        assert_eq!(roots.len(), expected.len());
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root.as_number().unwrap() - expected).abs() < 1e-12);
+        }
+        assert!(matches!(
+            solve("solve(x -> x, 1)").map_err(|error| error.kind),
+            Err(EvaluatorErrorKind::ArgumentCount { .. })
+        ));
+    }
+
+    #[test]
+    fn derive_symbolically() {
+        let context = Context::new();
+        let evaluate = |source: &str| {
+            let value = context.evaluate(&Parser::new(source).parse().unwrap());
+            value.map(|value| value.to_string())
+        };
+        assert_eq!(evaluate("derive(t^2 * 3, t)").unwrap(), "t -> 6 * t");
+        assert_eq!(evaluate("derive(x -> ln(x))").unwrap(), "x -> 1 / x");
+        assert_eq!(evaluate("derive(cos)").unwrap(), "x -> -sin(x)");
+        assert!(evaluate("derive((x, y) -> x * y)").is_err());
+        assert_eq!(evaluate("integrate_sym(cos)").unwrap(), "x -> sin(x)");
+        assert_eq!(evaluate("integrate_sym(t^-2, t)").unwrap(), "t -> -1 / t");
+        assert_eq!(
+            evaluate("series(exp, 0, 2)").unwrap(),
+            "x -> 1 + x + 0.5 * x^2"
+        );
+        assert_eq!(
+            evaluate("series(t^3, t, 1, 9)").unwrap(),
+            "t -> 1 + 3 * (t - 1) + 3 * (t - 1)^2 + (t - 1)^3"
+        );
+        assert!(evaluate("series(exp, 0, -1)").is_err());
+        assert_eq!(
+            evaluate("solve_sym(x^2 - 5*x + 6 == 0, x)").unwrap(),
+            "[2, 3]"
+        );
+        assert_eq!(evaluate("solve_sym(k*t - 1, t)").unwrap(), "[k -> 1 / k]");
+        assert_eq!(evaluate("limit((x^2 - 4)/(x - 2), x, 2)").unwrap(), "4");
+        assert_eq!(evaluate("limit(t -> 1/t, 0, \"left\")").unwrap(), "-inf");
+        assert!(evaluate("limit(t -> 1/t, 0, \"up\")").is_err());
+    }
+
+    #[test]
+    fn expand_and_factor_polynomials() {
+        let context = Context::new();
+        let evaluate = |source: &str| {
+            let value = context.evaluate(&Parser::new(source).parse().unwrap());
+            value.map(|value| value.to_string())
+        };
+        assert_eq!(evaluate("expand((x+1)^2)").unwrap(), "x -> x^2 + 2 * x + 1");
+        assert_eq!(
+            evaluate("expand((y - x)*y)").unwrap(),
+            "(x, y) -> -x * y + y^2"
+        );
+        assert_eq!(
+            evaluate("factor(t -> t^2 - 1)").unwrap(),
+            "t -> (t + 1) * (t - 1)"
+        );
+        assert_eq!(evaluate("expand(2^10 / 4)").unwrap(), "256");
+        assert!(evaluate("factor(sqrt(x))").is_err());
+    }
+
+    #[test]
+    fn integrate_rejects_bad_arguments() {
+        assert!(matches!(
+            evaluate("integrate(x^2, 0, 1)"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            evaluate("integrate(x -> x, 0)"),
+            Err(EvaluatorErrorKind::ArgumentCount { .. })
+        ));
+        assert!(matches!(
+            evaluate("integrate(x -> x, 0, 1, -1)"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn compute_trigonometric_functions() {
+        let near = |source: &str, expected: f64| {
+            let value = evaluate(source).unwrap();
+            assert!((value - expected).abs() < 1e-12, "{} = {}", source, value);
+        };
+        near("asin(1)", consts::FRAC_PI_2);
+        near("acos(-1)", consts::PI);
+        near("atan(1)", consts::FRAC_PI_4);
+        near("atan2(1, -1)", 3.0 * consts::FRAC_PI_4);
+        near("atan2(-1, 0)", -consts::FRAC_PI_2);
+        near("cosh(1)^2 - sinh(1)^2", 1.0);
+        near("tanh(atanh(0.5))", 0.5);
+        near("asinh(sinh(2)) + acosh(cosh(3))", 5.0);
+        assert!(evaluate("acosh(0.5)").unwrap().is_nan());
+    }
+
+    #[test]
+    fn compute_logarithms() {
+        assert_eq!(evaluate("ln(e)"), Ok(1.0));
+        assert_eq!(evaluate("log10(1000)"), Ok(3.0));
+        assert_eq!(evaluate("log2(1024)"), Ok(10.0));
+        assert_eq!(evaluate("log(100)"), Ok(2.0));
+        assert!((evaluate("log(81, 3)").unwrap() - 4.0).abs() < 1e-12);
+        assert_eq!(evaluate("expm1(1e-20)"), Ok(1e-20));
+        assert_eq!(evaluate("ln1p(1e-20)"), Ok(1e-20));
+        assert_eq!(evaluate("ln(0)"), Ok(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn find_primes() {
+        let context = Context::new();
+        let evaluate = |source: &str| {
+            let value = context.evaluate(&Parser::new(source).parse().unwrap());
+            value.map(|value| value.to_string())
+        };
+        assert_eq!(evaluate("isprime(97)").unwrap(), "true");
+        assert_eq!(evaluate("isprime(-7) || isprime(1)").unwrap(), "false");
+        assert_eq!(
+            evaluate("nextprime(2^53 - 200)").unwrap(),
+            "9007199254740847"
+        );
+        assert_eq!(evaluate("prevprime(100)").unwrap(), "97");
+        assert_eq!(
+            evaluate("factorint(2^52 - 1)").unwrap(),
+            "[3, 5, 53, 157, 1613, 2731, 8191]"
+        );
+        assert!(evaluate("isprime(2.5)").is_err());
+        assert_eq!(evaluate("isprime(2^61 - 1)").unwrap(), "false");
+        assert!(evaluate("isprime(2^64)").is_err());
+        assert!(evaluate("prevprime(2)").is_err());
+        assert!(evaluate("nextprime(2^53 - 1)").is_err());
+        assert!(evaluate("nextprime(2^53)").is_err());
+        assert_eq!(evaluate("prevprime(2^53)").unwrap(), "9007199254740881");
+        assert!(evaluate("prevprime(2^60)").is_err());
+        assert_eq!(
+            evaluate("factorint(2^53)").unwrap(),
+            format!("[{}]", ["2"; 53].join(", "))
+        );
+        assert_eq!(
+            evaluate("factorint(2^53 + 2)").unwrap(),
+            "[2, 17, 858001, 308761441]"
+        );
+        assert_eq!(
+            evaluate("factorint(2^60 * 3)").unwrap(),
+            format!("[{}, 3]", ["2"; 60].join(", "))
+        );
+    }
+
+    #[test]
+    fn compute_modular_arithmetic() {
+        assert_eq!(evaluate("powmod(4, 13, 497)"), Ok(445.0));
+        assert_eq!(evaluate("powmod(-2, 3, 5)"), Ok(2.0));
+        assert_eq!(evaluate("powmod(3, -1, 11)"), Ok(4.0));
+        assert_eq!(evaluate("powmod(2^53, 2^53, 2^53 - 1)"), Ok(1.0));
+        assert_eq!(evaluate("powmod(2^60, 2, 7)"), Ok(1.0));
+        assert_eq!(
+            evaluate("powmod(-(2^63), 3, 2^62 + 2^61)"),
+            Ok(2f64.powi(62))
+        );
+        assert!(matches!(
+            evaluate("invmod(3, 2^60)"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+        assert_eq!(evaluate("invmod(-3, 11)"), Ok(7.0));
+        assert!(matches!(
+            evaluate("invmod(6, 9)"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            evaluate("powmod(2, 3, 0)"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn manipulate_bits() {
+        let mut context = Context::new();
+        let evaluate = |context: &Context, source: &str| {
+            let value = context.evaluate(&Parser::new(source).parse().unwrap());
+            value.map(|value| value.as_number().unwrap())
+        };
+        assert_eq!(evaluate(&context, "popcount(0xff00ff)"), Ok(16.0));
+        assert_eq!(
+            evaluate(&context, "bitlength(0) + bitlength(0x100)"),
+            Ok(9.0)
+        );
+        assert_eq!(
+            evaluate(&context, "bit(0b1010, 1) + bit(0b1010, 2)"),
+            Ok(1.0)
+        );
+        assert_eq!(evaluate(&context, "setbit(0b1000, 0)"), Ok(9.0));
+        assert_eq!(evaluate(&context, "clearbit(0b1001, 3)"), Ok(1.0));
+        assert!(evaluate(&context, "popcount(-1)").is_err());
+        assert!(evaluate(&context, "rotl(1, 1)").is_err());
+        context.set_word_size(Some(8));
+        assert_eq!(evaluate(&context, "popcount(-1)"), Ok(8.0));
+        assert_eq!(evaluate(&context, "rotl(0b10000001, 1)"), Ok(3.0));
+        assert_eq!(
+            evaluate(&context, "rotr(0b10000001, 1)"),
+            Ok(0b11000000 as f64)
+        );
+        assert_eq!(evaluate(&context, "rotl(0xf0, 12)"), Ok(0x0f as f64));
+        assert_eq!(evaluate(&context, "setbit(0x1ff, 0)"), Ok(0xff as f64));
+        assert!(evaluate(&context, "bit(1, 8)").is_err());
+    }
+
+    #[test]
+    fn round_numbers() {
+        assert_eq!(evaluate("floor(-2.5) + ceil(2.1) + trunc(-2.9)"), Ok(-2.0));
+        assert_eq!(evaluate("frac(-2.25)"), Ok(-0.25));
+        assert_eq!(evaluate("round(2.5)"), Ok(3.0));
+        assert_eq!(evaluate("round(2.5, 0, \"half-even\")"), Ok(2.0));
+        assert_eq!(evaluate("round(1.005, 2)"), Ok(1.01));
+        assert_eq!(evaluate("round(1234.5, -2)"), Ok(1200.0));
+        assert_eq!(evaluate("roundsig(123456, 2)"), Ok(120000.0));
+        assert_eq!(evaluate("roundsig(0.012345, 3, \"up\")"), Ok(0.0124));
+        assert!(matches!(
+            evaluate("round(1.5, 0, \"nearest\")"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            evaluate("roundsig(1.5, 0)"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn aggregate_arguments_and_lists() {
+        assert_eq!(evaluate("min(3, 1, 2)"), Ok(1.0));
+        assert_eq!(evaluate("max(1, solve(x^2 == 4, x), -3)"), Ok(2.0));
+        assert_eq!(evaluate("sum(1, 2, 3, 4)"), Ok(10.0));
+        assert_eq!(evaluate("avg(1, (2, 3), 4)"), Ok(2.5));
+        assert_eq!(evaluate("clamp(5, 0, 2)"), Ok(2.0));
+        assert_eq!(evaluate("clamp(-5, 0, 2)"), Ok(0.0));
+        assert_eq!(evaluate("clamp(1, 0, 2)"), Ok(1.0));
+        assert!(matches!(
+            evaluate("clamp(1, 2, 0)"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+        let context = Context::new();
+        let evaluate = |source: &str| {
+            let value = context.evaluate(&Parser::new(source).parse().unwrap());
+            value.map(|value| value.to_string())
+        };
+        assert_eq!(evaluate("max(1 m, 20 cm, 3 ft)").unwrap(), "1 m");
+        assert_eq!(evaluate("sum(1 km, 500 m)").unwrap(), "1.5 km");
+        assert!(evaluate("sum(1 m, 1 s)").is_err());
+    }
+
+    #[test]
+    fn choose_piecewise_cases() {
+        let mut context = Context::new();
+        let mut define = |name: &str, source: &str| {
+            let function = context.evaluate(&Parser::new(source).parse().unwrap());
+            context.set_variable(name, function.unwrap());
+        };
+        define("absolute", "x -> piecewise((x < 0, -x), (x >= 0, x))");
+        define(
+            "tax",
+            "x -> piecewise((x <= 10000, 0), (x <= 40000, (x - 10000) * 20%), \
+             6000 + (x - 40000) * 40%)",
+        );
+        let call = |source: &str| context.evaluate(&Parser::new(source).parse().unwrap());
+        assert_eq!(call("absolute(-3)"), Ok(Value::Number(3.0)));
+        assert_eq!(call("absolute(2)"), Ok(Value::Number(2.0)));
+        assert_eq!(call("tax(25000)"), Ok(Value::Number(3000.0)));
+        assert_eq!(call("tax(50000)"), Ok(Value::Number(10000.0)));
+        assert_eq!(evaluate("piecewise((1 > 0, 1), (1 / 0, 2))"), Ok(1.0));
+        assert!(matches!(
+            evaluate("piecewise((1 < 0, 1))"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            evaluate("piecewise(1, (1 > 0, 2))"),
+            Err(EvaluatorErrorKind::InvalidArgument(_))
+        ));
+    }
+}
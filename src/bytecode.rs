@@ -0,0 +1,1093 @@
+use std::collections::HashMap;
+
+use crate::builtins::{self, BuiltinKind};
+use crate::error::Error;
+use crate::evaluator::{self, AngleUnit, Context, EvaluatorError, EvaluatorErrorKind};
+use crate::lexer::Span;
+use crate::observe::Stage;
+use crate::parser::{
+    BinaryOperator, Expr, ExprKind, Parser, ParserError, ParserErrorKind, UnaryOperator,
+};
+use crate::partial;
+use crate::value::{is_true, truth, Function, Value};
+
+/// Nested inlining after which calls are left, as recursive functions never end.
+const MAX_INLINE_DEPTH: usize = 16;
+
+/// An instruction of the stack machine. Operators pop their operands and push the result,
+/// truth values are 1 and 0.
+#[derive(Debug, Clone, Copy)]
+pub enum Instruction {
+    Constant(f64),
+    /// Pushes the input of the given index.
+    Input(usize),
+    Negate,
+    Percent,
+    Binary(BinaryOperator),
+    Call(fn(f64) -> f64),
+    /// Replaces the top of the stack by its truth value.
+    Truth,
+    Jump(usize),
+    /// Pops a condition and jumps if it is false.
+    JumpIfFalse(usize),
+    /// Pops a condition and jumps if it is true.
+    JumpIfTrue(usize),
+    /// Copies the top of the stack to the temporary of the given index, which holds a
+    /// subexpression occurring several times.
+    Store(usize),
+    /// Pushes the temporary of the given index.
+    Load(usize),
+}
+
+/// Options of `Expr::compile_with` and `Program::compile_script_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompileOptions {
+    /// Calls of user-defined functions, lambdas bound to a name, whose bodies have at most
+    /// this many nodes are replaced by their bodies with the arguments for the parameters.
+    /// Otherwise such calls cannot be compiled.
+    pub inline_size: Option<usize>,
+}
+
+/// A numeric formula compiled to bytecode, with its free variables as inputs.
+#[derive(Debug, Clone)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+    inputs: Vec<String>,
+    max_stack: usize,
+    temporaries: usize,
+}
+
+impl Expr {
+    /// Compiles a numeric formula for fast repeated evaluation. Variables other than the
+    /// builtin constants become inputs of the program, and only numeric builtins can be
+    /// called: units, strings, lambdas and builtins such as `integrate` stay with the
+    /// tree-walking evaluator. Subexpressions occurring several times outside of the
+    /// branches of conditionals are computed once.
+    pub fn compile(&self) -> Result<Program, EvaluatorError> {
+        self.compile_in(AngleUnit::Radians)
+    }
+
+    /// Compiles the formula with the trigonometric functions taking and returning angles
+    /// in `angle_unit`.
+    fn compile_in(&self, angle_unit: AngleUnit) -> Result<Program, EvaluatorError> {
+        let mut compiler = Compiler {
+            angle_unit,
+            ..Compiler::default()
+        };
+        let mut interner = Interner::default();
+        interner.intern(self);
+        compiler.ids = interner.ids;
+        let mut occurrences = HashMap::new();
+        count_occurrences(self, &compiler.ids, &mut occurrences);
+        compiler.common = occurrences
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(key, _)| (key, None))
+            .collect();
+        compiler.compile(self)?;
+        Ok(Program {
+            instructions: compiler.instructions,
+            inputs: compiler.inputs,
+            max_stack: compiler.max_depth,
+            temporaries: compiler.temporaries,
+        })
+    }
+
+    /// Compiles the formula, calling the user-defined functions of `context` as `options`
+    /// allow, with its angle unit.
+    pub fn compile_with(
+        &self,
+        context: &Context,
+        options: &CompileOptions,
+    ) -> Result<Program, EvaluatorError> {
+        let compile =
+            || inline(self, &HashMap::new(), context, options, 0).compile_in(context.angle_unit());
+        context
+            .observer
+            .observe(Stage::Compile, || self.to_string(), compile)
+    }
+
+    /// Compiles the formula into a closure taking the values of `parameters` in order,
+    /// for APIs expecting plain functions: `expr.bind(&["x", "y"])?(&[1.0, 2.0])`. Every
+    /// variable of the formula other than the builtin constants must be a parameter.
+    pub fn bind(
+        &self,
+        parameters: &[&str],
+    ) -> Result<impl Fn(&[f64]) -> Result<f64, EvaluatorError>, EvaluatorError> {
+        let compiled = Compiled::new(self)?;
+        let positions = compiled
+            .program
+            .inputs
+            .iter()
+            .map(|input| {
+                parameters
+                    .iter()
+                    .position(|parameter| parameter == input)
+                    .ok_or_else(|| {
+                        EvaluatorError::new(EvaluatorErrorKind::UnknownVariable(input.clone()))
+                            .or_span(self.span)
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let arity = parameters.len();
+        let in_order = positions
+            .iter()
+            .enumerate()
+            .all(|(index, position)| index == *position)
+            && positions.len() == arity;
+        Ok(move |values: &[f64]| {
+            if values.len() != arity {
+                return Err(EvaluatorError::new(EvaluatorErrorKind::ArgumentCount {
+                    function: String::from("formula"),
+                    min: arity,
+                    max: Some(arity),
+                    found: values.len(),
+                }));
+            }
+            if in_order {
+                return compiled.eval(values);
+            }
+            let inputs: Vec<f64> = positions.iter().map(|position| values[*position]).collect();
+            compiled.eval(&inputs)
+        })
+    }
+}
+
+/// A node of the syntax tree with the ids of its children, so that equal subexpressions
+/// have equal nodes without comparing whole subtrees.
+#[derive(PartialEq, Eq, Hash)]
+enum Node {
+    Number(u64),
+    Variable(String),
+    Unary(UnaryOperator, usize),
+    Binary(BinaryOperator, usize, usize),
+    Conditional(usize, usize, usize),
+    Call(String, Vec<usize>),
+    /// Strings, lambdas, tuples and values, which are not compiled, by their text.
+    Other(String),
+}
+
+/// Gives equal subexpressions the same id, bottom up, in time linear in the size of the
+/// tree.
+#[derive(Default)]
+struct Interner {
+    nodes: HashMap<Node, usize>,
+    /// The id of each subexpression, by its address in the tree being compiled.
+    ids: HashMap<*const Expr, usize>,
+}
+
+impl Interner {
+    fn intern(&mut self, expr: &Expr) -> usize {
+        let node = match &expr.kind {
+            ExprKind::Number(value) => Node::Number(value.to_bits()),
+            ExprKind::Variable(name) => Node::Variable(name.clone()),
+            ExprKind::Unary(operator, operand) => Node::Unary(*operator, self.intern(operand)),
+            ExprKind::Binary(operator, lhs, rhs) => {
+                Node::Binary(*operator, self.intern(lhs), self.intern(rhs))
+            }
+            ExprKind::Conditional(condition, then, otherwise) => Node::Conditional(
+                self.intern(condition),
+                self.intern(then),
+                self.intern(otherwise),
+            ),
+            ExprKind::Call(name, args) => Node::Call(
+                name.clone(),
+                args.iter().map(|arg| self.intern(arg)).collect(),
+            ),
+            _ => Node::Other(expr.to_string()),
+        };
+        let next = self.nodes.len();
+        let id = *self.nodes.entry(node).or_insert(next);
+        self.ids.insert(expr, id);
+        id
+    }
+}
+
+/// Counts the subexpressions by their id, other than leaves, that are always evaluated:
+/// not those in the branches of conditionals or the right operands of `&&` and `||`. The
+/// subexpressions of an occurrence already counted are not counted again.
+fn count_occurrences(
+    expr: &Expr,
+    ids: &HashMap<*const Expr, usize>,
+    occurrences: &mut HashMap<usize, usize>,
+) {
+    match &expr.kind {
+        ExprKind::Number(_) | ExprKind::Variable(_) | ExprKind::Value(_) => return,
+        _ => {}
+    }
+    let count = occurrences.entry(ids[&(expr as *const Expr)]).or_default();
+    *count += 1;
+    if *count > 1 {
+        return;
+    }
+    match &expr.kind {
+        ExprKind::Unary(_, operand) => count_occurrences(operand, ids, occurrences),
+        ExprKind::Binary(BinaryOperator::And | BinaryOperator::Or, lhs, _) => {
+            count_occurrences(lhs, ids, occurrences)
+        }
+        ExprKind::Binary(_, lhs, rhs) => {
+            count_occurrences(lhs, ids, occurrences);
+            count_occurrences(rhs, ids, occurrences);
+        }
+        ExprKind::Conditional(condition, _, _) => count_occurrences(condition, ids, occurrences),
+        ExprKind::Call(_, args) => {
+            for arg in args {
+                count_occurrences(arg, ids, occurrences);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Default)]
+struct Compiler {
+    instructions: Vec<Instruction>,
+    inputs: Vec<String>,
+    depth: usize,
+    max_depth: usize,
+    /// The ids of the subexpressions of the tree, from `Interner`.
+    ids: HashMap<*const Expr, usize>,
+    /// The ids of the common subexpressions, with their temporary once computed.
+    common: HashMap<usize, Option<usize>>,
+    temporaries: usize,
+    /// The number of enclosing branches, in which temporaries are only loaded.
+    branches: usize,
+    angle_unit: AngleUnit,
+}
+
+impl Compiler {
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        match instruction {
+            Instruction::Constant(_) | Instruction::Input(_) | Instruction::Load(_) => {
+                self.depth += 1
+            }
+            Instruction::Binary(_) | Instruction::JumpIfFalse(_) | Instruction::JumpIfTrue(_) => {
+                self.depth -= 1
+            }
+            _ => {}
+        }
+        self.max_depth = self.max_depth.max(self.depth);
+        self.instructions.push(instruction);
+        self.instructions.len() - 1
+    }
+
+    /// Points the jump at `index` to the next instruction.
+    fn patch(&mut self, index: usize) {
+        let target = self.instructions.len();
+        match &mut self.instructions[index] {
+            Instruction::Jump(to) | Instruction::JumpIfFalse(to) | Instruction::JumpIfTrue(to) => {
+                *to = target
+            }
+            _ => unreachable!("only jumps are patched"),
+        }
+    }
+
+    fn compile(&mut self, expr: &Expr) -> Result<(), EvaluatorError> {
+        let key = self
+            .ids
+            .get(&(expr as *const Expr))
+            .copied()
+            .filter(|key| self.common.contains_key(key));
+        if let Some(Some(temporary)) = key.map(|key| self.common[&key]) {
+            self.emit(Instruction::Load(temporary));
+            return Ok(());
+        }
+        self.compile_kind(&expr.kind)
+            .map_err(|error| error.or_span(expr.span))?;
+        if let Some(key) = key.filter(|_| self.branches == 0) {
+            self.emit(Instruction::Store(self.temporaries));
+            self.common.insert(key, Some(self.temporaries));
+            self.temporaries += 1;
+        }
+        Ok(())
+    }
+
+    /// Compiles a subexpression which is only evaluated on some runs.
+    fn compile_branch(&mut self, expr: &Expr) -> Result<(), EvaluatorError> {
+        self.branches += 1;
+        let result = self.compile(expr);
+        self.branches -= 1;
+        result
+    }
+
+    fn compile_kind(&mut self, kind: &ExprKind) -> Result<(), EvaluatorError> {
+        match kind {
+            ExprKind::Number(value) | ExprKind::Value(Value::Number(value)) => {
+                self.emit(Instruction::Constant(*value));
+            }
+            ExprKind::Value(Value::Bool(value)) => {
+                self.emit(Instruction::Constant(truth(*value)));
+            }
+            ExprKind::Variable(name) => match builtins::boolean(name)
+                .map(truth)
+                .or_else(|| builtins::constant(name))
+            {
+                Some(value) => {
+                    self.emit(Instruction::Constant(value));
+                }
+                None => {
+                    let index = match self.inputs.iter().position(|input| input == name) {
+                        Some(index) => index,
+                        None => {
+                            self.inputs.push(name.clone());
+                            self.inputs.len() - 1
+                        }
+                    };
+                    self.emit(Instruction::Input(index));
+                }
+            },
+            ExprKind::Unary(operator, operand) => {
+                self.compile(operand)?;
+                self.emit(match operator {
+                    UnaryOperator::Negate => Instruction::Negate,
+                    UnaryOperator::Percent => Instruction::Percent,
+                });
+            }
+            ExprKind::Binary(
+                operator @ (BinaryOperator::Add | BinaryOperator::Subtract),
+                lhs,
+                rhs,
+            ) if matches!(rhs.kind, ExprKind::Unary(UnaryOperator::Percent, _)) => {
+                // `a + b%` is `a + a * b%`, as in the evaluator.
+                self.compile(lhs)?;
+                self.compile(lhs)?;
+                self.compile(rhs)?;
+                self.emit(Instruction::Binary(BinaryOperator::Multiply));
+                self.emit(Instruction::Binary(*operator));
+            }
+            ExprKind::Binary(operator @ (BinaryOperator::And | BinaryOperator::Or), lhs, rhs) => {
+                self.compile(lhs)?;
+                let short_circuit = if *operator == BinaryOperator::And {
+                    self.emit(Instruction::JumpIfFalse(0))
+                } else {
+                    self.emit(Instruction::JumpIfTrue(0))
+                };
+                self.compile_branch(rhs)?;
+                self.emit(Instruction::Truth);
+                let end = self.emit(Instruction::Jump(0));
+                self.patch(short_circuit);
+                self.depth -= 1;
+                let decided = if *operator == BinaryOperator::And {
+                    0.0
+                } else {
+                    1.0
+                };
+                self.emit(Instruction::Constant(decided));
+                self.patch(end);
+            }
+            ExprKind::Binary(BinaryOperator::PlusMinus, _, _) => {
+                return Err(not_compilable(String::from("an uncertainty")))
+            }
+            ExprKind::Binary(operator, lhs, rhs) => {
+                self.compile(lhs)?;
+                self.compile(rhs)?;
+                self.emit(Instruction::Binary(*operator));
+            }
+            ExprKind::Conditional(condition, then, otherwise) => {
+                self.compile(condition)?;
+                let to_otherwise = self.emit(Instruction::JumpIfFalse(0));
+                self.compile_branch(then)?;
+                let end = self.emit(Instruction::Jump(0));
+                self.patch(to_otherwise);
+                self.depth -= 1;
+                self.compile_branch(otherwise)?;
+                self.patch(end);
+            }
+            ExprKind::Call(name, args) => {
+                let function = match builtins::lookup(name).map(|builtin| &builtin.kind) {
+                    Some(BuiltinKind::Numeric(function)) if args.len() == 1 => {
+                        match evaluator::degrees_function(name) {
+                            Some(degrees) if self.angle_unit == AngleUnit::Degrees => degrees,
+                            _ => *function,
+                        }
+                    }
+                    _ => return Err(not_compilable(format!("the call of '{}'", name))),
+                };
+                self.compile(&args[0])?;
+                self.emit(Instruction::Call(function));
+            }
+            ExprKind::String(_) => return Err(not_compilable(String::from("a string"))),
+            ExprKind::Lambda(_, _) => return Err(not_compilable(String::from("a lambda"))),
+            ExprKind::Tuple(_) => return Err(not_compilable(String::from("a list"))),
+            ExprKind::Value(value) => {
+                return Err(not_compilable(format!("a {}", value.type_name())))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn not_compilable(what: String) -> EvaluatorError {
+    EvaluatorError::new(EvaluatorErrorKind::NotCompilable(what))
+}
+
+fn size(expr: &Expr) -> usize {
+    1 + match &expr.kind {
+        ExprKind::Unary(_, operand) => size(operand),
+        ExprKind::Binary(_, lhs, rhs) => size(lhs) + size(rhs),
+        ExprKind::Conditional(condition, then, otherwise) => {
+            size(condition) + size(then) + size(otherwise)
+        }
+        ExprKind::Call(_, args) | ExprKind::Tuple(args) => args.iter().map(size).sum(),
+        ExprKind::Lambda(_, body) => size(body),
+        _ => 0,
+    }
+}
+
+/// The lambda that `name` is bound to in a script, or else in the context.
+fn user_function(
+    name: &str,
+    bindings: &HashMap<String, Expr>,
+    context: &Context,
+) -> Option<(Vec<String>, Expr)> {
+    match bindings.get(name).map(|binding| &binding.kind) {
+        Some(ExprKind::Lambda(params, body)) => Some((params.clone(), (**body).clone())),
+        Some(_) => None,
+        None => match context.variable(name) {
+            Some(Value::Function(Function::Lambda(params, body))) => {
+                Some((params.clone(), (**body).clone()))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Replaces the calls of small user-defined functions by their bodies, also inlining the
+/// calls within them up to `MAX_INLINE_DEPTH`.
+fn inline(
+    expr: &Expr,
+    bindings: &HashMap<String, Expr>,
+    context: &Context,
+    options: &CompileOptions,
+    depth: usize,
+) -> Expr {
+    let Some(inline_size) = options.inline_size else {
+        return expr.clone();
+    };
+    let inline_all = |expr: &Expr| Box::new(inline(expr, bindings, context, options, depth));
+    let kind = match &expr.kind {
+        ExprKind::Call(name, args) => {
+            let args: Vec<Expr> = args.iter().map(|arg| *inline_all(arg)).collect();
+            match user_function(name, bindings, context) {
+                Some((params, body))
+                    if params.len() == args.len()
+                        && size(&body) <= inline_size
+                        && depth < MAX_INLINE_DEPTH =>
+                {
+                    let replacements = params.into_iter().zip(args).collect();
+                    let body = body.substitute_all(&replacements);
+                    return inline(&body, bindings, context, options, depth + 1);
+                }
+                _ => ExprKind::Call(name.clone(), args),
+            }
+        }
+        ExprKind::Unary(operator, operand) => ExprKind::Unary(*operator, inline_all(operand)),
+        ExprKind::Binary(operator, lhs, rhs) => {
+            ExprKind::Binary(*operator, inline_all(lhs), inline_all(rhs))
+        }
+        ExprKind::Conditional(condition, then, otherwise) => ExprKind::Conditional(
+            inline_all(condition),
+            inline_all(then),
+            inline_all(otherwise),
+        ),
+        kind => kind.clone(),
+    };
+    Expr::new(kind, expr.span)
+}
+
+/// Replaces the subexpressions without inputs by their value, except those failing to
+/// evaluate, as a division by zero in a branch not taken, which then fail when running.
+fn fold(expr: &Expr, context: &Context) -> Expr {
+    let constant = partial::free_variables(expr)
+        .iter()
+        .all(|name| builtins::constant(name).is_some() || builtins::boolean(name).is_some());
+    // A percentage depends on the operation it is in.
+    let folded = !matches!(
+        expr.kind,
+        ExprKind::Number(_) | ExprKind::Unary(UnaryOperator::Percent, _)
+    );
+    if constant && folded {
+        match context.evaluate(expr) {
+            Ok(Value::Number(value)) => return Expr::new(ExprKind::Number(value), expr.span),
+            Ok(Value::Bool(value)) => {
+                return Expr::new(ExprKind::Value(Value::Bool(value)), expr.span)
+            }
+            _ => {}
+        }
+    }
+    let fold = |expr: &Expr| Box::new(fold(expr, context));
+    let kind = match &expr.kind {
+        ExprKind::Unary(operator, operand) => ExprKind::Unary(*operator, fold(operand)),
+        ExprKind::Binary(operator, lhs, rhs) => ExprKind::Binary(*operator, fold(lhs), fold(rhs)),
+        ExprKind::Conditional(condition, then, otherwise) => {
+            ExprKind::Conditional(fold(condition), fold(then), fold(otherwise))
+        }
+        ExprKind::Call(name, args) => {
+            ExprKind::Call(name.clone(), args.iter().map(|arg| *fold(arg)).collect())
+        }
+        kind => kind.clone(),
+    };
+    Expr::new(kind, expr.span)
+}
+
+/// Splits a script into its statements, with their offsets, at the `;` and new lines
+/// outside of strings.
+fn statements(source: &str) -> Vec<(usize, &str)> {
+    let mut statements = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    for (index, character) in source.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            ';' | '\n' if !quoted => {
+                statements.push((start, &source[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push((start, &source[start..]));
+    statements.retain(|(_, statement)| !statement.trim().is_empty());
+    statements
+}
+
+/// The position of the `=` of an assignment, not part of a comparison.
+fn assignment(statement: &str) -> Option<usize> {
+    let bytes = statement.as_bytes();
+    (0..bytes.len()).find(|&index| {
+        bytes[index] == b'='
+            && !matches!(
+                index.checked_sub(1).map(|before| bytes[before]),
+                Some(b'=' | b'<' | b'>' | b'!')
+            )
+            && bytes.get(index + 1) != Some(&b'=')
+    })
+}
+
+fn parse_at(source: &str, offset: usize) -> Result<Expr, ParserError> {
+    Parser::new(source).parse().map_err(|mut error| {
+        error.span.start += offset;
+        error.span.end += offset;
+        error
+    })
+}
+
+impl Program {
+    /// Compiles a script of statements `name = formula`, separated by `;` or new lines,
+    /// into a program computing the last one, which may also be a bare formula:
+    /// `k = 2; y = k * x`. Formulas see the names bound before them. Bindings without
+    /// inputs are evaluated when compiling, and their values folded into the later
+    /// statements with the constant subexpressions they make, so that running executes
+    /// fewer instructions.
+    pub fn compile_script(source: &str) -> Result<Program, Error> {
+        Program::compile_script_with(source, &Context::new(), &CompileOptions::default())
+    }
+
+    /// Compiles a script, calling the user-defined functions of the script or of `context`
+    /// as `options` allow, and evaluating its constant bindings in `context`. The variables
+    /// of `context` which are numbers are constants rather than inputs, and angles are in
+    /// its unit.
+    pub fn compile_script_with(
+        source: &str,
+        context: &Context,
+        options: &CompileOptions,
+    ) -> Result<Program, Error> {
+        let mut bindings = HashMap::new();
+        let mut result = None;
+        for (offset, statement) in statements(source) {
+            let (name, formula) = match assignment(statement) {
+                Some(position) => {
+                    let name = parse_at(&statement[..position], offset)?;
+                    let ExprKind::Variable(name) = &name.kind else {
+                        return Err(Error::Parser(ParserError {
+                            kind: ParserErrorKind::UnexpectedToken {
+                                found: name.to_string(),
+                                expected: "a name",
+                            },
+                            span: Span {
+                                start: offset + name.span.start,
+                                end: offset + name.span.end,
+                            },
+                        }));
+                    };
+                    let start = offset + position + 1;
+                    (
+                        Some(name.clone()),
+                        parse_at(&statement[position + 1..], start)?,
+                    )
+                }
+                None => (None, parse_at(statement, offset)?),
+            };
+            let formula = formula.substitute_all(&bindings);
+            let numbers = partial::free_variables(&formula)
+                .into_iter()
+                .filter_map(|name| match context.variable(&name) {
+                    Some(Value::Number(value)) => {
+                        let value = Expr::new(ExprKind::Number(*value), formula.span);
+                        Some((name, value))
+                    }
+                    _ => None,
+                })
+                .collect();
+            let formula = formula.substitute_all(&numbers);
+            let formula = fold(&inline(&formula, &bindings, context, options, 0), context);
+            if let Some(name) = name {
+                bindings.insert(name, formula.clone());
+            }
+            result = Some(formula);
+        }
+        let Some(formula) = result else {
+            return Err(Error::Parser(ParserError {
+                kind: ParserErrorKind::UnexpectedEnd {
+                    expected: "a formula",
+                },
+                span: Span {
+                    start: source.len(),
+                    end: source.len(),
+                },
+            }));
+        };
+        Ok(formula.compile_in(context.angle_unit())?)
+    }
+
+    /// Names of the inputs, in the order `run` expects their values.
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Number of values of the stack when running.
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    pub(crate) fn max_stack(&self) -> usize {
+        self.max_stack
+    }
+
+    /// Number of temporaries of `Instruction::Store` and `Instruction::Load`.
+    pub fn temporaries(&self) -> usize {
+        self.temporaries
+    }
+
+    pub fn run(&self, inputs: &[f64]) -> Result<f64, EvaluatorError> {
+        self.run_with(inputs, &mut Vec::new())
+    }
+
+    /// Runs the program with `scratch` holding its temporaries and stack, so that running
+    /// it repeatedly with the same buffer does not allocate.
+    pub fn run_with(&self, inputs: &[f64], scratch: &mut Vec<f64>) -> Result<f64, EvaluatorError> {
+        if inputs.len() != self.inputs.len() {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::ArgumentCount {
+                function: String::from("program"),
+                min: self.inputs.len(),
+                max: Some(self.inputs.len()),
+                found: inputs.len(),
+            }));
+        }
+        // The temporaries, then the stack.
+        let stack = scratch;
+        stack.clear();
+        stack.resize(self.temporaries, 0.0);
+        stack.reserve(self.max_stack);
+        let mut pc = 0;
+        while let Some(instruction) = self.instructions.get(pc) {
+            pc += 1;
+            match *instruction {
+                Instruction::Constant(value) => stack.push(value),
+                Instruction::Input(index) => stack.push(inputs[index]),
+                Instruction::Negate => {
+                    let value = stack.pop().unwrap();
+                    stack.push(-value);
+                }
+                Instruction::Percent => {
+                    let value = stack.pop().unwrap();
+                    stack.push(value / 100.0);
+                }
+                Instruction::Binary(operator) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(binary(operator, lhs, rhs)?);
+                }
+                Instruction::Call(function) => {
+                    let value = stack.pop().unwrap();
+                    stack.push(function(value));
+                }
+                Instruction::Truth => {
+                    let value = stack.pop().unwrap();
+                    stack.push(truth(is_true(value)));
+                }
+                Instruction::Jump(target) => pc = target,
+                Instruction::JumpIfFalse(target) => {
+                    if !is_true(stack.pop().unwrap()) {
+                        pc = target;
+                    }
+                }
+                Instruction::JumpIfTrue(target) => {
+                    if is_true(stack.pop().unwrap()) {
+                        pc = target;
+                    }
+                }
+                Instruction::Store(index) => stack[index] = *stack.last().unwrap(),
+                Instruction::Load(index) => stack.push(stack[index]),
+            }
+        }
+        Ok(if stack.len() > self.temporaries {
+            stack.pop().unwrap()
+        } else {
+            f64::NAN
+        })
+    }
+}
+
+/// A compiled formula running as native code when the `jit` feature is enabled and the
+/// formula only uses arithmetic, and on the stack machine otherwise.
+#[derive(Debug)]
+pub struct Compiled {
+    program: Program,
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    native: Option<crate::jit::Native>,
+}
+
+impl Compiled {
+    pub fn new(expr: &Expr) -> Result<Compiled, EvaluatorError> {
+        Ok(Compiled::from(expr.compile()?))
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Whether evaluation runs as native code.
+    pub fn is_native(&self) -> bool {
+        #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+        return self.native.is_some();
+        #[cfg(not(all(feature = "jit", target_arch = "x86_64", target_os = "linux")))]
+        return false;
+    }
+
+    /// Evaluates the formula, with the same results and errors as `Program::run`.
+    pub fn eval(&self, inputs: &[f64]) -> Result<f64, EvaluatorError> {
+        self.eval_with(inputs, &mut Vec::new())
+    }
+
+    /// Evaluates the formula with `scratch` as the space of native code or of the stack
+    /// machine, as `Program::run_with` does.
+    pub fn eval_with(&self, inputs: &[f64], scratch: &mut Vec<f64>) -> Result<f64, EvaluatorError> {
+        #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+        if let Some(native) = &self.native {
+            if inputs.len() == self.program.inputs.len() {
+                if scratch.len() < native.stack_size() {
+                    scratch.resize(native.stack_size(), 0.0);
+                }
+                if let Some(value) = native.run(inputs, scratch) {
+                    return Ok(value);
+                }
+            }
+        }
+        self.program.run_with(inputs, scratch)
+    }
+
+    /// Evaluates the formula once per row of `columns`, which pair input names with their
+    /// values: `eval_batch(&[("x", &xs), ("y", &ys)])`. Rows failing to evaluate, such as
+    /// divisions by zero, give NaN. Every input of the formula needs a column, and all
+    /// columns must have the same length; columns the formula does not use are ignored.
+    pub fn eval_batch(&self, columns: &[(&str, &[f64])]) -> Result<Vec<f64>, EvaluatorError> {
+        let (columns, rows) = self.batch_columns(columns)?;
+        Ok(self.eval_rows(&columns, 0..rows))
+    }
+
+    /// Like `eval_batch`, splitting the rows between as many threads as there are
+    /// processors.
+    pub fn eval_batch_parallel(
+        &self,
+        columns: &[(&str, &[f64])],
+    ) -> Result<Vec<f64>, EvaluatorError> {
+        let (columns, rows) = self.batch_columns(columns)?;
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        let chunk = rows.div_ceil(threads).max(MIN_PARALLEL_ROWS);
+        if chunk >= rows {
+            return Ok(self.eval_rows(&columns, 0..rows));
+        }
+        let columns = &columns;
+        Ok(std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..rows)
+                .step_by(chunk)
+                .map(|start| {
+                    scope.spawn(move || self.eval_rows(columns, start..rows.min(start + chunk)))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        }))
+    }
+
+    /// Orders `columns` as the inputs of the program and returns the number of rows.
+    fn batch_columns<'a>(
+        &self,
+        columns: &[(&str, &'a [f64])],
+    ) -> Result<(Vec<&'a [f64]>, usize), EvaluatorError> {
+        let rows = columns.first().map_or(0, |(_, values)| values.len());
+        if columns.iter().any(|(_, values)| values.len() != rows) {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                String::from("Input columns have different lengths"),
+            )));
+        }
+        let columns = self
+            .program
+            .inputs
+            .iter()
+            .map(|input| {
+                columns
+                    .iter()
+                    .find(|(name, _)| name == input)
+                    .map(|(_, values)| *values)
+                    .ok_or_else(|| {
+                        EvaluatorError::new(EvaluatorErrorKind::UnknownVariable(input.clone()))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((columns, rows))
+    }
+
+    fn eval_rows(&self, columns: &[&[f64]], rows: std::ops::Range<usize>) -> Vec<f64> {
+        let mut inputs = vec![0.0; columns.len()];
+        let mut scratch = Vec::new();
+        rows.map(|row| {
+            for (input, column) in inputs.iter_mut().zip(columns) {
+                *input = column[row];
+            }
+            self.eval_with(&inputs, &mut scratch).unwrap_or(f64::NAN)
+        })
+        .collect()
+    }
+}
+
+/// Fewest rows worth handing to a thread of `Compiled::eval_batch_parallel`.
+const MIN_PARALLEL_ROWS: usize = 4096;
+
+impl From<Program> for Compiled {
+    fn from(program: Program) -> Compiled {
+        Compiled {
+            #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+            native: crate::jit::Native::compile(&program),
+            program,
+        }
+    }
+}
+
+fn binary(operator: BinaryOperator, lhs: f64, rhs: f64) -> Result<f64, EvaluatorError> {
+    Ok(match operator {
+        BinaryOperator::Add => lhs + rhs,
+        BinaryOperator::Subtract => lhs - rhs,
+        BinaryOperator::Multiply => lhs * rhs,
+        BinaryOperator::Divide => {
+            if rhs == 0.0 {
+                return Err(EvaluatorError::new(EvaluatorErrorKind::DivisionByZero));
+            }
+            lhs / rhs
+        }
+        BinaryOperator::Power => lhs.powf(rhs),
+        BinaryOperator::Equal => truth(lhs == rhs),
+        BinaryOperator::NotEqual => truth(lhs != rhs),
+        BinaryOperator::Less => truth(lhs < rhs),
+        BinaryOperator::LessEqual => truth(lhs <= rhs),
+        BinaryOperator::Greater => truth(lhs > rhs),
+        BinaryOperator::GreaterEqual => truth(lhs >= rhs),
+        BinaryOperator::And => truth(is_true(lhs) && is_true(rhs)),
+        BinaryOperator::Or => truth(is_true(lhs) || is_true(rhs)),
+        BinaryOperator::PlusMinus => unreachable!("uncertainties are not compiled"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::Context;
+    use crate::lexer::Span;
+    use crate::parser::Parser;
+
+    fn compile(source: &str) -> Program {
+        Parser::new(source).parse().unwrap().compile().unwrap()
+    }
+
+    #[test]
+    fn run_like_the_evaluator() {
+        let sources = [
+            "x * y + sqrt(x) - 2^-y",
+            "200 + x% - (y - 10%)",
+            "x > y ? x / y : y != 0 && x / y < 1 || -pi",
+            "0 && 1 / 0 || x <= y",
+            "(x > 0 ? sqrt(x * y) : 1) + sqrt(x * y) * sqrt(x * y) - (x > 1 || x * y > 1)",
+        ];
+        for source in sources {
+            let program = compile(source);
+            assert_eq!(program.inputs(), ["x", "y"]);
+            for (x, y) in [(4.0, 2.0), (1.0, 3.0), (0.5, 0.0)] {
+                let mut context = Context::new();
+                context.set_variable("x", Value::Number(x));
+                context.set_variable("y", Value::Number(y));
+                let expected = context
+                    .evaluate(&Parser::new(source).parse().unwrap())
+                    .map(|value| value.as_number().unwrap())
+                    .map_err(|error| error.kind);
+                let found = program.run(&[x, y]).map_err(|error| error.kind);
+                assert_eq!(found, expected, "{} for x = {}, y = {}", source, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn compute_common_subexpressions_once() {
+        let program = compile("sqrt(x^2 + y^2) + 2 * sqrt(x^2 + y^2) / sqrt(x^2 + y^2)");
+        let calls = program
+            .instructions()
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::Call(_)))
+            .count();
+        assert_eq!(calls, 1);
+        assert_eq!(program.temporaries(), 1);
+        assert_eq!(program.run(&[3.0, 4.0]), Ok(7.0));
+        let mut scratch = vec![];
+        assert_eq!(program.run_with(&[3.0, 4.0], &mut scratch), Ok(7.0));
+        assert_eq!(program.run_with(&[6.0, 8.0], &mut scratch), Ok(12.0));
+        let compiled = Compiled::from(program);
+        assert_eq!(compiled.eval(&[3.0, 4.0]), Ok(7.0));
+        assert_eq!(compiled.eval_with(&[6.0, 8.0], &mut scratch), Ok(12.0));
+        // Only computed in a branch, so not shared.
+        assert_eq!(compile("x > 0 ? 1 / x : 1 / x + 1").temporaries(), 0);
+        assert_eq!(compile("(x + 1) * (x + 1.0) - (x + 2)").temporaries(), 1);
+    }
+
+    #[test]
+    fn propagate_constants_in_scripts() {
+        let script = "k = 2; r = sqrt(k^2 + 5)\ny = k * x + r * (x > 0 ? 1 / k : 1 / 0)";
+        let program = Program::compile_script(script).unwrap();
+        assert_eq!(program.inputs(), ["x"]);
+        assert!(!program
+            .instructions()
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::Call(_))));
+        assert_eq!(program.run(&[1.0]), Ok(3.5));
+        assert!(program.run(&[-1.0]).is_err());
+        let program = Program::compile_script("a = x == 2; a ? 10% : 1").unwrap();
+        assert_eq!(program.run(&[2.0]), Ok(0.1));
+        assert!(matches!(
+            Program::compile_script("k = 1; 2 * k = x"),
+            Err(Error::Parser(ParserError {
+                kind: ParserErrorKind::UnexpectedToken { .. },
+                span: Span { start: 7, end: 12 },
+            }))
+        ));
+        assert!(Program::compile_script(" ; ").is_err());
+    }
+
+    #[test]
+    fn inline_user_functions() {
+        let options = CompileOptions {
+            inline_size: Some(8),
+        };
+        let mut context = Context::new();
+        let square = Parser::new("t -> t * t").parse().unwrap();
+        context.set_variable("square", context.evaluate(&square).unwrap());
+        let sum = Parser::new("square(x + 1) + square(2)").parse().unwrap();
+        assert!(sum.compile().is_err());
+        let program = sum.compile_with(&context, &options).unwrap();
+        assert_eq!(program.run(&[2.0]), Ok(13.0));
+        let large = CompileOptions {
+            inline_size: Some(2),
+        };
+        assert!(sum.compile_with(&context, &large).is_err());
+        let script = "cube = t -> t * square(t); cube(x) - cube(2)";
+        let program = Program::compile_script_with(script, &context, &options).unwrap();
+        assert_eq!(program.run(&[3.0]), Ok(19.0));
+        assert!(program.instructions().iter().any(
+            |instruction| matches!(instruction, Instruction::Constant(value) if *value == 8.0)
+        ));
+        // Recursion stops being inlined.
+        let script = "f = n -> n < 1 ? 0 : f(n - 1); f(x)";
+        let options = CompileOptions {
+            inline_size: Some(16),
+        };
+        assert!(Program::compile_script_with(script, &context, &options).is_err());
+    }
+
+    #[test]
+    fn run_compiled_formulas() {
+        let arithmetic =
+            Compiled::new(&Parser::new("-x * 2 + y / 4 - 50%").parse().unwrap()).unwrap();
+        assert_eq!(
+            arithmetic.is_native(),
+            cfg!(feature = "jit") && cfg!(target_arch = "x86_64") && cfg!(target_os = "linux")
+        );
+        assert_eq!(arithmetic.eval(&[3.0, 2.0]), Ok(-2.75));
+        assert_eq!(arithmetic.eval(&[-1.5, 0.0]), Ok(1.5));
+        let division = Compiled::new(&Parser::new("x / y").parse().unwrap()).unwrap();
+        assert_eq!(
+            division.eval(&[1.0, 0.0]).map_err(|error| error.kind),
+            Err(EvaluatorErrorKind::DivisionByZero)
+        );
+        assert!(division.eval(&[f64::NAN, f64::NAN]).unwrap().is_nan());
+        assert!(division.eval(&[1.0]).is_err());
+        let conditional =
+            Compiled::new(&Parser::new("x > 1 ? sqrt(x) : 0").parse().unwrap()).unwrap();
+        assert!(!conditional.is_native());
+        assert_eq!(conditional.eval(&[9.0]), Ok(3.0));
+    }
+
+    #[test]
+    fn evaluate_batches() {
+        let compiled = Compiled::new(&Parser::new("x / y + 1").parse().unwrap()).unwrap();
+        let xs = [1.0, 2.0, 3.0];
+        let ys = [2.0, 0.0, 4.0];
+        let found = compiled
+            .eval_batch(&[("y", &ys), ("z", &[0.0; 3]), ("x", &xs)])
+            .unwrap();
+        assert_eq!(found[0], 1.5);
+        assert!(found[1].is_nan());
+        assert_eq!(found[2], 1.75);
+        assert_eq!(
+            compiled.eval_batch(&[("x", &xs)]).unwrap_err().kind,
+            EvaluatorErrorKind::UnknownVariable(String::from("y"))
+        );
+        assert!(compiled.eval_batch(&[("x", &xs), ("y", &[1.0])]).is_err());
+
+        let xs: Vec<f64> = (0..20_000).map(f64::from).collect();
+        let ys = vec![2.0; xs.len()];
+        let columns = [("x", xs.as_slice()), ("y", ys.as_slice())];
+        assert_eq!(
+            compiled.eval_batch_parallel(&columns).unwrap(),
+            compiled.eval_batch(&columns).unwrap()
+        );
+    }
+
+    #[test]
+    fn bind_parameters() {
+        let expr = Parser::new("y - 2 * x").parse().unwrap();
+        let f = expr.bind(&["x", "y"]).unwrap();
+        assert_eq!(f(&[1.0, 5.0]), Ok(3.0));
+        assert!(f(&[1.0]).is_err());
+        let g = expr.bind(&["y", "unused", "x"]).unwrap();
+        assert_eq!(g(&[5.0, 0.0, 1.0]), Ok(3.0));
+        assert_eq!(
+            expr.bind(&["x"]).err().unwrap().kind,
+            EvaluatorErrorKind::UnknownVariable(String::from("y"))
+        );
+        let evaluate: &dyn Fn(&[f64]) -> Result<f64, EvaluatorError> = &f;
+        assert_eq!(evaluate(&[0.0, 0.0]), Ok(0.0));
+    }
+
+    #[test]
+    fn reject_non_numeric_expressions() {
+        let error = Parser::new("1 + integrate(sin, 0, 1)")
+            .parse()
+            .unwrap()
+            .compile()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Cannot compile the call of 'integrate'");
+        assert_eq!(error.span, Some(Span::new(4, 24)));
+        assert!(compile("x").run(&[]).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::parser::{Expr, Parser, ParserError};
+
+pub const DEFAULT_CACHE_CAPACITY: usize = 128;
+
+/// Parsed expressions keyed by their source text, evicting the least recently used one when
+/// full. Only successful parses are cached.
+#[derive(Debug, Clone)]
+pub struct ExpressionCache {
+    capacity: usize,
+    entries: HashMap<String, (Arc<Expr>, u64)>,
+    /// Incremented on every access, to find the least recently used entry.
+    clock: u64,
+}
+
+impl ExpressionCache {
+    pub fn new(capacity: usize) -> ExpressionCache {
+        ExpressionCache {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the capacity, evicting entries if there are too many. A capacity of zero
+    /// disables caching.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.evict();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Parses `source`, or returns the expression parsed by a previous call.
+    pub fn parse(&mut self, source: &str) -> Result<Arc<Expr>, ParserError> {
+        self.clock += 1;
+        if let Some((expr, last_used)) = self.entries.get_mut(source) {
+            *last_used = self.clock;
+            return Ok(expr.clone());
+        }
+        let expr = Arc::new(Parser::new(source).parse()?);
+        if self.capacity > 0 {
+            if self.entries.len() >= self.capacity {
+                self.evict();
+            }
+            self.entries
+                .insert(source.to_string(), (expr.clone(), self.clock));
+        }
+        Ok(expr)
+    }
+
+    fn evict(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, (_, last_used))| *last_used)
+            .map(|(source, _)| source.clone());
+        if let Some(source) = oldest {
+            self.entries.remove(&source);
+        }
+    }
+}
+
+impl Default for ExpressionCache {
+    fn default() -> ExpressionCache {
+        ExpressionCache::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evict_least_recently_used() {
+        let mut cache = ExpressionCache::new(2);
+        let first = cache.parse("a * b + c").unwrap();
+        cache.parse("x").unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.parse("a * b + c").unwrap()));
+        cache.parse("y").unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.entries.contains_key("a * b + c"));
+        assert!(!cache.entries.contains_key("x"));
+        assert!(cache.parse("1 +").is_err());
+        assert_eq!(cache.len(), 2);
+        cache.set_capacity(0);
+        assert!(cache.is_empty());
+        cache.parse("z").unwrap();
+        assert!(cache.is_empty());
+    }
+}
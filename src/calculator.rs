@@ -0,0 +1,197 @@
+//! A configured calculator, for embedders setting its options in one place:
+//! `Calculator::builder().precision(6).angle(AngleUnit::Degrees).build()`.
+
+use crate::bytecode::{CompileOptions, Program};
+use crate::display::DisplayOptions;
+use crate::error::Error;
+use crate::evaluator::{
+    eval_with, AngleUnit, Context, EvaluatorError, EvaluatorErrorKind, Limits, NonFinite,
+};
+use crate::fixed::FixedFormat;
+use crate::parser::Parser;
+use crate::value::Value;
+
+/// The numbers formulas are computed with. Rationals are not a backend yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// `f64`, with every value type and builtin.
+    #[default]
+    Float,
+    /// Fixed point in the format, as `Expr::evaluate_fixed` does.
+    Fixed(FixedFormat),
+    /// Exact decimals rounded to that many fractional digits, as `Expr::evaluate_money`
+    /// does.
+    Money(u32),
+}
+
+/// Sets the options of a [`Calculator`], each left as in `Context::new` unless set.
+#[derive(Debug, Clone, Default)]
+pub struct CalculatorBuilder {
+    context: Context,
+    backend: Backend,
+    compile_options: CompileOptions,
+}
+
+impl CalculatorBuilder {
+    /// The significant digits results are written with by `Calculator::format`.
+    pub fn precision(mut self, digits: usize) -> CalculatorBuilder {
+        let options = DisplayOptions {
+            precision: Some(digits),
+            ..*self.context.display_options()
+        };
+        self.context.set_display_options(options);
+        self
+    }
+
+    pub fn display_options(mut self, options: DisplayOptions) -> CalculatorBuilder {
+        self.context.set_display_options(options);
+        self
+    }
+
+    pub fn angle(mut self, unit: AngleUnit) -> CalculatorBuilder {
+        self.context.set_angle_unit(unit);
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> CalculatorBuilder {
+        self.backend = backend;
+        self
+    }
+
+    /// Makes arithmetic on booleans an error, see `Context::set_strict_booleans`.
+    pub fn strict(mut self, strict: bool) -> CalculatorBuilder {
+        self.context.set_strict_booleans(strict);
+        self
+    }
+
+    pub fn non_finite(mut self, non_finite: NonFinite) -> CalculatorBuilder {
+        self.context.set_non_finite(non_finite);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> CalculatorBuilder {
+        self.context.set_limits(limits);
+        self
+    }
+
+    pub fn variable(mut self, name: &str, value: Value) -> CalculatorBuilder {
+        self.context.set_variable(name, value);
+        self
+    }
+
+    /// The options of `Calculator::compile`.
+    pub fn compile_options(mut self, options: CompileOptions) -> CalculatorBuilder {
+        self.compile_options = options;
+        self
+    }
+
+    pub fn build(self) -> Calculator {
+        Calculator {
+            context: self.context,
+            backend: self.backend,
+            compile_options: self.compile_options,
+        }
+    }
+}
+
+/// A context with its settings and a backend, evaluating and compiling formulas. Like
+/// contexts, calculators are `Send` and `Sync` and evaluate without being mutable.
+#[derive(Debug, Clone, Default)]
+pub struct Calculator {
+    context: Context,
+    backend: Backend,
+    compile_options: CompileOptions,
+}
+
+impl Calculator {
+    pub fn builder() -> CalculatorBuilder {
+        CalculatorBuilder::default()
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Parses and evaluates `source` with the backend. Exact backends see the variables
+    /// which are numbers only, and their results are returned as numbers.
+    pub fn eval(&self, source: &str) -> Result<Value, Error> {
+        let result = match self.backend {
+            Backend::Float => return eval_with(source, &self.context),
+            Backend::Fixed(format) => Parser::new(source)
+                .parse()?
+                .evaluate_fixed_in(&self.context, format)?
+                .to_f64(),
+            Backend::Money(scale) => Parser::new(source)
+                .parse()?
+                .evaluate_money_in(&self.context, scale)?
+                .to_f64(),
+        };
+        Ok(Value::Number(result))
+    }
+
+    /// Compiles the script `source` to bytecode with the functions, variables and angle
+    /// unit of the context, see `Program::compile_script_with`. Programs compute in `f64`,
+    /// so there are none for the exact backends.
+    pub fn compile(&self, source: &str) -> Result<Program, Error> {
+        let backend = match self.backend {
+            Backend::Float => {
+                return Program::compile_script_with(source, &self.context, &self.compile_options)
+            }
+            Backend::Fixed(_) => "with the fixed-point backend",
+            Backend::Money(_) => "with the money backend",
+        };
+        Err(EvaluatorError::new(EvaluatorErrorKind::NotCompilable(String::from(backend))).into())
+    }
+
+    /// Writes `value` with the display options, such as the precision.
+    pub fn format(&self, value: &Value) -> String {
+        self.context.format(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_calculators() {
+        let calculator = Calculator::builder()
+            .precision(4)
+            .angle(AngleUnit::Degrees)
+            .strict(true)
+            .variable("x", Value::Number(2.0))
+            .build();
+        let value = calculator.eval("sin(30) + x / 3").unwrap();
+        assert_eq!(calculator.format(&value), "1.167");
+        assert_eq!(calculator.eval("(x > 0) * 5").unwrap_err().code(), "E0204");
+        let program = calculator.compile("y = x * 3; y + 1").unwrap();
+        assert!(program.inputs().is_empty());
+        assert_eq!(program.run(&[]), Ok(7.0));
+        // Compiled formulas measure angles as the calculator does.
+        assert_eq!(calculator.compile("sin(30)").unwrap().run(&[]), Ok(0.5));
+        let program = calculator.compile("sin(z) + acos(z / 60)").unwrap();
+        assert_eq!(program.run(&[30.0]), Ok(60.5));
+        let degrees = Calculator::builder()
+            .angle(AngleUnit::Degrees)
+            .variable("x", Value::Number(30.0))
+            .build();
+        assert_eq!(degrees.compile("sin(x)").unwrap().run(&[]), Ok(0.5));
+        let money = Calculator::builder()
+            .backend(Backend::Money(2))
+            .variable("price", Value::Number(0.1))
+            .build();
+        assert_eq!(money.eval("price + 0.2"), Ok(Value::Number(0.3)));
+        assert_eq!(money.eval("0.1 + 0.2"), Ok(Value::Number(0.3)));
+        assert_eq!(money.compile("0.1 + 0.2").unwrap_err().code(), "E0209");
+        let fixed = Calculator::builder()
+            .backend(Backend::Fixed(FixedFormat::new(15, 16).unwrap()))
+            .build();
+        assert_eq!(fixed.eval("3 / 4 + 1"), Ok(Value::Number(1.75)));
+        assert!(fixed.eval("sin(1)").is_err());
+        assert_eq!(fixed.compile("3 / 4").unwrap_err().code(), "E0209");
+    }
+}
@@ -0,0 +1,319 @@
+//! The text of errors through a catalog of messages, so that embedders can show errors in
+//! other languages. Each message has a key, the code of `Error::code` or a key derived
+//! from it, and the values it quotes; a catalog gives its text, or keeps the English one.
+//!
+//! | Key | English |
+//! | --- | --- |
+//! | `E0001` | `Unexpected character at position {position}` |
+//! | `E0101` | `Unexpected '{found}', expected {expected}` |
+//! | `E0101.short` | `Unexpected '{found}'`, the headline of reports |
+//! | `E0102` | `Unexpected end of input, expected {expected}` |
+//! | `E0102.short` | `Unexpected end of input` |
+//! | `expected` | `expected {expected}`, the label of reports |
+//! | `E0103` | `Invalid number '{number}'` |
+//! | `E0104` | `Lambda parameters must be plain identifiers` |
+//! | `E0105` | `Expression nested deeper than {depth} levels` |
+//! | `E0201` | `Unknown variable '{name}'` |
+//! | `E0202` | `Unknown function '{name}'` |
+//! | `E0203.exact`, `E0203.range`, `E0203.open` | `'{function}' expects {min}`, `{min} to {max}` or `at least {min}` ` argument(s), found {found}` |
+//! | `E0204` | `Expected a {expected}, found a {found}` |
+//! | `E0205` | `Division by zero` |
+//! | `E0206` | `Cannot {operation} {lhs} and {rhs}` |
+//! | `E0207` | `No exchange rate from {from} to {to}` |
+//! | `E0208.time`, `.depth`, `.digits`, `.cost` | the limit, as `Result has more than {limit} digits` |
+//! | `E0209` | `Cannot compile {what}` |
+//! | `E0210`, `E0211` | `{message}` |
+//! | `E0212` to `E0215` | `Result is not a number` and so on |
+//! | `suggestion` | `, did you mean '{name}'?`, after the message |
+//! | `suggestion.help`, `E0105.help`, `E0201.help`, `E0202.help` | the help of reports |
+//!
+//! Type names, operations and what was expected are quoted in English, as the library
+//! writes them.
+
+use std::collections::HashMap;
+
+use crate::diagnostic::Diagnostic;
+use crate::error::Error;
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind, Limit};
+use crate::parser::{ParserErrorKind, MAX_NESTING_DEPTH};
+
+/// The texts of messages in a language.
+pub trait Catalog: Send + Sync {
+    /// The text of the message `key` quoting `arguments`, or `None` to keep the English
+    /// text.
+    fn text(&self, key: &str, arguments: &[(&str, String)]) -> Option<String>;
+}
+
+/// A catalog of templates such as `Variable inconnue '{name}'`, whose arguments are
+/// written in place of their names in braces.
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    templates: HashMap<String, String>,
+}
+
+impl Templates {
+    pub fn new() -> Templates {
+        Templates::default()
+    }
+
+    /// The templates of lines `key = template`, skipping blank lines and `#` comments, as
+    /// translations are shipped in files.
+    pub fn parse(source: &str) -> Templates {
+        let mut templates = Templates::new();
+        for line in source.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, template)) = line.split_once('=') {
+                templates.insert(key.trim(), template.trim());
+            }
+        }
+        templates
+    }
+
+    pub fn insert(&mut self, key: &str, template: &str) {
+        self.templates.insert(key.to_string(), template.to_string());
+    }
+}
+
+impl Catalog for Templates {
+    fn text(&self, key: &str, arguments: &[(&str, String)]) -> Option<String> {
+        let mut text = self.templates.get(key)?.clone();
+        for (name, value) in arguments {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        Some(text)
+    }
+}
+
+/// A message of an error: its key in catalogs and the values it quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub key: &'static str,
+    pub arguments: Vec<(&'static str, String)>,
+}
+
+impl Message {
+    fn new(key: &'static str, arguments: Vec<(&'static str, String)>) -> Message {
+        Message { key, arguments }
+    }
+
+    /// The text of the message in `catalog`, `english` if it has none.
+    fn text(&self, catalog: &dyn Catalog, english: String) -> String {
+        catalog.text(self.key, &self.arguments).unwrap_or(english)
+    }
+}
+
+impl Error {
+    /// The message of the error, without the suggestion of a close name.
+    pub fn message(&self) -> Message {
+        match self {
+            Error::Parser(error) => match &error.kind {
+                ParserErrorKind::Lexer(error) => {
+                    Message::new("E0001", vec![("position", error.span.start.to_string())])
+                }
+                ParserErrorKind::UnexpectedToken { found, expected } => Message::new(
+                    "E0101",
+                    vec![("found", found.clone()), ("expected", expected.to_string())],
+                ),
+                ParserErrorKind::UnexpectedEnd { expected } => {
+                    Message::new("E0102", vec![("expected", expected.to_string())])
+                }
+                ParserErrorKind::InvalidNumber(number) => {
+                    Message::new("E0103", vec![("number", number.clone())])
+                }
+                ParserErrorKind::InvalidLambdaParameters => Message::new("E0104", vec![]),
+                ParserErrorKind::TooDeeplyNested => {
+                    Message::new("E0105", vec![("depth", MAX_NESTING_DEPTH.to_string())])
+                }
+            },
+            Error::Evaluator(error) => evaluator_message(&error.kind),
+        }
+    }
+
+    /// The error as `to_string` writes it, in the language of `catalog`.
+    pub fn localize(&self, catalog: &dyn Catalog) -> String {
+        let english = match self {
+            Error::Evaluator(error) => EvaluatorError {
+                suggestion: None,
+                ..error.clone()
+            }
+            .to_string(),
+            error => error.to_string(),
+        };
+        let mut text = self.message().text(catalog, english);
+        if let Error::Evaluator(EvaluatorError {
+            suggestion: Some(name),
+            ..
+        }) = self
+        {
+            let suggestion = Message::new("suggestion", vec![("name", name.to_string())]);
+            text += &suggestion.text(catalog, format!(", did you mean '{}'?", name));
+        }
+        text
+    }
+}
+
+fn evaluator_message(kind: &EvaluatorErrorKind) -> Message {
+    let text = |value: &str| value.to_string();
+    match kind {
+        EvaluatorErrorKind::UnknownVariable(name) => {
+            Message::new("E0201", vec![("name", text(name))])
+        }
+        EvaluatorErrorKind::UnknownFunction(name) => {
+            Message::new("E0202", vec![("name", text(name))])
+        }
+        EvaluatorErrorKind::ArgumentCount {
+            function,
+            min,
+            max,
+            found,
+        } => {
+            let key = match max {
+                Some(max) if max == min => "E0203.exact",
+                Some(_) => "E0203.range",
+                None => "E0203.open",
+            };
+            let mut arguments = vec![
+                ("function", text(function)),
+                ("min", min.to_string()),
+                ("found", found.to_string()),
+            ];
+            arguments.extend(max.map(|max| ("max", max.to_string())));
+            Message::new(key, arguments)
+        }
+        EvaluatorErrorKind::TypeMismatch { expected, found } => Message::new(
+            "E0204",
+            vec![("expected", text(expected)), ("found", text(found))],
+        ),
+        EvaluatorErrorKind::DivisionByZero => Message::new("E0205", vec![]),
+        EvaluatorErrorKind::DimensionMismatch {
+            operation,
+            lhs,
+            rhs,
+        } => Message::new(
+            "E0206",
+            vec![
+                ("operation", text(operation)),
+                ("lhs", text(lhs)),
+                ("rhs", text(rhs)),
+            ],
+        ),
+        EvaluatorErrorKind::MissingExchangeRate { from, to } => {
+            Message::new("E0207", vec![("from", text(from)), ("to", text(to))])
+        }
+        EvaluatorErrorKind::LimitExceeded(limit) => {
+            let (key, limit) = match limit {
+                Limit::Time(timeout) => ("E0208.time", format!("{:?}", timeout)),
+                Limit::Depth(depth) => ("E0208.depth", depth.to_string()),
+                Limit::Digits(digits) => ("E0208.digits", digits.to_string()),
+                Limit::Cost(cost) => ("E0208.cost", cost.to_string()),
+            };
+            Message::new(key, vec![("limit", limit)])
+        }
+        EvaluatorErrorKind::NotCompilable(what) => {
+            Message::new("E0209", vec![("what", text(what))])
+        }
+        EvaluatorErrorKind::InvalidArgument(message) => {
+            Message::new("E0210", vec![("message", text(message))])
+        }
+        EvaluatorErrorKind::NotConverged(message) => {
+            Message::new("E0211", vec![("message", text(message))])
+        }
+        EvaluatorErrorKind::NotANumber => Message::new("E0212", vec![]),
+        EvaluatorErrorKind::Infinite => Message::new("E0213", vec![]),
+        EvaluatorErrorKind::Overflow => Message::new("E0214", vec![]),
+        EvaluatorErrorKind::Cancelled => Message::new("E0215", vec![]),
+    }
+}
+
+impl Diagnostic {
+    /// The report of `error` in the language of `catalog`, as `from` makes it in English.
+    pub fn localized(error: &Error, catalog: &dyn Catalog) -> Diagnostic {
+        let mut diagnostic = Diagnostic::from(error);
+        let message = error.message();
+        let short = match message.key {
+            "E0101" => Some("E0101.short"),
+            "E0102" => Some("E0102.short"),
+            _ => None,
+        };
+        match short {
+            Some(short) => {
+                let expected = message
+                    .arguments
+                    .iter()
+                    .filter(|(name, _)| *name == "expected")
+                    .cloned()
+                    .collect();
+                diagnostic.label =
+                    Message::new("expected", expected).text(catalog, diagnostic.label);
+                diagnostic.message =
+                    Message::new(short, message.arguments).text(catalog, diagnostic.message);
+            }
+            None => diagnostic.message = message.text(catalog, diagnostic.message),
+        }
+        let help = match error {
+            Error::Evaluator(EvaluatorError {
+                suggestion: Some(name),
+                ..
+            }) => Message::new("suggestion.help", vec![("name", name.to_string())]),
+            error => {
+                let message = error.message();
+                match message.key {
+                    "E0105" => Message::new("E0105.help", message.arguments),
+                    "E0201" => Message::new("E0201.help", message.arguments),
+                    "E0202" => Message::new("E0202.help", message.arguments),
+                    _ => return diagnostic,
+                }
+            }
+        };
+        diagnostic.help = diagnostic.help.map(|english| help.text(catalog, english));
+        diagnostic
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::Context;
+
+    #[test]
+    fn localize_errors() {
+        let catalog = Templates::parse(
+            "# French\n\
+             E0201 = Variable inconnue '{name}'\n\
+             E0201.help = affectez-la d'abord, comme {name} = 2\n\
+             suggestion = , vouliez-vous dire '{name}' ?\n\
+             E0203.exact = '{function}' attend {min} argument(s), en a reçu {found}\n",
+        );
+        let mut context = Context::new();
+        let error = context.eval("2 * y").unwrap_err();
+        assert_eq!(error.localize(&catalog), "Variable inconnue 'y'");
+        let diagnostic = Diagnostic::localized(&error, &catalog);
+        assert_eq!(diagnostic.message, "Variable inconnue 'y'");
+        assert_eq!(
+            diagnostic.help.as_deref(),
+            Some("affectez-la d'abord, comme y = 2")
+        );
+        let error = context.eval("sqrt(1, 2) + widht").unwrap_err();
+        assert_eq!(
+            error.localize(&catalog),
+            "'sqrt' attend 1 argument(s), en a reçu 2"
+        );
+        context.set_variable("width", crate::value::Value::Number(1.0));
+        let error = context.eval("widht").unwrap_err();
+        assert_eq!(
+            error.localize(&catalog),
+            "Variable inconnue 'widht', vouliez-vous dire 'width' ?"
+        );
+        // Messages missing from the catalog stay in English.
+        let error = context.eval("1 / 0").unwrap_err();
+        assert_eq!(error.localize(&catalog), error.to_string());
+        let error = context.eval("(1").unwrap_err();
+        assert_eq!(
+            Diagnostic::localized(&error, &catalog),
+            Diagnostic::from(&error)
+        );
+    }
+}
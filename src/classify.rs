@@ -0,0 +1,173 @@
+//! The classes of the tokens of formulas and scripts, for highlighting them in editors and
+//! web pages.
+
+use crate::builtins;
+use crate::evaluator::Context;
+use crate::lexer::{Lexer, Span, Token};
+use crate::value::Value;
+
+/// What a token is, as highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClass {
+    Number,
+    String,
+    /// Operators, the keywords `in` and `of` included.
+    Operator,
+    /// Parentheses, commas and the `;` between statements.
+    Punctuation,
+    Function,
+    /// Variables, constants and names yet to be defined.
+    Variable,
+    Unit,
+    /// From `#` to the end of the line.
+    Comment,
+    /// Input the lexer rejects, and parentheses without a match in their statement.
+    Error,
+}
+
+impl Context {
+    /// The tokens of `source`, a formula or a script of statements separated by `;` or new
+    /// lines, with their classes in order. Names are classed by what they stand for in
+    /// this context, as `m` is a unit unless it was assigned, and names called are
+    /// functions. The lexer carries on past input it rejects.
+    pub fn classify(&self, source: &str) -> Vec<(Span, TokenClass)> {
+        let mut classes = vec![];
+        // The open parentheses of the statement, by their indices in `classes`.
+        let mut open = vec![];
+        let mut start = 0;
+        let mut previous_end = 0;
+        while start < source.len() {
+            let mut lexer = Lexer::new(&source[start..]);
+            let mut tokens = vec![];
+            let error = loop {
+                match lexer.next_spanned_token() {
+                    Ok(Some((token, span))) => tokens.push((token, span)),
+                    Ok(None) => break None,
+                    Err(error) => break Some(error.span),
+                }
+            };
+            for index in 0..tokens.len() {
+                let span = Span::new(start + tokens[index].1.start, start + tokens[index].1.end);
+                if source[previous_end..span.start].contains('\n') {
+                    unmatched(&mut classes, &mut open);
+                }
+                previous_end = span.end;
+                let class = match &tokens[index].0 {
+                    Token::Number(_) => TokenClass::Number,
+                    Token::String(_) => TokenClass::String,
+                    Token::Operator(_) => TokenClass::Operator,
+                    Token::Identifier(name) => {
+                        let called =
+                            matches!(tokens.get(index + 1), Some((Token::OpenParenthesis, _)));
+                        self.class_of(&String::from_utf8_lossy(name), called)
+                    }
+                    Token::OpenParenthesis => {
+                        open.push(classes.len());
+                        TokenClass::Punctuation
+                    }
+                    Token::ClosedParenthesis if open.pop().is_none() => TokenClass::Error,
+                    Token::ClosedParenthesis | Token::Comma => TokenClass::Punctuation,
+                };
+                classes.push((span, class));
+            }
+            let Some(error) = error else {
+                break;
+            };
+            let error_start = (start + error.start).min(source.len());
+            let rest = &source[error_start..];
+            let (end, class) = match rest.chars().next() {
+                Some('#') => (
+                    rest.find('\n')
+                        .map_or(source.len(), |newline| error_start + newline),
+                    TokenClass::Comment,
+                ),
+                Some(';') => {
+                    unmatched(&mut classes, &mut open);
+                    (error_start + 1, TokenClass::Punctuation)
+                }
+                Some(character) => {
+                    let end = (start + error.end).max(error_start + character.len_utf8());
+                    (end.min(source.len()), TokenClass::Error)
+                }
+                None => break,
+            };
+            classes.push((Span::new(error_start, end), class));
+            previous_end = end;
+            start = end;
+        }
+        unmatched(&mut classes, &mut open);
+        classes
+    }
+
+    pub(crate) fn class_of(&self, name: &str, called: bool) -> TokenClass {
+        if name == "in" || name == "of" {
+            return TokenClass::Operator;
+        }
+        if called {
+            return TokenClass::Function;
+        }
+        match self.variable(name) {
+            Some(Value::Function(_)) => TokenClass::Function,
+            Some(_) => TokenClass::Variable,
+            None if builtins::constant(name).is_some() || builtins::boolean(name).is_some() => {
+                TokenClass::Variable
+            }
+            None if self.units.lookup(name).is_some() => TokenClass::Unit,
+            None if builtins::lookup(name).is_some() => TokenClass::Function,
+            None => TokenClass::Variable,
+        }
+    }
+}
+
+/// Marks the parentheses left open at the end of a statement as errors.
+fn unmatched(classes: &mut [(Span, TokenClass)], open: &mut Vec<usize>) {
+    for index in open.drain(..) {
+        classes[index].1 = TokenClass::Error;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classify_tokens() {
+        let mut context = Context::new();
+        context.set_variable("m", Value::Number(2.0));
+        let classes: Vec<(&str, TokenClass)> = {
+            let source = "2 * sin(x) km in m; f = \"a\" # note\n(1)) @ ((";
+            context
+                .classify(source)
+                .into_iter()
+                .map(|(span, class)| (&source[span.start..span.end], class))
+                .collect()
+        };
+        use TokenClass::*;
+        assert_eq!(
+            classes,
+            [
+                ("2", Number),
+                ("*", Operator),
+                ("sin", Function),
+                ("(", Punctuation),
+                ("x", Variable),
+                (")", Punctuation),
+                ("km", Unit),
+                ("in", Operator),
+                ("m", Variable),
+                (";", Punctuation),
+                ("f", Variable),
+                ("=", Operator),
+                ("\"a\"", String),
+                ("# note", Comment),
+                ("(", Punctuation),
+                ("1", Number),
+                (")", Punctuation),
+                (")", Error),
+                ("@", Error),
+                ("(", Error),
+                ("(", Error),
+            ]
+        );
+    }
+}
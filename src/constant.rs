@@ -0,0 +1,249 @@
+//! Evaluation of the arithmetic subset of formulas, whole numbers with `+ - * /` and
+//! parentheses, in `const` contexts: `const BYTES: f64 = const_eval("64 * 1024");`.
+
+use core::fmt;
+
+/// Bound on the nesting of parentheses and signs, as for the parser.
+const MAX_NESTING_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstEvalErrorKind {
+    /// A character outside of the subset, such as a letter or `^`.
+    UnexpectedCharacter,
+    UnexpectedEnd,
+    /// An opening parenthesis without its closing one.
+    Unclosed,
+    DivisionByZero,
+    /// A number with more digits than fit in 64 bits.
+    NumberTooLarge,
+    TooDeeplyNested,
+}
+
+/// An error of `try_const_eval` and the byte offset in the formula where it happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstEvalError {
+    pub kind: ConstEvalErrorKind,
+    pub position: usize,
+}
+
+impl ConstEvalError {
+    const fn message(&self) -> &'static str {
+        match self.kind {
+            ConstEvalErrorKind::UnexpectedCharacter => {
+                "Unexpected character, const_eval takes whole numbers, + - * / and parentheses"
+            }
+            ConstEvalErrorKind::UnexpectedEnd => "Unexpected end of input",
+            ConstEvalErrorKind::Unclosed => "Unclosed parenthesis",
+            ConstEvalErrorKind::DivisionByZero => "Division by zero",
+            ConstEvalErrorKind::NumberTooLarge => "Number too large",
+            ConstEvalErrorKind::TooDeeplyNested => "Too deeply nested",
+        }
+    }
+}
+
+impl fmt::Display for ConstEvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message(), self.position)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConstEvalError {}
+
+/// The value of a formula and the position after it.
+type Parsed = Result<(f64, usize), ConstEvalError>;
+
+const fn fail(kind: ConstEvalErrorKind, position: usize) -> Parsed {
+    Err(ConstEvalError { kind, position })
+}
+
+const fn skip_whitespace(bytes: &[u8], mut position: usize) -> usize {
+    while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+        position += 1;
+    }
+    position
+}
+
+/// Evaluates `source` as `Context::eval` would, with the same results, in `const`
+/// contexts.
+pub const fn try_const_eval(source: &str) -> Result<f64, ConstEvalError> {
+    let bytes = source.as_bytes();
+    match sum(bytes, 0, 0) {
+        Ok((value, position)) => {
+            let position = skip_whitespace(bytes, position);
+            if position < bytes.len() {
+                return Err(ConstEvalError {
+                    kind: ConstEvalErrorKind::UnexpectedCharacter,
+                    position,
+                });
+            }
+            Ok(value)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Like `try_const_eval`, failing the compilation of constants if the formula fails.
+pub const fn const_eval(source: &str) -> f64 {
+    match try_const_eval(source) {
+        Ok(value) => value,
+        Err(error) => panic!("{}", error.message()),
+    }
+}
+
+const fn sum(bytes: &[u8], position: usize, depth: usize) -> Parsed {
+    let (mut value, mut position) = match product(bytes, position, depth) {
+        Ok(parsed) => parsed,
+        Err(error) => return Err(error),
+    };
+    loop {
+        position = skip_whitespace(bytes, position);
+        if position == bytes.len() || !matches!(bytes[position], b'+' | b'-') {
+            return Ok((value, position));
+        }
+        let operator = bytes[position];
+        let (rhs, next) = match product(bytes, position + 1, depth) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(error),
+        };
+        value = if operator == b'+' {
+            value + rhs
+        } else {
+            value - rhs
+        };
+        position = next;
+    }
+}
+
+const fn product(bytes: &[u8], position: usize, depth: usize) -> Parsed {
+    let (mut value, mut position) = match unary(bytes, position, depth) {
+        Ok(parsed) => parsed,
+        Err(error) => return Err(error),
+    };
+    loop {
+        position = skip_whitespace(bytes, position);
+        if position == bytes.len() || !matches!(bytes[position], b'*' | b'/') {
+            return Ok((value, position));
+        }
+        let operator = bytes[position];
+        let (rhs, next) = match unary(bytes, position + 1, depth) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(error),
+        };
+        if operator == b'*' {
+            value *= rhs;
+        } else if rhs == 0.0 {
+            return fail(ConstEvalErrorKind::DivisionByZero, position);
+        } else {
+            value /= rhs;
+        }
+        position = next;
+    }
+}
+
+const fn unary(bytes: &[u8], position: usize, depth: usize) -> Parsed {
+    if depth >= MAX_NESTING_DEPTH {
+        return fail(ConstEvalErrorKind::TooDeeplyNested, position);
+    }
+    let position = skip_whitespace(bytes, position);
+    if position == bytes.len() {
+        return fail(ConstEvalErrorKind::UnexpectedEnd, position);
+    }
+    match bytes[position] {
+        b'-' => match unary(bytes, position + 1, depth + 1) {
+            Ok((value, next)) => Ok((-value, next)),
+            Err(error) => Err(error),
+        },
+        b'(' => {
+            let (value, next) = match sum(bytes, position + 1, depth + 1) {
+                Ok(parsed) => parsed,
+                Err(error) => return Err(error),
+            };
+            let next = skip_whitespace(bytes, next);
+            if next < bytes.len() && bytes[next] == b')' {
+                Ok((value, next + 1))
+            } else if next == bytes.len() {
+                fail(ConstEvalErrorKind::Unclosed, position)
+            } else {
+                fail(ConstEvalErrorKind::UnexpectedCharacter, next)
+            }
+        }
+        b'0'..=b'9' => number(bytes, position),
+        _ => fail(ConstEvalErrorKind::UnexpectedCharacter, position),
+    }
+}
+
+const fn number(bytes: &[u8], start: usize) -> Parsed {
+    let mut value: u64 = 0;
+    let mut position = start;
+    while position < bytes.len() && bytes[position].is_ascii_digit() {
+        let digit = (bytes[position] - b'0') as u64;
+        value = match value.checked_mul(10) {
+            Some(value) => match value.checked_add(digit) {
+                Some(value) => value,
+                None => return fail(ConstEvalErrorKind::NumberTooLarge, start),
+            },
+            None => return fail(ConstEvalErrorKind::NumberTooLarge, start),
+        };
+        position += 1;
+    }
+    // Fractions, exponents and identifiers such as `2x` are outside of the subset.
+    if position < bytes.len() && (bytes[position] == b'.' || bytes[position].is_ascii_alphabetic())
+    {
+        return fail(ConstEvalErrorKind::UnexpectedCharacter, position);
+    }
+    Ok((value as f64, position))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::evaluator::Context;
+    use crate::value::Value;
+
+    const KIBIBYTES: f64 = const_eval("64 * 1024 / 1024");
+
+    #[test]
+    fn evaluate_constants() {
+        assert_eq!(KIBIBYTES, 64.0);
+        let mut context = Context::new();
+        for source in [
+            "2 * (3 + 4)",
+            "7/2",
+            "-(3) - -2",
+            "1 - 2 - 3",
+            "10 / 4 * 2",
+            " 8 ",
+        ] {
+            assert_eq!(
+                try_const_eval(source).map(Value::Number).ok(),
+                context.eval(source).ok(),
+                "{}",
+                source
+            );
+        }
+        let error = |source| try_const_eval(source).map_err(|error| (error.kind, error.position));
+        assert_eq!(
+            error("1 / (2 - 2)"),
+            Err((ConstEvalErrorKind::DivisionByZero, 2))
+        );
+        assert_eq!(
+            error("2 ^ 3"),
+            Err((ConstEvalErrorKind::UnexpectedCharacter, 2))
+        );
+        assert_eq!(error("(1 + 2"), Err((ConstEvalErrorKind::Unclosed, 0)));
+        assert_eq!(error("1 +"), Err((ConstEvalErrorKind::UnexpectedEnd, 3)));
+        assert_eq!(
+            error("2.5"),
+            Err((ConstEvalErrorKind::UnexpectedCharacter, 1))
+        );
+        assert_eq!(
+            error("99999999999999999999"),
+            Err((ConstEvalErrorKind::NumberTooLarge, 0))
+        );
+        assert_eq!(
+            error(&"(".repeat(200)).map_err(|(kind, _)| kind),
+            Err(ConstEvalErrorKind::TooDeeplyNested)
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use crate::parser::{Expr, ExprKind};
+
+/// Typical evaluations of the formula or function argument of the builtins evaluating it
+/// repeatedly, after which they usually converge.
+const REPEATED_EVALUATIONS: &[(&str, u64)] = &[
+    // The 15 points of a Gauss-Kronrod rule on some subintervals.
+    ("integrate", 15 * 16),
+    ("diff", 20),
+    ("solve", 2100),
+    ("limit", 20),
+];
+
+impl Expr {
+    /// Estimates how many nodes evaluating the formula evaluates, saturating at
+    /// `u64::MAX`. Builtins such as `integrate` count their formula for its typical
+    /// evaluations, both branches of conditionals count for the costlier one, and calls of
+    /// user-defined functions only for their arguments, since their recursion is unknown
+    /// until evaluating. Only the number of operations matters: `9^9^9^9` is cheap.
+    pub fn cost(&self) -> u64 {
+        1u64.saturating_add(match &self.kind {
+            ExprKind::Unary(_, operand) => operand.cost(),
+            ExprKind::Binary(_, lhs, rhs) => lhs.cost().saturating_add(rhs.cost()),
+            ExprKind::Conditional(condition, then, otherwise) => condition
+                .cost()
+                .saturating_add(then.cost().max(otherwise.cost())),
+            ExprKind::Call(name, args) => {
+                let repeated = REPEATED_EVALUATIONS
+                    .iter()
+                    .find(|(builtin, _)| builtin == name)
+                    .map_or(1, |(_, evaluations)| *evaluations);
+                args.iter().enumerate().fold(0u64, |cost, (index, arg)| {
+                    let times = if index == 0 { repeated } else { 1 };
+                    cost.saturating_add(arg.cost().saturating_mul(times))
+                })
+            }
+            ExprKind::Tuple(items) => items
+                .iter()
+                .fold(0u64, |cost, item| cost.saturating_add(item.cost())),
+            ExprKind::Lambda(_, body) => body.cost(),
+            _ => 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::Parser;
+
+    fn cost(source: &str) -> u64 {
+        Parser::new(source).parse().unwrap().cost()
+    }
+
+    #[test]
+    fn estimate_costs() {
+        assert_eq!(cost("1 + 2 * x"), 5);
+        assert_eq!(cost("9^9^9^9"), 7);
+        assert_eq!(cost("x > 0 ? sqrt(x) : 0"), 6);
+        assert_eq!(cost("integrate(x^2, x, 0, 1)"), 1 + 3 * 240 + 3);
+        assert_eq!(
+            cost("integrate(y -> integrate(x * y, x, 0, 1), 0, 1)"),
+            1 + 240 * (2 + 240 * 3 + 3) + 2
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+use crate::units::{Dimension, Quantity, Unit, UnitDefinitionError};
+
+/// ISO 4217 codes of the currencies known without being defined.
+pub(crate) const CURRENCIES: &[&str] = &[
+    "USD", "EUR", "GBP", "JPY", "CHF", "CNY", "CAD", "AUD", "NZD", "SEK", "NOK", "DKK", "PLN",
+    "CZK", "HUF", "INR", "BRL", "MXN", "KRW", "SGD", "HKD", "ZAR", "TRY",
+];
+
+/// Exchange rates between currencies, as supplied by the caller. A rate is usable in both
+/// directions, and rates are chained when there is no direct one (`EUR -> USD -> JPY`).
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRates {
+    rates: Vec<(String, String, f64)>,
+}
+
+impl ExchangeRates {
+    /// Sets the rate so that one `from` is worth `rate` of `to`, replacing any previous rate
+    /// between the two currencies.
+    pub fn set(&mut self, from: &str, to: &str, rate: f64) -> Result<(), UnitDefinitionError> {
+        if from == to {
+            return Err(UnitDefinitionError::new(format!(
+                "Cannot set an exchange rate from {} to itself",
+                from
+            )));
+        }
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(UnitDefinitionError::new(format!(
+                "The exchange rate from {} to {} must be positive and finite",
+                from, to
+            )));
+        }
+        self.rates
+            .retain(|(a, b, _)| !((a == from && b == to) || (a == to && b == from)));
+        self.rates.push((from.to_string(), to.to_string(), rate));
+        Ok(())
+    }
+
+    /// The value of one `from` in `to`, if the currencies are connected by known rates.
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        let mut visited = vec![from];
+        let mut queue = VecDeque::from([(from, 1.0)]);
+        while let Some((currency, value)) = queue.pop_front() {
+            if currency == to {
+                return Some(value);
+            }
+            for (a, b, rate) in &self.rates {
+                let next = if a == currency {
+                    (b.as_str(), value * rate)
+                } else if b == currency {
+                    (a.as_str(), value / rate)
+                } else {
+                    continue;
+                };
+                if !visited.contains(&next.0) {
+                    visited.push(next.0);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The currency of a dimension that has exactly one, with its exponent.
+fn single_currency(dimension: &Dimension) -> Option<&(String, i32)> {
+    match dimension.currencies.as_slice() {
+        [currency] => Some(currency),
+        _ => None,
+    }
+}
+
+/// Expresses `quantity` in `target` when the two differ only by currency, as `EUR` and
+/// `USD` or `EUR/kg` and `USD/lb`. Any other quantity is returned unchanged, so that the
+/// operation using it reports the mismatch.
+pub(crate) fn exchange(
+    quantity: &Quantity,
+    target: &Unit,
+    rates: &ExchangeRates,
+) -> Result<Quantity, EvaluatorError> {
+    let (dimension, target_dimension) = (quantity.unit.dimension(), target.dimension());
+    if dimension == target_dimension || dimension.base != target_dimension.base {
+        return Ok(quantity.clone());
+    }
+    let (Some((from, exponent)), Some((to, target_exponent))) = (
+        single_currency(&dimension),
+        single_currency(&target_dimension),
+    ) else {
+        return Ok(quantity.clone());
+    };
+    if exponent != target_exponent {
+        return Ok(quantity.clone());
+    }
+    let rate = rates.rate(from, to).ok_or_else(|| {
+        EvaluatorError::new(EvaluatorErrorKind::MissingExchangeRate {
+            from: from.clone(),
+            to: to.clone(),
+        })
+    })?;
+    let value = quantity.base_value() * rate.powi(*exponent) / target.factor();
+    Ok(Quantity::new(value, target.clone()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chain_exchange_rates() {
+        let mut rates = ExchangeRates::default();
+        rates.set("EUR", "USD", 1.25).unwrap();
+        rates.set("USD", "JPY", 150.0).unwrap();
+        assert_eq!(rates.rate("USD", "EUR"), Some(0.8));
+        assert_eq!(rates.rate("EUR", "JPY"), Some(187.5));
+        assert_eq!(rates.rate("EUR", "GBP"), None);
+        rates.set("USD", "EUR", 1.0).unwrap();
+        assert_eq!(rates.rate("EUR", "USD"), Some(1.0));
+        assert!(rates.set("EUR", "EUR", 1.0).is_err());
+        assert!(rates.set("EUR", "GBP", -1.0).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use core::fmt;
+
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+use crate::parser::BinaryOperator;
+use crate::units::{Dimension, Quantity, Unit, UnitTable};
+use crate::value::Value;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// A day of the proleptic Gregorian calendar.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub struct Date {
+    /// Days since 1970-01-01.
+    days: i64,
+}
+
+impl Date {
+    /// The date of `day` of `month` (1 to 12) of `year`, if it exists.
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> Option<Date> {
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        // Days from civil, counting years from March so that leap days come last.
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let month = month as i64;
+        let day_of_year =
+            (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Some(Date {
+            days: era * 146097 + day_of_era - 719468,
+        })
+    }
+
+    /// Parses an ISO 8601 calendar date, `YYYY-MM-DD`.
+    pub fn parse(text: &str) -> Option<Date> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text),
+        };
+        let mut parts = text.split('-');
+        let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+        let year: i64 = year.parse().ok()?;
+        Date::from_ymd(
+            if negative { -year } else { year },
+            month.parse().ok()?,
+            day.parse().ok()?,
+        )
+    }
+
+    pub fn ymd(&self) -> (i64, u32, u32) {
+        let days = self.days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day)
+    }
+
+    fn add_days(&self, days: i64) -> Date {
+        Date {
+            days: self.days + days,
+        }
+    }
+
+    /// Combines dates with durations: adding or subtracting a whole number of days gives a
+    /// date, the difference of two dates is a number of days, and dates compare in order.
+    pub(crate) fn binary(
+        operator: BinaryOperator,
+        lhs: &Value,
+        rhs: &Value,
+    ) -> Result<Value, EvaluatorError> {
+        let compare = |condition: bool| Ok(Value::Bool(condition));
+        match (operator, lhs, rhs) {
+            (BinaryOperator::Add, Value::Date(date), Value::Quantity(duration))
+            | (BinaryOperator::Add, Value::Quantity(duration), Value::Date(date)) => {
+                Ok(Value::Date(date.add_days(whole_days(duration)?)))
+            }
+            (BinaryOperator::Subtract, Value::Date(date), Value::Quantity(duration)) => {
+                Ok(Value::Date(date.add_days(-whole_days(duration)?)))
+            }
+            (BinaryOperator::Subtract, Value::Date(lhs), Value::Date(rhs)) => {
+                let day = UnitTable::default().lookup("day").map(Unit::named);
+                Ok(Value::Quantity(Quantity::new(
+                    (lhs.days - rhs.days) as f64,
+                    day.unwrap_or_default(),
+                )))
+            }
+            (BinaryOperator::Equal, Value::Date(lhs), Value::Date(rhs)) => compare(lhs == rhs),
+            (BinaryOperator::NotEqual, Value::Date(lhs), Value::Date(rhs)) => compare(lhs != rhs),
+            (BinaryOperator::Less, Value::Date(lhs), Value::Date(rhs)) => compare(lhs < rhs),
+            (BinaryOperator::LessEqual, Value::Date(lhs), Value::Date(rhs)) => compare(lhs <= rhs),
+            (BinaryOperator::Greater, Value::Date(lhs), Value::Date(rhs)) => compare(lhs > rhs),
+            (BinaryOperator::GreaterEqual, Value::Date(lhs), Value::Date(rhs)) => {
+                compare(lhs >= rhs)
+            }
+            _ => Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                format!(
+                    "Cannot apply '{}' to a {} and a {}",
+                    operator.symbol(),
+                    lhs.type_name(),
+                    rhs.type_name()
+                ),
+            ))),
+        }
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn whole_days(duration: &Quantity) -> Result<i64, EvaluatorError> {
+    let time = Dimension::new([0, 0, 1, 0, 0, 0, 0]);
+    if duration.unit.dimension() != time {
+        return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+            format!(
+                "Dates can only be shifted by durations, not {}",
+                duration.describe()
+            ),
+        )));
+    }
+    let days = duration.base_value() / SECONDS_PER_DAY;
+    if (days - days.round()).abs() > 1e-9 || !days.is_finite() {
+        return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+            format!("Dates can only be shifted by whole days, not {}", duration),
+        )));
+    }
+    Ok(days.round() as i64)
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn convert_calendar_dates() {
+        assert_eq!(Date::from_ymd(1970, 1, 1).unwrap().days, 0);
+        assert_eq!(Date::from_ymd(2000, 3, 1).unwrap().days, 11017);
+        assert_eq!(Date::from_ymd(1969, 12, 31).unwrap().days, -1);
+        assert_eq!(Date::from_ymd(2024, 2, 29).unwrap().ymd(), (2024, 2, 29));
+        assert_eq!(Date::from_ymd(2023, 2, 29), None);
+        assert_eq!(Date::from_ymd(1900, 2, 29), None);
+        assert_eq!(Date::from_ymd(2025, 13, 1), None);
+        let date = Date::parse("1600-12-31").unwrap();
+        assert_eq!(date.ymd(), (1600, 12, 31));
+        assert_eq!(date.to_string(), "1600-12-31");
+        assert_eq!(Date::parse("2025-1-01"), None);
+    }
+}
@@ -0,0 +1,286 @@
+//! Errors written as compilers do, quoting the line of the formula and underlining the
+//! span at fault, with a note on how to fix the error when there is one:
+//!
+//! ```text
+//! error[E0201]: Unknown variable 'y'
+//!  --> <expr>:1:6
+//!   |
+//! 1 | sqrt(y) + 1
+//!   |      ^
+//!   = help: assign it first, as y = 2
+//! ```
+//!
+//! or as JSON for editors and CI tools with `to_json`.
+
+use core::fmt::Write;
+
+use crate::error::Error;
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+use crate::lexer::{LexerError, Span};
+use crate::parser::{ParserError, ParserErrorKind};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// What a report of an error says, for embedders writing it out as they like or with
+/// `render`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The code of `Error::code`, as `E0201`.
+    pub code: &'static str,
+    pub message: String,
+    /// What is said under the mark of the span, as `expected ')'`, perhaps empty.
+    pub label: String,
+    pub span: Option<Span>,
+    pub help: Option<String>,
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Diagnostic {
+        // Parse errors expecting a token say so under the mark rather than in the headline.
+        let (message, label, span) = match error {
+            Error::Parser(error) => match &error.kind {
+                ParserErrorKind::UnexpectedToken { found, expected } => (
+                    format!("Unexpected '{}'", found),
+                    format!("expected {}", expected),
+                    Some(error.span),
+                ),
+                ParserErrorKind::UnexpectedEnd { expected } => (
+                    String::from("Unexpected end of input"),
+                    format!("expected {}", expected),
+                    Some(error.span),
+                ),
+                _ => (error.to_string(), String::new(), Some(error.span)),
+            },
+            // The suggestion goes in the help.
+            Error::Evaluator(error) => {
+                let message = EvaluatorError {
+                    suggestion: None,
+                    ..error.clone()
+                };
+                (message.to_string(), String::new(), error.span)
+            }
+        };
+        let help = match error {
+            Error::Parser(ParserError {
+                kind: ParserErrorKind::TooDeeplyNested,
+                ..
+            }) => Some(String::from(
+                "assign parts of the formula to variables first",
+            )),
+            Error::Evaluator(EvaluatorError {
+                suggestion: Some(name),
+                ..
+            }) => Some(format!("did you mean '{}'?", name)),
+            Error::Evaluator(error) => match &error.kind {
+                EvaluatorErrorKind::UnknownVariable(name) if !name.starts_with('$') => {
+                    Some(format!("assign it first, as {} = 2", name))
+                }
+                EvaluatorErrorKind::UnknownFunction(name) => {
+                    Some(format!("define it first, as {}(x) = x^2", name))
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        Diagnostic {
+            code: error.code(),
+            message,
+            label,
+            span,
+            help,
+        }
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Diagnostic {
+        Diagnostic::from(&Error::Parser(error.clone()))
+    }
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(error: &LexerError) -> Diagnostic {
+        Diagnostic::from(&ParserError::from(error.clone()))
+    }
+}
+
+impl Diagnostic {
+    /// The report in `source`, read from `origin` such as a file name where it starts on
+    /// line `first_line`. Colors are escape sequences for terminals.
+    pub fn render(&self, source: &str, origin: &str, first_line: usize, colors: bool) -> String {
+        let paint = |color: &str, text: &str| {
+            if colors {
+                format!("{}{}{}", color, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+        let headline = format!(
+            "{}{}",
+            paint(RED, &format!("error[{}]", self.code)),
+            paint(BOLD, &format!(": {}", self.message))
+        );
+        let help = |gutter: &str| match &self.help {
+            Some(help) => format!("\n{} {} help: {}", gutter, paint(BLUE, "="), help),
+            None => String::new(),
+        };
+        let Some(Span { start, end }) = self.span else {
+            return headline + &help("");
+        };
+        let label = &self.label;
+        let start = start.min(source.len());
+        let (line, column, line_start, line_end) = locate(source, start, first_line);
+        let width = source[start..end.clamp(start, line_end)]
+            .chars()
+            .count()
+            .max(1);
+        let number = line.to_string();
+        let gutter = " ".repeat(number.len());
+        let bar = paint(BLUE, "|");
+        let mark = format!(
+            "{}{}",
+            "^".repeat(width),
+            if label.is_empty() { "" } else { " " }
+        );
+        format!(
+            "{}\n{}{} {}:{}:{}\n{} {}\n{} {} {}\n{} {} {}{}",
+            headline,
+            gutter,
+            paint(BLUE, "-->"),
+            origin,
+            line,
+            column + 1,
+            gutter,
+            bar,
+            paint(BLUE, &number),
+            bar,
+            &source[line_start..line_end],
+            gutter,
+            bar,
+            " ".repeat(column),
+            paint(RED, &(mark + label)),
+        ) + &help(&gutter)
+    }
+
+    /// The diagnostic as a JSON object on one line, with its `code`, `severity`, `message`,
+    /// `label`, `file` and, if it has a span, its `start` and `end` in bytes and the `line`
+    /// and `column` it starts at, counted as `render` does. `suggestions` lists the help.
+    pub fn to_json(&self, source: &str, origin: &str, first_line: usize) -> String {
+        let mut json = format!(
+            "{{\"code\":{},\"severity\":\"error\",\"message\":{},\"label\":{},\"file\":{}",
+            quote(self.code),
+            quote(&self.message),
+            quote(&self.label),
+            quote(origin)
+        );
+        if let Some(Span { start, end }) = self.span {
+            let (line, column, _, _) = locate(source, start.min(source.len()), first_line);
+            write!(
+                json,
+                ",\"start\":{},\"end\":{},\"line\":{},\"column\":{}",
+                start,
+                end,
+                line,
+                column + 1
+            )
+            .unwrap();
+        }
+        let suggestions: Vec<String> = self.help.iter().map(|help| quote(help)).collect();
+        write!(json, ",\"suggestions\":[{}]}}", suggestions.join(",")).unwrap();
+        json
+    }
+}
+
+/// The line of the byte `start` of `source`, its column from 0 in characters, and the
+/// bounds of the line.
+fn locate(source: &str, start: usize, first_line: usize) -> (usize, usize, usize, usize) {
+    let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |newline| start + newline);
+    let line = first_line + source[..start].matches('\n').count();
+    let column = source[line_start..start].chars().count();
+    (line, column, line_start, line_end)
+}
+
+/// `text` as a JSON string.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            character if character.is_control() => {
+                write!(quoted, "\\u{:04x}", character as u32).unwrap()
+            }
+            character => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::Context;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn render_diagnostics() {
+        let error = Parser::new("2 * (3").parse().unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&error).render("2 * (3", "script.calc", 2, false),
+            "error[E0102]: Unexpected end of input\n --> script.calc:2:7\n  |\n2 | 2 * (3\n  |       ^ expected ')'"
+        );
+        let mut context = Context::new();
+        let error = context.eval("sqrt(y) + 1").unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&error).render("sqrt(y) + 1", "<expr>", 1, true),
+            "\x1b[1;31merror[E0201]\x1b[0m\x1b[1m: Unknown variable 'y'\x1b[0m\n \x1b[1;34m-->\x1b[0m <expr>:1:6\n  \x1b[1;34m|\x1b[0m\n\x1b[1;34m1\x1b[0m \x1b[1;34m|\x1b[0m sqrt(y) + 1\n  \x1b[1;34m|\x1b[0m      \x1b[1;31m^\x1b[0m\n  \x1b[1;34m=\x1b[0m help: assign it first, as y = 2"
+        );
+        let error = context.eval("f(1) + 1").unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&error).help.as_deref(),
+            Some("define it first, as f(x) = x^2")
+        );
+        let error = context.eval("sqr(2)").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown function 'sqr', did you mean 'sqrt'?"
+        );
+        let diagnostic = Diagnostic::from(&error);
+        assert_eq!(diagnostic.message, "Unknown function 'sqr'");
+        assert_eq!(diagnostic.help.as_deref(), Some("did you mean 'sqrt'?"));
+        let mut lexer = Lexer::new("1 # 2");
+        let error = loop {
+            if let Err(error) = lexer.next_spanned_token() {
+                break error;
+            }
+        };
+        assert_eq!(
+            Diagnostic::from(&error).span,
+            Some(Span { start: 2, end: 3 })
+        );
+    }
+
+    #[test]
+    fn write_diagnostics_as_json() {
+        let mut context = Context::new();
+        let error = context.eval("1 +\n\"a\" * y").unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&error).to_json("1 +\n\"a\" * y", "<stdin>", 3),
+            r#"{"code":"E0201","severity":"error","message":"Unknown variable 'y'","label":"","file":"<stdin>","start":10,"end":11,"line":4,"column":7,"suggestions":["assign it first, as y = 2"]}"#
+        );
+        let error = Parser::new("(1").parse().unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&error).to_json("(1", "<expr>", 1),
+            r#"{"code":"E0102","severity":"error","message":"Unexpected end of input","label":"expected ')'","file":"<expr>","start":2,"end":2,"line":1,"column":3,"suggestions":[]}"#
+        );
+    }
+}
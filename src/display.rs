@@ -0,0 +1,284 @@
+use crate::value::Value;
+
+/// SI prefixes from 10^-24 to 10^24, in steps of a thousand.
+const SI_PREFIXES: [&str; 17] = [
+    "y", "z", "a", "f", "p", "n", "µ", "m", "", "k", "M", "G", "T", "P", "E", "Z", "Y",
+];
+const SI_PREFIX_OFFSET: i32 = 8;
+
+/// Significant digits kept by the scaled notations without a precision, hiding the error
+/// of scaling.
+const SIGNIFICANT_DIGITS: usize = 12;
+
+/// How numbers are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Notation {
+    /// Decimals such as `4700` or `0.0000123`, or scientific notation past the thresholds
+    /// of the display options.
+    #[default]
+    Plain,
+    /// A mantissa from 1 to 1000 and an exponent multiple of 3, as `4.7e3` or `12.3e-6`.
+    Engineering,
+    /// A mantissa from 1 to 1000 and an SI prefix, as `4.7k` or `12.3µ`. Numbers beyond
+    /// the prefixes are written in engineering notation.
+    SiPrefix,
+}
+
+impl Notation {
+    pub fn format(&self, number: f64) -> String {
+        DisplayOptions {
+            notation: *self,
+            ..DisplayOptions::default()
+        }
+        .format(number)
+    }
+}
+
+/// Options for writing numbers. The default writes the shortest decimal representing each
+/// number exactly, as `Display` does, in scientific notation from 1e21 and below 1e-7 so
+/// that huge and tiny numbers stay short.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayOptions {
+    pub notation: Notation,
+    /// Significant digits, or `None` for as many as needed to be exact.
+    pub precision: Option<usize>,
+    /// Plain numbers at least this large in magnitude are written in scientific notation.
+    pub scientific_above: Option<f64>,
+    /// Plain numbers smaller than this in magnitude, other than zero, are written in
+    /// scientific notation.
+    pub scientific_below: Option<f64>,
+    /// Separates groups of three integer digits, as in `1,234,567`.
+    pub thousands_separator: Option<char>,
+    /// Such as `,` for many European locales.
+    pub decimal_separator: char,
+    /// Removes the zeros ending the fractional part, which a precision may leave.
+    pub trim_trailing_zeros: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> DisplayOptions {
+        DisplayOptions {
+            notation: Notation::Plain,
+            precision: None,
+            scientific_above: Some(1e21),
+            scientific_below: Some(1e-7),
+            thousands_separator: None,
+            decimal_separator: '.',
+            trim_trailing_zeros: true,
+        }
+    }
+}
+
+impl DisplayOptions {
+    pub fn format(&self, number: f64) -> String {
+        if !number.is_finite() {
+            return number.to_string();
+        }
+        let magnitude = number.abs();
+        let scientific = self.notation == Notation::Plain
+            && number != 0.0
+            && (self
+                .scientific_above
+                .is_some_and(|above| magnitude >= above)
+                || self.scientific_below.is_some_and(|below| magnitude < below));
+        if self.notation == Notation::Plain && !scientific || number == 0.0 {
+            return self.digits(number);
+        }
+        let step = if scientific { 1 } else { 3 };
+        let (mantissa, exponent) = self.scaled(number, step);
+        let prefix = usize::try_from(exponent / 3 + SI_PREFIX_OFFSET)
+            .ok()
+            .and_then(|index| SI_PREFIXES.get(index));
+        let mantissa = self.digits(mantissa);
+        match (self.notation, prefix) {
+            (Notation::SiPrefix, Some(prefix)) => format!("{}{}", mantissa, prefix),
+            _ if exponent == 0 => mantissa,
+            _ => format!("{}e{}", mantissa, exponent),
+        }
+    }
+
+    /// Splits `number` into a mantissa of magnitude from 1 to `10^step` and an exponent
+    /// multiple of `step`, rounding the mantissa to the precision.
+    fn scaled(&self, number: f64, step: i32) -> (f64, i32) {
+        let digits = self.precision.unwrap_or(SIGNIFICANT_DIGITS);
+        let limit = 10f64.powi(step);
+        let mut exponent = (number.abs().log10().floor() as i32).div_euclid(step) * step;
+        let mut mantissa = round_significant(number / 10f64.powi(exponent), digits);
+        if mantissa.abs() >= limit {
+            mantissa = round_significant(mantissa / limit, digits);
+            exponent += step;
+        } else if mantissa.abs() < 1.0 {
+            mantissa = round_significant(mantissa * limit, digits);
+            exponent -= step;
+        }
+        (mantissa, exponent)
+    }
+
+    /// Writes `number` in fixed notation with the precision and separators.
+    fn digits(&self, number: f64) -> String {
+        let mut text = match self.precision {
+            None => number.to_string(),
+            Some(precision) => fixed(number, precision.max(1)),
+        };
+        if self.trim_trailing_zeros && text.contains('.') {
+            text = text.trim_end_matches('0').trim_end_matches('.').to_string();
+        }
+        let (integer, fraction) = match text.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (text.as_str(), None),
+        };
+        let (sign, integer) = match integer.strip_prefix('-') {
+            Some(integer) => ("-", integer),
+            None => ("", integer),
+        };
+        let mut result = String::from(sign);
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                result.extend(self.thousands_separator);
+            }
+            result.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            result.push(self.decimal_separator);
+            result.push_str(fraction);
+        }
+        result
+    }
+}
+
+/// `number` rounded to `precision` significant digits in fixed notation, from the digits
+/// of scientific notation so that those past the precision are zeros rather than those of
+/// the binary value.
+fn fixed(number: f64, precision: usize) -> String {
+    let scientific = format!("{:.*e}", precision - 1, number);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i64 = exponent.parse().unwrap_or(0);
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+    let mut text = String::from(sign);
+    if exponent < 0 {
+        text.push_str("0.");
+        text.push_str(&"0".repeat((-exponent - 1) as usize));
+        text.push_str(&digits);
+    } else {
+        let integer = exponent as usize + 1;
+        if digits.len() <= integer {
+            text.push_str(&digits);
+            text.push_str(&"0".repeat(integer - digits.len()));
+        } else {
+            text.push_str(&digits[..integer]);
+            text.push('.');
+            text.push_str(&digits[integer..]);
+        }
+    }
+    text
+}
+
+fn round_significant(number: f64, digits: usize) -> f64 {
+    format!("{:.*e}", digits.max(1) - 1, number)
+        .parse()
+        .unwrap_or(number)
+}
+
+impl Value {
+    /// Writes the value with numbers formatted by `options`. The magnitudes of quantities
+    /// are scaled without SI prefix, which would read as a prefix of their unit.
+    pub fn format(&self, options: &DisplayOptions) -> String {
+        match self {
+            Value::Number(number) => options.format(*number),
+            Value::Quantity(quantity) => {
+                let mut options = *options;
+                if options.notation == Notation::SiPrefix {
+                    options.notation = Notation::Engineering;
+                }
+                format!("{} {}", options.format(quantity.value), quantity.unit)
+            }
+            Value::Uncertain(uncertain) => format!(
+                "{} ± {}",
+                options.format(uncertain.value),
+                options.format(uncertain.sigma)
+            ),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.format(options)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_scaled_numbers() {
+        let engineering = |number| Notation::Engineering.format(number);
+        let si = |number| Notation::SiPrefix.format(number);
+        assert_eq!(engineering(4700.0), "4.7e3");
+        assert_eq!(engineering(0.0000123), "12.3e-6");
+        assert_eq!(engineering(-999.99999999999999), "-1e3");
+        assert_eq!(engineering(5.0), "5");
+        assert_eq!(si(4700.0), "4.7k");
+        assert_eq!(si(12.3e-6), "12.3µ");
+        assert_eq!(si(0.01), "10m");
+        assert_eq!(si(1e27), "1e27");
+        assert_eq!(si(0.0), "0");
+        assert_eq!(Notation::Plain.format(4700.0), "4700");
+    }
+
+    #[test]
+    fn apply_display_options() {
+        let options = DisplayOptions {
+            precision: Some(4),
+            thousands_separator: Some('.'),
+            decimal_separator: ',',
+            ..DisplayOptions::default()
+        };
+        assert_eq!(options.format(1234567.891), "1.235.000");
+        assert_eq!(options.format(-0.5), "-0,5");
+        let untrimmed = DisplayOptions {
+            trim_trailing_zeros: false,
+            ..options
+        };
+        assert_eq!(untrimmed.format(1.5), "1,500");
+        assert_eq!(untrimmed.format(0.0), "0,000");
+        let scientific = DisplayOptions {
+            scientific_above: Some(1e6),
+            scientific_below: Some(1e-3),
+            precision: Some(3),
+            ..DisplayOptions::default()
+        };
+        assert_eq!(scientific.format(123456789.0), "1.23e8");
+        assert_eq!(scientific.format(0.000123), "1.23e-4");
+        assert_eq!(scientific.format(999.0), "999");
+        assert_eq!(scientific.format(9.999e9), "1e10");
+        let precise = DisplayOptions {
+            notation: Notation::SiPrefix,
+            precision: Some(2),
+            ..DisplayOptions::default()
+        };
+        assert_eq!(precise.format(999.6), "1k");
+        assert_eq!(
+            DisplayOptions::default().format(0.1 + 0.2),
+            "0.30000000000000004"
+        );
+        let rounded = DisplayOptions {
+            precision: Some(3),
+            scientific_above: None,
+            ..DisplayOptions::default()
+        };
+        assert_eq!(rounded.format(2f64.powi(80)), "1210000000000000000000000");
+        assert_eq!(rounded.format(-0.000123456), "-0.000123");
+        assert_eq!(rounded.format(1e300), format!("1{}", "0".repeat(300)));
+        assert_eq!(DisplayOptions::default().format(1e300), "1e300");
+        assert_eq!(DisplayOptions::default().format(-1e-300), "-1e-300");
+        assert_eq!(
+            DisplayOptions::default().format(1e20),
+            "100000000000000000000"
+        );
+    }
+}
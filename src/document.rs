@@ -0,0 +1,234 @@
+//! Scripts kept parsed as they are edited, for editors: an edit lexes and parses again
+//! only the lines it touches, and the statements of the others are kept with their spans
+//! moved.
+
+use std::ops::Range;
+
+use crate::lexer::Span;
+use crate::parser::{Expr, ExprKind, Parser, ParserError};
+
+/// A statement of a script, between `;` or new lines and before any `#` comment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub span: Span,
+    /// What is assigned to, as `x` or `f(x)`, for statements such as `f(x) = x^2`.
+    pub target: Option<Result<Expr, ParserError>>,
+    /// The formula, or the one assigned.
+    pub formula: Result<Expr, ParserError>,
+}
+
+/// The source of a script and its statements, with spans in the whole source. Statements
+/// end at new lines, even in strings, so that each line parses on its own.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    source: String,
+    statements: Vec<Statement>,
+}
+
+impl Document {
+    pub fn new(source: &str) -> Document {
+        Document {
+            source: source.to_string(),
+            statements: statements(source, 0..source.len()),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+
+    /// Replaces the `deleted` bytes at `offset` with `inserted`, as editors report their
+    /// changes, and parses the lines changed again. Returns the indices of the statements
+    /// which are new; the others are those from before. Panics if the bytes replaced are
+    /// not within the source or do not fall on character boundaries.
+    pub fn edit(&mut self, offset: usize, deleted: usize, inserted: &str) -> Range<usize> {
+        let end = offset + deleted;
+        let line_start = self.source[..offset]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let line_end = self.source[end..]
+            .find('\n')
+            .map_or(self.source.len(), |newline| end + newline);
+        self.source.replace_range(offset..end, inserted);
+        let delta = inserted.len() as isize - deleted as isize;
+        let first = self
+            .statements
+            .partition_point(|statement| statement.span.end < line_start);
+        let last = self
+            .statements
+            .partition_point(|statement| statement.span.start <= line_end);
+        let changed_end = (line_end as isize + delta) as usize;
+        let replacements = statements(&self.source, line_start..changed_end);
+        let count = replacements.len();
+        let after: Vec<Statement> = self
+            .statements
+            .drain(last..)
+            .map(|statement| shift(statement, delta))
+            .collect();
+        self.statements.truncate(first);
+        self.statements.extend(replacements);
+        self.statements.extend(after);
+        first..first + count
+    }
+}
+
+/// The statements of the lines of `source` in `range`, which starts and ends at lines.
+fn statements(source: &str, range: Range<usize>) -> Vec<Statement> {
+    let mut statements = vec![];
+    let mut start = range.start;
+    let mut end = None;
+    let mut quoted = false;
+    for (index, character) in source[range.clone()].char_indices() {
+        let index = range.start + index;
+        match character {
+            '"' if end.is_none() => quoted = !quoted,
+            '#' if !quoted && end.is_none() => end = Some(index),
+            '\n' | ';' if character == '\n' || (!quoted && end.is_none()) => {
+                statements.extend(statement(source, start, end.unwrap_or(index)));
+                start = index + 1;
+                end = None;
+                quoted = false;
+            }
+            _ => {}
+        }
+    }
+    statements.extend(statement(source, start, end.unwrap_or(range.end)));
+    statements
+}
+
+fn statement(source: &str, start: usize, end: usize) -> Option<Statement> {
+    let text = &source[start..end];
+    let leading = text.len() - text.trim_start().len();
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let start = start + leading;
+    let parse = |from: usize, to: usize| {
+        let parsed = Parser::new(&source[from..to]).parse();
+        let delta = from as isize;
+        parsed
+            .map(|mut expr| {
+                shift_expr(&mut expr, delta);
+                expr
+            })
+            .map_err(|mut error| {
+                error.span = shift_span(error.span, delta);
+                error
+            })
+    };
+    let end = start + text.len();
+    let (target, formula) = match assignment(text) {
+        Some(position) => (
+            Some(parse(start, start + position)),
+            parse(start + position + 1, end),
+        ),
+        None => (None, parse(start, end)),
+    };
+    Some(Statement {
+        span: Span::new(start, end),
+        target,
+        formula,
+    })
+}
+
+/// The position of the `=` of an assignment, not part of a comparison nor in a string.
+fn assignment(statement: &str) -> Option<usize> {
+    let bytes = statement.as_bytes();
+    let mut quoted = false;
+    (0..bytes.len()).find(|&index| {
+        if bytes[index] == b'"' {
+            quoted = !quoted;
+        }
+        !quoted
+            && bytes[index] == b'='
+            && !matches!(
+                index.checked_sub(1).map(|before| bytes[before]),
+                Some(b'=' | b'<' | b'>' | b'!')
+            )
+            && bytes.get(index + 1) != Some(&b'=')
+    })
+}
+
+fn shift_span(span: Span, delta: isize) -> Span {
+    Span::new(
+        (span.start as isize + delta) as usize,
+        (span.end as isize + delta) as usize,
+    )
+}
+
+fn shift_expr(expr: &mut Expr, delta: isize) {
+    expr.span = shift_span(expr.span, delta);
+    match &mut expr.kind {
+        ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Variable(_) | ExprKind::Value(_) => {}
+        ExprKind::Unary(_, operand) | ExprKind::Lambda(_, operand) => shift_expr(operand, delta),
+        ExprKind::Binary(_, lhs, rhs) => {
+            shift_expr(lhs, delta);
+            shift_expr(rhs, delta);
+        }
+        ExprKind::Call(_, items) | ExprKind::Tuple(items) => {
+            for item in items {
+                shift_expr(item, delta);
+            }
+        }
+        ExprKind::Conditional(condition, then, otherwise) => {
+            shift_expr(condition, delta);
+            shift_expr(then, delta);
+            shift_expr(otherwise, delta);
+        }
+    }
+}
+
+fn shift(mut statement: Statement, delta: isize) -> Statement {
+    let shift_result = |result: &mut Result<Expr, ParserError>| match result {
+        Ok(expr) => shift_expr(expr, delta),
+        Err(error) => error.span = shift_span(error.span, delta),
+    };
+    statement.span = shift_span(statement.span, delta);
+    if let Some(target) = &mut statement.target {
+        shift_result(target);
+    }
+    shift_result(&mut statement.formula);
+    statement
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edit_documents() {
+        let mut document = Document::new("r = 2 # m\narea(r) = pi * r^2; area(r)\n\n(1 +\nr");
+        let edits: [(usize, usize, &str, Range<usize>); 5] = [
+            // `r = 2` becomes `rate = 20`.
+            (1, 0, "ate", 0..1),
+            (8, 0, "0", 0..1),
+            // Joins the two last lines and closes the parenthesis.
+            (47, 1, " ", 3..4),
+            (49, 0, ")", 3..4),
+            (14, 27, "x", 1..2),
+        ];
+        for (offset, deleted, inserted, changed) in edits {
+            assert_eq!(document.edit(offset, deleted, inserted), changed);
+            let parsed = Document::new(document.source());
+            // The spans must match too, which equality of expressions leaves out.
+            assert_eq!(
+                format!("{:?}", document.statements()),
+                format!("{:?}", parsed.statements()),
+                "{:?}",
+                document.source()
+            );
+        }
+        assert_eq!(document.source(), "rate = 20 # m\nx\n\n(1 + r)");
+        let statement = &document.statements()[2];
+        assert_eq!(statement.span, Span::new(17, 24));
+        assert_eq!(
+            statement.formula.as_ref().map(Expr::to_string),
+            Ok(String::from("1 + r"))
+        );
+    }
+}
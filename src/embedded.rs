@@ -0,0 +1,436 @@
+//! Lexing, parsing and evaluation without allocation, for microcontrollers without an
+//! allocator: the module only uses `core` and builds without the `std` feature. Formulas
+//! are the arithmetic of decimals, `+ - * /`, signs and parentheses, evaluated with
+//! fixed-point amounts of a given number of fractional digits. Tokens go to a buffer of
+//! fixed capacity and nesting is bounded, so the stack use is bounded too.
+
+use core::fmt;
+
+/// Largest number of fractional digits of an amount.
+pub const MAX_DECIMALS: u32 = 18;
+
+/// Bound on the nesting of parentheses and signs.
+pub const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddedErrorKind {
+    UnexpectedCharacter,
+    UnexpectedToken,
+    UnexpectedEnd,
+    /// More tokens than the buffer holds.
+    TooManyTokens,
+    TooDeeplyNested,
+    Overflow,
+    DivisionByZero,
+    /// More fractional digits than `MAX_DECIMALS`.
+    InvalidDecimals,
+}
+
+/// An error and the span of the formula, in bytes, where it happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddedError {
+    pub kind: EmbeddedErrorKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl fmt::Display for EmbeddedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.kind {
+            EmbeddedErrorKind::UnexpectedCharacter => "Unexpected character",
+            EmbeddedErrorKind::UnexpectedToken => "Unexpected token",
+            EmbeddedErrorKind::UnexpectedEnd => "Unexpected end of input",
+            EmbeddedErrorKind::TooManyTokens => "Too many tokens",
+            EmbeddedErrorKind::TooDeeplyNested => "Too deeply nested",
+            EmbeddedErrorKind::Overflow => "Overflow",
+            EmbeddedErrorKind::DivisionByZero => "Division by zero",
+            EmbeddedErrorKind::InvalidDecimals => "Too many fractional digits",
+        };
+        write!(f, "{} at position {}", message, self.start)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EmbeddedError {}
+
+/// A fixed-point amount, `units / 10^decimals`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    units: i64,
+    decimals: u32,
+}
+
+impl Decimal {
+    pub fn units(&self) -> i64 {
+        self.units
+    }
+
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+}
+
+impl fmt::Display for Decimal {
+    /// Writes all the fractional digits, as `2.50`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scale = 10u64.pow(self.decimals);
+        let magnitude = self.units.unsigned_abs();
+        if self.units < 0 {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", magnitude / scale)?;
+        if self.decimals > 0 {
+            let width = self.decimals as usize;
+            write!(f, ".{:0width$}", magnitude % scale, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    /// A literal in units of the amounts.
+    Number(i64),
+    Operator(u8),
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+/// The tokens of a formula, at most `N`.
+struct Tokens<const N: usize> {
+    tokens: [Token; N],
+    len: usize,
+}
+
+impl<const N: usize> Tokens<N> {
+    fn get(&self, index: usize) -> Option<Token> {
+        (index < self.len).then(|| self.tokens[index])
+    }
+}
+
+fn error(kind: EmbeddedErrorKind, start: usize, end: usize) -> EmbeddedError {
+    EmbeddedError { kind, start, end }
+}
+
+fn lex<const N: usize>(source: &str, decimals: u32) -> Result<Tokens<N>, EmbeddedError> {
+    let placeholder = Token {
+        kind: TokenKind::Open,
+        start: 0,
+        end: 0,
+    };
+    let mut tokens = Tokens {
+        tokens: [placeholder; N],
+        len: 0,
+    };
+    let bytes = source.as_bytes();
+    let mut position = 0;
+    while position < bytes.len() {
+        let start = position;
+        let kind = match bytes[position] {
+            byte if byte.is_ascii_whitespace() => {
+                position += 1;
+                continue;
+            }
+            b'0'..=b'9' | b'.' => {
+                let (units, end) = number(bytes, position, decimals)?;
+                position = end;
+                TokenKind::Number(units)
+            }
+            byte @ (b'+' | b'-' | b'*' | b'/') => {
+                position += 1;
+                TokenKind::Operator(byte)
+            }
+            b'(' => {
+                position += 1;
+                TokenKind::Open
+            }
+            b')' => {
+                position += 1;
+                TokenKind::Close
+            }
+            _ => {
+                let end = source[start..]
+                    .chars()
+                    .next()
+                    .map_or(start + 1, |character| start + character.len_utf8());
+                return Err(error(EmbeddedErrorKind::UnexpectedCharacter, start, end));
+            }
+        };
+        if tokens.len == N {
+            return Err(error(EmbeddedErrorKind::TooManyTokens, start, position));
+        }
+        tokens.tokens[tokens.len] = Token {
+            kind,
+            start,
+            end: position,
+        };
+        tokens.len += 1;
+    }
+    Ok(tokens)
+}
+
+/// The units of the literal at `start` and where it ends. Digits past `decimals` are
+/// rounded half to even.
+fn number(bytes: &[u8], start: usize, decimals: u32) -> Result<(i64, usize), EmbeddedError> {
+    let mut position = start;
+    let mut units: i128 = 0;
+    let mut fraction: Option<u32> = None;
+    // The first digit dropped, and whether any after it is not zero.
+    let mut dropped: Option<(u8, bool)> = None;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'.' if fraction.is_none() => fraction = Some(0),
+            digit @ b'0'..=b'9' => match fraction {
+                Some(digits) if digits == decimals => {
+                    dropped = match dropped {
+                        None => Some((digit - b'0', false)),
+                        Some((first, rest)) => Some((first, rest || digit != b'0')),
+                    }
+                }
+                _ => {
+                    units = units * 10 + i128::from(digit - b'0');
+                    if units > i128::from(i64::MAX) {
+                        return Err(error(EmbeddedErrorKind::Overflow, start, position + 1));
+                    }
+                    fraction = fraction.map(|digits| digits + 1);
+                }
+            },
+            _ => break,
+        }
+        position += 1;
+    }
+    if position == start + 1 && bytes[start] == b'.' {
+        return Err(error(
+            EmbeddedErrorKind::UnexpectedCharacter,
+            start,
+            position,
+        ));
+    }
+    if let Some((first, rest)) = dropped {
+        if first > 5 || first == 5 && (rest || units % 2 != 0) {
+            units += 1;
+        }
+    }
+    let overflow = || error(EmbeddedErrorKind::Overflow, start, position);
+    let padding = 10i128.pow(decimals - fraction.unwrap_or(0));
+    let units = units.checked_mul(padding).ok_or_else(overflow)?;
+    Ok((i64::try_from(units).map_err(|_| overflow())?, position))
+}
+
+/// `a / b` rounded to the nearest integer, ties to the even one.
+fn divide_half_even(a: i128, b: i128) -> i128 {
+    let (a, b) = if b < 0 { (-a, -b) } else { (a, b) };
+    let quotient = a.div_euclid(b);
+    let twice_remainder = 2 * a.rem_euclid(b);
+    if twice_remainder > b || twice_remainder == b && quotient % 2 != 0 {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+struct Evaluator<'a, const N: usize> {
+    tokens: &'a Tokens<N>,
+    position: usize,
+    /// `10^decimals`, the units of one.
+    one: i128,
+    /// The length of the formula, where errors at its end are.
+    end: usize,
+}
+
+impl<const N: usize> Evaluator<'_, N> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn unexpected(&self) -> EmbeddedError {
+        match self.peek() {
+            Some(token) => error(EmbeddedErrorKind::UnexpectedToken, token.start, token.end),
+            None => error(EmbeddedErrorKind::UnexpectedEnd, self.end, self.end),
+        }
+    }
+
+    fn checked(&self, units: Option<i128>, token: Token) -> Result<i64, EmbeddedError> {
+        units
+            .and_then(|units| i64::try_from(units).ok())
+            .ok_or(error(EmbeddedErrorKind::Overflow, token.start, token.end))
+    }
+
+    fn sum(&mut self, depth: usize) -> Result<i64, EmbeddedError> {
+        let mut value = self.product(depth)?;
+        while let Some(
+            token @ Token {
+                kind: TokenKind::Operator(operator @ (b'+' | b'-')),
+                ..
+            },
+        ) = self.peek()
+        {
+            self.position += 1;
+            let rhs = i128::from(self.product(depth)?);
+            let units = if operator == b'+' {
+                i128::from(value).checked_add(rhs)
+            } else {
+                i128::from(value).checked_sub(rhs)
+            };
+            value = self.checked(units, token)?;
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self, depth: usize) -> Result<i64, EmbeddedError> {
+        let mut value = self.unary(depth)?;
+        while let Some(
+            token @ Token {
+                kind: TokenKind::Operator(operator @ (b'*' | b'/')),
+                ..
+            },
+        ) = self.peek()
+        {
+            self.position += 1;
+            let rhs = i128::from(self.unary(depth)?);
+            let units = if operator == b'*' {
+                divide_half_even(i128::from(value) * rhs, self.one)
+            } else if rhs == 0 {
+                return Err(error(
+                    EmbeddedErrorKind::DivisionByZero,
+                    token.start,
+                    token.end,
+                ));
+            } else {
+                let numerator = i128::from(value).checked_mul(self.one);
+                let numerator =
+                    numerator.ok_or(error(EmbeddedErrorKind::Overflow, token.start, token.end))?;
+                divide_half_even(numerator, rhs)
+            };
+            value = self.checked(Some(units), token)?;
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self, depth: usize) -> Result<i64, EmbeddedError> {
+        let token = self.peek().ok_or_else(|| self.unexpected())?;
+        if depth >= MAX_DEPTH {
+            return Err(error(
+                EmbeddedErrorKind::TooDeeplyNested,
+                token.start,
+                token.end,
+            ));
+        }
+        match token.kind {
+            TokenKind::Number(units) => {
+                self.position += 1;
+                Ok(units)
+            }
+            TokenKind::Operator(b'-') => {
+                self.position += 1;
+                let value = self.unary(depth + 1)?;
+                self.checked(Some(-i128::from(value)), token)
+            }
+            TokenKind::Operator(b'+') => {
+                self.position += 1;
+                self.unary(depth + 1)
+            }
+            TokenKind::Open => {
+                self.position += 1;
+                let value = self.sum(depth + 1)?;
+                match self.peek() {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => {
+                        self.position += 1;
+                        Ok(value)
+                    }
+                    _ => Err(self.unexpected()),
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+/// Evaluates `source` with amounts of `decimals` fractional digits, using a buffer of
+/// `TOKENS` tokens. Literals and quotients are rounded half to even to `decimals` digits,
+/// as are products with more.
+///
+/// ```
+/// use rusculator::embedded::eval;
+///
+/// let average = eval::<32>("(2.5 + 3.25 + 4) / 3", 2).unwrap();
+/// assert_eq!(average.to_string(), "3.25");
+/// ```
+pub fn eval<const TOKENS: usize>(source: &str, decimals: u32) -> Result<Decimal, EmbeddedError> {
+    if decimals > MAX_DECIMALS {
+        return Err(error(EmbeddedErrorKind::InvalidDecimals, 0, 0));
+    }
+    let tokens = lex::<TOKENS>(source, decimals)?;
+    let mut evaluator = Evaluator {
+        tokens: &tokens,
+        position: 0,
+        one: 10i128.pow(decimals),
+        end: source.len(),
+    };
+    let units = evaluator.sum(0)?;
+    if evaluator.peek().is_some() {
+        return Err(evaluator.unexpected());
+    }
+    Ok(Decimal { units, decimals })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn evaluate_without_allocation() {
+        let cents = |source| eval::<16>(source, 2).map(|amount| amount.to_string());
+        // The same amounts as the money backend, for formulas rounding once.
+        for source in [
+            "1.005 + 2",
+            "-(2.5 - 4) * 2",
+            "10 / 4",
+            "2 / 3",
+            "0.5 * 0.25",
+        ] {
+            let money = Parser::new(source)
+                .parse()
+                .unwrap()
+                .evaluate_money(2, &[])
+                .unwrap();
+            assert_eq!(cents(source), Ok(money.to_string()), "{}", source);
+        }
+        assert_eq!(cents("7"), Ok(String::from("7.00")));
+        assert_eq!(eval::<16>("1 / 3", 0).map(|amount| amount.units()), Ok(0));
+        let kind = |result: Result<String, EmbeddedError>| result.map_err(|error| error.kind);
+        assert_eq!(
+            cents("1 / (2 - 2)").map_err(|error| (error.kind, error.start)),
+            Err((EmbeddedErrorKind::DivisionByZero, 2))
+        );
+        assert_eq!(
+            kind(cents("2 ^ 3")),
+            Err(EmbeddedErrorKind::UnexpectedCharacter)
+        );
+        assert_eq!(kind(cents("(1 + 2")), Err(EmbeddedErrorKind::UnexpectedEnd));
+        assert_eq!(kind(cents("1 2")), Err(EmbeddedErrorKind::UnexpectedToken));
+        assert_eq!(
+            kind(cents("1+1+1+1+1+1+1+1+1")),
+            Err(EmbeddedErrorKind::TooManyTokens)
+        );
+        assert_eq!(
+            kind(eval::<64>(&"-".repeat(40), 0).map(|amount| amount.to_string())),
+            Err(EmbeddedErrorKind::TooDeeplyNested)
+        );
+        assert_eq!(
+            kind(cents("99999999999999999 * 10")),
+            Err(EmbeddedErrorKind::Overflow)
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::builtins;
+use crate::evaluator::Context;
+use crate::parser::Expr;
+use crate::partial;
+use crate::value::Value;
+
+/// Points at which both formulas are compared.
+const SAMPLES: usize = 32;
+/// Points at which both formulas must be defined for them to be equivalent.
+const MIN_DEFINED_SAMPLES: usize = 8;
+/// Relative difference tolerated between the values at a point, for the rounding errors of
+/// different computations.
+const TOLERANCE: f64 = 1e-9;
+
+/// Pseudorandom values from -4 to 4 avoiding the neighbourhood of 0, where many formulas
+/// are singular, by xorshift from a fixed seed so that the outcome is reproducible.
+struct Samples(u64);
+
+impl Iterator for Samples {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        let unit = (self.0 >> 11) as f64 / (1u64 << 53) as f64;
+        let magnitude = 0.1 + 3.9 * unit;
+        Some(if self.0 & 1 == 0 {
+            magnitude
+        } else {
+            -magnitude
+        })
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    a == b || (a - b).abs() <= TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+impl Expr {
+    /// Whether the formulas are mathematically equivalent, as `(a+b)^2` and
+    /// `a^2 + 2*a*b + b^2`. Polynomials are compared exactly by their expansions, and other
+    /// formulas equal once simplified are equivalent. Otherwise both are evaluated at
+    /// pseudorandom values of their variables, other than the constants: they are
+    /// equivalent if they agree wherever both are defined, which may wrongly accept
+    /// formulas differing only at a few points.
+    pub fn equivalent_to(&self, other: &Expr) -> bool {
+        if let (Ok(lhs), Ok(rhs)) = (self.expand(), other.expand()) {
+            return lhs == rhs;
+        }
+        if self.simplify() == other.simplify() {
+            return true;
+        }
+        let mut variables = partial::free_variables(self);
+        variables.extend(partial::free_variables(other));
+        variables.retain(|name| builtins::constant(name).is_none());
+        let mut samples = Samples(0x2545_f491_4f6c_dd1d);
+        let mut defined = 0;
+        for _ in 0..SAMPLES {
+            let mut context = Context::new();
+            for name in &variables {
+                let value = samples.next().unwrap_or_default();
+                context.set_variable(name, Value::Number(value));
+            }
+            let evaluate = |expr: &Expr| context.evaluate(expr).and_then(|value| value.as_number());
+            match (evaluate(self), evaluate(other)) {
+                (Ok(lhs), Ok(rhs)) if lhs.is_finite() && rhs.is_finite() => {
+                    if !close(lhs, rhs) {
+                        return false;
+                    }
+                    defined += 1;
+                }
+                _ => {}
+            }
+        }
+        defined >= MIN_DEFINED_SAMPLES
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::Parser;
+
+    fn equivalent(lhs: &str, rhs: &str) -> bool {
+        let lhs = Parser::new(lhs).parse().unwrap();
+        let rhs = Parser::new(rhs).parse().unwrap();
+        lhs.equivalent_to(&rhs)
+    }
+
+    #[test]
+    fn compare_formulas() {
+        assert!(equivalent("(a+b)^2", "a^2 + 2*a*b + b^2"));
+        assert!(!equivalent("(a+b)^2", "a^2 + b^2"));
+        assert!(equivalent("sin(x)^2 + cos(x)^2", "1"));
+        assert!(equivalent("exp(x) * exp(y)", "exp(x + y)"));
+        assert!(equivalent("sqrt(x^2)", "abs(x)"));
+        assert!(!equivalent("sqrt(x^2)", "x"));
+        assert!(equivalent("ln(x^2)", "2 * ln(abs(x))"));
+        assert!(equivalent("sin(pi/2 - x)", "cos(x)"));
+        assert!(!equivalent("sqrt(-1 - x^2)", "0"));
+    }
+}
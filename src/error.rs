@@ -0,0 +1,76 @@
+use core::fmt;
+
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+use crate::parser::{ParserError, ParserErrorKind};
+
+/// An error of parsing or of evaluating, for the functions doing both.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Parser(ParserError),
+    Evaluator(EvaluatorError),
+}
+
+impl Error {
+    /// The code of the kind of error, stable across releases for tools to match on, as
+    /// `E0201` for unknown variables: `E00` for the lexer, `E01` for the parser and `E02`
+    /// for the evaluator. `explain` describes them.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Parser(error) => match error.kind {
+                ParserErrorKind::Lexer(_) => "E0001",
+                ParserErrorKind::UnexpectedToken { .. } => "E0101",
+                ParserErrorKind::UnexpectedEnd { .. } => "E0102",
+                ParserErrorKind::InvalidNumber(_) => "E0103",
+                ParserErrorKind::InvalidLambdaParameters => "E0104",
+                ParserErrorKind::TooDeeplyNested => "E0105",
+            },
+            Error::Evaluator(error) => match error.kind {
+                EvaluatorErrorKind::UnknownVariable(_) => "E0201",
+                EvaluatorErrorKind::UnknownFunction(_) => "E0202",
+                EvaluatorErrorKind::ArgumentCount { .. } => "E0203",
+                EvaluatorErrorKind::TypeMismatch { .. } => "E0204",
+                EvaluatorErrorKind::DivisionByZero => "E0205",
+                EvaluatorErrorKind::DimensionMismatch { .. } => "E0206",
+                EvaluatorErrorKind::MissingExchangeRate { .. } => "E0207",
+                EvaluatorErrorKind::LimitExceeded(_) => "E0208",
+                EvaluatorErrorKind::NotCompilable(_) => "E0209",
+                EvaluatorErrorKind::InvalidArgument(_) => "E0210",
+                EvaluatorErrorKind::NotConverged(_) => "E0211",
+                EvaluatorErrorKind::NotANumber => "E0212",
+                EvaluatorErrorKind::Infinite => "E0213",
+                EvaluatorErrorKind::Overflow => "E0214",
+                EvaluatorErrorKind::Cancelled => "E0215",
+            },
+        }
+    }
+}
+
+impl From<ParserError> for Error {
+    fn from(error: ParserError) -> Error {
+        Error::Parser(error)
+    }
+}
+
+impl From<EvaluatorError> for Error {
+    fn from(error: EvaluatorError) -> Error {
+        Error::Evaluator(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parser(error) => write!(f, "{}", error),
+            Error::Evaluator(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parser(error) => Some(error),
+            Error::Evaluator(error) => Some(error),
+        }
+    }
+}
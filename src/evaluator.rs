@@ -0,0 +1,1575 @@
+use core::fmt;
+use std::collections::HashMap;
+use std::error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::builtins::{self, Builtin, BuiltinKind};
+use crate::cache::ExpressionCache;
+use crate::currency::{self, ExchangeRates};
+use crate::display::{DisplayOptions, Notation};
+use crate::error::Error;
+use crate::lexer::{Lexer, LexerError, Span};
+use crate::observe::{Slot, Stage};
+use crate::parser::Parser;
+use crate::parser::{BinaryOperator, Expr, ExprKind, UnaryOperator};
+use crate::profile::Profile;
+use crate::radix::Radix;
+use crate::spelling;
+use crate::units::{Dimension, Quantity, Unit, UnitDefinitionError, UnitTable};
+use crate::value::{Function, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvaluatorErrorKind {
+    UnknownVariable(String),
+    UnknownFunction(String),
+    ArgumentCount {
+        function: String,
+        min: usize,
+        max: Option<usize>,
+        found: usize,
+    },
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    DivisionByZero,
+    DimensionMismatch {
+        operation: &'static str,
+        lhs: String,
+        rhs: String,
+    },
+    MissingExchangeRate {
+        from: String,
+        to: String,
+    },
+    LimitExceeded(Limit),
+    NotCompilable(String),
+    InvalidArgument(String),
+    NotConverged(String),
+    NotANumber,
+    Infinite,
+    Overflow,
+    /// The cancellation flag of the evaluator was set.
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluatorError {
+    pub kind: EvaluatorErrorKind,
+    pub span: Option<Span>,
+    /// A name close to that of an unknown variable or function, as `sqrt` for `sqr`.
+    pub suggestion: Option<Box<str>>,
+}
+
+impl EvaluatorError {
+    pub fn new(kind: EvaluatorErrorKind) -> EvaluatorError {
+        EvaluatorError {
+            kind,
+            span: None,
+            suggestion: None,
+        }
+    }
+
+    /// Attaches `span` unless the error already points at a more precise location.
+    pub fn or_span(mut self, span: Span) -> EvaluatorError {
+        if self.span.is_none() {
+            self.span = Some(span);
+        }
+        self
+    }
+}
+
+impl fmt::Display for EvaluatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            EvaluatorErrorKind::UnknownVariable(name) => write!(f, "Unknown variable '{}'", name),
+            EvaluatorErrorKind::UnknownFunction(name) => write!(f, "Unknown function '{}'", name),
+            EvaluatorErrorKind::ArgumentCount {
+                function,
+                min,
+                max,
+                found,
+            } => {
+                write!(f, "'{}' expects ", function)?;
+                match max {
+                    Some(max) if max == min => write!(f, "{}", min)?,
+                    Some(max) => write!(f, "{} to {}", min, max)?,
+                    None => write!(f, "at least {}", min)?,
+                }
+                write!(f, " argument(s), found {}", found)
+            }
+            EvaluatorErrorKind::TypeMismatch { expected, found } => {
+                write!(f, "Expected a {}, found a {}", expected, found)
+            }
+            EvaluatorErrorKind::DivisionByZero => write!(f, "Division by zero"),
+            EvaluatorErrorKind::DimensionMismatch {
+                operation,
+                lhs,
+                rhs,
+            } => write!(f, "Cannot {} {} and {}", operation, lhs, rhs),
+            EvaluatorErrorKind::MissingExchangeRate { from, to } => {
+                write!(f, "No exchange rate from {} to {}", from, to)
+            }
+            EvaluatorErrorKind::LimitExceeded(limit) => match limit {
+                Limit::Time(timeout) => write!(f, "Evaluation took longer than {:?}", timeout),
+                Limit::Depth(depth) => {
+                    write!(f, "Evaluation nested deeper than {} levels", depth)
+                }
+                Limit::Digits(digits) => write!(f, "Result has more than {} digits", digits),
+                Limit::Cost(cost) => write!(
+                    f,
+                    "Evaluation takes more than {} steps, the cost budget",
+                    cost
+                ),
+            },
+            EvaluatorErrorKind::NotCompilable(what) => write!(f, "Cannot compile {}", what),
+            EvaluatorErrorKind::NotANumber => write!(f, "Result is not a number"),
+            EvaluatorErrorKind::Infinite => write!(f, "Result is infinite"),
+            EvaluatorErrorKind::Overflow => write!(f, "Arithmetic overflow"),
+            EvaluatorErrorKind::Cancelled => write!(f, "Evaluation was cancelled"),
+            EvaluatorErrorKind::InvalidArgument(message)
+            | EvaluatorErrorKind::NotConverged(message) => write!(f, "{}", message),
+        }?;
+        match &self.suggestion {
+            Some(name) => write!(f, ", did you mean '{}'?", name),
+            None => Ok(()),
+        }
+    }
+}
+impl error::Error for EvaluatorError {}
+
+/// A resource limit, as reported by [`EvaluatorErrorKind::LimitExceeded`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Time(Duration),
+    Depth(usize),
+    Digits(usize),
+    Cost(u64),
+}
+
+/// Largest word size of the bitwise functions, the bits of the integers which numbers
+/// represent exactly.
+pub const MAX_WORD_SIZE: u32 = 53;
+
+/// What evaluation does with numbers that are NaN or infinite, as `sqrt(-1)` or `10^400`
+/// make. Set with `Context::set_non_finite`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFinite {
+    /// Keeps them, as floating-point arithmetic does.
+    #[default]
+    Propagate,
+    /// Fails at the subexpression producing them, with `NotANumber` or `Infinite`.
+    Error,
+    /// Replaces infinities by the largest finite number of the same sign. NaN still fails.
+    Clamp,
+}
+
+/// The unit of the angles of the trigonometric functions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AngleUnit {
+    #[default]
+    Radians,
+    /// `sin`, `cos` and `tan` take degrees, and the inverse functions and `atan2` return
+    /// them, exactly at multiples of 30 and 45 degrees. Formulas compiled with the context
+    /// do as well, while `Expr::compile` uses radians.
+    Degrees,
+}
+
+/// Resource limits protecting evaluation from hostile input. No limit is imposed by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// Wall-clock time a single evaluation may take.
+    pub timeout: Option<Duration>,
+    /// Nesting depth of subexpressions and function calls.
+    pub max_depth: Option<usize>,
+    /// Number of digits of a result written out exactly, as by `tobase`.
+    pub max_digits: Option<usize>,
+    /// Nodes an evaluation may evaluate. Formulas whose estimated cost, see `Expr::cost`,
+    /// is higher fail before evaluating, and others once they have evaluated that many.
+    pub max_cost: Option<u64>,
+}
+
+/// Variables, units and settings of evaluation. A context is `Send` and `Sync`, so one
+/// context behind an `Arc` or a reference can serve many threads evaluating through
+/// `evaluate`, which does not need a mutable context.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    variables: HashMap<String, Value>,
+    pub(crate) units: UnitTable,
+    exchange_rates: ExchangeRates,
+    display_radixes: Vec<Radix>,
+    limits: Limits,
+    non_finite: NonFinite,
+    angle_unit: AngleUnit,
+    display_options: DisplayOptions,
+    strict_booleans: bool,
+    word_size: Option<u32>,
+    expressions: ExpressionCache,
+    pub(crate) observer: Slot,
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Context>();
+};
+
+impl Context {
+    pub fn new() -> Context {
+        Context::default()
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    /// The variables set, functions included, sorted by name.
+    pub fn variables(&self) -> Vec<(&str, &Value)> {
+        let mut variables: Vec<(&str, &Value)> = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect();
+        variables.sort_by_key(|(name, _)| *name);
+        variables
+    }
+
+    pub fn remove_variable(&mut self, name: &str) -> Option<Value> {
+        self.variables.remove(name)
+    }
+
+    pub fn evaluate(&self, expr: &Expr) -> Result<Value, EvaluatorError> {
+        let evaluate = || Evaluator::new(self).evaluate(expr);
+        self.observer
+            .observe(Stage::Evaluate, || expr.to_string(), evaluate)
+    }
+
+    /// Parses and evaluates `source`. Parsed expressions are cached by source text, so
+    /// evaluating the same formula again with other variables skips parsing.
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        let text = || source.to_string();
+        if self.observer.is_set() {
+            let mut lexer = Lexer::new(source);
+            let lex = || loop {
+                if lexer.next_spanned_token()?.is_none() {
+                    return Ok::<(), LexerError>(());
+                }
+            };
+            // Lexing errors are also those of parsing, returned below.
+            let _ = self.observer.observe(Stage::Lex, text, lex);
+        }
+        let parse = || self.expressions.parse(source);
+        let expr = self.observer.observe(Stage::Parse, text, parse)?;
+        let evaluate = || Evaluator::new(self).evaluate(&expr);
+        Ok(self.observer.observe(Stage::Evaluate, text, evaluate)?)
+    }
+
+    /// Sets how many parsed expressions `eval` keeps; zero disables the cache.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.expressions.set_capacity(capacity);
+    }
+
+    /// The reduction steps of evaluating `expr`, see [`Evaluator::trace`].
+    pub fn trace(&self, expr: &Expr) -> Result<Vec<Expr>, EvaluatorError> {
+        Evaluator::new(self).trace(expr)
+    }
+
+    /// Evaluates `expr` with the time spent, see [`Evaluator::profile`].
+    pub fn profile(&self, expr: &Expr) -> (Result<Value, EvaluatorError>, Profile) {
+        Evaluator::new(self).profile(expr)
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Sets what evaluation does with NaN and infinities, kept by default.
+    pub fn set_non_finite(&mut self, non_finite: NonFinite) {
+        self.non_finite = non_finite;
+    }
+
+    /// What evaluation does with NaN and infinities.
+    pub fn non_finite(&self) -> NonFinite {
+        self.non_finite
+    }
+
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.angle_unit = unit;
+    }
+
+    pub fn angle_unit(&self) -> AngleUnit {
+        self.angle_unit
+    }
+
+    /// Selects the radixes `format` writes numbers in, such as hexadecimal and decimal
+    /// together for programming. No radix at all means plain decimal.
+    pub fn set_display_radixes(&mut self, radixes: &[Radix]) {
+        self.display_radixes = radixes.to_vec();
+    }
+
+    pub fn display_radixes(&self) -> &[Radix] {
+        &self.display_radixes
+    }
+
+    /// Makes arithmetic on booleans, such as `(x > 0) * 5`, an error rather than computing
+    /// with 1 and 0.
+    pub fn set_strict_booleans(&mut self, strict: bool) {
+        self.strict_booleans = strict;
+    }
+
+    /// Sets the width in bits of the words of the bitwise functions, such as 8 or 32: their
+    /// arguments wrap around to the word, negative numbers in two's complement, and `rotl`
+    /// and `rotr` rotate within it. Without a word size they take nonnegative integers.
+    /// Panics unless the size is from 1 to [`MAX_WORD_SIZE`].
+    pub fn set_word_size(&mut self, bits: Option<u32>) {
+        assert!(
+            bits.is_none_or(|bits| (1..=MAX_WORD_SIZE).contains(&bits)),
+            "Word sizes are from 1 to {} bits",
+            MAX_WORD_SIZE
+        );
+        self.word_size = bits;
+    }
+
+    pub fn word_size(&self) -> Option<u32> {
+        self.word_size
+    }
+
+    /// Selects how `format` writes decimal numbers.
+    pub fn set_display_options(&mut self, options: DisplayOptions) {
+        self.display_options = options;
+    }
+
+    pub fn display_options(&self) -> &DisplayOptions {
+        &self.display_options
+    }
+
+    /// Selects the notation `format` writes decimal numbers in, keeping the other options.
+    pub fn set_notation(&mut self, notation: Notation) {
+        self.display_options.notation = notation;
+    }
+
+    pub fn notation(&self) -> Notation {
+        self.display_options.notation
+    }
+
+    /// Formats a result in the display radixes, as `0xff = 255`. Numbers that are not
+    /// integers, and values other than numbers, are written in decimal with the display
+    /// options of the context.
+    pub fn format(&self, value: &Value) -> String {
+        self.format_with(value, &self.display_options)
+    }
+
+    /// Like `format`, writing decimal numbers with `options`.
+    pub fn format_with(&self, value: &Value, options: &DisplayOptions) -> String {
+        let Value::Number(number) = value else {
+            return value.format(options);
+        };
+        let formatted: Vec<String> = self
+            .display_radixes
+            .iter()
+            .filter_map(|radix| match radix {
+                Radix::Decimal => Some(options.format(*number)),
+                _ => radix.format(*number),
+            })
+            .collect();
+        if formatted.is_empty() {
+            return options.format(*number);
+        }
+        formatted.join(" = ")
+    }
+
+    pub fn units(&self) -> &UnitTable {
+        &self.units
+    }
+
+    /// The names starting with `prefix` of the variables, builtins, constants and units,
+    /// in order, for completing identifiers being typed.
+    pub fn completions(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .variables
+            .keys()
+            .cloned()
+            .chain(builtins::names().map(String::from))
+            .chain(self.units.symbols())
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Defines `symbol` as a unit the size of `value`, which is a quantity (`inch / 72`) or
+    /// a plain number for dimensionless units.
+    pub fn define_unit(&mut self, symbol: &str, value: &Value) -> Result<(), UnitDefinitionError> {
+        let (factor, dimension) = match value {
+            Value::Number(number) => (*number, Dimension::default()),
+            Value::Quantity(quantity) => (quantity.base_value(), quantity.unit.dimension()),
+            _ => {
+                return Err(UnitDefinitionError::new(format!(
+                    "Unit '{}' must be defined by a quantity, not a {}",
+                    symbol,
+                    value.type_name()
+                )))
+            }
+        };
+        self.units.define_unit(symbol, factor, dimension)
+    }
+
+    pub fn define_prefix(&mut self, symbol: &str, factor: f64) -> Result<(), UnitDefinitionError> {
+        self.units.define_prefix(symbol, factor)
+    }
+
+    pub fn define_currency(&mut self, code: &str) -> Result<(), UnitDefinitionError> {
+        self.units.define_currency(code)
+    }
+
+    pub fn exchange_rates(&self) -> &ExchangeRates {
+        &self.exchange_rates
+    }
+
+    /// Sets the rate so that one `from` is worth `rate` of `to`. Both must be currencies.
+    pub fn set_exchange_rate(
+        &mut self,
+        from: &str,
+        to: &str,
+        rate: f64,
+    ) -> Result<(), UnitDefinitionError> {
+        for code in [from, to] {
+            let is_currency = self
+                .units
+                .lookup(code)
+                .is_some_and(|unit| unit.dimension == Dimension::currency(code));
+            if !is_currency {
+                return Err(UnitDefinitionError::new(format!(
+                    "'{}' is not a currency",
+                    code
+                )));
+            }
+        }
+        self.exchange_rates.set(from, to, rate)
+    }
+
+    /// Loads exchange rates, one per line, in the same format as unit definitions:
+    ///
+    /// ```text
+    /// # ECB reference rates
+    /// 1 EUR = 1.08 USD
+    /// 100 JPY = 0.62 EUR
+    /// ```
+    pub fn load_exchange_rates(&mut self, source: &str) -> Result<(), UnitDefinitionError> {
+        for (index, line) in source.lines().enumerate() {
+            self.load_exchange_rate(line).map_err(|mut error| {
+                error.line = Some(index + 1);
+                error
+            })?;
+        }
+        Ok(())
+    }
+
+    fn load_exchange_rate(&mut self, line: &str) -> Result<(), UnitDefinitionError> {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let Some((from, to)) = line.split_once('=') else {
+            return Err(UnitDefinitionError::new(String::from(
+                "Expected '<amount> <currency> = <amount> <currency>'",
+            )));
+        };
+        let (from, from_amount) = self.currency_amount(from)?;
+        let (to, to_amount) = self.currency_amount(to)?;
+        self.set_exchange_rate(&from, &to, to_amount / from_amount)
+    }
+
+    fn currency_amount(&self, source: &str) -> Result<(String, f64), UnitDefinitionError> {
+        let value = Parser::new(source)
+            .parse()
+            .map_err(|error| UnitDefinitionError::new(error.to_string()))
+            .and_then(|expr| {
+                self.evaluate(&expr)
+                    .map_err(|error| UnitDefinitionError::new(error.to_string()))
+            })?;
+        match value {
+            Value::Quantity(quantity) => match quantity.unit.components.as_slice() {
+                [(unit, 1)] if unit.dimension == Dimension::currency(&unit.symbol) => {
+                    Ok((unit.symbol.clone(), quantity.value))
+                }
+                _ => Err(UnitDefinitionError::new(format!(
+                    "Expected an amount of a currency, found {}",
+                    quantity
+                ))),
+            },
+            value => Err(UnitDefinitionError::new(format!(
+                "Expected an amount of a currency, found {}",
+                value
+            ))),
+        }
+    }
+
+    /// Loads unit definitions, one per line:
+    ///
+    /// ```text
+    /// # Typography
+    /// unit pt = inch / 72
+    /// unit pica = 12 pt
+    /// prefix Ki = 1024
+    /// ```
+    ///
+    /// Definitions are expressions evaluated in this context, so they may use the units
+    /// defined on previous lines.
+    pub fn load_unit_definitions(&mut self, source: &str) -> Result<(), UnitDefinitionError> {
+        for (index, line) in source.lines().enumerate() {
+            self.load_unit_definition(line).map_err(|mut error| {
+                error.line = Some(index + 1);
+                error
+            })?;
+        }
+        Ok(())
+    }
+
+    fn load_unit_definition(&mut self, line: &str) -> Result<(), UnitDefinitionError> {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let Some((symbol, definition)) = rest.split_once('=') else {
+            return Err(UnitDefinitionError::new(String::from(
+                "Expected 'unit <symbol> = <expression>' or 'prefix <symbol> = <factor>'",
+            )));
+        };
+        let symbol = symbol.trim();
+        let value = Parser::new(definition)
+            .parse()
+            .map_err(|error| UnitDefinitionError::new(error.to_string()))
+            .and_then(|expr| {
+                self.evaluate(&expr)
+                    .map_err(|error| UnitDefinitionError::new(error.to_string()))
+            })?;
+        match keyword {
+            "unit" => self.define_unit(symbol, &value),
+            "prefix" => match value {
+                Value::Number(factor) => self.define_prefix(symbol, factor),
+                _ => Err(UnitDefinitionError::new(format!(
+                    "Prefix '{}' must be defined by a number",
+                    symbol
+                ))),
+            },
+            _ => Err(UnitDefinitionError::new(format!(
+                "Unknown definition '{}', expected 'unit' or 'prefix'",
+                keyword
+            ))),
+        }
+    }
+}
+
+/// A callback of `Evaluator::set_progress`.
+type Progress<'a> = Box<dyn FnMut(usize) + 'a>;
+
+/// Tree-walking evaluator. Local bindings (lambda parameters, bound variables of
+/// `integrate` and friends) shadow the variables of the context.
+pub struct Evaluator<'a> {
+    context: &'a Context,
+    locals: Vec<(String, Value)>,
+    depth: usize,
+    deadline: Option<Instant>,
+    pub(crate) profile: Option<Profile>,
+    cancel: Option<&'a AtomicBool>,
+    progress: Option<(usize, Progress<'a>)>,
+    /// Nodes evaluated so far, as reported to the progress callback.
+    evaluated: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    /// Creates an evaluator; the timeout of the context starts running now.
+    pub fn new(context: &'a Context) -> Evaluator<'a> {
+        Evaluator {
+            context,
+            locals: vec![],
+            depth: 0,
+            deadline: context
+                .limits
+                .timeout
+                .map(|timeout| Instant::now() + timeout),
+            profile: None,
+            cancel: None,
+            progress: None,
+            evaluated: 0,
+        }
+    }
+
+    pub fn context(&self) -> &'a Context {
+        self.context
+    }
+
+    /// Makes evaluation fail with `Cancelled` as soon as `cancel` is set, as by another
+    /// thread when the user aborts a long computation.
+    pub fn set_cancellation(&mut self, cancel: &'a AtomicBool) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Calls `callback` with the number of nodes evaluated so far every `interval` nodes,
+    /// for example to keep a user interface responsive during long computations.
+    pub fn set_progress(&mut self, interval: usize, callback: impl FnMut(usize) + 'a) {
+        self.progress = Some((interval.max(1), Box::new(callback)));
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, EvaluatorError> {
+        self.check_limits()
+            .map_err(|error| error.or_span(expr.span))?;
+        if let Some(max_cost) = self.context.limits.max_cost {
+            if self.depth == 0 && expr.cost() > max_cost || self.evaluated as u64 >= max_cost {
+                return Err(
+                    EvaluatorError::new(EvaluatorErrorKind::LimitExceeded(Limit::Cost(max_cost)))
+                        .or_span(expr.span),
+                );
+            }
+        }
+        self.evaluated += 1;
+        if let Some((interval, callback)) = &mut self.progress {
+            if self.evaluated.is_multiple_of(*interval) {
+                callback(self.evaluated);
+            }
+        }
+        self.depth += 1;
+        let start = self.profile.is_some().then(Instant::now);
+        let result = self
+            .evaluate_kind(&expr.kind)
+            .and_then(|value| self.check_finite(value));
+        if let (Some(profile), Some(start)) = (&mut self.profile, start) {
+            profile.record_node(expr.span, start.elapsed());
+        }
+        self.depth -= 1;
+        result.map_err(|error| error.or_span(expr.span))
+    }
+
+    fn check_finite(&self, mut value: Value) -> Result<Value, EvaluatorError> {
+        let non_finite = self.context.non_finite;
+        if non_finite == NonFinite::Propagate {
+            return Ok(value);
+        }
+        let number = match &mut value {
+            Value::Number(number) => number,
+            Value::Quantity(quantity) => &mut quantity.value,
+            _ => return Ok(value),
+        };
+        if number.is_nan() {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::NotANumber));
+        }
+        if number.is_infinite() {
+            if non_finite == NonFinite::Error {
+                return Err(EvaluatorError::new(EvaluatorErrorKind::Infinite));
+            }
+            *number = f64::MAX.copysign(*number);
+        }
+        Ok(value)
+    }
+
+    fn check_limits(&self) -> Result<(), EvaluatorError> {
+        let limits = &self.context.limits;
+        let exceeded = |limit| {
+            Err(EvaluatorError::new(EvaluatorErrorKind::LimitExceeded(
+                limit,
+            )))
+        };
+        if self
+            .cancel
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+        {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::Cancelled));
+        }
+        if let Some(max_depth) = limits.max_depth.filter(|max| self.depth >= *max) {
+            return exceeded(Limit::Depth(max_depth));
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, limits.timeout) {
+            if Instant::now() > deadline {
+                return exceeded(Limit::Time(timeout));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if a result written out with `digits` digits exceeds the digit limit.
+    pub fn check_digits(&self, digits: usize) -> Result<(), EvaluatorError> {
+        match self.context.limits.max_digits {
+            Some(max_digits) if digits > max_digits => Err(EvaluatorError::new(
+                EvaluatorErrorKind::LimitExceeded(Limit::Digits(max_digits)),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn evaluate_kind(&mut self, kind: &ExprKind) -> Result<Value, EvaluatorError> {
+        match kind {
+            ExprKind::Number(value) => Ok(Value::Number(*value)),
+            ExprKind::String(string) => Ok(Value::String(string.clone())),
+            ExprKind::Variable(name) => self
+                .lookup(name)
+                .ok_or_else(|| self.unknown(EvaluatorErrorKind::UnknownVariable(name.clone()))),
+            ExprKind::Unary(operator, operand) => {
+                let operand = self.evaluate(operand)?;
+                self.check_arithmetic(&operand)?;
+                Value::unary(*operator, &operand)
+            }
+            ExprKind::Binary(
+                operator @ (BinaryOperator::Add | BinaryOperator::Subtract),
+                lhs,
+                rhs,
+            ) if matches!(rhs.kind, ExprKind::Unary(UnaryOperator::Percent, _)) => {
+                // Calculator convention: `200 + 10%` adds ten percent of 200.
+                let lhs = self.evaluate(lhs)?;
+                let rate = self.evaluate(rhs)?;
+                let share = Value::binary(BinaryOperator::Multiply, &lhs, &rate)?;
+                Value::binary(*operator, &lhs, &share)
+            }
+            ExprKind::Binary(operator @ (BinaryOperator::And | BinaryOperator::Or), lhs, rhs) => {
+                let lhs = self.evaluate(lhs)?.is_true()?;
+                if lhs == (*operator == BinaryOperator::Or) {
+                    return Ok(Value::Bool(lhs));
+                }
+                Ok(Value::Bool(self.evaluate(rhs)?.is_true()?))
+            }
+            ExprKind::Conditional(condition, then, otherwise) => {
+                if self.evaluate(condition)?.is_true()? {
+                    self.evaluate(then)
+                } else {
+                    self.evaluate(otherwise)
+                }
+            }
+            ExprKind::Binary(operator, lhs, rhs) => {
+                let lhs = self.evaluate(lhs)?;
+                let mut rhs = self.evaluate(rhs)?;
+                let commensurable = !matches!(
+                    operator,
+                    BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Power
+                );
+                if let (true, Value::Quantity(target)) = (commensurable, &lhs) {
+                    rhs = self.exchange(rhs, &target.unit)?;
+                }
+                if !operator.is_comparison() {
+                    self.check_arithmetic(&lhs)?;
+                    self.check_arithmetic(&rhs)?;
+                }
+                Value::binary(*operator, &lhs, &rhs)
+            }
+            ExprKind::Call(name, args) => self.evaluate_call(name, args),
+            ExprKind::Tuple(items) => Ok(Value::List(self.evaluate_all(items)?)),
+            ExprKind::Lambda(params, body) => Ok(Value::Function(Function::Lambda(
+                params.clone(),
+                body.clone(),
+            ))),
+            ExprKind::Value(value) => Ok(value.clone()),
+        }
+    }
+
+    /// Converts amounts of money into the currency of `target` using the exchange rates of
+    /// the context; any other value is returned unchanged.
+    pub fn exchange(&self, value: Value, target: &Unit) -> Result<Value, EvaluatorError> {
+        match value {
+            Value::Quantity(quantity) => Ok(Value::Quantity(currency::exchange(
+                &quantity,
+                target,
+                &self.context.exchange_rates,
+            )?)),
+            value => Ok(value),
+        }
+    }
+
+    /// Rejects booleans as operands of arithmetic when the context is strict about them.
+    fn check_arithmetic(&self, operand: &Value) -> Result<(), EvaluatorError> {
+        match operand {
+            Value::Bool(_) if self.context.strict_booleans => {
+                Err(EvaluatorError::new(EvaluatorErrorKind::TypeMismatch {
+                    expected: "number",
+                    found: "boolean",
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The error of an unknown variable or function, suggesting the closest of the names
+    /// in scope, only those of functions for functions.
+    fn unknown(&self, kind: EvaluatorErrorKind) -> EvaluatorError {
+        let (name, functions) = match &kind {
+            EvaluatorErrorKind::UnknownFunction(name) => (name, true),
+            EvaluatorErrorKind::UnknownVariable(name) => (name, false),
+            _ => return EvaluatorError::new(kind),
+        };
+        let names = self.context.completions("");
+        let locals = self
+            .locals
+            .iter()
+            .map(|(local, value)| (local.as_str(), Some(value)));
+        let candidates = names
+            .iter()
+            .map(|name| (name.as_str(), self.context.variable(name)))
+            .chain(locals)
+            .filter(|(name, value)| match value {
+                _ if !functions => true,
+                Some(value) => matches!(value, Value::Function(_)),
+                None => builtins::lookup(name).is_some(),
+            })
+            .map(|(name, _)| name);
+        let suggestion = spelling::closest(name, candidates).map(String::into_boxed_str);
+        EvaluatorError {
+            suggestion,
+            ..EvaluatorError::new(kind)
+        }
+    }
+
+    pub(crate) fn lookup(&self, name: &str) -> Option<Value> {
+        if let Some((_, value)) = self.locals.iter().rev().find(|(local, _)| local == name) {
+            return Some(value.clone());
+        }
+        if let Some(value) = self.context.variable(name) {
+            return Some(value.clone());
+        }
+        if let Some(value) = builtins::constant(name) {
+            return Some(Value::Number(value));
+        }
+        if let Some(value) = builtins::boolean(name) {
+            return Some(Value::Bool(value));
+        }
+        if let Some(unit) = self.context.units.lookup(name) {
+            return Some(Value::Quantity(Quantity::new(1.0, Unit::named(unit))));
+        }
+        builtins::lookup(name).map(|builtin| Value::Function(Function::Builtin(builtin.name)))
+    }
+
+    /// Resolves an argument that is expected to be a function, without evaluating anything
+    /// else: a lambda or the name of a function. Returns `None` for any other expression.
+    pub fn function_argument(&mut self, expr: &Expr) -> Result<Option<Function>, EvaluatorError> {
+        match &expr.kind {
+            ExprKind::Lambda(_, _) | ExprKind::Variable(_) => match self.evaluate(expr) {
+                Ok(Value::Function(function)) => Ok(Some(function)),
+                Ok(_) => Ok(None),
+                Err(error) => match error.kind {
+                    EvaluatorErrorKind::UnknownVariable(_) => Ok(None),
+                    _ => Err(error),
+                },
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn evaluate_call(&mut self, name: &str, args: &[Expr]) -> Result<Value, EvaluatorError> {
+        let start = self.profile.is_some().then(Instant::now);
+        let result = self.evaluate_named_call(name, args);
+        if let (Some(profile), Some(start)) = (&mut self.profile, start) {
+            profile.record_function(name, start.elapsed());
+        }
+        result
+    }
+
+    fn evaluate_named_call(&mut self, name: &str, args: &[Expr]) -> Result<Value, EvaluatorError> {
+        let builtin = match self.lookup(name) {
+            Some(Value::Function(Function::Builtin(builtin))) => builtins::lookup(builtin),
+            Some(Value::Function(function)) => {
+                let values = self.evaluate_all(args)?;
+                return self.call(&function, &values);
+            }
+            // Called names such as `min`, which is also a unit, are functions.
+            _ => builtins::lookup(name),
+        };
+        let builtin = builtin
+            .ok_or_else(|| self.unknown(EvaluatorErrorKind::UnknownFunction(name.to_string())))?;
+        builtin.check_arity(args.len())?;
+        match builtin.kind {
+            BuiltinKind::Special(special) => special(self, args),
+            _ => {
+                let values = self.evaluate_all(args)?;
+                self.call_builtin(builtin, &values)
+            }
+        }
+    }
+
+    /// Calls a builtin taking evaluated arguments, converting the angles of the
+    /// trigonometric functions when the context measures them in degrees.
+    fn call_builtin(&self, builtin: &Builtin, args: &[Value]) -> Result<Value, EvaluatorError> {
+        if self.context.angle_unit == AngleUnit::Degrees {
+            if let (Some(function), [Value::Number(number)]) =
+                (degrees_function(builtin.name), args)
+            {
+                return Ok(Value::Number(function(*number)));
+            }
+            if matches!(builtin.name, "asin" | "acos" | "atan" | "atan2") {
+                // Multiples of 45 degrees, such as `atan2(0, -1)`.
+                let exact = match args {
+                    [Value::Number(y), Value::Number(x)] => {
+                        *y == 0.0 || *x == 0.0 || y.abs() == x.abs()
+                    }
+                    _ => false,
+                };
+                return builtin.call(args).map(|value| match value {
+                    Value::Number(radians) if exact => {
+                        Value::Number((radians.to_degrees() / 45.0).round() * 45.0)
+                    }
+                    Value::Number(radians) => Value::Number(radians.to_degrees()),
+                    value => value,
+                });
+            }
+        }
+        builtin.call(args)
+    }
+
+    fn evaluate_all(&mut self, args: &[Expr]) -> Result<Vec<Value>, EvaluatorError> {
+        args.iter().map(|arg| self.evaluate(arg)).collect()
+    }
+
+    pub fn call(&mut self, function: &Function, args: &[Value]) -> Result<Value, EvaluatorError> {
+        match function {
+            Function::Builtin(name) => {
+                let builtin = builtins::lookup(name).ok_or_else(|| {
+                    EvaluatorError::new(EvaluatorErrorKind::UnknownFunction(name.to_string()))
+                })?;
+                builtin.check_arity(args.len())?;
+                self.call_builtin(builtin, args)
+            }
+            Function::Lambda(params, body) => {
+                if params.len() != args.len() {
+                    return Err(EvaluatorError::new(EvaluatorErrorKind::ArgumentCount {
+                        function: function.to_string(),
+                        min: params.len(),
+                        max: Some(params.len()),
+                        found: args.len(),
+                    }));
+                }
+                let bindings = params.iter().cloned().zip(args.iter().cloned()).collect();
+                self.with_locals(bindings, |evaluator| evaluator.evaluate(body))
+            }
+        }
+    }
+
+    pub fn with_locals<R>(
+        &mut self,
+        bindings: Vec<(String, Value)>,
+        f: impl FnOnce(&mut Evaluator<'a>) -> R,
+    ) -> R {
+        let depth = self.locals.len();
+        self.locals.extend(bindings);
+        let result = f(self);
+        self.locals.truncate(depth);
+        result
+    }
+}
+
+/// The sines of the angles from 0 to 90 degrees whose sine has a simple exact value.
+const EXACT_SINES: [(f64, f64); 5] = [
+    (0.0, 0.0),
+    (30.0, 0.5),
+    (45.0, std::f64::consts::FRAC_1_SQRT_2),
+    (60.0, 0.8660254037844386),
+    (90.0, 1.0),
+];
+
+/// The sine of an angle in degrees, reduced to the first quadrant so that multiples of 30
+/// and 45 degrees have their exact sines: `sin(180)` is 0 rather than `1.2e-16`.
+fn sin_degrees(degrees: f64) -> f64 {
+    let mut angle = degrees.rem_euclid(360.0);
+    let sign = if angle > 180.0 {
+        angle -= 180.0;
+        -1.0
+    } else {
+        1.0
+    };
+    if angle > 90.0 {
+        angle = 180.0 - angle;
+    }
+    let sine = EXACT_SINES
+        .iter()
+        .find(|(exact, _)| *exact == angle)
+        .map_or_else(|| angle.to_radians().sin(), |(_, sine)| *sine);
+    sign * sine
+}
+
+fn cos_degrees(degrees: f64) -> f64 {
+    sin_degrees(degrees.rem_euclid(360.0) + 90.0)
+}
+
+fn tan_degrees(degrees: f64) -> f64 {
+    let sine = sin_degrees(degrees);
+    if sine == 0.0 {
+        0.0
+    } else {
+        sine / cos_degrees(degrees)
+    }
+}
+
+/// The angle from -90 to 90 degrees of `sine`, if it is one of the exact sines.
+fn exact_degrees(sine: f64) -> Option<f64> {
+    let (angle, _) = EXACT_SINES.iter().find(|(_, exact)| *exact == sine.abs())?;
+    Some(if sine < 0.0 { -angle } else { *angle })
+}
+
+fn asin_degrees(sine: f64) -> f64 {
+    exact_degrees(sine).unwrap_or_else(|| sine.asin().to_degrees())
+}
+
+fn acos_degrees(cosine: f64) -> f64 {
+    match exact_degrees(cosine) {
+        Some(angle) => 90.0 - angle,
+        None => cosine.acos().to_degrees(),
+    }
+}
+
+fn atan_degrees(tangent: f64) -> f64 {
+    let angle = tangent.atan().to_degrees();
+    if tangent == 0.0 || tangent.abs() == 1.0 || tangent.is_infinite() {
+        (angle / 45.0).round() * 45.0
+    } else {
+        angle
+    }
+}
+
+/// The trigonometric builtin `name` of one number in degrees, as evaluation and compiled
+/// formulas call it when the context measures angles in degrees.
+pub(crate) fn degrees_function(name: &str) -> Option<fn(f64) -> f64> {
+    Some(match name {
+        "sin" => sin_degrees,
+        "cos" => cos_degrees,
+        "tan" => tan_degrees,
+        "asin" => asin_degrees,
+        "acos" => acos_degrees,
+        "atan" => atan_degrees,
+        _ => return None,
+    })
+}
+
+/// Parses and evaluates `source` in a new context, with the builtin functions, constants
+/// and units only: `rusculator::eval("2 km in m")`.
+pub fn eval(source: &str) -> Result<Value, Error> {
+    eval_with(source, &Context::new())
+}
+
+/// Parses and evaluates `source` with the variables and settings of `context`, which is
+/// left as it is; `Context::eval` also caches the parsed formula.
+pub fn eval_with(source: &str, context: &Context) -> Result<Value, Error> {
+    let expr = Parser::new(source).parse()?;
+    Ok(context.evaluate(&expr)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn evaluate(context: &Context, source: &str) -> Result<Value, EvaluatorError> {
+        context.evaluate(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn evaluate_arithmetic() {
+        let context = Context::new();
+        assert_eq!(
+            evaluate(&context, "1 + 2 * 3 - 4 / 2"),
+            Ok(Value::Number(5.0))
+        );
+        assert_eq!(evaluate(&context, "-2^2"), Ok(Value::Number(-4.0)));
+        assert_eq!(evaluate(&context, "2^3^2"), Ok(Value::Number(512.0)));
+        assert_eq!(
+            evaluate(&context, "sqrt(16) + abs(-1)"),
+            Ok(Value::Number(5.0))
+        );
+    }
+
+    #[test]
+    fn eval_in_one_call() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(Value::Number(7.0)));
+        assert_eq!(eval("2 *").unwrap_err().code(), "E0102");
+        let mut context = Context::new();
+        context.set_variable("x", Value::Number(4.0));
+        assert_eq!(eval_with("sqrt(x)", &context), Ok(Value::Number(2.0)));
+        assert_eq!(eval("sqrt(x)").unwrap_err().code(), "E0201");
+    }
+
+    #[test]
+    fn evaluate_variables_and_lambdas() {
+        let mut context = Context::new();
+        context.set_variable("x", Value::Number(3.0));
+        let square = Parser::new("y -> y * x").parse().unwrap();
+        let square = context.evaluate(&square).unwrap();
+        context.set_variable("f", square);
+        assert_eq!(evaluate(&context, "f(2) + x"), Ok(Value::Number(9.0)));
+        let subtract = Parser::new("(a, b) -> a - b").parse().unwrap();
+        context.set_variable("g", context.evaluate(&subtract).unwrap());
+        assert_eq!(evaluate(&context, "g(5, 7)"), Ok(Value::Number(-2.0)));
+    }
+
+    #[test]
+    fn evaluate_quantities() {
+        let context = Context::new();
+        let evaluate = |source| evaluate(&context, source).map(|value| value.to_string());
+        assert_eq!(evaluate("3 m + 4 cm"), Ok(String::from("3.04 m")));
+        assert_eq!(
+            evaluate("60 km / 1 h + 1 km/h"),
+            Ok(String::from("61 km/h"))
+        );
+        assert_eq!(evaluate("2 N * 3 m"), Ok(String::from("6 N*m")));
+        assert_eq!(
+            evaluate("1 J - 1 N*m + 1 kg*m^2/s^2"),
+            Ok(String::from("1 J"))
+        );
+        assert_eq!(evaluate("(3 m)^2 / 2 m"), Ok(String::from("4.5 m")));
+        assert_eq!(evaluate("3 m / 4 cm"), Ok(String::from("75")));
+        assert_eq!(evaluate("1 kW > 999 W"), Ok(String::from("true")));
+        let error = evaluate("3 m + 4 s").unwrap_err();
+        assert_eq!(error.to_string(), "Cannot add m (length) and s (time)");
+        let error = evaluate("2 + 1 W").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot add a dimensionless number and W (power)"
+        );
+    }
+
+    #[test]
+    fn convert_and_define_units() {
+        let mut context = Context::new();
+        let definitions = "# Typography\nunit pt = inch / 72\nunit pica = 12 pt\n\n\
+                           unit nmi = 1852 m  # nautical mile\nunit kn = 1 nmi/h\nprefix Ki = 1024";
+        context.load_unit_definitions(definitions).unwrap();
+        let evaluate = |source| evaluate(&context, source).map(|value| value.to_string());
+        assert_eq!(evaluate("6 pica in inch"), Ok(String::from("1 inch")));
+        assert_eq!(
+            evaluate("convert(36 kpt, pt)"),
+            Ok(String::from("36000 pt"))
+        );
+        assert_eq!(evaluate("10 kn in km/h"), Ok(String::from("18.52 km/h")));
+        assert_eq!(evaluate("2 Kim in m"), Ok(String::from("2048 m")));
+        assert_eq!(
+            evaluate("5 mi in s").unwrap_err().to_string(),
+            "Cannot convert mi (length) and s (time)"
+        );
+        let error = context
+            .load_unit_definitions("unit a = 1 m\nunit b = 2 zz")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: Unknown variable 'zz'");
+        assert!(context.load_unit_definitions("constant c = 3").is_err());
+    }
+
+    #[test]
+    fn convert_currencies() {
+        let mut context = Context::new();
+        context
+            .load_exchange_rates("# Reference rates\n1 EUR = 1.25 USD\n100 JPY = 0.5 EUR")
+            .unwrap();
+        context.define_currency("BTC").unwrap();
+        let evaluate = |source| evaluate(&context, source).map(|value| value.to_string());
+        assert_eq!(
+            evaluate("100 USD + 50 EUR in USD"),
+            Ok(String::from("162.5 USD"))
+        );
+        assert_eq!(evaluate("10 EUR - 5 USD"), Ok(String::from("6 EUR")));
+        assert_eq!(evaluate("1000 JPY in USD"), Ok(String::from("6.25 USD")));
+        assert_eq!(
+            evaluate("2 EUR/kg in USD/kg"),
+            Ok(String::from("2.5 USD/kg"))
+        );
+        assert_eq!(evaluate("1 EUR > 1 USD"), Ok(String::from("true")));
+        assert_eq!(
+            evaluate("1 BTC + 1 USD").unwrap_err().kind,
+            EvaluatorErrorKind::MissingExchangeRate {
+                from: String::from("USD"),
+                to: String::from("BTC")
+            }
+        );
+        assert_eq!(
+            evaluate("1 GBP in EUR").unwrap_err().to_string(),
+            "No exchange rate from GBP to EUR"
+        );
+        assert_eq!(
+            evaluate("1 EUR + 1 m").unwrap_err().to_string(),
+            "Cannot add EUR (currency) and m (length)"
+        );
+        let error = context.load_exchange_rates("1 EUR = 2 m").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Line 1: Expected an amount of a currency, found 2 m"
+        );
+        assert!(context.set_exchange_rate("EUR", "m", 1.0).is_err());
+    }
+
+    #[test]
+    fn program_in_other_bases() {
+        let mut context = Context::new();
+        let value = |context: &Context, source| evaluate(context, source).unwrap();
+        assert_eq!(value(&context, "0xff + 0b11 - 0o10"), Value::Number(250.0));
+        assert_eq!(
+            value(&context, "tobase(255, 16)"),
+            Value::String(String::from("ff"))
+        );
+        assert_eq!(
+            value(&context, "frombase(\"-zz\", 36)"),
+            Value::Number(-1295.0)
+        );
+        assert!(Parser::new("0xfg").parse().is_err());
+        assert_eq!(context.format(&Value::Number(255.0)), "255");
+        context.set_display_radixes(&[Radix::Hexadecimal, Radix::Decimal, Radix::Binary]);
+        assert_eq!(
+            context.format(&value(&context, "0xf0 + 15")),
+            "0xff = 255 = 0b11111111"
+        );
+        context.set_display_radixes(&[Radix::Octal]);
+        assert_eq!(context.format(&Value::Number(8.0)), "0o10");
+        assert_eq!(context.format(&Value::Number(0.5)), "0.5");
+        let error = evaluate(&context, "tobase(1.5, 2)").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot write 1.5 in base 2, expected an integer"
+        );
+        let error = evaluate(&context, "frombase(\"12\", 37)").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The base must be an integer from 2 to 36, found 37"
+        );
+    }
+
+    #[test]
+    fn compute_with_dates() {
+        let context = Context::new();
+        let evaluate = |source| evaluate(&context, source).map(|value| value.to_string());
+        assert_eq!(
+            evaluate("date(2025, 1, 1) + 90 days"),
+            Ok(String::from("2025-04-01"))
+        );
+        assert_eq!(
+            evaluate("date(2025, 6, 1) - date(2025, 1, 1)"),
+            Ok(String::from("151 day"))
+        );
+        assert_eq!(
+            evaluate("date(\"2024-03-01\") - 1 day"),
+            Ok(String::from("2024-02-29"))
+        );
+        assert_eq!(
+            evaluate("2 weeks + date(2025, 12, 25)"),
+            Ok(String::from("2026-01-08"))
+        );
+        assert_eq!(
+            evaluate("date(2025, 1, 2) > date(2025, 1, 1)"),
+            Ok(String::from("true"))
+        );
+        assert_eq!(
+            evaluate("date(2025, 1, 1) + 12 h").unwrap_err().to_string(),
+            "Dates can only be shifted by whole days, not 12 h"
+        );
+        assert_eq!(
+            evaluate("date(2025, 1, 1) + date(2025, 1, 1)")
+                .unwrap_err()
+                .to_string(),
+            "Cannot apply '+' to a date and a date"
+        );
+        assert!(evaluate("date(2025, 2, 30)").is_err());
+    }
+
+    #[test]
+    fn apply_percentages() {
+        let context = Context::new();
+        let evaluate = |source| evaluate(&context, source).map(|value| value.to_string());
+        assert_eq!(evaluate("200 + 10%"), Ok(String::from("220")));
+        assert_eq!(evaluate("200 - 10%"), Ok(String::from("180")));
+        assert_eq!(evaluate("200 * 10%"), Ok(String::from("20")));
+        assert_eq!(evaluate("10% of 50"), Ok(String::from("5")));
+        assert_eq!(evaluate("10%"), Ok(String::from("0.1")));
+        assert_eq!(evaluate("2 m + 50%"), Ok(String::from("3 m")));
+        assert_eq!(evaluate("25% of 2 h in min"), Ok(String::from("30 min")));
+    }
+
+    #[test]
+    fn enforce_limits() {
+        let mut context = Context::new();
+        let recursive = Parser::new("n -> f(n + 1)").parse().unwrap();
+        context.set_variable("f", context.evaluate(&recursive).unwrap());
+        context.set_limits(Limits {
+            max_depth: Some(50),
+            max_digits: Some(8),
+            ..Limits::default()
+        });
+        let kind = |context: &Context, source| evaluate(context, source).unwrap_err().kind;
+        assert_eq!(
+            kind(&context, "f(0)"),
+            EvaluatorErrorKind::LimitExceeded(Limit::Depth(50))
+        );
+        assert_eq!(
+            kind(&context, "tobase(256, 2)"),
+            EvaluatorErrorKind::LimitExceeded(Limit::Digits(8))
+        );
+        assert_eq!(
+            evaluate(&context, "tobase(255, 2)"),
+            Ok(Value::String(String::from("11111111")))
+        );
+        context.set_limits(Limits {
+            timeout: Some(Duration::from_millis(20)),
+            ..Limits::default()
+        });
+        let slow = "integrate(x -> integrate(y -> sin(x * y), 0, 100, 1e-14), 0, 100, 1e-14)";
+        assert_eq!(
+            kind(&context, slow),
+            EvaluatorErrorKind::LimitExceeded(Limit::Time(Duration::from_millis(20)))
+        );
+    }
+
+    #[test]
+    fn cancel_and_report_progress() {
+        let context = Context::new();
+        let slow =
+            Parser::new("integrate(x -> integrate(y -> sin(x * y), 0, 100, 1e-14), 0, 100, 1e-14)")
+                .parse()
+                .unwrap();
+        let cancel = AtomicBool::new(false);
+        let mut reports = vec![];
+        {
+            let mut evaluator = Evaluator::new(&context);
+            evaluator.set_cancellation(&cancel);
+            evaluator.set_progress(1000, |evaluated| {
+                reports.push(evaluated);
+                if evaluated >= 5000 {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            });
+            let error = evaluator.evaluate(&slow).unwrap_err();
+            assert_eq!(error.kind, EvaluatorErrorKind::Cancelled);
+            assert!(error.span.is_some());
+        }
+        assert_eq!(reports, [1000, 2000, 3000, 4000, 5000]);
+        let mut evaluator = Evaluator::new(&context);
+        evaluator.set_cancellation(&cancel);
+        assert!(evaluator
+            .evaluate(&Parser::new("1").parse().unwrap())
+            .is_err());
+        cancel.store(false, Ordering::Relaxed);
+        assert_eq!(
+            evaluator.evaluate(&Parser::new("1").parse().unwrap()),
+            Ok(Value::Number(1.0))
+        );
+    }
+
+    #[test]
+    fn enforce_cost_budget() {
+        let mut context = Context::new();
+        let nested = "t -> integrate(integrate(x * y * t, x, 0, 1), y, 0, 1)";
+        let nested = context.evaluate(&Parser::new(nested).parse().unwrap());
+        context.set_variable("g", nested.unwrap());
+        context.set_limits(Limits {
+            max_cost: Some(1000),
+            ..Limits::default()
+        });
+        let exceeded = EvaluatorErrorKind::LimitExceeded(Limit::Cost(1000));
+        assert!(evaluate(&context, "integrate(sin(x), x, 0, 1) + 9^9^9^9").is_ok());
+        // Rejected before evaluating.
+        let source = "1 + integrate(integrate(x * y, x, 0, 1), y, 0, 1)";
+        let error = evaluate(&context, source).unwrap_err();
+        assert_eq!(error.kind, exceeded);
+        assert_eq!(error.span, Some(Span::new(0, source.len())));
+        // Stopped while evaluating.
+        assert_eq!(evaluate(&context, "g(1)").unwrap_err().kind, exceeded);
+    }
+
+    #[test]
+    fn evaluate_branches_lazily() {
+        let mut context = Context::new();
+        let inverse = Parser::new("x -> x != 0 ? 1/x : 0").parse().unwrap();
+        context.set_variable("inverse", context.evaluate(&inverse).unwrap());
+        assert_eq!(evaluate(&context, "inverse(4)"), Ok(Value::Number(0.25)));
+        assert_eq!(evaluate(&context, "inverse(0)"), Ok(Value::Number(0.0)));
+        assert_eq!(evaluate(&context, "0 && 1/0"), Ok(Value::Bool(false)));
+        assert_eq!(evaluate(&context, "2 || 1/0"), Ok(Value::Bool(true)));
+        assert_eq!(evaluate(&context, "1 && 0 || 3 > 2"), Ok(Value::Bool(true)));
+        assert_eq!(
+            evaluate(&context, "0 ? 1 : 0 ? 2 : 3"),
+            Ok(Value::Number(3.0))
+        );
+        assert_eq!(
+            evaluate(&context, "1 || 1 m").map_err(|error| error.kind),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            evaluate(&context, "1 m ? 1 : 0").unwrap_err().to_string(),
+            "Expected a number, found a quantity"
+        );
+    }
+
+    #[test]
+    fn eval_cached_formulas() {
+        let mut context = Context::new();
+        for (a, b, c) in [(1.0, 2.0, 3.0), (4.0, 5.0, 6.0)] {
+            context.set_variable("a", Value::Number(a));
+            context.set_variable("b", Value::Number(b));
+            context.set_variable("c", Value::Number(c));
+            assert_eq!(context.eval("a*b + c"), Ok(Value::Number(a * b + c)));
+        }
+        assert_eq!(context.expressions.len(), 1);
+        assert!(matches!(context.eval("a +"), Err(Error::Parser(_))));
+        assert_eq!(
+            context.eval("d").unwrap_err().to_string(),
+            "Unknown variable 'd'"
+        );
+    }
+
+    #[test]
+    fn handle_non_finite_numbers() {
+        let mut context = Context::new();
+        assert!(evaluate(&context, "sqrt(-1) + 1")
+            .unwrap()
+            .as_number()
+            .unwrap()
+            .is_nan());
+        context.set_non_finite(NonFinite::Error);
+        let error = evaluate(&context, "1 + sqrt(-1) * 2").unwrap_err();
+        assert_eq!(error.kind, EvaluatorErrorKind::NotANumber);
+        assert_eq!(error.span, Some(Span::new(4, 12)));
+        let error = evaluate(&context, "exp(1000) m").unwrap_err();
+        assert_eq!(error.to_string(), "Result is infinite");
+        assert_eq!(evaluate(&context, "2 + 2"), Ok(Value::Number(4.0)));
+        context.set_non_finite(NonFinite::Clamp);
+        assert_eq!(
+            evaluate(&context, "-exp(1000) - 1"),
+            Ok(Value::Number(f64::MIN))
+        );
+        assert!(evaluate(&context, "sqrt(-1)").is_err());
+    }
+
+    #[test]
+    fn format_in_notations() {
+        let mut context = Context::new();
+        let resistance = evaluate(&context, "4700").unwrap();
+        assert_eq!(context.format(&resistance), "4700");
+        context.set_notation(Notation::SiPrefix);
+        assert_eq!(context.format(&resistance), "4.7k");
+        let options = DisplayOptions {
+            notation: Notation::Engineering,
+            ..DisplayOptions::default()
+        };
+        assert_eq!(context.format_with(&resistance, &options), "4.7e3");
+        let length = evaluate(&context, "12300 m").unwrap();
+        assert_eq!(context.format(&length), "12.3e3 m");
+        context.set_display_radixes(&[Radix::Hexadecimal, Radix::Decimal]);
+        assert_eq!(context.format(&resistance), "0x125c = 4.7k");
+    }
+
+    #[test]
+    fn compose_booleans() {
+        let mut context = Context::new();
+        context.set_variable("x", Value::Number(3.0));
+        assert_eq!(evaluate(&context, "(x > 0) * 5"), Ok(Value::Number(5.0)));
+        assert_eq!(evaluate(&context, "x == 3"), Ok(Value::Bool(true)));
+        assert_eq!(evaluate(&context, "true + true"), Ok(Value::Number(2.0)));
+        assert_eq!(
+            evaluate(&context, "xor(x > 1, true, not(false))"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(evaluate(&context, "and(1, x)"), Ok(Value::Bool(true)));
+        assert!(evaluate(&context, "or(0, 2 m)").is_err());
+        assert_eq!(evaluate(&context, "false ? 1 : 2"), Ok(Value::Number(2.0)));
+        context.set_strict_booleans(true);
+        assert_eq!(
+            evaluate(&context, "(x > 0) * 5").unwrap_err().to_string(),
+            "Expected a number, found a boolean"
+        );
+        assert_eq!(
+            evaluate(&context, "(x > 0) == true && not(x < 0)"),
+            Ok(Value::Bool(true))
+        );
+    }
+
+    #[test]
+    fn measure_angles_in_degrees() {
+        let mut context = Context::new();
+        context.set_angle_unit(AngleUnit::Degrees);
+        let number =
+            |context: &Context, source| evaluate(context, source).unwrap().as_number().unwrap();
+        assert!((number(&context, "sin(30)") - 0.5).abs() < 1e-15);
+        assert!(
+            (number(&context, "integrate(cos, 0, 90)") - 180.0 / std::f64::consts::PI).abs() < 1e-9
+        );
+        assert!((number(&context, "atan2(1, 1)") - 45.0).abs() < 1e-12);
+        assert!((number(&context, "acos(0)") - 90.0).abs() < 1e-12);
+        let exact = |source| number(&context, source);
+        assert_eq!(exact("cos(90)"), 0.0);
+        assert_eq!(exact("sin(180)"), 0.0);
+        assert_eq!(exact("sin(-210)"), 0.5);
+        assert_eq!(exact("cos(720 + 60)"), 0.5);
+        assert_eq!(exact("tan(45)"), 1.0);
+        assert_eq!(exact("tan(180)"), 0.0);
+        assert_eq!(exact("asin(0.5)"), 30.0);
+        assert_eq!(exact("acos(-1)"), 180.0);
+        assert_eq!(exact("acos(sqrt(2) / 2)"), 45.0);
+        assert_eq!(exact("atan(-1)"), -45.0);
+        assert_eq!(exact("atan2(0, -1)"), 180.0);
+        assert_eq!(exact("sin(10^7 * 360 + 30)"), 0.5);
+        context.set_angle_unit(AngleUnit::Radians);
+        assert!((number(&context, "acos(0)") - std::f64::consts::FRAC_PI_2).abs() < 1e-15);
+    }
+
+    #[test]
+    fn complete_names() {
+        let mut context = Context::new();
+        context.set_variable("sigma", Value::Number(1.0));
+        context.define_currency("SIL").unwrap();
+        assert_eq!(context.completions("si"), ["sigma", "sin", "sinh"]);
+        assert_eq!(context.completions("SI"), ["SIL"]);
+        assert!(context.completions("p").contains(&String::from("pi")));
+        assert!(!context.completions("").contains(&String::from("km")));
+        assert!(context.completions("m").contains(&String::from("min")));
+    }
+
+    #[test]
+    fn list_and_remove_variables() {
+        let mut context = Context::new();
+        context.set_variable("y", Value::Number(2.0));
+        context.set_variable("x", Value::Bool(true));
+        let names: Vec<&str> = context.variables().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["x", "y"]);
+        assert_eq!(context.remove_variable("y"), Some(Value::Number(2.0)));
+        assert_eq!(context.remove_variable("y"), None);
+        assert!(evaluate(&context, "y").is_err());
+    }
+
+    #[test]
+    fn share_context_between_threads() {
+        let mut context = Context::new();
+        context.set_variable("square", evaluate(&context, "x -> x^2 + offset").unwrap());
+        context.set_variable("offset", Value::Number(1.0));
+        let context = &context;
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|n| scope.spawn(move || evaluate(context, &format!("square({})", n))))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap().unwrap())
+                .collect()
+        });
+        assert_eq!(results, [1.0, 2.0, 5.0, 10.0].map(Value::Number).to_vec());
+    }
+
+    #[test]
+    fn report_errors_with_spans() {
+        let context = Context::new();
+        let error = evaluate(&context, "1 + 2 / (3 - 3)").unwrap_err();
+        assert_eq!(error.kind, EvaluatorErrorKind::DivisionByZero);
+        assert_eq!(error.span, Some(Span::new(4, 15)));
+        let error = evaluate(&context, "2 * y").unwrap_err();
+        assert_eq!(
+            error.kind,
+            EvaluatorErrorKind::UnknownVariable(String::from("y"))
+        );
+        assert_eq!(error.span, Some(Span::new(4, 5)));
+        let error = evaluate(&context, "sqrt(1, 2)").unwrap_err();
+        assert_eq!(error.to_string(), "'sqrt' expects 1 argument(s), found 2");
+        let mut context = context.clone();
+        context.set_variable("double", evaluate(&context, "width -> widht * 2").unwrap());
+        let error = evaluate(&context, "double(3)").unwrap_err();
+        assert_eq!(error.suggestion.as_deref(), Some("width"));
+        // Only functions are suggested for calls.
+        let error = evaluate(&context, "pii(2) + mi").unwrap_err();
+        assert_eq!(error.suggestion, None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Evaluation of formulas with exact number types, such as fixed point, instead of `f64`.
+
+use crate::builtins;
+use crate::evaluator::{Context, EvaluatorError, EvaluatorErrorKind};
+use crate::parser::{BinaryOperator, Expr, ExprKind, UnaryOperator};
+use crate::value::Value;
+
+/// A number type evaluating formulas, in a format set when evaluating starts, such as a
+/// number of fraction bits.
+pub(crate) trait Exact: Copy {
+    type Format: Copy;
+
+    /// Checks or converts the value of a variable.
+    fn in_format(self, format: Self::Format) -> Result<Self, EvaluatorError>;
+    /// Converts a literal or a builtin constant.
+    fn from_f64(format: Self::Format, value: f64) -> Result<Self, EvaluatorError>;
+    /// One for true, zero for false.
+    fn truth(format: Self::Format, value: bool) -> Self;
+    fn is_true(&self) -> bool;
+    /// The value if it is a whole number.
+    fn whole(&self) -> Option<i64>;
+    fn binary(operator: BinaryOperator, lhs: Self, rhs: Self) -> Result<Self, EvaluatorError>;
+    fn negate(self) -> Result<Self, EvaluatorError>;
+    fn percent(self) -> Result<Self, EvaluatorError>;
+    fn abs(self) -> Result<Self, EvaluatorError>;
+    fn sqrt(self) -> Result<Self, EvaluatorError>;
+}
+
+/// The error of operators exact numbers do not have.
+pub(crate) fn unsupported(operator: BinaryOperator) -> EvaluatorError {
+    EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+        "Exact numbers do not support '{}'",
+        operator.symbol()
+    )))
+}
+
+/// Raises to a whole exponent by repeated squaring, rounding after each product.
+pub(crate) fn power<T: Exact>(
+    base: T,
+    exponent: T,
+    format: T::Format,
+) -> Result<T, EvaluatorError> {
+    let Some(exponent) = exponent.whole() else {
+        return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+            String::from("Exact powers need a whole exponent"),
+        )));
+    };
+    let one = T::truth(format, true);
+    let mut remaining = exponent.unsigned_abs();
+    let mut square = base;
+    let mut result = one;
+    while remaining > 0 {
+        if remaining % 2 == 1 {
+            result = T::binary(BinaryOperator::Multiply, result, square)?;
+        }
+        remaining /= 2;
+        if remaining > 0 {
+            square = T::binary(BinaryOperator::Multiply, square, square)?;
+        }
+    }
+    if exponent < 0 {
+        result = T::binary(BinaryOperator::Divide, one, result)?;
+    }
+    Ok(result)
+}
+
+/// The variables of `context` which are numbers, converted for an exact type. The others
+/// are not seen by exact evaluation.
+pub(crate) fn numbers<T>(
+    context: &Context,
+    convert: impl Fn(&str, f64) -> Result<T, EvaluatorError>,
+) -> Result<Vec<(&str, T)>, EvaluatorError> {
+    context
+        .variables()
+        .into_iter()
+        .filter_map(|(name, value)| match value {
+            Value::Number(number) => Some(convert(name, *number).map(|number| (name, number))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Evaluates `expr` with the values of `variables`. Only numbers
+/// are supported: arithmetic with whole powers, comparisons, `&&`, `||`, conditionals and
+/// the builtins `abs` and `sqrt`.
+pub(crate) fn evaluate<T: Exact>(
+    expr: &Expr,
+    format: T::Format,
+    variables: &[(&str, T)],
+) -> Result<T, EvaluatorError> {
+    ExactEvaluator { format, variables }.evaluate(expr)
+}
+
+struct ExactEvaluator<'a, T: Exact> {
+    format: T::Format,
+    variables: &'a [(&'a str, T)],
+}
+
+impl<'a, T: Exact> ExactEvaluator<'a, T> {
+    fn evaluate(&self, expr: &Expr) -> Result<T, EvaluatorError> {
+        self.evaluate_kind(&expr.kind)
+            .map_err(|error| error.or_span(expr.span))
+    }
+
+    fn evaluate_kind(&self, kind: &ExprKind) -> Result<T, EvaluatorError> {
+        match kind {
+            ExprKind::Number(value) => T::from_f64(self.format, *value),
+            ExprKind::Variable(name) => {
+                if let Some((_, value)) = self.variables.iter().find(|(n, _)| n == name) {
+                    return value.in_format(self.format);
+                }
+                if let Some(value) = builtins::boolean(name) {
+                    return Ok(T::truth(self.format, value));
+                }
+                match builtins::constant(name) {
+                    Some(value) => T::from_f64(self.format, value),
+                    None => Err(EvaluatorError::new(EvaluatorErrorKind::UnknownVariable(
+                        name.clone(),
+                    ))),
+                }
+            }
+            ExprKind::Unary(UnaryOperator::Negate, operand) => self.evaluate(operand)?.negate(),
+            ExprKind::Unary(UnaryOperator::Percent, operand) => self.evaluate(operand)?.percent(),
+            ExprKind::Binary(
+                operator @ (BinaryOperator::Add | BinaryOperator::Subtract),
+                lhs,
+                rhs,
+            ) if matches!(rhs.kind, ExprKind::Unary(UnaryOperator::Percent, _)) => {
+                let lhs = self.evaluate(lhs)?;
+                let share = T::binary(BinaryOperator::Multiply, lhs, self.evaluate(rhs)?)?;
+                T::binary(*operator, lhs, share)
+            }
+            ExprKind::Binary(operator @ (BinaryOperator::And | BinaryOperator::Or), lhs, rhs) => {
+                let lhs = self.evaluate(lhs)?.is_true();
+                if lhs == (*operator == BinaryOperator::Or) {
+                    return Ok(T::truth(self.format, lhs));
+                }
+                let rhs = self.evaluate(rhs)?.is_true();
+                Ok(T::truth(self.format, rhs))
+            }
+            ExprKind::Binary(operator, lhs, rhs) => {
+                T::binary(*operator, self.evaluate(lhs)?, self.evaluate(rhs)?)
+            }
+            ExprKind::Conditional(condition, then, otherwise) => {
+                if self.evaluate(condition)?.is_true() {
+                    self.evaluate(then)
+                } else {
+                    self.evaluate(otherwise)
+                }
+            }
+            ExprKind::Call(name, args) if name == "abs" || name == "sqrt" => {
+                if args.len() != 1 {
+                    return Err(EvaluatorError::new(EvaluatorErrorKind::ArgumentCount {
+                        function: name.clone(),
+                        min: 1,
+                        max: Some(1),
+                        found: args.len(),
+                    }));
+                }
+                let arg = self.evaluate(&args[0])?;
+                match name.as_str() {
+                    "abs" => arg.abs(),
+                    _ => arg.sqrt(),
+                }
+            }
+            ExprKind::Call(name, _) => Err(EvaluatorError::new(
+                EvaluatorErrorKind::UnknownFunction(name.clone()),
+            )),
+            ExprKind::String(_) => Err(not_a_number("string")),
+            ExprKind::Lambda(_, _) => Err(not_a_number("function")),
+            ExprKind::Tuple(_) => Err(not_a_number("list")),
+            ExprKind::Value(value) => T::from_f64(self.format, value.as_number()?),
+        }
+    }
+}
+
+fn not_a_number(found: &'static str) -> EvaluatorError {
+    EvaluatorError::new(EvaluatorErrorKind::TypeMismatch {
+        expected: "number",
+        found,
+    })
+}
@@ -0,0 +1,226 @@
+//! Longer descriptions of the codes of `Error::code`, with examples, as printed by
+//! `rusculator explain E0201`.
+
+/// The descriptions by code, in order. Each starts with a line summing it up.
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "E0001",
+        "A character that cannot start a token.
+
+Formulas are made of numbers, names, strings in double quotes, operators, parentheses
+and commas. Other characters, such as `@` or `#` outside of scripts, are rejected:
+
+    2 @ 3       # error
+    2 * 3       # ok
+
+In scripts, `#` starts a comment running to the end of the line.",
+    ),
+    (
+        "E0101",
+        "A token where another was expected.
+
+The parser found a token that cannot come where it is, such as a second operator or a
+closing parenthesis without an opening one. The error says what was expected instead:
+
+    2 * * 3     # error: expected an expression
+    (1 + 2))    # error: expected end of input
+    2 * 3",
+    ),
+    (
+        "E0102",
+        "The formula ends before it is complete.
+
+An operator is missing its right operand, or a parenthesis was left open:
+
+    2 *         # error
+    sqrt(2      # error: expected ')'
+    sqrt(2)",
+    ),
+    (
+        "E0103",
+        "A number that cannot be read.
+
+Numbers are decimals such as `1.5e3`, or integers in hexadecimal, octal or binary
+written `0xff`, `0o17` and `0b101`, whose digits must be those of their base:
+
+    0b102       # error: 2 is not a binary digit
+    0b101",
+    ),
+    (
+        "E0104",
+        "The parameters of a function are not names.
+
+The left side of `->` names the parameters of the function, one name or names in
+parentheses:
+
+    (1, 2) -> 3         # error
+    (x, y) -> x * y",
+    ),
+    (
+        "E0105",
+        "The formula is nested too deeply.
+
+Parentheses, function calls and operators nest up to a bound, so that hostile input
+cannot exhaust the stack. Assign parts of the formula to variables instead:
+
+    a = (1 + 2) * 3
+    a^2",
+    ),
+    (
+        "E0201",
+        "A name that is not defined.
+
+The name is not a variable, a constant, a unit nor a function. Assign it first, or
+check its spelling, which the error suggests when a name is close:
+
+    2 * y       # error
+    y = 2
+    2 * y",
+    ),
+    (
+        "E0202",
+        "A function that is not defined.
+
+The function called is not a builtin nor a function defined before. Define it first:
+
+    f(3)        # error
+    f(x) = x^2
+    f(3)",
+    ),
+    (
+        "E0203",
+        "The wrong number of arguments.
+
+Each function takes a number of arguments, which the error gives:
+
+    sqrt(1, 2)  # error: 'sqrt' expects 1 argument(s)
+    sqrt(2)",
+    ),
+    (
+        "E0204",
+        "A value of the wrong type.
+
+An operator or function received a value it does not take, such as a string where a
+number is expected:
+
+    sqrt(\"4\")   # error
+    sqrt(4)",
+    ),
+    (
+        "E0205",
+        "Division by zero.
+
+The divisor of `/` is zero:
+
+    1 / (3 - 3)     # error",
+    ),
+    (
+        "E0206",
+        "Quantities of different dimensions.
+
+Adding, subtracting, comparing or converting quantities needs them to measure the same
+thing:
+
+    2 km + 3 s      # error: a length and a time
+    2 km + 300 m",
+    ),
+    (
+        "E0207",
+        "No exchange rate between two currencies.
+
+Converting amounts of money needs the rate between their currencies, which must be set
+first in the exchange rates of the context:
+
+    10 USD in EUR   # error without a rate",
+    ),
+    (
+        "E0208",
+        "A limit of evaluation was exceeded.
+
+Contexts can bound the time, recursion depth, size of numbers and cost of formulas, as
+the server does for its requests. The error says which limit was reached. Simplify the
+formula, or raise the limit where it is set.",
+    ),
+    (
+        "E0209",
+        "A formula that cannot be compiled.
+
+Compiling to bytecode or native code takes numeric formulas only: strings, units and
+functions other than the numeric builtins are left to the evaluator.",
+    ),
+    (
+        "E0210",
+        "An argument out of the range a function takes.
+
+The argument has the right type but a value the function rejects, such as a negative
+tolerance for `integrate` or a base below two for `tobase`. The error says which.",
+    ),
+    (
+        "E0211",
+        "A numeric method did not converge.
+
+Integration, root finding and the like stop when they cannot reach the tolerance
+asked. Try another interval or a looser tolerance.",
+    ),
+    (
+        "E0212",
+        "A result that is not a number.
+
+With non-finite results made errors, operations giving NaN fail, as the square root of
+a negative number does:
+
+    sqrt(-1)    # error once non-finite results fail",
+    ),
+    (
+        "E0213",
+        "A result that is infinite.
+
+With non-finite results made errors, operations overflowing to infinity fail:
+
+    10^400      # error once non-finite results fail",
+    ),
+    (
+        "E0214",
+        "Arithmetic overflow.
+
+Exact integers of a fixed word size, exact money amounts and exact polynomials are
+bounded, and the result did not fit. Widen the word size, or compute with floating
+point numbers.",
+    ),
+    (
+        "E0215",
+        "Evaluation was cancelled.
+
+The program evaluating the formula cancelled it before it finished, as on a time out
+or on a request of the user.",
+    ),
+];
+
+/// The description of the error `code`, as `E0201`, with examples of formulas making it,
+/// or `None` for codes that do not exist.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::Context;
+
+    #[test]
+    fn explain_codes() {
+        let mut context = Context::new();
+        for formula in ["2 @ 3", "sqrt(2", "0b102", "2 * y", "sqrt(1, 2)", "1 / 0"] {
+            let code = context.eval(formula).unwrap_err().code();
+            assert!(explain(code).is_some(), "{}", code);
+        }
+        assert!(explain("e0202")
+            .unwrap()
+            .starts_with("A function that is not defined."));
+        assert_eq!(explain("E9999"), None);
+        assert!(EXPLANATIONS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
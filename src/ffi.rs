@@ -0,0 +1,238 @@
+//! A C interface to the evaluator, exported from the cdylib built with the `ffi` feature
+//! by `cargo rustc --lib --features ffi --crate-type cdylib`, and declared in
+//! `include/rusculator.h`. Functions returning a status return one of the
+//! `RUSC_` constants, and the message of the last error of a context stays available
+//! from `rusc_last_error` until the next call taking the context.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+use crate::error::Error;
+use crate::evaluator::Context;
+use crate::value::Value;
+
+pub const RUSC_OK: c_int = 0;
+/// A formula was well formed but could not be evaluated, or did not evaluate to a number.
+pub const RUSC_EVALUATION_ERROR: c_int = 1;
+/// A pointer was null or a string was not UTF-8.
+pub const RUSC_INVALID_ARGUMENT: c_int = 2;
+pub const RUSC_PARSE_ERROR: c_int = 3;
+
+/// A context with the message of its last error, opaque to C.
+pub struct RuscContext {
+    context: Context,
+    error: Option<CString>,
+}
+
+impl RuscContext {
+    /// Records `message` as the last error, returning `status`.
+    fn fail(&mut self, status: c_int, message: String) -> c_int {
+        // Messages quote formulas, which cannot hold nul bytes once read from C strings.
+        self.error = CString::new(message).ok();
+        status
+    }
+
+    fn fail_with(&mut self, error: Error) -> c_int {
+        let status = match error {
+            Error::Parser(_) => RUSC_PARSE_ERROR,
+            Error::Evaluator(_) => RUSC_EVALUATION_ERROR,
+        };
+        self.fail(status, error.to_string())
+    }
+}
+
+/// The string of `pointer`, `None` if it is null or not UTF-8.
+///
+/// # Safety
+///
+/// `pointer` must be null or point to a nul-terminated string.
+unsafe fn string<'a>(pointer: *const c_char) -> Option<&'a str> {
+    if pointer.is_null() {
+        return None;
+    }
+    CStr::from_ptr(pointer).to_str().ok()
+}
+
+/// A new context with the builtin functions, constants and units, to be freed with
+/// `rusc_ctx_free`.
+#[no_mangle]
+pub extern "C" fn rusc_ctx_new() -> *mut RuscContext {
+    Box::into_raw(Box::new(RuscContext {
+        context: Context::new(),
+        error: None,
+    }))
+}
+
+/// Frees a context of `rusc_ctx_new`; null is ignored.
+///
+/// # Safety
+///
+/// `ctx` must be null or a context of `rusc_ctx_new` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rusc_ctx_free(ctx: *mut RuscContext) {
+    if !ctx.is_null() {
+        drop(Box::from_raw(ctx));
+    }
+}
+
+/// Sets the variable `name` to a number.
+///
+/// # Safety
+///
+/// `ctx` must be a context of `rusc_ctx_new` and `name` a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rusc_ctx_set_var(
+    ctx: *mut RuscContext,
+    name: *const c_char,
+    value: f64,
+) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return RUSC_INVALID_ARGUMENT;
+    };
+    let Some(name) = string(name) else {
+        return ctx.fail(RUSC_INVALID_ARGUMENT, String::from("Invalid variable name"));
+    };
+    ctx.context.set_variable(name, Value::Number(value));
+    ctx.error = None;
+    RUSC_OK
+}
+
+/// Evaluates `formula`, writing its value to `result` if it is a number.
+///
+/// # Safety
+///
+/// `ctx` must be a context of `rusc_ctx_new`, `formula` a nul-terminated string and
+/// `result` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn rusc_eval(
+    ctx: *mut RuscContext,
+    formula: *const c_char,
+    result: *mut f64,
+) -> c_int {
+    let Some(ctx) = ctx.as_mut() else {
+        return RUSC_INVALID_ARGUMENT;
+    };
+    let Some(formula) = string(formula) else {
+        return ctx.fail(RUSC_INVALID_ARGUMENT, String::from("Invalid formula"));
+    };
+    match ctx.context.eval(formula) {
+        Ok(Value::Number(value)) => {
+            if !result.is_null() {
+                *result = value;
+            }
+            ctx.error = None;
+            RUSC_OK
+        }
+        Ok(value) => ctx.fail(
+            RUSC_EVALUATION_ERROR,
+            format!("Expected a number, found {}", value.type_name()),
+        ),
+        Err(error) => ctx.fail_with(error),
+    }
+}
+
+/// Evaluates `formula` and writes out its value of any type as the context displays it,
+/// returning null on errors. The string is freed with `rusc_string_free`.
+///
+/// # Safety
+///
+/// `ctx` must be a context of `rusc_ctx_new` and `formula` a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rusc_eval_string(
+    ctx: *mut RuscContext,
+    formula: *const c_char,
+) -> *mut c_char {
+    let Some(ctx) = ctx.as_mut() else {
+        return ptr::null_mut();
+    };
+    let Some(formula) = string(formula) else {
+        ctx.fail(RUSC_INVALID_ARGUMENT, String::from("Invalid formula"));
+        return ptr::null_mut();
+    };
+    match ctx.context.eval(formula) {
+        Ok(value) => match CString::new(ctx.context.format(&value)) {
+            Ok(text) => {
+                ctx.error = None;
+                text.into_raw()
+            }
+            Err(_) => {
+                let message = String::from("The value holds a nul byte, which C strings cannot");
+                ctx.fail(RUSC_EVALUATION_ERROR, message);
+                ptr::null_mut()
+            }
+        },
+        Err(error) => {
+            ctx.fail_with(error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The message of the last error of `ctx`, or null if its last call succeeded. The string
+/// belongs to the context.
+///
+/// # Safety
+///
+/// `ctx` must be null or a context of `rusc_ctx_new`.
+#[no_mangle]
+pub unsafe extern "C" fn rusc_last_error(ctx: *const RuscContext) -> *const c_char {
+    match ctx.as_ref().and_then(|ctx| ctx.error.as_ref()) {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Frees a string of `rusc_eval_string`; null is ignored.
+///
+/// # Safety
+///
+/// `string` must be null or a string of `rusc_eval_string` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rusc_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evaluate_through_the_c_interface() {
+        let formula = |text: &str| CString::new(text).unwrap();
+        let message = |ctx| unsafe {
+            let error = rusc_last_error(ctx);
+            (!error.is_null()).then(|| CStr::from_ptr(error).to_str().unwrap().to_string())
+        };
+        unsafe {
+            let ctx = rusc_ctx_new();
+            let mut result = 0.0;
+            assert_eq!(rusc_ctx_set_var(ctx, formula("r").as_ptr(), 2.0), RUSC_OK);
+            assert_eq!(
+                rusc_eval(ctx, formula("r^2 + 1").as_ptr(), &mut result),
+                RUSC_OK
+            );
+            assert_eq!(result, 5.0);
+            assert_eq!(message(ctx), None);
+            let status = rusc_eval(ctx, formula("r + y").as_ptr(), &mut result);
+            assert_eq!(status, RUSC_EVALUATION_ERROR);
+            assert_eq!(message(ctx).as_deref(), Some("Unknown variable 'y'"));
+            let status = rusc_eval(ctx, formula("2 *").as_ptr(), &mut result);
+            assert_eq!(status, RUSC_PARSE_ERROR);
+            let status = rusc_eval(ctx, ptr::null(), &mut result);
+            assert_eq!(status, RUSC_INVALID_ARGUMENT);
+            let text = rusc_eval_string(ctx, formula("r * 1 km").as_ptr());
+            assert_eq!(CStr::from_ptr(text).to_str(), Ok("2 km"));
+            rusc_string_free(text);
+            assert!(rusc_eval_string(ctx, formula("(").as_ptr()).is_null());
+            assert!(message(ctx).is_some());
+            let nul = Value::String(String::from("a\0b"));
+            (*ctx).context.set_variable("s", nul);
+            let text = rusc_eval_string(ctx, formula("s").as_ptr());
+            assert!(text.is_null());
+            assert!(message(ctx).unwrap().contains("nul byte"));
+            rusc_ctx_free(ctx);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use core::fmt;
+
+use crate::evaluator::{Context, EvaluatorError, EvaluatorErrorKind};
+use crate::exact::{self, Exact};
+use crate::parser::{BinaryOperator, Expr};
+
+/// A Qm.n fixed-point format: a sign bit, `m` integer bits and `n` fraction bits, stored
+/// in 64 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedFormat {
+    integer_bits: u32,
+    fraction_bits: u32,
+}
+
+impl FixedFormat {
+    /// Returns `None` if the format does not fit in 64 bits with the sign bit.
+    pub fn new(integer_bits: u32, fraction_bits: u32) -> Option<FixedFormat> {
+        (integer_bits + fraction_bits < 64).then_some(FixedFormat {
+            integer_bits,
+            fraction_bits,
+        })
+    }
+
+    pub fn integer_bits(&self) -> u32 {
+        self.integer_bits
+    }
+
+    pub fn fraction_bits(&self) -> u32 {
+        self.fraction_bits
+    }
+
+    /// The fixed-point number with the raw value `raw`, that is `raw / 2^n`.
+    pub fn from_raw(&self, raw: i64) -> Result<Fixed, EvaluatorError> {
+        self.fixed(raw.into())
+    }
+
+    pub fn from_int(&self, value: i64) -> Result<Fixed, EvaluatorError> {
+        self.fixed(i128::from(value) << self.fraction_bits)
+    }
+
+    /// The fixed-point number nearest to `value`.
+    pub fn from_f64(&self, value: f64) -> Result<Fixed, EvaluatorError> {
+        let raw = (value * (self.fraction_bits as f64).exp2()).round();
+        if raw.is_nan() || raw.abs() >= 2f64.powi(64) {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::Overflow));
+        }
+        self.fixed(raw as i128)
+    }
+
+    fn fixed(&self, raw: i128) -> Result<Fixed, EvaluatorError> {
+        let bound = 1i128 << (self.integer_bits + self.fraction_bits);
+        if raw < -bound || raw >= bound {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::Overflow));
+        }
+        Ok(Fixed {
+            raw: raw as i64,
+            format: *self,
+        })
+    }
+}
+
+impl fmt::Display for FixedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Q{}.{}", self.integer_bits, self.fraction_bits)
+    }
+}
+
+/// A fixed-point number. Arithmetic is exact on integers, so results do not depend on the
+/// platform, and products and quotients are rounded to the nearest number, ties upwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixed {
+    raw: i64,
+    format: FixedFormat,
+}
+
+impl Fixed {
+    pub fn raw(&self) -> i64 {
+        self.raw
+    }
+
+    pub fn format(&self) -> FixedFormat {
+        self.format
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.raw as f64 / (self.format.fraction_bits as f64).exp2()
+    }
+
+    pub fn binary(
+        operator: BinaryOperator,
+        lhs: Fixed,
+        rhs: Fixed,
+    ) -> Result<Fixed, EvaluatorError> {
+        let format = lhs.format;
+        let (a, b) = (i128::from(lhs.raw), i128::from(rhs.raw));
+        let raw = match operator {
+            BinaryOperator::Add => a + b,
+            BinaryOperator::Subtract => a - b,
+            BinaryOperator::Multiply => round_shift(a * b, format.fraction_bits),
+            BinaryOperator::Divide => {
+                if b == 0 {
+                    return Err(EvaluatorError::new(EvaluatorErrorKind::DivisionByZero));
+                }
+                round_divide(a << format.fraction_bits, b)
+            }
+            BinaryOperator::Power => return exact::power(lhs, rhs, format),
+            BinaryOperator::Equal => return Ok(Fixed::truth(format, a == b)),
+            BinaryOperator::NotEqual => return Ok(Fixed::truth(format, a != b)),
+            BinaryOperator::Less => return Ok(Fixed::truth(format, a < b)),
+            BinaryOperator::LessEqual => return Ok(Fixed::truth(format, a <= b)),
+            BinaryOperator::Greater => return Ok(Fixed::truth(format, a > b)),
+            BinaryOperator::GreaterEqual => return Ok(Fixed::truth(format, a >= b)),
+            BinaryOperator::And => return Ok(Fixed::truth(format, a != 0 && b != 0)),
+            BinaryOperator::Or => return Ok(Fixed::truth(format, a != 0 || b != 0)),
+            BinaryOperator::PlusMinus => return Err(exact::unsupported(operator)),
+        };
+        format.fixed(raw)
+    }
+}
+
+/// `value / 2^shift` rounded to the nearest integer, ties upwards.
+fn round_shift(value: i128, shift: u32) -> i128 {
+    if shift == 0 {
+        return value;
+    }
+    (value + (1 << (shift - 1))) >> shift
+}
+
+/// `a / b` rounded to the nearest integer, ties upwards.
+fn round_divide(a: i128, b: i128) -> i128 {
+    let (a, b) = if b < 0 { (-a, -b) } else { (a, b) };
+    (2 * a + b).div_euclid(2 * b)
+}
+
+impl fmt::Display for Fixed {
+    /// Writes the exact decimal expansion, which has at most n fraction digits.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bits = self.format.fraction_bits;
+        let magnitude = i128::from(self.raw).unsigned_abs();
+        if self.raw < 0 {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", magnitude >> bits)?;
+        let mut fraction = magnitude & ((1 << bits) - 1);
+        if fraction != 0 {
+            write!(f, ".")?;
+            while fraction != 0 {
+                fraction *= 10;
+                write!(f, "{}", fraction >> bits)?;
+                fraction &= (1 << bits) - 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Expr {
+    /// Evaluates the formula in fixed point, without floating-point arithmetic besides
+    /// rounding the literals and builtin constants to `format`. Only numbers are supported:
+    /// arithmetic with whole powers, comparisons, `&&`, `||`, conditionals and the builtins
+    /// `abs` and `sqrt`.
+    pub fn evaluate_fixed(
+        &self,
+        format: FixedFormat,
+        variables: &[(&str, Fixed)],
+    ) -> Result<Fixed, EvaluatorError> {
+        exact::evaluate(self, format, variables)
+    }
+
+    /// Evaluates the formula in fixed point with the variables of `context` which are
+    /// numbers.
+    pub fn evaluate_fixed_in(
+        &self,
+        context: &Context,
+        format: FixedFormat,
+    ) -> Result<Fixed, EvaluatorError> {
+        let variables = exact::numbers(context, |_, number| format.from_f64(number))?;
+        self.evaluate_fixed(format, &variables)
+    }
+}
+
+impl Exact for Fixed {
+    type Format = FixedFormat;
+
+    fn in_format(self, format: FixedFormat) -> Result<Fixed, EvaluatorError> {
+        if self.format != format {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                format!("Expected a number in {}, found {}", format, self.format),
+            )));
+        }
+        Ok(self)
+    }
+
+    fn from_f64(format: FixedFormat, value: f64) -> Result<Fixed, EvaluatorError> {
+        format.from_f64(value)
+    }
+
+    fn truth(format: FixedFormat, value: bool) -> Fixed {
+        Fixed {
+            raw: if value { 1 << format.fraction_bits } else { 0 },
+            format,
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        self.raw != 0
+    }
+
+    fn whole(&self) -> Option<i64> {
+        let one = 1i64 << self.format.fraction_bits;
+        (self.raw % one == 0).then_some(self.raw / one)
+    }
+
+    fn binary(operator: BinaryOperator, lhs: Fixed, rhs: Fixed) -> Result<Fixed, EvaluatorError> {
+        Fixed::binary(operator, lhs, rhs)
+    }
+
+    fn negate(self) -> Result<Fixed, EvaluatorError> {
+        self.format.fixed(-i128::from(self.raw))
+    }
+
+    fn percent(self) -> Result<Fixed, EvaluatorError> {
+        self.format.fixed(round_divide(self.raw.into(), 100))
+    }
+
+    fn abs(self) -> Result<Fixed, EvaluatorError> {
+        self.format.fixed(i128::from(self.raw).abs())
+    }
+
+    fn sqrt(self) -> Result<Fixed, EvaluatorError> {
+        if self.raw < 0 {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                String::from("Square root of a negative number"),
+            )));
+        }
+        let square = i128::from(self.raw) << self.format.fraction_bits;
+        let root = square.isqrt();
+        // The root is nearer to `root + 1` above `(root + 1/2)^2 = root^2 + root + 1/4`.
+        let rounded = if square - root * root > root {
+            root + 1
+        } else {
+            root
+        };
+        self.format.fixed(rounded)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn evaluate(format: FixedFormat, source: &str) -> Result<String, EvaluatorError> {
+        let x = format.from_int(3)?;
+        let expr = Parser::new(source).parse().unwrap();
+        Ok(expr.evaluate_fixed(format, &[("x", x)])?.to_string())
+    }
+
+    #[test]
+    fn compute_in_fixed_point() {
+        let q16_16 = FixedFormat::new(15, 16).unwrap();
+        assert_eq!(q16_16.to_string(), "Q15.16");
+        assert_eq!(evaluate(q16_16, "x * 2.5 - 1/4").unwrap(), "7.25");
+        assert_eq!(evaluate(q16_16, "1 / 3").unwrap(), "0.3333282470703125");
+        assert_eq!(evaluate(q16_16, "sqrt(2)").unwrap(), "1.414215087890625");
+        assert_eq!(evaluate(q16_16, "x^-2 * 9").unwrap(), "1.000030517578125");
+        assert_eq!(
+            evaluate(q16_16, "200 + 10% > x ? -abs(-x) : 0").unwrap(),
+            "-3"
+        );
+        assert_eq!(
+            evaluate(q16_16, "x / (x - 3)").unwrap_err().kind,
+            EvaluatorErrorKind::DivisionByZero
+        );
+        let error = evaluate(q16_16, "1 + 200^2 * x").unwrap_err();
+        assert_eq!(error.to_string(), "Arithmetic overflow");
+        assert_eq!(error.span, Some(crate::lexer::Span::new(4, 9)));
+        assert!(evaluate(q16_16, "x^0.5").is_err());
+        assert!(evaluate(q16_16, "sin(x)").is_err());
+        assert!(FixedFormat::new(32, 32).is_none());
+        let q1_62 = FixedFormat::new(1, 62).unwrap();
+        assert_eq!(q1_62.from_f64(-2.0).unwrap().raw(), i64::MIN);
+        assert!(q1_62.from_f64(2.0).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Formulas parsed once and evaluated many times with other values of their variables.
+
+use std::sync::Arc;
+
+use crate::evaluator::{Context, Evaluator, EvaluatorError};
+use crate::parser::{Expr, Parser, ParserError};
+use crate::partial;
+use crate::value::Value;
+
+/// A parsed formula and the context it is evaluated in: `CompiledExpr::new("x^2 + y")?`
+/// then `eval(&[("x", Value::Number(2.0)), ("y", Value::Number(1.0))])`. Clones share the
+/// formula and context, and are `Send` and `Sync`.
+#[derive(Debug, Clone)]
+pub struct CompiledExpr {
+    expr: Arc<Expr>,
+    context: Arc<Context>,
+    variables: Arc<[String]>,
+}
+
+impl CompiledExpr {
+    /// Parses `source` to evaluate with the builtin functions, constants and units only.
+    pub fn new(source: &str) -> Result<CompiledExpr, ParserError> {
+        CompiledExpr::with_context(source, Arc::new(Context::new()))
+    }
+
+    /// Parses `source` to evaluate in `context`, whose variables are seen unless bound.
+    pub fn with_context(source: &str, context: Arc<Context>) -> Result<CompiledExpr, ParserError> {
+        let expr = Parser::new(source).parse()?;
+        let variables = {
+            let evaluator = Evaluator::new(&context);
+            partial::free_variables(&expr)
+                .into_iter()
+                .filter(|name| evaluator.lookup(name).is_none())
+                .collect()
+        };
+        Ok(CompiledExpr {
+            expr: Arc::new(expr),
+            context,
+            variables,
+        })
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// The variables which need a binding, those the context does not know, in
+    /// alphabetical order.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Evaluates the formula with `bindings` for its variables, which may also hide those
+    /// of the context.
+    pub fn eval(&self, bindings: &[(&str, Value)]) -> Result<Value, EvaluatorError> {
+        let bindings = bindings
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        Evaluator::new(&self.context)
+            .with_locals(bindings, |evaluator| evaluator.evaluate(&self.expr))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::EvaluatorErrorKind;
+
+    #[test]
+    fn evaluate_compiled_expressions() {
+        let mut context = Context::new();
+        context.set_variable("k", Value::Number(10.0));
+        let formula =
+            CompiledExpr::with_context("k * x^2 + y + 0 * pi", Arc::new(context)).unwrap();
+        assert_eq!(formula.variables(), ["x", "y"]);
+        let copy = formula.clone();
+        let eval = |x: f64| {
+            copy.eval(&[("x", Value::Number(x)), ("y", Value::Number(1.0))])
+                .map(|value| value.to_string())
+        };
+        assert_eq!(eval(2.0), Ok(String::from("41")));
+        assert_eq!(eval(3.0), Ok(String::from("91")));
+        assert_eq!(
+            formula.eval(&[("x", Value::Number(1.0))]).unwrap_err().kind,
+            EvaluatorErrorKind::UnknownVariable(String::from("y"))
+        );
+        let formula = CompiledExpr::new("sqrt(b) + c * km").unwrap();
+        assert_eq!(formula.variables(), ["b", "c"]);
+        assert!(CompiledExpr::new("2 *").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Exact algorithms on 64-bit integers.
+
+/// `a * b mod m`, without overflow.
+pub(crate) fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(m)) as u64
+}
+
+/// `base^exponent mod m`, by repeated squaring.
+pub(crate) fn pow_mod(base: u64, mut exponent: u64, m: u64) -> u64 {
+    let mut base = base % m;
+    let mut result = 1 % m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+pub(crate) fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// The inverse of `a` modulo `m`, if `a` and `m` are coprime, by the extended Euclidean
+/// algorithm.
+pub(crate) fn inv_mod(a: u64, m: u64) -> Option<u64> {
+    let (mut r, mut next_r) = (i128::from(m), i128::from(a % m));
+    let (mut t, mut next_t) = (0i128, 1i128);
+    while next_r != 0 {
+        let quotient = r / next_r;
+        (r, next_r) = (next_r, r - quotient * next_r);
+        (t, next_t) = (next_t, t - quotient * next_t);
+    }
+    (r == 1).then(|| t.rem_euclid(i128::from(m)) as u64)
+}
+
+/// Bases of the Miller-Rabin test making it exact below 2^64.
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+pub(crate) fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let shift = (n - 1).trailing_zeros();
+    let odd = (n - 1) >> shift;
+    'witness: for a in WITNESSES {
+        let mut x = pow_mod(a, odd, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..shift {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// The smallest prime above `n`, if it fits in 64 bits.
+pub(crate) fn next_prime(n: u64) -> Option<u64> {
+    (n.checked_add(1)?..=u64::MAX).find(|&candidate| is_prime(candidate))
+}
+
+/// The largest prime below `n`, if any.
+pub(crate) fn prev_prime(n: u64) -> Option<u64> {
+    (2..n).rev().find(|&candidate| is_prime(candidate))
+}
+
+/// The prime factors of `n` in increasing order, repeated by multiplicity. Empty for 0
+/// and 1.
+pub(crate) fn factorize(mut n: u64) -> Vec<u64> {
+    let mut factors = vec![];
+    if n == 0 {
+        return factors;
+    }
+    for p in WITNESSES {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+    }
+    split(n, &mut factors);
+    factors.sort_unstable();
+    factors
+}
+
+/// Pushes the prime factors of `n`, which has no factor below 41.
+fn split(n: u64, factors: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n) {
+        factors.push(n);
+        return;
+    }
+    let divisor = pollard_rho(n);
+    split(divisor, factors);
+    split(n / divisor, factors);
+}
+
+/// A nontrivial divisor of the composite `n`, by Pollard's rho method.
+fn pollard_rho(n: u64) -> u64 {
+    let mut increment = 1;
+    loop {
+        let step = |x: u64| ((u128::from(x) * u128::from(x) + increment) % u128::from(n)) as u64;
+        let (mut x, mut y, mut divisor) = (2, 2, 1);
+        while divisor == 1 {
+            x = step(x);
+            y = step(step(y));
+            divisor = gcd(x.abs_diff(y), n);
+        }
+        if divisor != n {
+            return divisor;
+        }
+        increment += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn find_and_factor_primes() {
+        let primes: Vec<u64> = (0..30).filter(|&n| is_prime(n)).collect();
+        assert_eq!(primes, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert!(is_prime(18446744073709551557));
+        assert!(!is_prime(3215031751));
+        assert_eq!(next_prime(13), Some(17));
+        assert_eq!(next_prime(u64::MAX), None);
+        assert_eq!(prev_prime(13), Some(11));
+        assert_eq!(prev_prime(2), None);
+        assert_eq!(factorize(360), [2, 2, 2, 3, 3, 5]);
+        assert_eq!(factorize(1), []);
+        assert_eq!(
+            factorize(600851475143 * 1000003),
+            [71, 839, 1471, 6857, 1000003]
+        );
+        assert_eq!(factorize(4294967291 * 4294967279), [4294967279, 4294967291]);
+    }
+
+    #[test]
+    fn compute_modular_powers_and_inverses() {
+        assert_eq!(pow_mod(4, 13, 497), 445);
+        assert_eq!(pow_mod(2, 0, 1), 0);
+        assert_eq!(pow_mod(u64::MAX - 1, u64::MAX, u64::MAX), u64::MAX - 1);
+        assert_eq!(inv_mod(3, 11), Some(4));
+        assert_eq!(inv_mod(10, 17), Some(12));
+        assert_eq!(inv_mod(6, 9), None);
+        assert_eq!(inv_mod(1, 1), Some(0));
+    }
+}
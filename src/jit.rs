@@ -0,0 +1,248 @@
+//! Native code for numeric programs on x86-64 Linux, enabled by the `jit` feature.
+//!
+//! Only straight-line arithmetic is compiled (constants, inputs, temporaries, negation,
+//! percent and the four operations); any other program is left to the stack machine.
+//! Every stack slot of the program has a fixed address in a scratch buffer, since the
+//! stack depth of each instruction is known when compiling, and the temporaries follow the
+//! stack.
+
+use core::ffi::c_void;
+
+use crate::bytecode::{Instruction, Program};
+use crate::parser::BinaryOperator;
+
+const PROT_READ: i32 = 1;
+const PROT_WRITE: i32 = 2;
+const PROT_EXEC: i32 = 4;
+const MAP_PRIVATE: i32 = 2;
+const MAP_ANONYMOUS: i32 = 0x20;
+
+extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: i32, flags: i32, fd: i32, off: i64)
+        -> *mut c_void;
+    fn mprotect(addr: *mut c_void, len: usize, prot: i32) -> i32;
+    fn munmap(addr: *mut c_void, len: usize) -> i32;
+}
+
+/// Status returned by the native code, the result being left in the first stack slot.
+const STATUS_OK: i32 = 0;
+const STATUS_DIVISION_BY_ZERO: i32 = 1;
+
+type NativeFunction = unsafe extern "C" fn(inputs: *const f64, stack: *mut f64) -> i32;
+
+/// Executable code compiled from a program.
+#[derive(Debug)]
+pub struct Native {
+    code: *mut c_void,
+    len: usize,
+    stack_size: usize,
+    /// Number of inputs the code reads, one more than the largest index.
+    inputs: usize,
+}
+
+// The code is never written after it is made executable.
+unsafe impl Send for Native {}
+unsafe impl Sync for Native {}
+
+impl Native {
+    /// Compiles `program`, or returns `None` if it uses unsupported instructions.
+    pub fn compile(program: &Program) -> Option<Native> {
+        let mut assembler = Assembler::default();
+        let mut depth = 0usize;
+        let temporaries = program.max_stack();
+        let mut max_depth = temporaries + program.temporaries();
+        let mut inputs = 0;
+        for instruction in program.instructions() {
+            match *instruction {
+                Instruction::Constant(value) => {
+                    assembler.mov_rax_imm(value.to_bits());
+                    assembler.store_rax(depth);
+                    depth += 1;
+                }
+                Instruction::Input(index) => {
+                    inputs = inputs.max(index + 1);
+                    assembler.load_rax_input(index);
+                    assembler.store_rax(depth);
+                    depth += 1;
+                }
+                Instruction::Store(index) => {
+                    assembler.load_rax(depth - 1);
+                    assembler.store_rax(temporaries + index);
+                }
+                Instruction::Load(index) => {
+                    assembler.load_rax(temporaries + index);
+                    assembler.store_rax(depth);
+                    depth += 1;
+                }
+                Instruction::Negate => {
+                    assembler.load_rax(depth - 1);
+                    // btc rax, 63
+                    assembler.emit(&[0x48, 0x0F, 0xBA, 0xF8, 0x3F]);
+                    assembler.store_rax(depth - 1);
+                }
+                Instruction::Percent => {
+                    assembler.load_xmm(0, depth - 1);
+                    assembler.mov_rax_imm(100f64.to_bits());
+                    // movq xmm1, rax
+                    assembler.emit(&[0x66, 0x48, 0x0F, 0x6E, 0xC8]);
+                    assembler.arithmetic(0x5E);
+                    assembler.store_xmm0(depth - 1);
+                }
+                Instruction::Binary(operator) => {
+                    let opcode = match operator {
+                        BinaryOperator::Add => 0x58,
+                        BinaryOperator::Subtract => 0x5C,
+                        BinaryOperator::Multiply => 0x59,
+                        BinaryOperator::Divide => 0x5E,
+                        _ => return None,
+                    };
+                    assembler.load_xmm(0, depth - 2);
+                    assembler.load_xmm(1, depth - 1);
+                    if operator == BinaryOperator::Divide {
+                        assembler.check_nonzero_xmm1();
+                    }
+                    assembler.arithmetic(opcode);
+                    assembler.store_xmm0(depth - 2);
+                    depth -= 1;
+                }
+                _ => return None,
+            }
+            max_depth = max_depth.max(depth);
+        }
+        if depth != 1 {
+            return None;
+        }
+        // xor eax, eax; ret
+        assembler.emit(&[0x31, 0xC0, 0xC3]);
+        Native::map(&assembler.code, max_depth, inputs)
+    }
+
+    fn map(code: &[u8], stack_size: usize, inputs: usize) -> Option<Native> {
+        let len = code.len();
+        // SAFETY: a fresh private anonymous mapping, written before being made executable
+        // and never written again.
+        unsafe {
+            let memory = mmap(
+                core::ptr::null_mut(),
+                len,
+                PROT_READ | PROT_WRITE,
+                MAP_PRIVATE | MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            if memory as isize == -1 {
+                return None;
+            }
+            core::ptr::copy_nonoverlapping(code.as_ptr(), memory as *mut u8, len);
+            if mprotect(memory, len, PROT_READ | PROT_EXEC) != 0 {
+                munmap(memory, len);
+                return None;
+            }
+            Some(Native {
+                code: memory,
+                len,
+                stack_size,
+                inputs,
+            })
+        }
+    }
+
+    /// Number of values of the scratch stack needed by `run`.
+    pub fn stack_size(&self) -> usize {
+        self.stack_size
+    }
+
+    /// Runs the code on `inputs`, which must have as many values as the program has
+    /// inputs. Returns `None` on division by zero.
+    pub fn run(&self, inputs: &[f64], stack: &mut [f64]) -> Option<f64> {
+        assert!(inputs.len() >= self.inputs);
+        assert!(stack.len() >= self.stack_size);
+        // SAFETY: the code only reads the inputs indexed by the program, which `inputs`
+        // bounds, and writes the stack slots below its maximum depth, which `stack_size`
+        // bounds.
+        let status = unsafe {
+            let function: NativeFunction = core::mem::transmute(self.code);
+            function(inputs.as_ptr(), stack.as_mut_ptr())
+        };
+        match status {
+            STATUS_OK => Some(stack[0]),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for Native {
+    fn drop(&mut self) {
+        // SAFETY: the mapping was created by `map` and is not used after this.
+        unsafe {
+            munmap(self.code, self.len);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Assembler {
+    code: Vec<u8>,
+}
+
+impl Assembler {
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn displacement(&mut self, slot: usize) {
+        self.emit(&((slot * 8) as i32).to_le_bytes());
+    }
+
+    fn mov_rax_imm(&mut self, bits: u64) {
+        self.emit(&[0x48, 0xB8]);
+        self.emit(&bits.to_le_bytes());
+    }
+
+    /// mov rax, [rdi + 8 * index]
+    fn load_rax_input(&mut self, index: usize) {
+        self.emit(&[0x48, 0x8B, 0x87]);
+        self.displacement(index);
+    }
+
+    /// mov rax, [rsi + 8 * slot]
+    fn load_rax(&mut self, slot: usize) {
+        self.emit(&[0x48, 0x8B, 0x86]);
+        self.displacement(slot);
+    }
+
+    /// mov [rsi + 8 * slot], rax
+    fn store_rax(&mut self, slot: usize) {
+        self.emit(&[0x48, 0x89, 0x86]);
+        self.displacement(slot);
+    }
+
+    /// movsd xmm0 or xmm1, [rsi + 8 * slot]
+    fn load_xmm(&mut self, register: u8, slot: usize) {
+        self.emit(&[0xF2, 0x0F, 0x10, 0x86 | (register << 3)]);
+        self.displacement(slot);
+    }
+
+    /// movsd [rsi + 8 * slot], xmm0
+    fn store_xmm0(&mut self, slot: usize) {
+        self.emit(&[0xF2, 0x0F, 0x11, 0x86]);
+        self.displacement(slot);
+    }
+
+    /// addsd, subsd, mulsd or divsd xmm0, xmm1
+    fn arithmetic(&mut self, opcode: u8) {
+        self.emit(&[0xF2, 0x0F, opcode, 0xC1]);
+    }
+
+    /// Returns the division by zero status if xmm1 is zero, as the stack machine does.
+    fn check_nonzero_xmm1(&mut self) {
+        // xorpd xmm2, xmm2; ucomisd xmm1, xmm2
+        self.emit(&[0x66, 0x0F, 0x57, 0xD2, 0x66, 0x0F, 0x2E, 0xCA]);
+        // jp +8 (NaN is not zero); jne +6
+        self.emit(&[0x7A, 0x08, 0x75, 0x06]);
+        // mov eax, STATUS_DIVISION_BY_ZERO; ret
+        self.emit(&[0xB8]);
+        self.emit(&STATUS_DIVISION_BY_ZERO.to_le_bytes());
+        self.emit(&[0xC3]);
+    }
+}
@@ -1,36 +1,86 @@
 use core::fmt;
 use std::error::Error;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Token {
     Number(Vec<u8>),
+    Identifier(Vec<u8>),
+    String(Vec<u8>),
     Operator(Vec<u8>),
     OpenParenthesis,
     ClosedParenthesis,
+    Comma,
 }
 
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(content) | Token::Identifier(content) | Token::Operator(content) => {
+                write!(f, "{}", String::from_utf8_lossy(content))
+            }
+            Token::String(content) => write!(f, "\"{}\"", String::from_utf8_lossy(content)),
+            Token::OpenParenthesis => write!(f, "("),
+            Token::ClosedParenthesis => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+const MULTI_CHAR_OPERATORS: [&[u8]; 7] = [b"->", b"==", b"!=", b"<=", b">=", b"&&", b"||"];
+
+/// `±` in UTF-8, also written `+/-`.
+const PLUS_MINUS: &[u8] = "±".as_bytes();
+
 trait CheckableChar {
     fn is_ascii_operator(&self) -> bool;
+    fn is_identifier_start(&self) -> bool;
+    fn is_identifier_continue(&self) -> bool;
 }
 
 impl CheckableChar for u8 {
     fn is_ascii_operator(&self) -> bool {
-        *self == b'+' || *self == b'-' || *self == b'*' || *self == b'/'
+        b"+-*/^%=<>!&|?:".contains(self)
+    }
+
+    fn is_identifier_start(&self) -> bool {
+        self.is_ascii_alphabetic() || *self == b'_'
+    }
+
+    fn is_identifier_continue(&self) -> bool {
+        self.is_ascii_alphanumeric() || *self == b'_'
     }
 }
 
-#[derive(Debug)]
-pub struct LexerError {}
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexerError {
+    pub span: Span,
+}
 
 impl LexerError {
-    fn new() -> LexerError {
-        LexerError {}
+    fn new(span: Span) -> LexerError {
+        LexerError { span }
     }
 }
 
 impl fmt::Display for LexerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Lexer error encountered")
+        write!(f, "Unexpected character at position {}", self.span.start)
     }
 }
 impl Error for LexerError {}
@@ -38,9 +88,11 @@ impl Error for LexerError {}
 pub trait LexerString {
     fn get_next_char(&self) -> u8;
     fn get_current_char(&self) -> u8;
+    fn peek_char(&self, offset: usize) -> u8;
     fn shift_chars(&mut self);
     fn consume_char_type(&mut self, char_type: fn(&u8) -> bool) -> Vec<u8>;
     fn skip_whitespace(&mut self) -> bool;
+    fn position(&self) -> usize;
     fn eof(&self) -> bool;
 }
 
@@ -65,6 +117,13 @@ impl LexerString for VecLexerString {
         self.string[self.current_char]
     }
 
+    fn peek_char(&self, offset: usize) -> u8 {
+        match self.string.get(self.current_char + offset) {
+            Some(char) => *char,
+            None => b'\0',
+        }
+    }
+
     fn shift_chars(&mut self) {
         self.current_char += 1;
         self.next_char += 1;
@@ -88,8 +147,12 @@ impl LexerString for VecLexerString {
         skipped
     }
 
+    fn position(&self) -> usize {
+        self.current_char
+    }
+
     fn eof(&self) -> bool {
-        self.current_char + 1 >= self.string.len()
+        self.current_char >= self.string.len()
     }
 }
 pub struct Lexer<T: LexerString> {
@@ -97,23 +160,109 @@ pub struct Lexer<T: LexerString> {
 }
 
 impl<T: LexerString> Lexer<T> {
+    pub fn from_string(string: T) -> Lexer<T> {
+        Lexer { string }
+    }
+
     pub fn next_token(&mut self) -> Result<Option<Token>, LexerError> {
+        Ok(self.next_spanned_token()?.map(|(token, _)| token))
+    }
+
+    pub fn next_spanned_token(&mut self) -> Result<Option<(Token, Span)>, LexerError> {
         self.string.skip_whitespace();
+        if self.string.eof() {
+            return Ok(None);
+        }
+        let start = self.string.position();
         let mut content: Vec<u8> = vec![];
-        let mut token: Option<Token> = None;
-        if self.string.get_current_char().is_ascii_digit() {
-            content.extend(self.string.consume_char_type(u8::is_ascii_digit));
-            token = Some(Token::Number(content));
-        } else if self.string.get_current_char().is_ascii_alphabetic() {
-            content.extend(self.string.consume_char_type(u8::is_ascii_alphabetic));
-        } else if self.string.get_current_char().is_ascii_operator() {
-            content.push(self.string.get_current_char());
-            token = Some(Token::Operator(content));
+        let token;
+        let current = self.string.get_current_char();
+        if current.is_ascii_digit()
+            || (current == b'.' && self.string.get_next_char().is_ascii_digit())
+        {
+            self.consume_number(&mut content);
+            token = Token::Number(content);
+        } else if current == b'"' {
+            self.string.shift_chars();
+            content.extend(
+                self.string
+                    .consume_char_type(|char| *char != b'"' && *char != b'\0'),
+            );
+            if self.string.eof() {
+                return Err(LexerError::new(Span::new(start, self.string.position())));
+            }
+            self.string.shift_chars();
+            token = Token::String(content);
+        } else if current.is_identifier_start() {
+            content.extend(self.string.consume_char_type(u8::is_identifier_continue));
+            token = Token::Identifier(content);
+        } else if current == b'(' {
+            self.string.shift_chars();
+            token = Token::OpenParenthesis;
+        } else if current == b')' {
+            self.string.shift_chars();
+            token = Token::ClosedParenthesis;
+        } else if current == b',' {
+            self.string.shift_chars();
+            token = Token::Comma;
+        } else if current.is_ascii_operator() {
+            content.push(current);
+            self.string.shift_chars();
+            let next = self.string.get_current_char();
+            if MULTI_CHAR_OPERATORS.contains(&[current, next].as_slice()) {
+                content.push(next);
+                self.string.shift_chars();
+            } else if current == b'+' && next == b'/' && self.string.get_next_char() == b'-' {
+                content = PLUS_MINUS.to_vec();
+                self.string.shift_chars();
+                self.string.shift_chars();
+            }
+            token = Token::Operator(content);
+        } else if current == PLUS_MINUS[0] && self.string.get_next_char() == PLUS_MINUS[1] {
+            self.string.shift_chars();
             self.string.shift_chars();
+            token = Token::Operator(PLUS_MINUS.to_vec());
         } else {
-            return Err(LexerError::new());
+            return Err(LexerError::new(Span::new(start, start + 1)));
+        }
+        Ok(Some((token, Span::new(start, self.string.position()))))
+    }
+
+    fn consume_number(&mut self, content: &mut Vec<u8>) {
+        let radix = match self.string.get_next_char() {
+            b'x' | b'X' => 16,
+            b'o' | b'O' => 8,
+            b'b' | b'B' => 2,
+            _ => 0,
+        };
+        let is_prefixed = self.string.get_current_char() == b'0'
+            && radix != 0
+            && (self.string.peek_char(2) as char).is_digit(radix);
+        if is_prefixed {
+            // Radix literals such as `0xff`; the parser validates the digits.
+            content.extend(self.string.consume_char_type(u8::is_identifier_continue));
+            return;
+        }
+        content.extend(self.string.consume_char_type(u8::is_ascii_digit));
+        if self.string.get_current_char() == b'.' && self.string.get_next_char().is_ascii_digit() {
+            content.push(b'.');
+            self.string.shift_chars();
+            content.extend(self.string.consume_char_type(u8::is_ascii_digit));
+        }
+        let current = self.string.get_current_char();
+        let next = self.string.get_next_char();
+        let has_exponent = (current == b'e' || current == b'E')
+            && (next.is_ascii_digit()
+                || ((next == b'+' || next == b'-') && self.string.peek_char(2).is_ascii_digit()));
+        if has_exponent {
+            content.push(current);
+            self.string.shift_chars();
+            if !next.is_ascii_digit() {
+                content.push(next);
+                self.string.shift_chars();
+            }
+            content.extend(self.string.consume_char_type(u8::is_ascii_digit));
         }
-        Ok(token)
     }
 
     pub fn eof(&self) -> bool {
@@ -164,4 +313,47 @@ mod test {
             ]
         )
     }
+
+    #[test]
+    fn parse_identifiers_calls_and_lambdas() {
+        let mut lexer = Lexer::new("integrate(x -> 2.5e-1*x^2, 0, 1)");
+        let mut tokens: Vec<(Token, Span)> = vec![];
+        while let Some(token) = lexer.next_spanned_token().unwrap() {
+            tokens.push(token);
+        }
+        assert_eq!(
+            tokens[0],
+            (Token::Identifier(Vec::from(b"integrate")), Span::new(0, 9))
+        );
+        assert_eq!(tokens[1].0, Token::OpenParenthesis);
+        assert_eq!(
+            tokens[3],
+            (Token::Operator(Vec::from(b"->")), Span::new(12, 14))
+        );
+        assert_eq!(tokens[4].0, Token::Number(Vec::from(b"2.5e-1")));
+        assert_eq!(tokens[9].0, Token::Comma);
+        assert_eq!(tokens.last().unwrap().0, Token::ClosedParenthesis);
+    }
+
+    #[test]
+    fn parse_strings_and_radix_literals() {
+        let mut lexer = Lexer::new("frombase(\"ff\", 16) + 0x1F - 0b2");
+        let mut tokens: Vec<Token> = vec![];
+        while let Some(token) = lexer.next_token().unwrap() {
+            tokens.push(token);
+        }
+        assert_eq!(tokens[2], Token::String(Vec::from(b"ff")));
+        assert_eq!(tokens[7], Token::Number(Vec::from(b"0x1F")));
+        assert_eq!(tokens[9], Token::Number(Vec::from(b"0")));
+        assert_eq!(tokens[10], Token::Identifier(Vec::from(b"b2")));
+        let mut lexer = Lexer::new("\"open");
+        assert_eq!(lexer.next_token().unwrap_err().span, Span::new(0, 5));
+    }
+
+    #[test]
+    fn reject_unknown_characters() {
+        let mut lexer = Lexer::new("1 $ 2");
+        assert!(lexer.next_token().unwrap().is_some());
+        assert_eq!(lexer.next_token().unwrap_err().span, Span::new(2, 3));
+    }
 }
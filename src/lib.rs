@@ -1,5 +1,158 @@
+//! The `std` feature, on by default, enables everything but `const_eval` and the
+//! `embedded` module, which build with `core` alone for targets without an allocator.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+mod builtins;
+#[cfg(feature = "std")]
+mod bytecode;
+#[cfg(feature = "std")]
+mod cache;
+#[cfg(feature = "std")]
+mod calculator;
+#[cfg(feature = "std")]
+mod catalog;
+#[cfg(feature = "std")]
+mod classify;
+mod constant;
+#[cfg(feature = "std")]
+mod cost;
+#[cfg(feature = "std")]
+mod currency;
+#[cfg(feature = "std")]
+mod date;
+#[cfg(feature = "std")]
+mod diagnostic;
+#[cfg(feature = "std")]
+mod display;
+#[cfg(feature = "std")]
+mod document;
+pub mod embedded;
+#[cfg(feature = "std")]
+mod equivalence;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "std")]
+mod evaluator;
+#[cfg(feature = "std")]
+mod exact;
+#[cfg(feature = "std")]
+mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+mod fixed;
+#[cfg(feature = "std")]
+mod formula;
+#[cfg(feature = "std")]
+mod integer;
+#[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+mod jit;
+#[cfg(feature = "std")]
 mod lexer;
-pub use lexer::{Lexer, LexerString, VecLexerString, LexerError};
+#[cfg(feature = "std")]
+mod limit;
+#[cfg(feature = "std")]
+mod money;
+#[cfg(feature = "std")]
+pub mod numeric;
+#[cfg(feature = "std")]
+mod observe;
+#[cfg(feature = "std")]
+mod parser;
+#[cfg(feature = "std")]
+mod partial;
+#[cfg(feature = "std")]
+mod polynomial;
+#[cfg(feature = "std")]
+mod profile;
+#[cfg(feature = "std")]
+mod radix;
+#[cfg(feature = "std")]
+mod rewrite;
+#[cfg(feature = "std")]
+mod rounding;
+#[cfg(feature = "std")]
+mod spelling;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
+mod suggest;
+#[cfg(feature = "std")]
+mod symbolic;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "std")]
+mod trace;
+#[cfg(feature = "std")]
+mod uncertain;
+#[cfg(feature = "std")]
+mod units;
+#[cfg(feature = "std")]
+mod value;
+#[cfg(feature = "std")]
+pub use bytecode::{CompileOptions, Compiled, Instruction, Program};
+#[cfg(feature = "std")]
+pub use cache::{ExpressionCache, DEFAULT_CACHE_CAPACITY};
+#[cfg(feature = "std")]
+pub use calculator::{Backend, Calculator, CalculatorBuilder};
+#[cfg(feature = "std")]
+pub use catalog::{Catalog, Message, Templates};
+#[cfg(feature = "std")]
+pub use classify::TokenClass;
+pub use constant::{const_eval, try_const_eval, ConstEvalError, ConstEvalErrorKind};
+#[cfg(feature = "std")]
+pub use currency::ExchangeRates;
+#[cfg(feature = "std")]
+pub use date::Date;
+#[cfg(feature = "std")]
+pub use diagnostic::Diagnostic;
+#[cfg(feature = "std")]
+pub use display::{DisplayOptions, Notation};
+#[cfg(feature = "std")]
+pub use document::{Document, Statement};
+#[cfg(feature = "std")]
+pub use error::Error;
+#[cfg(feature = "std")]
+pub use evaluator::{
+    eval, eval_with, AngleUnit, Context, Evaluator, EvaluatorError, EvaluatorErrorKind, Limit,
+    Limits, NonFinite, MAX_WORD_SIZE,
+};
+#[cfg(feature = "std")]
+pub use explain::explain;
+#[cfg(feature = "std")]
+pub use fixed::{Fixed, FixedFormat};
+#[cfg(feature = "std")]
+pub use formula::CompiledExpr;
+#[cfg(feature = "std")]
+pub use lexer::{Lexer, LexerError, LexerString, Span, Token, VecLexerString};
+#[cfg(feature = "std")]
+pub use limit::Approach;
+#[cfg(feature = "std")]
+pub use money::{Money, DEFAULT_SCALE, MAX_SCALE};
+#[cfg(feature = "tracing")]
+pub use observe::{Event, Observer, Stage};
+#[cfg(feature = "std")]
+pub use parser::{
+    BinaryOperator, Expr, ExprKind, Parser, ParserError, ParserErrorKind, UnaryOperator,
+};
+#[cfg(feature = "std")]
+pub use profile::{Profile, ProfileEntry};
+#[cfg(feature = "std")]
+pub use radix::Radix;
+#[cfg(feature = "std")]
+pub use rewrite::{Rule, RuleSet};
+#[cfg(feature = "std")]
+pub use stream::{Evaluated, Stream};
+#[cfg(feature = "std")]
+pub use suggest::Suggestion;
+#[cfg(feature = "std")]
+pub use uncertain::Uncertain;
+#[cfg(feature = "std")]
+pub use units::{Dimension, NamedUnit, Quantity, Unit, UnitDefinitionError, UnitTable};
+#[cfg(feature = "std")]
+pub use value::{Function, Value};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,245 @@
+//! Limits of formulas of one variable.
+
+use std::ops::RangeInclusive;
+
+use crate::evaluator::{Context, Evaluator, EvaluatorError, EvaluatorErrorKind};
+use crate::parser::{BinaryOperator, Expr, ExprKind};
+use crate::polynomial;
+use crate::value::Value;
+
+/// How many times in a row l'Hôpital's rule may be applied.
+const MAX_DERIVATIONS: usize = 4;
+/// Powers of ten of the distances from the point, relative to its magnitude, or of the
+/// points themselves for infinite limits, at which limits are estimated numerically.
+const STEPS: RangeInclusive<i32> = 3..=10;
+/// Difference between successive estimates, relative to their magnitude if above 1, within
+/// which they have converged. Converged estimates below it relative to the largest sample
+/// are taken as 0, the samples shrinking towards it.
+const TOLERANCE: f64 = 1e-6;
+
+/// The side from which `Expr::limit` approaches the point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Approach {
+    /// From both sides, whose limits must agree.
+    #[default]
+    Both,
+    /// From below.
+    Left,
+    /// From above.
+    Right,
+}
+
+impl Expr {
+    /// The limit as `variable` tends to `at`, which may be infinite, evaluating the other
+    /// variables in `context`. Rational functions are solved exactly and quotients tending
+    /// to `0/0` or `inf/inf` by l'Hôpital's rule. Other formulas are evaluated ever closer
+    /// to the point, and fail with "Cannot determine" unless the values settle or diverge.
+    pub fn limit(
+        &self,
+        context: &Context,
+        variable: &str,
+        at: f64,
+        approach: Approach,
+    ) -> Result<f64, EvaluatorError> {
+        // The signs of the offsets from the point.
+        let sides: &[f64] = match approach {
+            _ if at == f64::INFINITY => &[-1.0],
+            _ if at == f64::NEG_INFINITY => &[1.0],
+            Approach::Both => &[-1.0, 1.0],
+            Approach::Left => &[-1.0],
+            Approach::Right => &[1.0],
+        };
+        let mut evaluator = Evaluator::new(context);
+        let mut limits = vec![];
+        for side in sides {
+            let limit = one_sided(self, &mut evaluator, variable, at, *side, 0)?;
+            limits.push(limit);
+        }
+        if let [below, above] = limits[..] {
+            if !close(below, above) {
+                return Err(
+                    EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+                        "The limit of {} as {} -> {} does not exist: it is {} from the left \
+                         and {} from the right",
+                        self, variable, at, below, above
+                    )))
+                    .or_span(self.span),
+                );
+            }
+        }
+        Ok(limits[0])
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    a == b
+        || a.is_finite()
+            && b.is_finite()
+            && (a - b).abs() <= TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+fn one_sided(
+    expr: &Expr,
+    evaluator: &mut Evaluator,
+    variable: &str,
+    at: f64,
+    side: f64,
+    derivations: usize,
+) -> Result<f64, EvaluatorError> {
+    if let Some((below, above)) = polynomial::rational_limits(expr, variable, at) {
+        return Ok(if side < 0.0 { below } else { above });
+    }
+    if let ExprKind::Binary(BinaryOperator::Divide, numerator, denominator) = &expr.kind {
+        let limits = (
+            one_sided(numerator, evaluator, variable, at, side, derivations),
+            one_sided(denominator, evaluator, variable, at, side, derivations),
+        );
+        if let (Ok(numerator_limit), Ok(denominator_limit)) = limits {
+            let indeterminate = numerator_limit == 0.0 && denominator_limit == 0.0
+                || numerator_limit.is_infinite() && denominator_limit.is_infinite();
+            if !indeterminate && denominator_limit != 0.0 {
+                return Ok(numerator_limit / denominator_limit);
+            }
+            if indeterminate && derivations < MAX_DERIVATIONS {
+                if let (Ok(numerator), Ok(denominator)) =
+                    (numerator.derive(variable), denominator.derive(variable))
+                {
+                    let quotient = Expr::new(
+                        ExprKind::Binary(
+                            BinaryOperator::Divide,
+                            Box::new(numerator),
+                            Box::new(denominator),
+                        ),
+                        expr.span,
+                    );
+                    let limit =
+                        one_sided(&quotient, evaluator, variable, at, side, derivations + 1);
+                    if limit.is_ok() {
+                        return limit;
+                    }
+                }
+            }
+        }
+    }
+    numeric(expr, evaluator, variable, at, side)
+}
+
+/// Estimates the limit from the values at points approaching `at`: the first three
+/// successive values agreeing, or a divergence of the last three.
+fn numeric(
+    expr: &Expr,
+    evaluator: &mut Evaluator,
+    variable: &str,
+    at: f64,
+    side: f64,
+) -> Result<f64, EvaluatorError> {
+    let mut estimates = vec![];
+    for step in STEPS {
+        let x = if at.is_infinite() {
+            -side * 10f64.powi(step)
+        } else {
+            at + side * at.abs().max(1.0) * 10f64.powi(-step)
+        };
+        let binding = vec![(variable.to_string(), Value::Number(x))];
+        let value = evaluator.with_locals(binding, |evaluator| evaluator.evaluate(expr));
+        estimates.push(
+            value
+                .and_then(|value| value.as_number())
+                .unwrap_or(f64::NAN),
+        );
+    }
+    for window in estimates.windows(3) {
+        if window.iter().all(|value| value.is_finite())
+            && close(window[0], window[1])
+            && close(window[1], window[2])
+        {
+            let estimate = window[2];
+            // Continuous formulas have their exact value at the point.
+            if at.is_finite() {
+                let binding = vec![(variable.to_string(), Value::Number(at))];
+                let value = evaluator
+                    .with_locals(binding, |evaluator| evaluator.evaluate(expr))
+                    .and_then(|value| value.as_number());
+                if let Ok(value) = value {
+                    if value.is_finite() && close(value, estimate) {
+                        return Ok(value);
+                    }
+                }
+            }
+            let largest = estimates
+                .iter()
+                .filter(|value| value.is_finite())
+                .fold(0f64, |largest, value| largest.max(value.abs()));
+            return Ok(if estimate.abs() < TOLERANCE * largest {
+                0.0
+            } else {
+                estimate
+            });
+        }
+    }
+    if let [a, b, c] = estimates[estimates.len() - 3..] {
+        if a.is_infinite() && a == b && b == c {
+            return Ok(c);
+        }
+        // Growing in magnitude without slowing down.
+        let growing = a.signum() == c.signum()
+            && a.abs() < b.abs()
+            && b.abs() < c.abs()
+            && (c - b).abs() >= (b - a).abs() / 2.0;
+        if growing {
+            return Ok(c.signum() * f64::INFINITY);
+        }
+    }
+    let mut point = at.to_string();
+    if at.is_finite() {
+        point.push(if side < 0.0 { '-' } else { '+' });
+    }
+    Err(
+        EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+            "Cannot determine the limit of {} as {} -> {}",
+            expr, variable, point
+        )))
+        .or_span(expr.span),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn limit(source: &str, at: f64, approach: Approach) -> Result<f64, EvaluatorError> {
+        let expr = Parser::new(source).parse().unwrap();
+        expr.limit(&Context::new(), "x", at, approach)
+    }
+
+    #[test]
+    fn find_limits() {
+        let inf = f64::INFINITY;
+        assert_eq!(
+            limit("(x^2 - 1)/(x - 1)", 1.0, Approach::Both).unwrap(),
+            2.0
+        );
+        assert_eq!(
+            limit("(2*x^2 + 1)/(3 - x^2)", inf, Approach::Both).unwrap(),
+            -2.0
+        );
+        assert_eq!(limit("x^3 / (x + 1)", -inf, Approach::Both).unwrap(), inf);
+        assert_eq!(limit("1/x", 0.0, Approach::Right).unwrap(), inf);
+        assert_eq!(limit("1/x", 0.0, Approach::Left).unwrap(), -inf);
+        assert_eq!(limit("1/x^2", 0.0, Approach::Both).unwrap(), inf);
+        assert!(limit("1/x", 0.0, Approach::Both).is_err());
+        assert_eq!(limit("sin(x)/x", 0.0, Approach::Both).unwrap(), 1.0);
+        assert_eq!(limit("(1 - cos(x))/x^2", 0.0, Approach::Both).unwrap(), 0.5);
+        assert_eq!(limit("x / exp(x)", inf, Approach::Both).unwrap(), 0.0);
+        assert_eq!(limit("x * ln(x)", 0.0, Approach::Right).unwrap(), 0.0);
+        assert_eq!(limit("ln(x)", 0.0, Approach::Right).unwrap(), -inf);
+        assert_eq!(limit("cos(x)", 1.0, Approach::Both).unwrap(), 1f64.cos());
+        let e = limit("(1 + 1/x)^x", inf, Approach::Both).unwrap();
+        assert!((e - std::f64::consts::E).abs() < 1e-6);
+        assert!(limit("sin(1/x)", 0.0, Approach::Right).is_err());
+        assert_eq!(limit("1e-7 + exp(-x)", inf, Approach::Both).unwrap(), 1e-7);
+        let small = limit("2e-9 * cos(1/x)", inf, Approach::Both).unwrap();
+        assert!((small - 2e-9).abs() < 1e-18);
+    }
+}
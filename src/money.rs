@@ -0,0 +1,369 @@
+use core::fmt;
+
+use crate::evaluator::{Context, EvaluatorError, EvaluatorErrorKind};
+use crate::exact::{self, Exact};
+use crate::parser::{BinaryOperator, Expr};
+
+/// Largest number of fractional digits of an amount. Quotients and square roots are
+/// rounded to it, as are products with more digits.
+pub const MAX_SCALE: u32 = 18;
+
+pub const DEFAULT_SCALE: u32 = 2;
+
+/// An exact decimal amount, `units / 10^scale`, for financial formulas. Sums, differences
+/// and products are exact, so results only depend on where rounding happens: in quotients
+/// and square roots, past `MAX_SCALE` digits, and in `round`. Rounding is half to even,
+/// the banker's rounding.
+#[derive(Debug, Clone, Copy)]
+pub struct Money {
+    units: i128,
+    scale: u32,
+}
+
+impl Money {
+    /// Returns `None` if `scale` exceeds `MAX_SCALE`.
+    pub fn new(units: i128, scale: u32) -> Option<Money> {
+        (scale <= MAX_SCALE).then_some(Money { units, scale })
+    }
+
+    /// Parses a decimal such as `-12.345`, rounded to `scale` digits, which must not exceed
+    /// `MAX_SCALE`.
+    pub fn parse(text: &str, scale: u32) -> Option<Money> {
+        if scale > MAX_SCALE {
+            return None;
+        }
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty()
+            || !(integer.bytes().chain(fraction.bytes())).all(|byte| byte.is_ascii_digit())
+        {
+            return None;
+        }
+        let mut units: i128 = 0;
+        for digit in integer.bytes().chain(fraction.bytes()) {
+            units = units
+                .checked_mul(10)?
+                .checked_add(i128::from(digit - b'0'))?;
+        }
+        let exact = Money {
+            units: if negative { -units } else { units },
+            scale: u32::try_from(fraction.len()).ok()?,
+        };
+        let exact = if exact.scale > MAX_SCALE {
+            exact.round(MAX_SCALE).ok()?
+        } else {
+            exact
+        };
+        exact.round(scale).ok()
+    }
+
+    /// The amount written by `value`'s shortest decimal representation, so that the
+    /// literal `2.675` is two point six seven five rather than the nearest `f64`.
+    pub fn from_f64(value: f64, scale: u32) -> Option<Money> {
+        if !value.is_finite() {
+            return None;
+        }
+        Money::parse(&value.to_string(), scale)
+    }
+
+    pub fn units(&self) -> i128 {
+        self.units
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.units as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// Rounds half to even to `scale` fractional digits, or pads with zeros.
+    pub fn round(&self, scale: u32) -> Result<Money, EvaluatorError> {
+        let units = if scale >= self.scale {
+            self.units
+                .checked_mul(power_of_ten(scale - self.scale)?)
+                .ok_or_else(overflow)?
+        } else {
+            divide_half_even(self.units, power_of_ten(self.scale - scale)?)
+        };
+        Ok(Money { units, scale })
+    }
+
+    /// Brings both amounts to the larger scale.
+    fn align(lhs: Money, rhs: Money) -> Result<(i128, i128, u32), EvaluatorError> {
+        let scale = lhs.scale.max(rhs.scale);
+        Ok((lhs.round(scale)?.units, rhs.round(scale)?.units, scale))
+    }
+
+    /// Keeps at most `MAX_SCALE` digits.
+    fn limited(units: i128, scale: u32) -> Result<Money, EvaluatorError> {
+        let money = Money { units, scale };
+        if scale > MAX_SCALE {
+            money.round(MAX_SCALE)
+        } else {
+            Ok(money)
+        }
+    }
+
+    pub fn binary(
+        operator: BinaryOperator,
+        lhs: Money,
+        rhs: Money,
+    ) -> Result<Money, EvaluatorError> {
+        let truth = |value| Ok(Money::truth((), value));
+        match operator {
+            BinaryOperator::Multiply => {
+                let units = lhs.units.checked_mul(rhs.units).ok_or_else(overflow)?;
+                return Money::limited(units, lhs.scale + rhs.scale);
+            }
+            BinaryOperator::Divide => {
+                if rhs.units == 0 {
+                    return Err(EvaluatorError::new(EvaluatorErrorKind::DivisionByZero));
+                }
+                // lhs * 10^(MAX_SCALE + rhs.scale - lhs.scale) / rhs has MAX_SCALE digits.
+                let numerator = lhs.round(MAX_SCALE + rhs.scale)?.units;
+                let units = divide_half_even(numerator, rhs.units);
+                return Ok(Money {
+                    units,
+                    scale: MAX_SCALE,
+                }
+                .trimmed());
+            }
+            BinaryOperator::Power => return exact::power(lhs, rhs, ()),
+            BinaryOperator::And => return truth(lhs.is_true() && rhs.is_true()),
+            BinaryOperator::Or => return truth(lhs.is_true() || rhs.is_true()),
+            BinaryOperator::PlusMinus => return Err(exact::unsupported(operator)),
+            _ => {}
+        }
+        let (a, b, scale) = Money::align(lhs, rhs)?;
+        let units = match operator {
+            BinaryOperator::Add => a.checked_add(b).ok_or_else(overflow)?,
+            BinaryOperator::Subtract => a.checked_sub(b).ok_or_else(overflow)?,
+            BinaryOperator::Equal => return truth(a == b),
+            BinaryOperator::NotEqual => return truth(a != b),
+            BinaryOperator::Less => return truth(a < b),
+            BinaryOperator::LessEqual => return truth(a <= b),
+            BinaryOperator::Greater => return truth(a > b),
+            BinaryOperator::GreaterEqual => return truth(a >= b),
+            _ => unreachable!("handled above"),
+        };
+        Ok(Money { units, scale })
+    }
+
+    /// Drops trailing fractional zeros.
+    fn trimmed(mut self) -> Money {
+        while self.scale > 0 && self.units % 10 == 0 {
+            self.units /= 10;
+            self.scale -= 1;
+        }
+        self
+    }
+}
+
+impl PartialEq for Money {
+    /// Compares amounts, so that `1.5` equals `1.50`.
+    fn eq(&self, other: &Money) -> bool {
+        Money::align(*self, *other).is_ok_and(|(a, b, _)| a == b)
+    }
+}
+
+fn overflow() -> EvaluatorError {
+    EvaluatorError::new(EvaluatorErrorKind::Overflow)
+}
+
+fn power_of_ten(exponent: u32) -> Result<i128, EvaluatorError> {
+    10i128.checked_pow(exponent).ok_or_else(overflow)
+}
+
+/// `a / b` rounded to the nearest integer, ties to the even one.
+fn divide_half_even(a: i128, b: i128) -> i128 {
+    let (a, b) = if b < 0 { (-a, -b) } else { (a, b) };
+    let quotient = a.div_euclid(b);
+    let twice_remainder = 2 * a.rem_euclid(b);
+    if twice_remainder > b || twice_remainder == b && quotient % 2 != 0 {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.units.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        if self.units < 0 {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", integer)?;
+        if !fraction.is_empty() {
+            write!(f, ".{}", fraction)?;
+        }
+        Ok(())
+    }
+}
+
+impl Exact for Money {
+    type Format = ();
+
+    fn in_format(self, _: ()) -> Result<Money, EvaluatorError> {
+        Ok(self)
+    }
+
+    fn from_f64(_: (), value: f64) -> Result<Money, EvaluatorError> {
+        Money::from_f64(value, MAX_SCALE)
+            .map(Money::trimmed)
+            .ok_or_else(overflow)
+    }
+
+    fn truth(_: (), value: bool) -> Money {
+        Money {
+            units: value.into(),
+            scale: 0,
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        self.units != 0
+    }
+
+    fn whole(&self) -> Option<i64> {
+        let trimmed = self.trimmed();
+        (trimmed.scale == 0)
+            .then(|| i64::try_from(trimmed.units).ok())
+            .flatten()
+    }
+
+    fn binary(operator: BinaryOperator, lhs: Money, rhs: Money) -> Result<Money, EvaluatorError> {
+        Money::binary(operator, lhs, rhs)
+    }
+
+    fn negate(self) -> Result<Money, EvaluatorError> {
+        Ok(Money {
+            units: self.units.checked_neg().ok_or_else(overflow)?,
+            scale: self.scale,
+        })
+    }
+
+    fn percent(self) -> Result<Money, EvaluatorError> {
+        Money::limited(self.units, self.scale + 2)
+    }
+
+    fn abs(self) -> Result<Money, EvaluatorError> {
+        Ok(Money {
+            units: self.units.checked_abs().ok_or_else(overflow)?,
+            scale: self.scale,
+        })
+    }
+
+    fn sqrt(self) -> Result<Money, EvaluatorError> {
+        if self.units < 0 {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                String::from("Square root of a negative number"),
+            )));
+        }
+        // The root of units * 10^(2 MAX_SCALE - scale) has MAX_SCALE digits.
+        let square = self.round(2 * MAX_SCALE - self.scale)?.units;
+        let root = square.isqrt();
+        // Squares of integers plus one half are never integers, so there are no ties.
+        let rounded = if square - root * root > root {
+            root + 1
+        } else {
+            root
+        };
+        Ok(Money {
+            units: rounded,
+            scale: MAX_SCALE,
+        }
+        .trimmed())
+    }
+}
+
+impl Expr {
+    /// Evaluates the formula with exact decimal amounts and rounds the result to `scale`
+    /// fractional digits, half to even. Literals are taken as written, and the supported
+    /// operations are those of `evaluate_fixed`.
+    pub fn evaluate_money(
+        &self,
+        scale: u32,
+        variables: &[(&str, Money)],
+    ) -> Result<Money, EvaluatorError> {
+        if scale > MAX_SCALE {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                format!("Amounts have at most {} fractional digits", MAX_SCALE),
+            )));
+        }
+        exact::evaluate(self, (), variables)?
+            .round(scale)
+            .map_err(|error| error.or_span(self.span))
+    }
+
+    /// Evaluates the formula with exact decimal amounts and the variables of `context`
+    /// which are numbers, as amounts of up to `MAX_SCALE` digits.
+    pub fn evaluate_money_in(
+        &self,
+        context: &Context,
+        scale: u32,
+    ) -> Result<Money, EvaluatorError> {
+        let variables = exact::numbers(context, |name, number| {
+            Money::from_f64(number, MAX_SCALE).ok_or_else(|| {
+                EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+                    "'{}' is not an amount",
+                    name
+                )))
+            })
+        })?;
+        self.evaluate_money(scale, &variables)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn evaluate(source: &str) -> Result<String, EvaluatorError> {
+        let price = Money::parse("19.99", DEFAULT_SCALE).unwrap();
+        let expr = Parser::new(source).parse().unwrap();
+        Ok(expr
+            .evaluate_money(DEFAULT_SCALE, &[("price", price)])?
+            .to_string())
+    }
+
+    #[test]
+    fn round_half_to_even() {
+        assert_eq!(Money::parse("0.125", 2).unwrap().to_string(), "0.12");
+        assert_eq!(Money::parse("0.135", 2).unwrap().to_string(), "0.14");
+        assert_eq!(Money::parse("-2.5", 0).unwrap().to_string(), "-2");
+        assert_eq!(Money::parse("7", 2).unwrap().to_string(), "7.00");
+        assert_eq!(Money::parse(".5", 1).unwrap().to_string(), "0.5");
+        assert_eq!(Money::from_f64(2.675, 2).unwrap().to_string(), "2.68");
+        assert!(Money::parse("1e3", 2).is_none());
+        assert!(Money::parse("-", 2).is_none());
+        assert_eq!(Money::parse("1.5", 1), Money::parse("1.50", 2));
+    }
+
+    #[test]
+    fn compute_with_amounts() {
+        assert_eq!(evaluate("0.1 + 0.2").unwrap(), "0.30");
+        assert_eq!(evaluate("price * 3 + 7.5%").unwrap(), "64.47");
+        assert_eq!(evaluate("100 / 3 * 3").unwrap(), "100.00");
+        assert_eq!(evaluate("1 / 8").unwrap(), "0.12");
+        assert_eq!(
+            evaluate("price > 20 ? 0 : -abs(price - 20)").unwrap(),
+            "-0.01"
+        );
+        assert_eq!(evaluate("1.1^2").unwrap(), "1.21");
+        assert_eq!(evaluate("sqrt(2)").unwrap(), "1.41");
+        assert_eq!(
+            evaluate("price / (price - 19.99)").unwrap_err().kind,
+            EvaluatorErrorKind::DivisionByZero
+        );
+        assert!(evaluate("2^0.5").is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! Numerical algorithms working on plain `f64` functions. The functions may fail, in which
+//! case the first error aborts the computation and is returned unchanged.
+
+pub const DEFAULT_TOLERANCE: f64 = 1e-10;
+
+const MAX_SUBDIVISIONS: usize = 1000;
+
+// Gauss-Kronrod 7-15 nodes and weights on [-1, 1]. The odd-indexed Kronrod nodes are
+// the Gauss nodes, the last node is the midpoint.
+#[allow(clippy::excessive_precision)]
+const KRONROD_NODES: [f64; 8] = [
+    0.991455371120812639206854697526329,
+    0.949107912342758524526189684047851,
+    0.864864423359769072789712788640926,
+    0.741531185599394439863864773280788,
+    0.586087235467691130294144845693013,
+    0.405845151377397166906606412076961,
+    0.207784955007898467600689403773245,
+    0.000000000000000000000000000000000,
+];
+#[allow(clippy::excessive_precision)]
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224963732008058970,
+    0.063092092629978553290700663189204,
+    0.104790010322250183839876322541518,
+    0.140653259715525918745189590510238,
+    0.169004726639267902826583426598550,
+    0.190350578064785409913256402421014,
+    0.204432940075298892414161999234649,
+    0.209482141084727828012999174891714,
+];
+#[allow(clippy::excessive_precision)]
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129484966168869693270611432679082,
+    0.279705391489276667901467771423780,
+    0.381830050505118944950369775488975,
+    0.417959183673469387755102040816327,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quadrature {
+    pub value: f64,
+    /// Estimate of the absolute error of `value`.
+    pub error: f64,
+    /// Whether the error estimate is within the requested tolerance.
+    pub converged: bool,
+}
+
+struct Interval {
+    start: f64,
+    end: f64,
+    value: f64,
+    error: f64,
+}
+
+fn gauss_kronrod<E>(
+    f: &mut impl FnMut(f64) -> Result<f64, E>,
+    start: f64,
+    end: f64,
+) -> Result<Interval, E> {
+    let center = (start + end) / 2.0;
+    let half_length = (end - start) / 2.0;
+    let f_center = f(center)?;
+    let mut kronrod = f_center * KRONROD_WEIGHTS[7];
+    let mut gauss = f_center * GAUSS_WEIGHTS[3];
+    for j in 0..7 {
+        let offset = half_length * KRONROD_NODES[j];
+        let sum = f(center - offset)? + f(center + offset)?;
+        kronrod += KRONROD_WEIGHTS[j] * sum;
+        if j % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[j / 2] * sum;
+        }
+    }
+    Ok(Interval {
+        start,
+        end,
+        value: kronrod * half_length,
+        error: ((kronrod - gauss) * half_length).abs(),
+    })
+}
+
+fn adaptive<E>(
+    mut f: impl FnMut(f64) -> Result<f64, E>,
+    start: f64,
+    end: f64,
+    tolerance: f64,
+) -> Result<Quadrature, E> {
+    let mut intervals = vec![gauss_kronrod(&mut f, start, end)?];
+    loop {
+        let value: f64 = intervals.iter().map(|interval| interval.value).sum();
+        let error: f64 = intervals.iter().map(|interval| interval.error).sum();
+        let converged = error <= tolerance * value.abs().max(1.0);
+        if converged || !error.is_finite() || intervals.len() >= MAX_SUBDIVISIONS {
+            return Ok(Quadrature {
+                value,
+                error,
+                converged,
+            });
+        }
+        let (worst, _) = intervals
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.error.total_cmp(&b.error))
+            .unwrap();
+        let interval = intervals.swap_remove(worst);
+        let middle = (interval.start + interval.end) / 2.0;
+        intervals.push(gauss_kronrod(&mut f, interval.start, middle)?);
+        intervals.push(gauss_kronrod(&mut f, middle, interval.end)?);
+    }
+}
+
+/// Integrates `f` over `[a, b]` with adaptive Gauss-Kronrod quadrature, stopping once the
+/// estimated error is below `tolerance` (relative, or absolute for results smaller than
+/// one). Infinite bounds are mapped onto a finite interval.
+pub fn integrate<E>(
+    mut f: impl FnMut(f64) -> Result<f64, E>,
+    a: f64,
+    b: f64,
+    tolerance: f64,
+) -> Result<Quadrature, E> {
+    if a > b {
+        let quadrature = integrate(f, b, a, tolerance)?;
+        return Ok(Quadrature {
+            value: -quadrature.value,
+            ..quadrature
+        });
+    }
+    if a == b {
+        return Ok(Quadrature {
+            value: 0.0,
+            error: 0.0,
+            converged: true,
+        });
+    }
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => adaptive(f, a, b, tolerance),
+        (true, false) => adaptive(
+            |t| Ok(f(a + t / (1.0 - t))? / ((1.0 - t) * (1.0 - t))),
+            0.0,
+            1.0,
+            tolerance,
+        ),
+        (false, true) => adaptive(|t| Ok(f(b - (1.0 - t) / t)? / (t * t)), 0.0, 1.0, tolerance),
+        (false, false) => adaptive(
+            |t| {
+                let denominator = 1.0 - t * t;
+                Ok(f(t / denominator)? * (1.0 + t * t) / (denominator * denominator))
+            },
+            -1.0,
+            1.0,
+            tolerance,
+        ),
+    }
+}
+
+pub const MAX_DERIVATIVE_ORDER: u32 = 6;
+
+const RIDDERS_SHRINK: f64 = 1.4;
+const RIDDERS_TABLE_SIZE: usize = 10;
+const RIDDERS_SAFETY: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Derivative {
+    pub value: f64,
+    /// Estimate of the absolute error of `value`.
+    pub error: f64,
+}
+
+/// A reasonable initial step for `differentiate` at `x0`.
+pub fn default_step(x0: f64) -> f64 {
+    0.1 * x0.abs().max(1.0)
+}
+
+/// Central finite difference of the given order, whose error is a series in `step^2`.
+fn central_difference<E>(
+    f: &mut impl FnMut(f64) -> Result<f64, E>,
+    x0: f64,
+    order: u32,
+    step: f64,
+) -> Result<f64, E> {
+    let mut sum = 0.0;
+    let mut binomial = 1.0;
+    for k in 0..=order {
+        let offset = (order as f64 / 2.0 - k as f64) * step;
+        let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+        sum += sign * binomial * f(x0 + offset)?;
+        binomial = binomial * (order - k) as f64 / (k + 1) as f64;
+    }
+    Ok(sum / step.powi(order as i32))
+}
+
+/// Computes the `order`-th derivative of `f` at `x0` with Ridders' method: central
+/// differences with shrinking steps, starting from `step`, are extrapolated to a zero step,
+/// which avoids both the truncation error of large steps and the cancellation of small ones.
+pub fn differentiate<E>(
+    mut f: impl FnMut(f64) -> Result<f64, E>,
+    x0: f64,
+    order: u32,
+    step: f64,
+) -> Result<Derivative, E> {
+    let mut step = step;
+    let mut table = [[0.0; RIDDERS_TABLE_SIZE]; RIDDERS_TABLE_SIZE];
+    table[0][0] = central_difference(&mut f, x0, order, step)?;
+    let mut best = Derivative {
+        value: table[0][0],
+        error: f64::INFINITY,
+    };
+    let shrink_squared = RIDDERS_SHRINK * RIDDERS_SHRINK;
+    for i in 1..RIDDERS_TABLE_SIZE {
+        step /= RIDDERS_SHRINK;
+        table[0][i] = central_difference(&mut f, x0, order, step)?;
+        let mut factor = shrink_squared;
+        for j in 1..=i {
+            table[j][i] = (table[j - 1][i] * factor - table[j - 1][i - 1]) / (factor - 1.0);
+            factor *= shrink_squared;
+            let error = (table[j][i] - table[j - 1][i])
+                .abs()
+                .max((table[j][i] - table[j - 1][i - 1]).abs());
+            if error <= best.error {
+                best = Derivative {
+                    value: table[j][i],
+                    error,
+                };
+            }
+        }
+        if (table[i][i] - table[i - 1][i - 1]).abs() >= RIDDERS_SAFETY * best.error {
+            break;
+        }
+    }
+    Ok(best)
+}
+
+pub const DEFAULT_ROOT_INTERVAL: (f64, f64) = (-100.0, 100.0);
+
+const ROOT_SAMPLES: usize = 2000;
+const MAX_ROOT_ITERATIONS: usize = 100;
+
+/// Refines a root of `f` inside `[a, b]`, where `f(a)` and `f(b)` have opposite signs, with
+/// Newton steps that fall back to bisection whenever they would leave the bracket.
+fn refine_bracket<E>(
+    f: &mut impl FnMut(f64) -> Result<f64, E>,
+    mut a: f64,
+    mut b: f64,
+    mut fa: f64,
+) -> Result<f64, E> {
+    let mut x = (a + b) / 2.0;
+    for _ in 0..MAX_ROOT_ITERATIONS {
+        let fx = f(x)?;
+        if fx == 0.0 {
+            return Ok(x);
+        }
+        if (fx < 0.0) == (fa < 0.0) {
+            a = x;
+            fa = fx;
+        } else {
+            b = x;
+        }
+        if (b - a).abs() <= f64::EPSILON * x.abs().max(1.0) {
+            break;
+        }
+        let h = (b - a).abs().min(default_step(x)) * 1e-3;
+        let slope = (f(x + h)? - f(x - h)?) / (2.0 * h);
+        let newton = x - fx / slope;
+        x = if newton.is_finite() && newton > a.min(b) && newton < a.max(b) {
+            newton
+        } else {
+            (a + b) / 2.0
+        };
+    }
+    Ok(x)
+}
+
+/// Plain Newton iteration started at `x`, used for roots where `f` touches zero without
+/// changing sign. Gives up as soon as it leaves `[lo, hi]`.
+fn newton<E>(
+    f: &mut impl FnMut(f64) -> Result<f64, E>,
+    mut x: f64,
+    lo: f64,
+    hi: f64,
+) -> Result<Option<f64>, E> {
+    for _ in 0..MAX_ROOT_ITERATIONS {
+        let fx = f(x)?;
+        if fx.abs() < 1e-14 {
+            return Ok(Some(x));
+        }
+        let h = default_step(x) * 1e-4;
+        let slope = (f(x + h)? - f(x - h)?) / (2.0 * h);
+        x -= fx / slope;
+        if !(lo..=hi).contains(&x) {
+            return Ok(None);
+        }
+    }
+    Ok(None)
+}
+
+/// Finds the roots of `f` in `[lo, hi]` by sampling the interval for sign changes (and for
+/// local minima of `|f|`, which catch roots of even multiplicity) and refining each
+/// candidate. The roots are returned in increasing order.
+pub fn find_roots<E>(
+    mut f: impl FnMut(f64) -> Result<f64, E>,
+    lo: f64,
+    hi: f64,
+) -> Result<Vec<f64>, E> {
+    let mut roots: Vec<f64> = vec![];
+    let width = (hi - lo) / ROOT_SAMPLES as f64;
+    let xs: Vec<f64> = (0..=ROOT_SAMPLES).map(|i| lo + width * i as f64).collect();
+    let values = xs.iter().map(|x| f(*x)).collect::<Result<Vec<f64>, E>>()?;
+    for i in 0..ROOT_SAMPLES {
+        let (fa, fb) = (values[i], values[i + 1]);
+        if fa == 0.0 {
+            roots.push(xs[i]);
+        } else if fa.is_finite() && fb.is_finite() && (fa < 0.0) != (fb < 0.0) && fb != 0.0 {
+            let root = refine_bracket(&mut f, xs[i], xs[i + 1], fa)?;
+            // Poles also change sign, but |f| grows instead of vanishing there.
+            if f(root)?.abs() <= fa.abs().min(fb.abs()) {
+                roots.push(root);
+            }
+        } else if i > 0
+            && fa.abs() < values[i - 1].abs()
+            && fa.abs() < fb.abs()
+            && (fa < 0.0) == (fb < 0.0)
+            && (fa < 0.0) == (values[i - 1] < 0.0)
+        {
+            if let Some(root) = newton(&mut f, xs[i], xs[i - 1], xs[i + 1])? {
+                roots.push(root);
+            }
+        }
+    }
+    if values[ROOT_SAMPLES] == 0.0 {
+        roots.push(xs[ROOT_SAMPLES]);
+    }
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|a, b| (*a - *b).abs() <= width * 1e-6);
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn integrate_ok(f: fn(f64) -> f64, a: f64, b: f64) -> Quadrature {
+        integrate(|x| Ok::<f64, ()>(f(x)), a, b, DEFAULT_TOLERANCE).unwrap()
+    }
+
+    #[test]
+    fn integrate_finite_and_infinite_intervals() {
+        let quadrature = integrate_ok(|x| x.cos(), 0.0, std::f64::consts::FRAC_PI_2);
+        assert!(quadrature.converged);
+        assert!((quadrature.value - 1.0).abs() < 1e-12);
+        assert!(quadrature.error < 1e-10);
+        assert!((integrate_ok(|x| x * x, 3.0, 0.0).value + 9.0).abs() < 1e-12);
+        assert!((integrate_ok(|x| (-x).exp(), 0.0, f64::INFINITY).value - 1.0).abs() < 1e-10);
+        let cauchy = integrate_ok(|x| 1.0 / (1.0 + x * x), f64::NEG_INFINITY, f64::INFINITY);
+        assert!((cauchy.value - std::f64::consts::PI).abs() < 1e-10);
+    }
+
+    #[test]
+    fn differentiate_smooth_functions() {
+        let derivative = differentiate(|x| Ok::<f64, ()>(x.sin()), 1.0, 1, 0.1).unwrap();
+        assert!((derivative.value - 1.0f64.cos()).abs() < 1e-12);
+        assert!(derivative.error < 1e-10);
+        let derivative = differentiate(|x| Ok::<f64, ()>(x.exp()), 0.0, 4, 0.5).unwrap();
+        assert!((derivative.value - 1.0).abs() < 1e-5);
+        let derivative = differentiate(|x| Ok::<f64, ()>(x.powi(5)), 1e6, 2, default_step(1e6));
+        assert!((derivative.unwrap().value / 2e19 - 1.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn find_simple_double_and_no_roots() {
+        let roots = find_roots(|x| Ok::<f64, ()>(x * x - 4.0), -10.0, 10.0).unwrap();
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] + 2.0).abs() < 1e-12 && (roots[1] - 2.0).abs() < 1e-12);
+        let roots = find_roots(|x| Ok::<f64, ()>((x - 1.0) * (x - 1.0)), -10.0, 10.0).unwrap();
+        assert_eq!(roots.len(), 1);
+        assert!((roots[0] - 1.0).abs() < 1e-6);
+        assert_eq!(
+            find_roots(|x| Ok::<f64, ()>(1.0 / x), -1.0, 1.3).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            find_roots(|x| Ok::<f64, ()>(x * x + 1.0), -5.0, 5.0).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn integrate_propagates_errors() {
+        let result = integrate(
+            |x| if x > 0.5 { Err("too big") } else { Ok(x) },
+            0.0,
+            1.0,
+            DEFAULT_TOLERANCE,
+        );
+        assert_eq!(result, Err("too big"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Events of the stages run on formulas, reported to the observer of a context with the
+//! `tracing` feature so that embedders can forward them to their telemetry, as spans of
+//! the tracing crate. Without the feature, or an observer, nothing is measured.
+
+use std::fmt;
+#[cfg(feature = "tracing")]
+use std::sync::Arc;
+#[cfg(feature = "tracing")]
+use std::time::{Duration, Instant};
+
+use crate::evaluator::Context;
+
+/// A stage of the pipeline from source text to value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// A pass of the lexer over a formula, made for observers only: the parser lexes as
+    /// it goes, so parse events include lexing too.
+    Lex,
+    Parse,
+    /// `Expr::compile_with`.
+    Compile,
+    Evaluate,
+}
+
+/// A stage done on a formula, reported once it finished.
+#[cfg(feature = "tracing")]
+#[derive(Debug)]
+pub struct Event<'a> {
+    pub stage: Stage,
+    /// The formula, as written for expressions not parsed from source.
+    pub source: &'a str,
+    pub time: Duration,
+    pub error: Option<&'a (dyn std::error::Error + 'static)>,
+}
+
+/// Receives the events of the contexts it is set on, from any thread evaluating.
+#[cfg(feature = "tracing")]
+pub trait Observer: Send + Sync {
+    fn observe(&self, event: &Event);
+}
+
+/// The observer of a context, if any.
+#[derive(Clone, Default)]
+pub(crate) struct Slot(#[cfg(feature = "tracing")] Option<Arc<dyn Observer>>);
+
+impl fmt::Debug for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        #[cfg(feature = "tracing")]
+        let observer = self.0.as_ref().map(|_| "Observer");
+        #[cfg(not(feature = "tracing"))]
+        let observer: Option<&str> = None;
+        f.debug_tuple("Slot").field(&observer).finish()
+    }
+}
+
+impl Slot {
+    /// Whether events are reported, and worth the work of those made for observers.
+    pub(crate) fn is_set(&self) -> bool {
+        #[cfg(feature = "tracing")]
+        return self.0.is_some();
+        #[cfg(not(feature = "tracing"))]
+        return false;
+    }
+
+    /// Runs `stage` on the formula of `source`, reporting it to the observer if any.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn observe<T, E: std::error::Error + 'static>(
+        &self,
+        stage: Stage,
+        source: impl FnOnce() -> String,
+        run: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let Some(observer) = &self.0 else {
+            return run();
+        };
+        let start = Instant::now();
+        let result = run();
+        let time = start.elapsed();
+        observer.observe(&Event {
+            stage,
+            source: &source(),
+            time,
+            error: result
+                .as_ref()
+                .err()
+                .map(|error| error as &(dyn std::error::Error + 'static)),
+        });
+        result
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[inline]
+    pub(crate) fn observe<T, E>(
+        &self,
+        _: Stage,
+        _: impl FnOnce() -> String,
+        run: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        run()
+    }
+}
+
+impl Context {
+    /// Reports the events of this context and of its clones to `observer`.
+    #[cfg(feature = "tracing")]
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn Observer>>) {
+        self.observer = Slot(observer);
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use super::*;
+    use crate::bytecode::CompileOptions;
+    use crate::parser::Parser;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(Stage, String, bool)>>);
+
+    impl Observer for Recorder {
+        fn observe(&self, event: &Event) {
+            let record = (event.stage, event.source.to_string(), event.error.is_some());
+            self.0.lock().unwrap().push(record);
+        }
+    }
+
+    #[test]
+    fn report_events() {
+        let recorder = Arc::new(Recorder::default());
+        let mut context = Context::new();
+        context.set_observer(Some(recorder.clone()));
+        context.eval("2 * x").unwrap_err();
+        let expr = Parser::new("x + 1").parse().unwrap();
+        let options = CompileOptions::default();
+        expr.compile_with(&context, &options).unwrap();
+        context.clone().eval("1 +").unwrap_err();
+        let events = recorder.0.lock().unwrap().clone();
+        let event = |stage, source: &str, failed| (stage, source.to_string(), failed);
+        assert_eq!(
+            events,
+            [
+                event(Stage::Lex, "2 * x", false),
+                event(Stage::Parse, "2 * x", false),
+                event(Stage::Evaluate, "2 * x", true),
+                event(Stage::Compile, "x + 1", false),
+                event(Stage::Lex, "1 +", false),
+                event(Stage::Parse, "1 +", true),
+            ]
+        );
+        context.set_observer(None);
+        context.eval("1").unwrap();
+        assert_eq!(recorder.0.lock().unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,779 @@
+use core::fmt;
+use std::error::Error;
+
+use crate::lexer::{Lexer, LexerError, LexerString, Span, Token, VecLexerString};
+use crate::radix::{self, Radix};
+use crate::value::Value;
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum UnaryOperator {
+    Negate,
+    /// Postfix `%`, dividing by a hundred.
+    Percent,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// `&&` and `||` short-circuit: the right operand is only evaluated when the left one
+    /// does not decide the result.
+    And,
+    Or,
+    /// `±`, making a number with an uncertainty.
+    PlusMinus,
+}
+
+impl BinaryOperator {
+    fn from_token(token: &Token) -> Option<BinaryOperator> {
+        match token {
+            Token::Operator(content) => match content.as_slice() {
+                b"+" => Some(BinaryOperator::Add),
+                b"-" => Some(BinaryOperator::Subtract),
+                b"*" => Some(BinaryOperator::Multiply),
+                b"/" => Some(BinaryOperator::Divide),
+                b"^" => Some(BinaryOperator::Power),
+                b"==" => Some(BinaryOperator::Equal),
+                b"!=" => Some(BinaryOperator::NotEqual),
+                b"<" => Some(BinaryOperator::Less),
+                b"<=" => Some(BinaryOperator::LessEqual),
+                b">" => Some(BinaryOperator::Greater),
+                b">=" => Some(BinaryOperator::GreaterEqual),
+                b"&&" => Some(BinaryOperator::And),
+                b"||" => Some(BinaryOperator::Or),
+                b"\xC2\xB1" => Some(BinaryOperator::PlusMinus),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::PlusMinus => {
+                ADDITIVE_PRECEDENCE
+            }
+            BinaryOperator::Multiply | BinaryOperator::Divide => MULTIPLICATIVE_PRECEDENCE,
+            BinaryOperator::Power => POWER_PRECEDENCE,
+            BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Less
+            | BinaryOperator::LessEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual => COMPARISON_PRECEDENCE,
+            BinaryOperator::And => AND_PRECEDENCE,
+            BinaryOperator::Or => OR_PRECEDENCE,
+        }
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        self.precedence() == COMPARISON_PRECEDENCE
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        *self == BinaryOperator::Power
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Power => "^",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+            BinaryOperator::PlusMinus => "±",
+        }
+    }
+}
+
+/// Bound on the recursion of the parser, so that hostile input such as ten thousand
+/// opening parentheses is an error rather than a stack overflow.
+pub(crate) const MAX_NESTING_DEPTH: usize = 128;
+
+const KEYWORDS: [&[u8]; 2] = [b"in", b"of"];
+
+const LAMBDA_PRECEDENCE: u8 = 0;
+const CONDITIONAL_PRECEDENCE: u8 = 1;
+const OR_PRECEDENCE: u8 = 2;
+const AND_PRECEDENCE: u8 = 3;
+const COMPARISON_PRECEDENCE: u8 = 4;
+const ADDITIVE_PRECEDENCE: u8 = 5;
+const MULTIPLICATIVE_PRECEDENCE: u8 = 6;
+const UNARY_PRECEDENCE: u8 = 7;
+const POWER_PRECEDENCE: u8 = 8;
+const ATOM_PRECEDENCE: u8 = 9;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ExprKind {
+    Number(f64),
+    String(String),
+    Variable(String),
+    Unary(UnaryOperator, Box<Expr>),
+    Binary(BinaryOperator, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    /// `condition ? then : otherwise`; only the branch that is taken is evaluated.
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    Lambda(Vec<String>, Box<Expr>),
+    /// `(a, b, ...)`, evaluating to the list of the values of its items.
+    Tuple(Vec<Expr>),
+    /// An already evaluated value, as in the steps of a trace. Never produced by parsing.
+    Value(Value),
+}
+
+/// A node of the syntax tree. Equality only compares the structure, not the spans.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Expr) -> bool {
+        self.kind == other.kind
+    }
+}
+
+/// Drops the subexpressions from a stack rather than recursively, so that trees as deep as
+/// a chain of a hundred thousand sums do not overflow the stack.
+impl Drop for Expr {
+    fn drop(&mut self) {
+        let mut stack = vec![];
+        take_children(&mut self.kind, &mut stack);
+        while let Some(mut expr) = stack.pop() {
+            take_children(&mut expr.kind, &mut stack);
+        }
+    }
+}
+
+fn take_children(kind: &mut ExprKind, stack: &mut Vec<Expr>) {
+    let mut take = |expr: &mut Box<Expr>| {
+        stack.push(std::mem::replace(&mut **expr, Expr::number(0.0)));
+    };
+    match kind {
+        ExprKind::Unary(_, operand) | ExprKind::Lambda(_, operand) => take(operand),
+        ExprKind::Binary(_, lhs, rhs) => {
+            take(lhs);
+            take(rhs);
+        }
+        ExprKind::Conditional(condition, then, otherwise) => {
+            take(condition);
+            take(then);
+            take(otherwise);
+        }
+        ExprKind::Call(_, items) | ExprKind::Tuple(items) => stack.append(items),
+        ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Variable(_) | ExprKind::Value(_) => {}
+    }
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Expr {
+        Expr { kind, span }
+    }
+
+    pub fn number(value: f64) -> Expr {
+        Expr::new(ExprKind::Number(value), Span::default())
+    }
+
+    pub fn variable(name: &str) -> Expr {
+        Expr::new(ExprKind::Variable(name.to_string()), Span::default())
+    }
+
+    pub fn unary(operator: UnaryOperator, operand: Expr) -> Expr {
+        Expr::new(
+            ExprKind::Unary(operator, Box::new(operand)),
+            Span::default(),
+        )
+    }
+
+    pub fn binary(operator: BinaryOperator, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::new(
+            ExprKind::Binary(operator, Box::new(lhs), Box::new(rhs)),
+            Span::default(),
+        )
+    }
+
+    pub fn call(name: &str, args: Vec<Expr>) -> Expr {
+        Expr::new(ExprKind::Call(name.to_string(), args), Span::default())
+    }
+
+    fn precedence(&self) -> u8 {
+        match &self.kind {
+            ExprKind::Number(value) if *value < 0.0 => UNARY_PRECEDENCE,
+            ExprKind::Number(_)
+            | ExprKind::String(_)
+            | ExprKind::Variable(_)
+            | ExprKind::Call(_, _)
+            | ExprKind::Tuple(_) => ATOM_PRECEDENCE,
+            ExprKind::Unary(UnaryOperator::Negate, _) => UNARY_PRECEDENCE,
+            ExprKind::Unary(UnaryOperator::Percent, _) => ATOM_PRECEDENCE,
+            ExprKind::Binary(operator, _, _) => operator.precedence(),
+            ExprKind::Conditional(_, _, _) => CONDITIONAL_PRECEDENCE,
+            ExprKind::Lambda(_, _) => LAMBDA_PRECEDENCE,
+            ExprKind::Value(Value::Number(value)) if *value < 0.0 => UNARY_PRECEDENCE,
+            ExprKind::Value(Value::Quantity(_)) => MULTIPLICATIVE_PRECEDENCE,
+            ExprKind::Value(Value::Uncertain(_)) => ADDITIVE_PRECEDENCE,
+            ExprKind::Value(Value::Function(_)) => LAMBDA_PRECEDENCE,
+            ExprKind::Value(_) => ATOM_PRECEDENCE,
+        }
+    }
+}
+
+fn write_operand(f: &mut fmt::Formatter, operand: &Expr, parenthesize: bool) -> fmt::Result {
+    if parenthesize {
+        write!(f, "({})", operand)
+    } else {
+        write!(f, "{}", operand)
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, items: &[Expr]) -> fmt::Result {
+    write!(f, "(")?;
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    write!(f, ")")
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Number(value) => write!(f, "{}", value),
+            ExprKind::String(string) => write!(f, "\"{}\"", string),
+            ExprKind::Variable(name) => write!(f, "{}", name),
+            ExprKind::Unary(UnaryOperator::Negate, operand) => {
+                write!(f, "-")?;
+                write_operand(f, operand, operand.precedence() < UNARY_PRECEDENCE)
+            }
+            ExprKind::Unary(UnaryOperator::Percent, operand) => {
+                write_operand(f, operand, operand.precedence() < ATOM_PRECEDENCE)?;
+                write!(f, "%")
+            }
+            ExprKind::Binary(operator, lhs, rhs) => {
+                let precedence = operator.precedence();
+                let right_associative = operator.is_right_associative();
+                write_operand(
+                    f,
+                    lhs,
+                    lhs.precedence() < precedence
+                        || (right_associative && lhs.precedence() == precedence),
+                )?;
+                if *operator == BinaryOperator::Power {
+                    write!(f, "^")?;
+                } else {
+                    write!(f, " {} ", operator.symbol())?;
+                }
+                let rhs_parenthesized = if right_associative {
+                    rhs.precedence() < UNARY_PRECEDENCE
+                } else {
+                    rhs.precedence() <= precedence
+                };
+                write_operand(f, rhs, rhs_parenthesized)
+            }
+            ExprKind::Call(name, args) => {
+                write!(f, "{}", name)?;
+                write_list(f, args)
+            }
+            ExprKind::Tuple(items) => write_list(f, items),
+            ExprKind::Conditional(condition, then, otherwise) => {
+                write_operand(
+                    f,
+                    condition,
+                    condition.precedence() <= CONDITIONAL_PRECEDENCE,
+                )?;
+                write!(f, " ? {} : {}", then, otherwise)
+            }
+            ExprKind::Lambda(params, body) => {
+                if params.len() == 1 {
+                    write!(f, "{} -> {}", params[0], body)
+                } else {
+                    write!(f, "({}) -> {}", params.join(", "), body)
+                }
+            }
+            ExprKind::Value(Value::String(string)) => write!(f, "\"{}\"", string),
+            ExprKind::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParserErrorKind {
+    Lexer(LexerError),
+    UnexpectedToken {
+        found: String,
+        expected: &'static str,
+    },
+    UnexpectedEnd {
+        expected: &'static str,
+    },
+    InvalidNumber(String),
+    InvalidLambdaParameters,
+    TooDeeplyNested,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserError {
+    pub kind: ParserErrorKind,
+    pub span: Span,
+}
+
+impl ParserError {
+    fn new(kind: ParserErrorKind, span: Span) -> ParserError {
+        ParserError { kind, span }
+    }
+}
+
+impl From<LexerError> for ParserError {
+    fn from(error: LexerError) -> ParserError {
+        let span = error.span;
+        ParserError::new(ParserErrorKind::Lexer(error), span)
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ParserErrorKind::Lexer(error) => write!(f, "{}", error),
+            ParserErrorKind::UnexpectedToken { found, expected } => {
+                write!(f, "Unexpected '{}', expected {}", found, expected)
+            }
+            ParserErrorKind::UnexpectedEnd { expected } => {
+                write!(f, "Unexpected end of input, expected {}", expected)
+            }
+            ParserErrorKind::InvalidNumber(number) => write!(f, "Invalid number '{}'", number),
+            ParserErrorKind::InvalidLambdaParameters => {
+                write!(f, "Lambda parameters must be plain identifiers")
+            }
+            ParserErrorKind::TooDeeplyNested => {
+                write!(
+                    f,
+                    "Expression nested deeper than {} levels",
+                    MAX_NESTING_DEPTH
+                )
+            }
+        }
+    }
+}
+impl Error for ParserError {}
+
+pub struct Parser<T: LexerString> {
+    lexer: Lexer<T>,
+    current: Option<(Token, Span)>,
+    end: usize,
+    depth: usize,
+}
+
+impl<T: LexerString> Parser<T> {
+    pub fn from_lexer(lexer: Lexer<T>) -> Parser<T> {
+        Parser {
+            lexer,
+            current: None,
+            end: 0,
+            depth: 0,
+        }
+    }
+
+    /// Parses the whole input as a single expression.
+    pub fn parse(&mut self) -> Result<Expr, ParserError> {
+        self.advance()?;
+        let expr = self.parse_expression()?;
+        match &self.current {
+            None => Ok(expr),
+            Some((token, span)) => Err(ParserError::new(
+                ParserErrorKind::UnexpectedToken {
+                    found: token.to_string(),
+                    expected: "end of input",
+                },
+                *span,
+            )),
+        }
+    }
+
+    fn advance(&mut self) -> Result<Option<(Token, Span)>, ParserError> {
+        let next = self.lexer.next_spanned_token()?;
+        if let Some((_, span)) = &next {
+            self.end = span.end;
+        }
+        Ok(std::mem::replace(&mut self.current, next))
+    }
+
+    fn error(&self, expected: &'static str) -> ParserError {
+        match &self.current {
+            Some((token, span)) => ParserError::new(
+                ParserErrorKind::UnexpectedToken {
+                    found: token.to_string(),
+                    expected,
+                },
+                *span,
+            ),
+            None => ParserError::new(
+                ParserErrorKind::UnexpectedEnd { expected },
+                Span::new(self.end, self.end),
+            ),
+        }
+    }
+
+    fn current_is(&self, token: &Token) -> bool {
+        matches!(&self.current, Some((current, _)) if current == token)
+    }
+
+    fn current_is_operator(&self, operator: &[u8]) -> bool {
+        matches!(&self.current, Some((Token::Operator(content), _)) if content == operator)
+    }
+
+    fn expect(&mut self, token: Token, expected: &'static str) -> Result<Span, ParserError> {
+        if !self.current_is(&token) {
+            return Err(self.error(expected));
+        }
+        Ok(self.advance()?.unwrap().1)
+    }
+
+    fn current_is_keyword(&self, keyword: &[u8]) -> bool {
+        matches!(&self.current, Some((Token::Identifier(content), _)) if content == keyword)
+    }
+
+    /// Runs `parse` one nesting level deeper.
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Expr, ParserError>,
+    ) -> Result<Expr, ParserError> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            let span = match &self.current {
+                Some((_, span)) => *span,
+                None => Span::new(self.end, self.end),
+            };
+            return Err(ParserError::new(ParserErrorKind::TooDeeplyNested, span));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_expression(&mut self) -> Result<Expr, ParserError> {
+        self.nested(Self::parse_nested_expression)
+    }
+
+    fn parse_nested_expression(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.parse_binary(OR_PRECEDENCE)?;
+        while self.current_is_keyword(b"in") {
+            self.advance()?;
+            let unit = self.parse_binary(OR_PRECEDENCE)?;
+            let span = expr.span.to(unit.span);
+            expr = Expr::new(
+                ExprKind::Call(String::from("convert"), vec![expr, unit]),
+                span,
+            );
+        }
+        if self.current_is_operator(b"?") {
+            self.advance()?;
+            let then = self.parse_expression()?;
+            self.expect(Token::Operator(Vec::from(b":")), "':'")?;
+            let otherwise = self.parse_expression()?;
+            let span = expr.span.to(otherwise.span);
+            return Ok(Expr::new(
+                ExprKind::Conditional(Box::new(expr), Box::new(then), Box::new(otherwise)),
+                span,
+            ));
+        }
+        if self.current_is_operator(b"->") {
+            self.advance()?;
+            let params = lambda_params(&expr)?;
+            let body = self.parse_expression()?;
+            let span = expr.span.to(body.span);
+            return Ok(Expr::new(ExprKind::Lambda(params, Box::new(body)), span));
+        }
+        Ok(expr)
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, ParserError> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(operator) = self.current_binary_operator(min_precedence) {
+            self.advance()?;
+            let rhs = self.parse_binary(operator.precedence() + 1)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expr::new(
+                ExprKind::Binary(operator, Box::new(lhs), Box::new(rhs)),
+                span,
+            );
+        }
+        Ok(lhs)
+    }
+
+    fn current_binary_operator(&self, min_precedence: u8) -> Option<BinaryOperator> {
+        let (token, _) = self.current.as_ref()?;
+        BinaryOperator::from_token(token).filter(|operator| {
+            operator.precedence() >= min_precedence && *operator != BinaryOperator::Power
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParserError> {
+        self.nested(Self::parse_nested_unary)
+    }
+
+    fn parse_nested_unary(&mut self) -> Result<Expr, ParserError> {
+        if self.current_is_operator(b"-") || self.current_is_operator(b"+") {
+            let negate = self.current_is_operator(b"-");
+            let (_, start) = self.advance()?.unwrap();
+            let operand = self.parse_unary()?;
+            if !negate {
+                return Ok(operand);
+            }
+            let span = start.to(operand.span);
+            return Ok(Expr::new(
+                ExprKind::Unary(UnaryOperator::Negate, Box::new(operand)),
+                span,
+            ));
+        }
+        self.parse_implicit_product()
+    }
+
+    /// Juxtaposition such as `3 m` or `2 pi`: binds tighter than `*` and `/`, so that
+    /// `60 km / 2 h` is a speed. `10% of 50` is a product at the same level.
+    fn parse_implicit_product(&mut self) -> Result<Expr, ParserError> {
+        let mut lhs = self.parse_power()?;
+        while matches!(
+            &self.current,
+            Some((Token::Identifier(content), _)) if !KEYWORDS.contains(&content.as_slice())
+        ) || self.current_is(&Token::OpenParenthesis)
+        {
+            let rhs = self.parse_power()?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expr::new(
+                ExprKind::Binary(BinaryOperator::Multiply, Box::new(lhs), Box::new(rhs)),
+                span,
+            );
+        }
+        if self.current_is_keyword(b"of") {
+            self.advance()?;
+            let rhs = self.nested(Self::parse_implicit_product)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expr::new(
+                ExprKind::Binary(BinaryOperator::Multiply, Box::new(lhs), Box::new(rhs)),
+                span,
+            );
+        }
+        Ok(lhs)
+    }
+
+    fn parse_power(&mut self) -> Result<Expr, ParserError> {
+        let mut base = self.parse_primary()?;
+        while self.current_is_operator(b"%") {
+            let (_, end) = self.advance()?.unwrap();
+            let span = base.span.to(end);
+            base = Expr::new(
+                ExprKind::Unary(UnaryOperator::Percent, Box::new(base)),
+                span,
+            );
+        }
+        if !self.current_is_operator(b"^") {
+            return Ok(base);
+        }
+        self.advance()?;
+        let exponent = self.parse_unary()?;
+        let span = base.span.to(exponent.span);
+        Ok(Expr::new(
+            ExprKind::Binary(BinaryOperator::Power, Box::new(base), Box::new(exponent)),
+            span,
+        ))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParserError> {
+        match self.current.clone() {
+            Some((Token::Number(content), span)) => {
+                self.advance()?;
+                let text = String::from_utf8_lossy(&content).into_owned();
+                let value = match text.get(..2).and_then(Radix::from_prefix) {
+                    Some(radix) => radix::from_base(&text[2..], radix.base()),
+                    None => text.parse::<f64>().ok(),
+                };
+                match value {
+                    Some(value) => Ok(Expr::new(ExprKind::Number(value), span)),
+                    None => Err(ParserError::new(ParserErrorKind::InvalidNumber(text), span)),
+                }
+            }
+            Some((Token::String(content), span)) => {
+                self.advance()?;
+                let string = String::from_utf8_lossy(&content).into_owned();
+                Ok(Expr::new(ExprKind::String(string), span))
+            }
+            Some((Token::Identifier(content), span)) => {
+                self.advance()?;
+                let name = String::from_utf8_lossy(&content).into_owned();
+                if self.current_is(&Token::OpenParenthesis) {
+                    self.advance()?;
+                    let args = self.parse_arguments()?;
+                    let end = self.expect(Token::ClosedParenthesis, "')'")?;
+                    return Ok(Expr::new(ExprKind::Call(name, args), span.to(end)));
+                }
+                Ok(Expr::new(ExprKind::Variable(name), span))
+            }
+            Some((Token::OpenParenthesis, start)) => {
+                self.advance()?;
+                let mut items = self.parse_arguments()?;
+                let end = self.expect(Token::ClosedParenthesis, "')'")?;
+                if items.len() == 1 {
+                    let mut expr = items.remove(0);
+                    expr.span = start.to(end);
+                    return Ok(expr);
+                }
+                if !self.current_is_operator(b"->") {
+                    if items.is_empty() {
+                        return Err(self.error("'->' after a parameter list"));
+                    }
+                    return Ok(Expr::new(ExprKind::Tuple(items), start.to(end)));
+                }
+                self.advance()?;
+                let params = items
+                    .iter()
+                    .map(lambda_param)
+                    .collect::<Result<Vec<String>, ParserError>>()?;
+                let body = self.parse_expression()?;
+                let span = start.to(body.span);
+                Ok(Expr::new(ExprKind::Lambda(params, Box::new(body)), span))
+            }
+            _ => Err(self.error("an expression")),
+        }
+    }
+
+    fn parse_arguments(&mut self) -> Result<Vec<Expr>, ParserError> {
+        let mut args = vec![];
+        if self.current_is(&Token::ClosedParenthesis) {
+            return Ok(args);
+        }
+        args.push(self.parse_expression()?);
+        while self.current_is(&Token::Comma) {
+            self.advance()?;
+            args.push(self.parse_expression()?);
+        }
+        Ok(args)
+    }
+}
+
+fn lambda_param(expr: &Expr) -> Result<String, ParserError> {
+    match &expr.kind {
+        ExprKind::Variable(name) => Ok(name.clone()),
+        _ => Err(ParserError::new(
+            ParserErrorKind::InvalidLambdaParameters,
+            expr.span,
+        )),
+    }
+}
+
+fn lambda_params(expr: &Expr) -> Result<Vec<String>, ParserError> {
+    Ok(vec![lambda_param(expr)?])
+}
+
+impl Parser<VecLexerString> {
+    pub fn new(str: &str) -> Parser<VecLexerString> {
+        Parser::from_lexer(Lexer::new(str))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(source: &str) -> Expr {
+        Parser::new(source).parse().unwrap()
+    }
+
+    #[test]
+    fn parse_precedence_and_associativity() {
+        assert_eq!(
+            parse("1 + 2 * 3 - 4"),
+            Expr::binary(
+                BinaryOperator::Subtract,
+                Expr::binary(
+                    BinaryOperator::Add,
+                    Expr::number(1.0),
+                    Expr::binary(
+                        BinaryOperator::Multiply,
+                        Expr::number(2.0),
+                        Expr::number(3.0)
+                    )
+                ),
+                Expr::number(4.0)
+            )
+        );
+        assert_eq!(parse("-2^3^2").to_string(), "-2^3^2");
+        assert_eq!(parse("(1 - 2) - (3 - 4)").to_string(), "1 - 2 - (3 - 4)");
+        assert_eq!(parse("2^-x").to_string(), "2^-x");
+        assert_eq!(parse("x^2 - 4 == 0").to_string(), "x^2 - 4 == 0");
+        assert_eq!(parse("(a < b) >= c").to_string(), "a < b >= c");
+        assert_eq!(parse("60 km / 2 h").to_string(), "60 * km / (2 * h)");
+        assert_eq!(parse("-3 m^2").to_string(), "-(3 * m^2)");
+        assert_eq!(parse("200 + 10%").to_string(), "200 + 10%");
+        assert_eq!(
+            parse("a || b && c < d ? x : y ? 1 : 2").to_string(),
+            "a || b && c < d ? x : y ? 1 : 2"
+        );
+        assert_eq!(
+            parse("(a ? b : c) ? (d || e) && f : g").to_string(),
+            "(a ? b : c) ? (d || e) && f : g"
+        );
+        assert_eq!(parse("(1 + 2)%^2").to_string(), "(1 + 2)%^2");
+        assert_eq!(parse("2 * 10% of 50 m").to_string(), "2 * (10% * (50 * m))");
+        assert_eq!(
+            parse("5 mi + 1 km in km").to_string(),
+            "convert(5 * mi + 1 * km, km)"
+        );
+    }
+
+    #[test]
+    fn parse_calls_and_lambdas() {
+        let expr = parse("integrate(x -> x^2, 0, 1)");
+        assert_eq!(expr.to_string(), "integrate(x -> x^2, 0, 1)");
+        assert_eq!(expr.span, Span::new(0, 25));
+        assert_eq!(parse("(x, y) -> x * y").to_string(), "(x, y) -> x * y");
+        assert_eq!(parse("f((x, 1), 2)").to_string(), "f((x, 1), 2)");
+    }
+
+    #[test]
+    fn reject_deep_nesting() {
+        assert!(
+            Parser::new(&format!("{}1{}", "(".repeat(60), ")".repeat(60)))
+                .parse()
+                .is_ok()
+        );
+        let error = Parser::new(&"(".repeat(10_000)).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::TooDeeplyNested);
+        let error = Parser::new(&"-".repeat(10_000)).parse().unwrap_err();
+        assert_eq!(error.kind, ParserErrorKind::TooDeeplyNested);
+        // Chains of left-associative operators are parsed in loops, however long.
+        for chain in [" + ", " * ", " ", " in "] {
+            let source = vec!["x"; 100_000].join(chain);
+            assert!(Parser::new(&source).parse().is_ok(), "{:?}", chain);
+        }
+    }
+
+    #[test]
+    fn report_unexpected_tokens() {
+        let error = Parser::new("1 + * 2").parse().unwrap_err();
+        assert_eq!(error.span, Span::new(4, 5));
+        let error = Parser::new("f(1, 2").parse().unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParserErrorKind::UnexpectedEnd { expected: "')'" }
+        );
+    }
+}
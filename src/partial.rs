@@ -0,0 +1,312 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::evaluator::Context;
+use crate::parser::{Expr, ExprKind};
+
+impl Expr {
+    /// Replaces the subexpressions computable in `context` by their values, leaving those
+    /// depending on unbound variables: with `a = 2`, `a * 3 + x * (a + 1)` becomes
+    /// `6 + x * 3`. A conditional whose condition is known becomes the chosen branch.
+    /// Subexpressions failing to evaluate for another reason, such as `1 / 0`, are kept
+    /// so that the error is reported when the result is evaluated.
+    pub fn partial_eval(&self, context: &Context) -> Expr {
+        match &self.kind {
+            ExprKind::Number(_)
+            | ExprKind::String(_)
+            | ExprKind::Value(_)
+            | ExprKind::Lambda(_, _) => return self.clone(),
+            _ => {}
+        }
+        if let Ok(value) = context.evaluate(self) {
+            return Expr::new(ExprKind::Value(value), self.span);
+        }
+        let partial = |expr: &Expr| Box::new(expr.partial_eval(context));
+        let kind = match &self.kind {
+            ExprKind::Unary(operator, operand) => ExprKind::Unary(*operator, partial(operand)),
+            ExprKind::Binary(operator, lhs, rhs) => {
+                ExprKind::Binary(*operator, partial(lhs), partial(rhs))
+            }
+            ExprKind::Conditional(condition, then, otherwise) => {
+                let condition = partial(condition);
+                if let ExprKind::Value(value) = &condition.kind {
+                    if let Ok(condition) = value.is_true() {
+                        let branch = if condition { then } else { otherwise };
+                        return branch.partial_eval(context);
+                    }
+                }
+                ExprKind::Conditional(condition, partial(then), partial(otherwise))
+            }
+            ExprKind::Call(name, args) => {
+                // The expression of `integrate(x^2, x, 0, a)` is kept, the variable it
+                // binds hiding any of the context.
+                let bound = if bound_variable(name, args).is_some() {
+                    2
+                } else {
+                    0
+                };
+                let args = args[..bound]
+                    .iter()
+                    .cloned()
+                    .chain(args[bound..].iter().map(|arg| arg.partial_eval(context)));
+                ExprKind::Call(name.clone(), args.collect())
+            }
+            ExprKind::Tuple(items) => ExprKind::Tuple(
+                items
+                    .iter()
+                    .map(|item| item.partial_eval(context))
+                    .collect(),
+            ),
+            _ => self.kind.clone(),
+        };
+        Expr::new(kind, self.span)
+    }
+
+    /// Replaces the free occurrences of `variable` by `replacement`, which may be a
+    /// subexpression or an `ExprKind::Value`.
+    pub fn substitute(&self, variable: &str, replacement: &Expr) -> Expr {
+        let mut replacements = HashMap::new();
+        replacements.insert(variable.to_string(), replacement.clone());
+        self.substitute_all(&replacements)
+    }
+
+    /// Replaces the free occurrences of several variables at once, so that substituting
+    /// `y` for `x` and `x` for `y` swaps them. Parameters of lambdas shadow the variables
+    /// in their body, as the variables of builtins such as `integrate(x^2, x, 0, 1)` do in
+    /// their expression, and are renamed where a replacement would be captured: `y` for
+    /// `x` in `y -> x * y` gives `y1 -> y * y1`.
+    pub fn substitute_all(&self, replacements: &HashMap<String, Expr>) -> Expr {
+        let substitute = |expr: &Expr| Box::new(expr.substitute_all(replacements));
+        let kind = match &self.kind {
+            ExprKind::Variable(name) => match replacements.get(name) {
+                Some(replacement) => return replacement.clone(),
+                None => return self.clone(),
+            },
+            ExprKind::Unary(operator, operand) => ExprKind::Unary(*operator, substitute(operand)),
+            ExprKind::Binary(operator, lhs, rhs) => {
+                ExprKind::Binary(*operator, substitute(lhs), substitute(rhs))
+            }
+            ExprKind::Conditional(condition, then, otherwise) => ExprKind::Conditional(
+                substitute(condition),
+                substitute(then),
+                substitute(otherwise),
+            ),
+            ExprKind::Call(name, args) => match bound_variable(name, args) {
+                Some(variable) => {
+                    let (params, body) = substitute_bound(&[variable], &args[0], replacements);
+                    let variable = ExprKind::Variable(params.into_iter().next().unwrap());
+                    let args = [body, Expr::new(variable, args[1].span)]
+                        .into_iter()
+                        .chain(args[2..].iter().map(|arg| arg.substitute_all(replacements)));
+                    ExprKind::Call(name.clone(), args.collect())
+                }
+                None => ExprKind::Call(
+                    name.clone(),
+                    args.iter()
+                        .map(|arg| arg.substitute_all(replacements))
+                        .collect(),
+                ),
+            },
+            ExprKind::Tuple(items) => ExprKind::Tuple(
+                items
+                    .iter()
+                    .map(|item| item.substitute_all(replacements))
+                    .collect(),
+            ),
+            ExprKind::Lambda(params, body) => {
+                let params: Vec<&str> = params.iter().map(String::as_str).collect();
+                let (params, body) = substitute_bound(&params, body, replacements);
+                ExprKind::Lambda(params, Box::new(body))
+            }
+            _ => return self.clone(),
+        };
+        Expr::new(kind, self.span)
+    }
+}
+
+/// The builtins taking an expression and the variable it is a function of, as
+/// `integrate(x^2, x, 0, 1)`, rather than a function.
+const BINDERS: &[&str] = &["derive", "diff", "integrate", "limit", "series", "solve"];
+
+/// The variable that the call of `name` binds in its first argument. A name first is taken
+/// for a function, as in `integrate(sin, a, b)`, unless it is the variable.
+fn bound_variable<'e>(name: &str, args: &'e [Expr]) -> Option<&'e str> {
+    if !BINDERS.contains(&name) {
+        return None;
+    }
+    let ExprKind::Variable(variable) = &args.get(1)?.kind else {
+        return None;
+    };
+    match &args[0].kind {
+        ExprKind::Lambda(_, _) => None,
+        ExprKind::Variable(first) if first != variable => None,
+        _ => Some(variable),
+    }
+}
+
+/// Substitutes `replacements` in `body`, in which `params` are bound, renaming those of
+/// `params` that a replacement would capture.
+fn substitute_bound(
+    params: &[&str],
+    body: &Expr,
+    replacements: &HashMap<String, Expr>,
+) -> (Vec<String>, Expr) {
+    let free = free_variables(body);
+    let mut replacements: HashMap<String, Expr> = replacements
+        .iter()
+        .filter(|(name, _)| free.contains(*name) && !params.contains(&name.as_str()))
+        .map(|(name, replacement)| (name.clone(), replacement.clone()))
+        .collect();
+    let captured: BTreeSet<String> = replacements.values().flat_map(free_variables).collect();
+    let mut taken: BTreeSet<String> = free.union(&captured).cloned().collect();
+    taken.extend(params.iter().map(|param| param.to_string()));
+    let params = params
+        .iter()
+        .map(|param| {
+            if !captured.contains(*param) {
+                return param.to_string();
+            }
+            let fresh = (1..)
+                .map(|index| format!("{}{}", param, index))
+                .find(|name| !taken.contains(name))
+                .unwrap();
+            taken.insert(fresh.clone());
+            let variable = Expr::new(ExprKind::Variable(fresh.clone()), body.span);
+            replacements.insert(param.to_string(), variable);
+            fresh
+        })
+        .collect();
+    (params, body.substitute_all(&replacements))
+}
+
+/// The names of the variables of `expr` that are not parameters of an enclosing lambda, or
+/// the variable of a builtin such as `integrate`, in alphabetical order. Constants and units are names as well.
+pub(crate) fn free_variables(expr: &Expr) -> BTreeSet<String> {
+    let mut variables = BTreeSet::new();
+    collect_variables(expr, &[], &mut variables);
+    variables
+}
+
+fn collect_variables(expr: &Expr, bound: &[String], variables: &mut BTreeSet<String>) {
+    match &expr.kind {
+        ExprKind::Variable(name) => {
+            if !bound.contains(name) {
+                variables.insert(name.clone());
+            }
+        }
+        ExprKind::Unary(_, operand) => collect_variables(operand, bound, variables),
+        ExprKind::Binary(_, lhs, rhs) => {
+            collect_variables(lhs, bound, variables);
+            collect_variables(rhs, bound, variables);
+        }
+        ExprKind::Conditional(condition, then, otherwise) => {
+            for expr in [condition, then, otherwise] {
+                collect_variables(expr, bound, variables);
+            }
+        }
+        ExprKind::Call(name, args) if bound_variable(name, args).is_some() => {
+            let bound = [bound, &[bound_variable(name, args).unwrap().to_string()]].concat();
+            collect_variables(&args[0], &bound, variables);
+            for arg in &args[2..] {
+                collect_variables(arg, &bound[..bound.len() - 1], variables);
+            }
+        }
+        ExprKind::Call(_, items) | ExprKind::Tuple(items) => {
+            for item in items {
+                collect_variables(item, bound, variables);
+            }
+        }
+        ExprKind::Lambda(params, body) => {
+            let bound = [bound, params].concat();
+            collect_variables(body, &bound, variables);
+        }
+        ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Value(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::evaluator::Context;
+    use crate::parser::{Expr, ExprKind, Parser};
+    use crate::value::Value;
+
+    fn partial_eval(context: &Context, source: &str) -> String {
+        let expr = Parser::new(source).parse().unwrap();
+        expr.partial_eval(context).to_string()
+    }
+
+    #[test]
+    fn fold_bound_subexpressions() {
+        let mut context = Context::new();
+        context.set_variable("a", Value::Number(2.0));
+        assert_eq!(
+            partial_eval(&context, "a * 3 + x * (a + 1) - sqrt(a^2 * 4)"),
+            "6 + x * 3 - 4"
+        );
+        assert_eq!(partial_eval(&context, "a > 1 ? x * a : 1 / 0"), "x * 2");
+        assert_eq!(
+            partial_eval(&context, "x > a ? x : 1 / 0"),
+            "x > 2 ? x : 1 / 0"
+        );
+        assert_eq!(partial_eval(&context, "a km in m"), "2000 m");
+        let expr = Parser::new("y^a + a")
+            .parse()
+            .unwrap()
+            .partial_eval(&context);
+        assert_eq!(expr.compile().unwrap().inputs(), ["y"]);
+    }
+
+    #[test]
+    fn substitute_variables() {
+        let parse = |source: &str| Parser::new(source).parse().unwrap();
+        let expr = parse("x^2 + f(x, y -> x * y)");
+        assert_eq!(
+            expr.substitute("x", &parse("a + 1")).to_string(),
+            "(a + 1)^2 + f(a + 1, y -> (a + 1) * y)"
+        );
+        let mut replacements = HashMap::new();
+        replacements.insert(String::from("x"), parse("y"));
+        replacements.insert(String::from("y"), parse("x"));
+        assert_eq!(
+            parse("x - y").substitute_all(&replacements).to_string(),
+            "y - x"
+        );
+        let value = Expr::new(ExprKind::Value(Value::Number(3.0)), parse("x").span);
+        let expr = parse("x -> x + y").substitute("y", &value);
+        assert_eq!(expr.to_string(), "x -> x + 3");
+        assert_eq!(expr.substitute("x", &value).to_string(), "x -> x + 3");
+        assert_eq!(parse("y * 2").substitute("y", &value).to_string(), "3 * 2");
+    }
+
+    #[test]
+    fn substitute_without_capture() {
+        let parse = |source: &str| Parser::new(source).parse().unwrap();
+        let expr = parse("integrate(x, x, 0, x) + derive(sin(x) * a, x)");
+        assert_eq!(
+            expr.substitute("x", &parse("y + 1")).to_string(),
+            "integrate(x, x, 0, y + 1) + derive(sin(x) * a, x)"
+        );
+        let names: Vec<String> = super::free_variables(&expr).into_iter().collect();
+        assert_eq!(names, ["a", "x"]);
+        assert_eq!(
+            parse("integrate(sin, a, b)")
+                .substitute("a", &parse("0"))
+                .to_string(),
+            "integrate(sin, 0, b)"
+        );
+        let expr = parse("y -> x * y");
+        assert_eq!(
+            expr.substitute("x", &parse("y")).to_string(),
+            "y1 -> y * y1"
+        );
+        let expr = parse("integrate(x * y, x, 0, 1)").substitute("y", &parse("x + y1"));
+        assert_eq!(expr.to_string(), "integrate(x1 * (x + y1), x1, 0, 1)");
+        let mut context = Context::new();
+        context.set_variable("x", Value::Number(5.0));
+        assert_eq!(
+            partial_eval(&context, "integrate(x * y, x, 0, x)"),
+            "integrate(x * y, x, 0, 5)"
+        );
+    }
+}
@@ -0,0 +1,778 @@
+//! Polynomials in several variables with exact rational coefficients.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+use crate::lexer::Span;
+use crate::parser::{BinaryOperator, Expr, ExprKind, UnaryOperator};
+use crate::value::Value;
+
+/// Largest constant term whose divisors are tried as rational roots by `factor`.
+const MAX_ROOT_SEARCH: u128 = 1_000_000_000_000;
+
+fn overflow() -> EvaluatorError {
+    EvaluatorError::new(EvaluatorErrorKind::Overflow)
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// A reduced fraction with a positive denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    const ZERO: Rational = Rational::integer(0);
+    const ONE: Rational = Rational::integer(1);
+
+    const fn integer(value: i128) -> Rational {
+        Rational {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    fn new(numerator: i128, denominator: i128) -> Result<Rational, EvaluatorError> {
+        if denominator == 0 {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::DivisionByZero));
+        }
+        let divisor = gcd(numerator.unsigned_abs(), denominator.unsigned_abs()) as i128;
+        let sign = denominator.signum();
+        Ok(Rational {
+            numerator: sign * numerator / divisor,
+            denominator: sign * denominator / divisor,
+        })
+    }
+
+    /// The exact value of the shortest decimal of `value`, so `0.1` is `1/10`.
+    fn from_f64(value: f64) -> Result<Rational, EvaluatorError> {
+        if !value.is_finite() {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                format!("{} is not a coefficient", value),
+            )));
+        }
+        let text = format!("{:e}", value);
+        let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+        let fraction_digits = mantissa
+            .split_once('.')
+            .map_or(0, |(_, digits)| digits.len());
+        let digits: i128 = mantissa.replace('.', "").parse().map_err(|_| overflow())?;
+        let exponent = exponent.parse::<i32>().unwrap_or(0) - fraction_digits as i32;
+        let power = 10i128
+            .checked_pow(exponent.unsigned_abs())
+            .ok_or_else(overflow)?;
+        if exponent >= 0 {
+            Ok(Rational::integer(
+                digits.checked_mul(power).ok_or_else(overflow)?,
+            ))
+        } else {
+            Rational::new(digits, power)
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    fn add(self, other: Rational) -> Result<Rational, EvaluatorError> {
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)
+            .zip(other.numerator.checked_mul(self.denominator))
+            .and_then(|(a, b)| a.checked_add(b));
+        let denominator = self.denominator.checked_mul(other.denominator);
+        Rational::new(
+            numerator.ok_or_else(overflow)?,
+            denominator.ok_or_else(overflow)?,
+        )
+    }
+
+    fn negate(self) -> Rational {
+        Rational {
+            numerator: -self.numerator,
+            denominator: self.denominator,
+        }
+    }
+
+    fn multiply(self, other: Rational) -> Result<Rational, EvaluatorError> {
+        let numerator = self.numerator.checked_mul(other.numerator);
+        let denominator = self.denominator.checked_mul(other.denominator);
+        Rational::new(
+            numerator.ok_or_else(overflow)?,
+            denominator.ok_or_else(overflow)?,
+        )
+    }
+
+    fn divide(self, other: Rational) -> Result<Rational, EvaluatorError> {
+        self.multiply(Rational::new(other.denominator, other.numerator)?)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+/// The variables of a term with their exponents, sorted by name.
+type Monomial = Vec<(String, u32)>;
+
+fn multiply_monomials(a: &Monomial, b: &Monomial) -> Monomial {
+    let mut product: BTreeMap<String, u32> = a.iter().cloned().collect();
+    for (variable, exponent) in b {
+        *product.entry(variable.clone()).or_default() += exponent;
+    }
+    product.into_iter().collect()
+}
+
+fn degree(monomial: &Monomial) -> u32 {
+    monomial.iter().map(|(_, exponent)| exponent).sum()
+}
+
+/// The order of the terms when written: higher degrees first, then higher powers of the
+/// first variables in alphabetical order.
+fn graded(a: &Monomial, b: &Monomial) -> Ordering {
+    degree(b).cmp(&degree(a)).then_with(|| {
+        for ((x, i), (y, j)) in a.iter().zip(b) {
+            let order = x.cmp(y).then_with(|| j.cmp(i));
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        b.len().cmp(&a.len())
+    })
+}
+
+/// A polynomial as the nonzero coefficients of its monomials.
+#[derive(Debug, Clone, PartialEq)]
+struct Polynomial {
+    terms: BTreeMap<Monomial, Rational>,
+}
+
+impl Polynomial {
+    fn constant(value: Rational) -> Polynomial {
+        let mut terms = BTreeMap::new();
+        if !value.is_zero() {
+            terms.insert(vec![], value);
+        }
+        Polynomial { terms }
+    }
+
+    fn variable(name: &str) -> Polynomial {
+        let mut terms = BTreeMap::new();
+        terms.insert(vec![(name.to_string(), 1)], Rational::ONE);
+        Polynomial { terms }
+    }
+
+    fn as_constant(&self) -> Option<Rational> {
+        match self.terms.len() {
+            0 => Some(Rational::ZERO),
+            1 => self.terms.get(&vec![]).copied(),
+            _ => None,
+        }
+    }
+
+    fn add_term(&mut self, monomial: Monomial, value: Rational) -> Result<(), EvaluatorError> {
+        let sum = match self.terms.get(&monomial) {
+            Some(own) => own.add(value)?,
+            None => value,
+        };
+        if sum.is_zero() {
+            self.terms.remove(&monomial);
+        } else {
+            self.terms.insert(monomial, sum);
+        }
+        Ok(())
+    }
+
+    fn add(mut self, other: &Polynomial) -> Result<Polynomial, EvaluatorError> {
+        for (monomial, value) in &other.terms {
+            self.add_term(monomial.clone(), *value)?;
+        }
+        Ok(self)
+    }
+
+    fn negate(mut self) -> Polynomial {
+        for value in self.terms.values_mut() {
+            *value = value.negate();
+        }
+        self
+    }
+
+    fn multiply(&self, other: &Polynomial) -> Result<Polynomial, EvaluatorError> {
+        let mut product = Polynomial::constant(Rational::ZERO);
+        for (a, x) in &self.terms {
+            for (b, y) in &other.terms {
+                product.add_term(multiply_monomials(a, b), x.multiply(*y)?)?;
+            }
+        }
+        Ok(product)
+    }
+
+    fn scale(mut self, factor: Rational) -> Result<Polynomial, EvaluatorError> {
+        for value in self.terms.values_mut() {
+            *value = value.multiply(factor)?;
+        }
+        Ok(self)
+    }
+
+    fn power(&self, mut exponent: u64) -> Result<Polynomial, EvaluatorError> {
+        let mut base = self.clone();
+        let mut result = Polynomial::constant(Rational::ONE);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.multiply(&base)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.multiply(&base)?;
+            }
+        }
+        Ok(result)
+    }
+
+    fn from_expr(expr: &Expr) -> Result<Polynomial, EvaluatorError> {
+        let not_polynomial = || {
+            EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+                "{} is not a polynomial",
+                expr
+            )))
+            .or_span(expr.span)
+        };
+        match &expr.kind {
+            ExprKind::Number(value) | ExprKind::Value(Value::Number(value)) => {
+                Ok(Polynomial::constant(Rational::from_f64(*value)?))
+            }
+            ExprKind::Variable(name) => Ok(Polynomial::variable(name)),
+            ExprKind::Unary(UnaryOperator::Negate, operand) => {
+                Ok(Polynomial::from_expr(operand)?.negate())
+            }
+            ExprKind::Unary(UnaryOperator::Percent, operand) => {
+                Polynomial::from_expr(operand)?.scale(Rational::new(1, 100)?)
+            }
+            ExprKind::Binary(operator, lhs, rhs) => {
+                let lhs = Polynomial::from_expr(lhs)?;
+                let rhs = Polynomial::from_expr(rhs)?;
+                match operator {
+                    BinaryOperator::Add => lhs.add(&rhs),
+                    BinaryOperator::Subtract => lhs.add(&rhs.negate()),
+                    BinaryOperator::Multiply => lhs.multiply(&rhs),
+                    BinaryOperator::Divide => match rhs.as_constant() {
+                        Some(divisor) => lhs.scale(Rational::ONE.divide(divisor)?),
+                        None => Err(not_polynomial()),
+                    },
+                    BinaryOperator::Power => match rhs.as_constant() {
+                        Some(exponent) if exponent.denominator == 1 && exponent.numerator >= 0 => {
+                            let exponent =
+                                u64::try_from(exponent.numerator).map_err(|_| overflow())?;
+                            lhs.power(exponent)
+                        }
+                        _ => Err(not_polynomial()),
+                    },
+                    _ => Err(not_polynomial()),
+                }
+            }
+            _ => Err(not_polynomial()),
+        }
+    }
+
+    fn to_expr(&self, span: Span) -> Expr {
+        let mut terms: Vec<(&Monomial, &Rational)> = self.terms.iter().collect();
+        terms.sort_by(|(a, _), (b, _)| graded(a, b));
+        let mut result: Option<Expr> = None;
+        for (monomial, value) in terms {
+            let negative = value.numerator < 0 && result.is_some();
+            let magnitude = if negative { value.negate() } else { *value };
+            let term = term_expr(monomial, magnitude, span);
+            result = Some(match result {
+                None => term,
+                Some(sum) if negative => binary(BinaryOperator::Subtract, sum, term, span),
+                Some(sum) => binary(BinaryOperator::Add, sum, term, span),
+            });
+        }
+        result.unwrap_or_else(|| number(0, span))
+    }
+
+    /// The only variable, if there is exactly one.
+    fn single_variable(&self) -> Option<&str> {
+        let mut variables = self.terms.keys().flatten().map(|(name, _)| name.as_str());
+        let first = variables.next()?;
+        variables.all(|name| name == first).then_some(first)
+    }
+}
+
+fn number(value: i128, span: Span) -> Expr {
+    Expr::new(ExprKind::Number(value as f64), span)
+}
+
+fn binary(operator: BinaryOperator, lhs: Expr, rhs: Expr, span: Span) -> Expr {
+    Expr::new(
+        ExprKind::Binary(operator, Box::new(lhs), Box::new(rhs)),
+        span,
+    )
+}
+
+/// `n * monomial / d` for the coefficient `n/d`.
+fn term_expr(monomial: &Monomial, value: Rational, span: Span) -> Expr {
+    let factors = monomial
+        .iter()
+        .map(|(variable, exponent)| {
+            let variable = Expr::new(ExprKind::Variable(variable.clone()), span);
+            match exponent {
+                1 => variable,
+                _ => binary(
+                    BinaryOperator::Power,
+                    variable,
+                    number((*exponent).into(), span),
+                    span,
+                ),
+            }
+        })
+        .collect();
+    scaled_product(value, factors, span)
+}
+
+/// `n * factors / d`, the sign going on the first factor.
+fn scaled_product(value: Rational, mut factors: Vec<Expr>, span: Span) -> Expr {
+    if value.numerator.abs() != 1 || factors.is_empty() {
+        factors.insert(0, number(value.numerator.abs(), span));
+    }
+    if value.numerator < 0 {
+        let first = factors.remove(0);
+        let negated = Expr::new(
+            ExprKind::Unary(UnaryOperator::Negate, Box::new(first)),
+            span,
+        );
+        factors.insert(0, negated);
+    }
+    let mut product = factors
+        .into_iter()
+        .reduce(|lhs, rhs| binary(BinaryOperator::Multiply, lhs, rhs, span))
+        .unwrap_or_else(|| number(1, span));
+    if value.denominator != 1 {
+        product = binary(
+            BinaryOperator::Divide,
+            product,
+            number(value.denominator, span),
+            span,
+        );
+    }
+    product
+}
+
+/// The divisors of `n`, or `None` if `n` is too large to search.
+fn divisors(n: u128) -> Option<Vec<u128>> {
+    if n > MAX_ROOT_SEARCH {
+        return None;
+    }
+    let mut small = vec![];
+    let mut large = vec![];
+    let mut d = 1;
+    while d * d <= n {
+        if n.is_multiple_of(d) {
+            small.push(d);
+            if d * d != n {
+                large.push(n / d);
+            }
+        }
+        d += 1;
+    }
+    small.extend(large.into_iter().rev());
+    Some(small)
+}
+
+/// The coefficients of a polynomial in one variable, from the constant term up.
+fn dense(polynomial: &Polynomial) -> Vec<Rational> {
+    let mut coefficients = vec![];
+    for (monomial, value) in &polynomial.terms {
+        let exponent = monomial.first().map_or(0, |(_, exponent)| *exponent) as usize;
+        if coefficients.len() <= exponent {
+            coefficients.resize(exponent + 1, Rational::ZERO);
+        }
+        coefficients[exponent] = *value;
+    }
+    coefficients
+}
+
+fn sparse(coefficients: &[Rational], variable: &str) -> Polynomial {
+    let mut terms = BTreeMap::new();
+    for (exponent, value) in coefficients.iter().enumerate() {
+        if !value.is_zero() {
+            let monomial = match exponent {
+                0 => vec![],
+                _ => vec![(variable.to_string(), exponent as u32)],
+            };
+            terms.insert(monomial, *value);
+        }
+    }
+    Polynomial { terms }
+}
+
+/// Divides the coefficients by `x - root`, returning `None` if there is a remainder.
+fn divide_root(
+    coefficients: &[Rational],
+    root: Rational,
+) -> Result<Option<Vec<Rational>>, EvaluatorError> {
+    let mut quotient = vec![Rational::ZERO; coefficients.len() - 1];
+    let mut carry = Rational::ZERO;
+    for index in (0..coefficients.len()).rev() {
+        let value = coefficients[index].add(carry.multiply(root)?)?;
+        if index == 0 {
+            return Ok(value.is_zero().then_some(quotient));
+        }
+        quotient[index - 1] = value;
+        carry = value;
+    }
+    Ok(None)
+}
+
+/// The value of the polynomial in one variable at `x`, by Horner's method.
+fn evaluate(coefficients: &[Rational], x: Rational) -> Result<Rational, EvaluatorError> {
+    coefficients
+        .iter()
+        .rev()
+        .try_fold(Rational::ZERO, |value, coefficient| {
+            value.multiply(x)?.add(*coefficient)
+        })
+}
+
+/// Divides the coefficients by `x - root` as many times as possible, returning how many.
+fn divide_all(coefficients: &mut Vec<Rational>, root: Rational) -> Result<u32, EvaluatorError> {
+    let mut multiplicity = 0;
+    while coefficients.len() > 1 {
+        match divide_root(coefficients, root)? {
+            Some(quotient) => *coefficients = quotient,
+            None => break,
+        }
+        multiplicity += 1;
+    }
+    Ok(multiplicity)
+}
+
+/// A quotient of polynomials.
+struct Fraction {
+    numerator: Polynomial,
+    denominator: Polynomial,
+}
+
+impl Fraction {
+    fn from_expr(expr: &Expr) -> Result<Fraction, EvaluatorError> {
+        let not_rational = || {
+            EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+                "{} is not a rational function",
+                expr
+            )))
+            .or_span(expr.span)
+        };
+        match &expr.kind {
+            ExprKind::Unary(UnaryOperator::Negate, operand) => {
+                let fraction = Fraction::from_expr(operand)?;
+                Ok(Fraction {
+                    numerator: fraction.numerator.negate(),
+                    denominator: fraction.denominator,
+                })
+            }
+            ExprKind::Binary(operator, lhs, rhs) => {
+                let lhs = Fraction::from_expr(lhs)?;
+                let rhs = Fraction::from_expr(rhs)?;
+                let (numerator, denominator) = match operator {
+                    BinaryOperator::Add | BinaryOperator::Subtract => {
+                        let mut other = rhs.numerator.multiply(&lhs.denominator)?;
+                        if *operator == BinaryOperator::Subtract {
+                            other = other.negate();
+                        }
+                        (
+                            lhs.numerator.multiply(&rhs.denominator)?.add(&other)?,
+                            lhs.denominator.multiply(&rhs.denominator)?,
+                        )
+                    }
+                    BinaryOperator::Multiply => (
+                        lhs.numerator.multiply(&rhs.numerator)?,
+                        lhs.denominator.multiply(&rhs.denominator)?,
+                    ),
+                    BinaryOperator::Divide if !rhs.numerator.terms.is_empty() => (
+                        lhs.numerator.multiply(&rhs.denominator)?,
+                        lhs.denominator.multiply(&rhs.numerator)?,
+                    ),
+                    BinaryOperator::Power => {
+                        let exponent = rhs
+                            .numerator
+                            .as_constant()
+                            .zip(rhs.denominator.as_constant())
+                            .map(|(numerator, denominator)| numerator.divide(denominator))
+                            .transpose()?
+                            .filter(|exponent| exponent.denominator == 1)
+                            .ok_or_else(not_rational)?;
+                        let power = u64::try_from(exponent.numerator.unsigned_abs())
+                            .map_err(|_| overflow())?;
+                        let numerator = lhs.numerator.power(power)?;
+                        let denominator = lhs.denominator.power(power)?;
+                        if exponent.numerator >= 0 {
+                            (numerator, denominator)
+                        } else if numerator.terms.is_empty() {
+                            return Err(not_rational());
+                        } else {
+                            (denominator, numerator)
+                        }
+                    }
+                    _ => return Err(not_rational()),
+                };
+                Ok(Fraction {
+                    numerator,
+                    denominator,
+                })
+            }
+            _ => Ok(Fraction {
+                numerator: Polynomial::from_expr(expr)?,
+                denominator: Polynomial::constant(Rational::ONE),
+            }),
+        }
+    }
+}
+
+/// The limits of a rational function of `variable` at `at`, from below and from above, or
+/// `None` if `expr` is not such a function. Common factors `x - at` are canceled exactly.
+pub(crate) fn rational_limits(expr: &Expr, variable: &str, at: f64) -> Option<(f64, f64)> {
+    let fraction = Fraction::from_expr(expr).ok()?;
+    let polynomials = [&fraction.numerator, &fraction.denominator];
+    if polynomials.iter().any(|polynomial| {
+        polynomial
+            .terms
+            .keys()
+            .flatten()
+            .any(|(name, _)| name != variable)
+    }) {
+        return None;
+    }
+    let mut numerator = dense(&fraction.numerator);
+    let mut denominator = dense(&fraction.denominator);
+    if numerator.is_empty() {
+        return Some((0.0, 0.0));
+    }
+    if at.is_infinite() {
+        let leading = |coefficients: &[Rational]| coefficients[coefficients.len() - 1].to_f64();
+        // The degree of the numerator minus that of the denominator.
+        let growth = numerator.len() as i32 - denominator.len() as i32;
+        let mut ratio = leading(&numerator) / leading(&denominator);
+        if at < 0.0 && growth % 2 != 0 {
+            ratio = -ratio;
+        }
+        let value = match growth.cmp(&0) {
+            Ordering::Less => 0.0,
+            Ordering::Equal => ratio,
+            Ordering::Greater => ratio.signum() * f64::INFINITY,
+        };
+        return Some((value, value));
+    }
+    let root = Rational::from_f64(at).ok()?;
+    // The multiplicity of `at` as a zero, negative for a pole.
+    let zero = divide_all(&mut numerator, root).ok()? as i32
+        - divide_all(&mut denominator, root).ok()? as i32;
+    let ratio = evaluate(&numerator, root)
+        .and_then(|numerator| numerator.divide(evaluate(&denominator, root)?))
+        .ok()?
+        .to_f64();
+    Some(match zero.cmp(&0) {
+        Ordering::Greater => (0.0, 0.0),
+        Ordering::Equal => (ratio, ratio),
+        Ordering::Less => {
+            let above = ratio.signum() * f64::INFINITY;
+            let below = if zero % 2 == 0 { above } else { -above };
+            (below, above)
+        }
+    })
+}
+
+/// `content * product of factors^multiplicity`.
+struct Factorization {
+    content: Rational,
+    factors: Vec<(Polynomial, u32)>,
+}
+
+impl Factorization {
+    fn to_expr(&self, span: Span) -> Expr {
+        let factors = self
+            .factors
+            .iter()
+            .map(|(factor, multiplicity)| match multiplicity {
+                1 => factor.to_expr(span),
+                _ => binary(
+                    BinaryOperator::Power,
+                    factor.to_expr(span),
+                    number((*multiplicity).into(), span),
+                    span,
+                ),
+            })
+            .collect();
+        scaled_product(self.content, factors, span)
+    }
+}
+
+/// Factors out the content, the common powers of variables and, for polynomials in one
+/// variable, the linear factors of the rational roots.
+fn factorize(polynomial: &Polynomial) -> Result<Factorization, EvaluatorError> {
+    let Some((_, leading)) = polynomial
+        .terms
+        .iter()
+        .min_by(|(a, _), (b, _)| graded(a, b))
+    else {
+        return Ok(Factorization {
+            content: Rational::ZERO,
+            factors: vec![],
+        });
+    };
+    // The content makes the other coefficients coprime integers, the leading one positive.
+    let mut numerators = 0;
+    let mut denominators = 1;
+    for value in polynomial.terms.values() {
+        numerators = gcd(numerators, value.numerator.unsigned_abs());
+        let denominator = value.denominator.unsigned_abs();
+        denominators = (denominators / gcd(denominators, denominator))
+            .checked_mul(denominator)
+            .ok_or_else(overflow)?;
+    }
+    let sign = leading.numerator.signum();
+    let content = Rational::new(
+        sign * i128::try_from(numerators).map_err(|_| overflow())?,
+        i128::try_from(denominators).map_err(|_| overflow())?,
+    )?;
+    let mut primitive = polynomial.clone().scale(Rational::ONE.divide(content)?)?;
+    // The common powers of the variables.
+    let mut factors = vec![];
+    let mut common: BTreeMap<String, u32> = BTreeMap::new();
+    if let Some(first) = primitive.terms.keys().next() {
+        common = first.iter().cloned().collect();
+    }
+    for monomial in primitive.terms.keys() {
+        let own: BTreeMap<&String, u32> = monomial.iter().map(|(name, e)| (name, *e)).collect();
+        common.retain(|name, exponent| {
+            *exponent = (*exponent).min(own.get(name).copied().unwrap_or(0));
+            *exponent > 0
+        });
+    }
+    if !common.is_empty() {
+        let terms = std::mem::take(&mut primitive.terms);
+        for (monomial, value) in terms {
+            let reduced: Monomial = monomial
+                .into_iter()
+                .filter_map(|(name, exponent)| {
+                    let exponent = exponent - common.get(&name).copied().unwrap_or(0);
+                    (exponent > 0).then_some((name, exponent))
+                })
+                .collect();
+            primitive.terms.insert(reduced, value);
+        }
+        for (name, exponent) in &common {
+            factors.push((Polynomial::variable(name), *exponent));
+        }
+    }
+    if let Some(variable) = primitive.single_variable().map(str::to_string) {
+        let mut coefficients = dense(&primitive);
+        let constant = coefficients[0].numerator.unsigned_abs();
+        let leading = coefficients[coefficients.len() - 1]
+            .numerator
+            .unsigned_abs();
+        if let (Some(ps), Some(qs)) = (divisors(constant), divisors(leading)) {
+            let mut roots = vec![];
+            for p in &ps {
+                for q in &qs {
+                    for sign in [-1, 1] {
+                        let root = Rational::new(sign * *p as i128, *q as i128)?;
+                        if !roots.contains(&root) {
+                            roots.push(root);
+                        }
+                    }
+                }
+            }
+            roots.sort_by(|a: &Rational, b: &Rational| {
+                (a.numerator * b.denominator).cmp(&(b.numerator * a.denominator))
+            });
+            for root in roots {
+                let multiplicity = divide_all(&mut coefficients, root)?;
+                if multiplicity > 0 {
+                    // q x - p, with integer coefficients as the root is p/q.
+                    let linear = [
+                        Rational::integer(-root.numerator),
+                        Rational::integer(root.denominator),
+                    ];
+                    factors.push((sparse(&linear, &variable), multiplicity));
+                }
+            }
+            // The linear factors are primitive, so the quotient has integer coefficients.
+            let scale = integer_gcd(&coefficients);
+            primitive = sparse(&coefficients, &variable).scale(Rational::ONE.divide(scale)?)?;
+        }
+    }
+    if primitive.as_constant() != Some(Rational::ONE) {
+        factors.push((primitive, 1));
+    }
+    Ok(Factorization { content, factors })
+}
+
+fn integer_gcd(coefficients: &[Rational]) -> Rational {
+    let divisor = coefficients.iter().fold(0, |divisor, value| {
+        gcd(divisor, value.numerator.unsigned_abs())
+    });
+    let sign = coefficients
+        .last()
+        .map_or(1, |value| value.numerator.signum());
+    Rational::integer(sign * divisor.max(1) as i128)
+}
+
+impl Expr {
+    /// Multiplies out the polynomial, collecting the terms with exact rational coefficients:
+    /// `(x + 1)^3` becomes `x^3 + 3 * x^2 + 3 * x + 1`. Every name is a variable, and the
+    /// formula may only use `+`, `-`, `*`, division by constants and whole powers.
+    pub fn expand(&self) -> Result<Expr, EvaluatorError> {
+        Ok(Polynomial::from_expr(self)?.to_expr(self.span))
+    }
+
+    /// Factors the polynomial over the rationals, as far as `x^2 - 1` becoming
+    /// `(x + 1) * (x - 1)`: the common factor of the coefficients and powers of variables
+    /// come out, then the linear factors of polynomials in one variable.
+    pub fn factor(&self) -> Result<Expr, EvaluatorError> {
+        Ok(factorize(&Polynomial::from_expr(self)?)?.to_expr(self.span))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> crate::parser::Expr {
+        Parser::new(source).parse().unwrap()
+    }
+
+    #[test]
+    fn expand_polynomials() {
+        let expand = |source: &str| parse(source).expand().unwrap().to_string();
+        assert_eq!(expand("(x + 1)^3"), "x^3 + 3 * x^2 + 3 * x + 1");
+        assert_eq!(expand("(x + y)^2 - x*y"), "x^2 + x * y + y^2");
+        assert_eq!(expand("x/2 + x/3 - 0.1"), "5 * x / 6 - 1 / 10");
+        assert_eq!(expand("(1 - x)*(1 + x)"), "-x^2 + 1");
+        assert_eq!(expand("(x - x)^2"), "0");
+        assert!(parse("1 / x").expand().is_err());
+        assert!(parse("sin(x)").expand().is_err());
+        assert!(parse("(x + 1)^200").expand().is_err());
+    }
+
+    #[test]
+    fn factor_polynomials() {
+        let factor = |source: &str| parse(source).factor().unwrap().to_string();
+        assert_eq!(factor("x^2 - 1"), "(x + 1) * (x - 1)");
+        assert_eq!(factor("2*x^3 - 2*x"), "2 * x * (x + 1) * (x - 1)");
+        assert_eq!(factor("x^2 - 2*x + 1"), "(x - 1)^2");
+        assert_eq!(factor("6*x^2 - 5*x + 1"), "(3 * x - 1) * (2 * x - 1)");
+        assert_eq!(factor("x^2 + 1"), "x^2 + 1");
+        assert_eq!(factor("x^2*y + x*y^2"), "x * y * (x + y)");
+        assert_eq!(factor("-x^2/2 + 1/2"), "-(x + 1) * (x - 1) / 2");
+        assert_eq!(factor("4"), "4");
+    }
+}
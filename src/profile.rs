@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::evaluator::{Evaluator, EvaluatorError};
+use crate::lexer::Span;
+use crate::parser::Expr;
+use crate::value::Value;
+
+/// Evaluations of a node, or calls of a function, with the time they took including
+/// their subexpressions and nested calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProfileEntry {
+    pub count: usize,
+    pub time: Duration,
+}
+
+/// Where an evaluation spent its time, as recorded by `Evaluator::profile`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Profile {
+    /// By the span of the node in its source. Nodes without a source, as made by
+    /// `simplify`, are together at the empty span.
+    pub nodes: BTreeMap<(usize, usize), ProfileEntry>,
+    /// By the name of the builtin or user-defined function called.
+    pub functions: BTreeMap<String, ProfileEntry>,
+}
+
+impl Profile {
+    pub(crate) fn record_node(&mut self, span: Span, time: Duration) {
+        record(self.nodes.entry((span.start, span.end)).or_default(), time);
+    }
+
+    pub(crate) fn record_function(&mut self, name: &str, time: Duration) {
+        match self.functions.get_mut(name) {
+            Some(entry) => record(entry, time),
+            None => {
+                self.functions
+                    .insert(name.to_string(), ProfileEntry { count: 1, time });
+            }
+        }
+    }
+
+    /// The spans of the nodes by decreasing time.
+    pub fn slowest_nodes(&self) -> Vec<(Span, ProfileEntry)> {
+        let mut nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|(&(start, end), entry)| (Span { start, end }, *entry))
+            .collect();
+        nodes.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.time));
+        nodes
+    }
+}
+
+fn record(entry: &mut ProfileEntry, time: Duration) {
+    entry.count += 1;
+    entry.time += time;
+}
+
+impl<'a> Evaluator<'a> {
+    /// Evaluates `expr` recording the evaluations of each node and the calls of each
+    /// function, with their time, to find out what makes a formula slow. The profile
+    /// covers the evaluation up to an error too.
+    pub fn profile(&mut self, expr: &Expr) -> (Result<Value, EvaluatorError>, Profile) {
+        let outer = self.profile.replace(Profile::default());
+        let result = self.evaluate(expr);
+        let profile = std::mem::replace(&mut self.profile, outer).unwrap_or_default();
+        (result, profile)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evaluator::Context;
+    use crate::parser::Parser;
+
+    #[test]
+    fn profile_evaluations() {
+        let mut context = Context::new();
+        let square = Parser::new("t -> t^2").parse().unwrap();
+        context.set_variable("square", context.evaluate(&square).unwrap());
+        let source = "square(sqrt(16)) + square(3) * integrate(sqrt(x), x, 0, 1)";
+        let expr = Parser::new(source).parse().unwrap();
+        let (result, profile) = context.profile(&expr);
+        assert!(result.is_ok());
+        assert_eq!(profile.functions["square"].count, 2);
+        assert_eq!(profile.functions["integrate"].count, 1);
+        assert!(profile.functions["sqrt"].count > 2);
+        let whole = profile.nodes[&(0, source.len())];
+        assert_eq!(whole.count, 1);
+        assert_eq!(profile.slowest_nodes()[0].1.time, whole.time);
+        let sqrt_x = source.find("sqrt(x)").unwrap();
+        assert!(profile.nodes[&(sqrt_x, sqrt_x + 7)].count > 2);
+        let (result, profile) = context.profile(&Parser::new("1 + 1/0").parse().unwrap());
+        assert!(result.is_err());
+        assert_eq!(profile.nodes[&(0, 1)].count, 1);
+    }
+}
@@ -0,0 +1,118 @@
+/// Smallest and largest bases of `tobase` and `frombase`, whose digits are `0-9a-z`.
+pub const MIN_BASE: u32 = 2;
+pub const MAX_BASE: u32 = 36;
+
+/// A radix results can be displayed in.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Radix {
+    Binary,
+    Octal,
+    Decimal,
+    Hexadecimal,
+}
+
+impl Radix {
+    pub fn base(&self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Decimal => 10,
+            Radix::Hexadecimal => 16,
+        }
+    }
+
+    /// The prefix of literals in this radix, also accepted as input (`0xff`).
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Radix::Binary => "0b",
+            Radix::Octal => "0o",
+            Radix::Decimal => "",
+            Radix::Hexadecimal => "0x",
+        }
+    }
+
+    pub fn from_prefix(prefix: &str) -> Option<Radix> {
+        match prefix {
+            "0b" | "0B" => Some(Radix::Binary),
+            "0o" | "0O" => Some(Radix::Octal),
+            "0x" | "0X" => Some(Radix::Hexadecimal),
+            _ => None,
+        }
+    }
+
+    /// Formats `value` with the prefix of the radix. Only integers can be written in radixes
+    /// other than decimal.
+    pub fn format(&self, value: f64) -> Option<String> {
+        if *self == Radix::Decimal {
+            return Some(value.to_string());
+        }
+        let digits = to_base(value, self.base())?;
+        Some(match digits.strip_prefix('-') {
+            Some(digits) => format!("-{}{}", self.prefix(), digits),
+            None => format!("{}{}", self.prefix(), digits),
+        })
+    }
+}
+
+/// Writes an integer in `base`, with lowercase digits. Returns `None` for values that are
+/// not integers or too large to be represented exactly.
+pub fn to_base(value: f64, base: u32) -> Option<String> {
+    if !(MIN_BASE..=MAX_BASE).contains(&base)
+        || value.fract() != 0.0
+        || value.abs() >= 2f64.powi(127)
+    {
+        return None;
+    }
+    let mut integer = value.abs() as u128;
+    let mut digits = vec![];
+    loop {
+        digits.push(char::from_digit((integer % base as u128) as u32, base)?);
+        integer /= base as u128;
+        if integer == 0 {
+            break;
+        }
+    }
+    if value < 0.0 {
+        digits.push('-');
+    }
+    Some(digits.iter().rev().collect())
+}
+
+/// Reads an integer written in `base`, optionally preceded by `-`.
+pub fn from_base(digits: &str, base: u32) -> Option<f64> {
+    if !(MIN_BASE..=MAX_BASE).contains(&base) || digits.starts_with('+') {
+        return None;
+    }
+    i128::from_str_radix(digits, base)
+        .ok()
+        .map(|value| value as f64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn convert_between_bases() {
+        assert_eq!(to_base(255.0, 16), Some(String::from("ff")));
+        assert_eq!(to_base(-5.0, 2), Some(String::from("-101")));
+        assert_eq!(to_base(0.0, 36), Some(String::from("0")));
+        assert_eq!(to_base(1.5, 2), None);
+        assert_eq!(to_base(10.0, 1), None);
+        assert_eq!(from_base("FF", 16), Some(255.0));
+        assert_eq!(from_base("-z", 36), Some(-35.0));
+        assert_eq!(from_base("12", 2), None);
+        assert_eq!(from_base("+1", 10), None);
+    }
+
+    #[test]
+    fn format_in_radix() {
+        assert_eq!(
+            Radix::Hexadecimal.format(-255.0),
+            Some(String::from("-0xff"))
+        );
+        assert_eq!(Radix::Octal.format(8.0), Some(String::from("0o10")));
+        assert_eq!(Radix::Binary.format(0.5), None);
+        assert_eq!(Radix::Decimal.format(0.5), Some(String::from("0.5")));
+    }
+}
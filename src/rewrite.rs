@@ -0,0 +1,323 @@
+//! Rewriting formulas by rules such as `sin(x)^2 + cos(x)^2 => 1`.
+
+use std::collections::HashMap;
+
+use crate::lexer::Span;
+use crate::parser::{
+    BinaryOperator, Expr, ExprKind, Parser, ParserError, ParserErrorKind, UnaryOperator,
+};
+use crate::value::Value;
+
+/// Passes over a formula after which rewriting stops even if rules still apply, as rules
+/// such as `a + b => b + a` never reach a fixpoint.
+const MAX_PASSES: usize = 64;
+
+/// The subexpressions matched by the variables of a pattern.
+type Bindings = Vec<(String, Expr)>;
+
+/// The terms of a sum, each negative or not, or the factors of a product.
+type Parts = Vec<(bool, Expr)>;
+
+/// Replaces the subexpressions matching `pattern` by `replacement`. The variables of the
+/// pattern match any subexpression, the same wherever they appear, which replaces them in
+/// the replacement. Sums and products match in any order, and a pattern sum or product
+/// also matches some of the terms or factors of a larger one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pattern: Expr,
+    replacement: Expr,
+}
+
+impl Rule {
+    pub fn new(pattern: Expr, replacement: Expr) -> Rule {
+        Rule {
+            pattern,
+            replacement,
+        }
+    }
+
+    /// Parses `pattern => replacement`.
+    pub fn parse(source: &str) -> Result<Rule, ParserError> {
+        let Some(arrow) = source.find("=>") else {
+            return Err(ParserError {
+                kind: ParserErrorKind::UnexpectedEnd { expected: "=>" },
+                span: Span {
+                    start: source.len(),
+                    end: source.len(),
+                },
+            });
+        };
+        let pattern = Parser::new(&source[..arrow]).parse()?;
+        let offset = arrow + 2;
+        let replacement = Parser::new(&source[offset..])
+            .parse()
+            .map_err(|mut error| {
+                error.span.start += offset;
+                error.span.end += offset;
+                error
+            })?;
+        Ok(Rule::new(pattern, replacement))
+    }
+
+    /// Rewrites `expr` itself, not its subexpressions, if it matches.
+    fn rewrite(&self, expr: &Expr) -> Option<Expr> {
+        let mut bindings = vec![];
+        let (patterns, parts, operator) = match &self.pattern.kind {
+            ExprKind::Binary(BinaryOperator::Add | BinaryOperator::Subtract, _, _) => {
+                (terms(&self.pattern), terms(expr), BinaryOperator::Add)
+            }
+            ExprKind::Binary(BinaryOperator::Multiply, _, _) => (
+                factors(&self.pattern),
+                factors(expr),
+                BinaryOperator::Multiply,
+            ),
+            _ => {
+                return matches(&self.pattern, expr, &mut bindings).then(|| self.replace(&bindings))
+            }
+        };
+        let mut used = vec![];
+        if parts.len() < patterns.len() || !match_all(&patterns, &parts, &mut used, &mut bindings) {
+            return None;
+        }
+        // The other terms or factors, then the replacement.
+        let mut result: Option<Expr> = None;
+        let rest = parts
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !used.contains(index));
+        for (_, (negative, part)) in rest.chain([(0, (false, self.replace(&bindings)))]) {
+            result = Some(match result {
+                None if negative => negate(part),
+                None => part,
+                Some(lhs) => {
+                    let operator = match operator {
+                        BinaryOperator::Add if negative => BinaryOperator::Subtract,
+                        operator => operator,
+                    };
+                    let span = lhs.span;
+                    Expr::new(
+                        ExprKind::Binary(operator, Box::new(lhs), Box::new(part)),
+                        span,
+                    )
+                }
+            });
+        }
+        result
+    }
+
+    fn replace(&self, bindings: &Bindings) -> Expr {
+        let replacements: HashMap<String, Expr> = bindings.iter().cloned().collect();
+        self.replacement.substitute_all(&replacements)
+    }
+}
+
+fn negate(expr: Expr) -> Expr {
+    let span = expr.span;
+    Expr::new(ExprKind::Unary(UnaryOperator::Negate, Box::new(expr)), span)
+}
+
+fn terms(expr: &Expr) -> Parts {
+    match &expr.kind {
+        ExprKind::Binary(BinaryOperator::Add, lhs, rhs) => [terms(lhs), terms(rhs)].concat(),
+        ExprKind::Binary(BinaryOperator::Subtract, lhs, rhs) => {
+            let negated = terms(rhs)
+                .into_iter()
+                .map(|(negative, term)| (!negative, term));
+            terms(lhs).into_iter().chain(negated).collect()
+        }
+        ExprKind::Unary(UnaryOperator::Negate, operand) => terms(operand)
+            .into_iter()
+            .map(|(negative, term)| (!negative, term))
+            .collect(),
+        _ => vec![(false, expr.clone())],
+    }
+}
+
+fn factors(expr: &Expr) -> Parts {
+    match &expr.kind {
+        ExprKind::Binary(BinaryOperator::Multiply, lhs, rhs) => {
+            [factors(lhs), factors(rhs)].concat()
+        }
+        _ => vec![(false, expr.clone())],
+    }
+}
+
+/// Matches each pattern to a distinct part of the same sign, in any order, pushing the
+/// indices of the parts matched to `used`.
+fn match_all(
+    patterns: &[(bool, Expr)],
+    parts: &[(bool, Expr)],
+    used: &mut Vec<usize>,
+    bindings: &mut Bindings,
+) -> bool {
+    let Some(((sign, pattern), patterns)) = patterns.split_first() else {
+        return true;
+    };
+    for (index, (negative, part)) in parts.iter().enumerate() {
+        if used.contains(&index) || negative != sign {
+            continue;
+        }
+        let mut attempt = bindings.clone();
+        if matches(pattern, part, &mut attempt) {
+            used.push(index);
+            if match_all(patterns, parts, used, &mut attempt) {
+                *bindings = attempt;
+                return true;
+            }
+            used.pop();
+        }
+    }
+    false
+}
+
+fn matches(pattern: &Expr, expr: &Expr, bindings: &mut Bindings) -> bool {
+    match (&pattern.kind, &expr.kind) {
+        (ExprKind::Variable(name), _) => match bindings.iter().find(|(bound, _)| bound == name) {
+            Some((_, bound)) => bound == expr,
+            None => {
+                bindings.push((name.clone(), expr.clone()));
+                true
+            }
+        },
+        (ExprKind::Binary(BinaryOperator::Add | BinaryOperator::Subtract, _, _), _) => {
+            let (patterns, parts) = (terms(pattern), terms(expr));
+            patterns.len() == parts.len() && match_all(&patterns, &parts, &mut vec![], bindings)
+        }
+        (ExprKind::Binary(BinaryOperator::Multiply, _, _), _) => {
+            let (patterns, parts) = (factors(pattern), factors(expr));
+            patterns.len() == parts.len() && match_all(&patterns, &parts, &mut vec![], bindings)
+        }
+        (ExprKind::Number(a), ExprKind::Number(b) | ExprKind::Value(Value::Number(b))) => a == b,
+        (ExprKind::Unary(operator, operand), ExprKind::Unary(other, expr)) => {
+            operator == other && matches(operand, expr, bindings)
+        }
+        (ExprKind::Binary(operator, lhs, rhs), ExprKind::Binary(other, left, right)) => {
+            operator == other && matches(lhs, left, bindings) && matches(rhs, right, bindings)
+        }
+        (ExprKind::Call(name, patterns), ExprKind::Call(other, args)) => {
+            name == other
+                && patterns.len() == args.len()
+                && patterns
+                    .iter()
+                    .zip(args)
+                    .all(|(pattern, arg)| matches(pattern, arg, bindings))
+        }
+        _ => pattern == expr,
+    }
+}
+
+/// Rules applied together, the first matching rule rewriting each subexpression.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn new() -> RuleSet {
+        RuleSet::default()
+    }
+
+    pub fn push(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    /// Rewrites the subexpressions of `expr`, innermost first, until no rule applies.
+    pub fn apply(&self, expr: &Expr) -> Expr {
+        let mut current = expr.clone();
+        for _ in 0..MAX_PASSES {
+            let (next, changed) = self.pass(&current);
+            if !changed {
+                break;
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Rewrites each subexpression at most once, returning whether any was.
+    fn pass(&self, expr: &Expr) -> (Expr, bool) {
+        let mut changed = false;
+        let mut pass = |expr: &Expr| {
+            let (expr, rewritten) = self.pass(expr);
+            changed |= rewritten;
+            Box::new(expr)
+        };
+        let kind = match &expr.kind {
+            ExprKind::Unary(operator, operand) => ExprKind::Unary(*operator, pass(operand)),
+            ExprKind::Binary(operator, lhs, rhs) => {
+                ExprKind::Binary(*operator, pass(lhs), pass(rhs))
+            }
+            ExprKind::Conditional(condition, then, otherwise) => {
+                ExprKind::Conditional(pass(condition), pass(then), pass(otherwise))
+            }
+            ExprKind::Call(name, args) => {
+                ExprKind::Call(name.clone(), args.iter().map(|arg| *pass(arg)).collect())
+            }
+            ExprKind::Tuple(items) => {
+                ExprKind::Tuple(items.iter().map(|item| *pass(item)).collect())
+            }
+            kind => kind.clone(),
+        };
+        let expr = Expr::new(kind, expr.span);
+        for rule in &self.rules {
+            if let Some(rewritten) = rule.rewrite(&expr) {
+                return (rewritten, true);
+            }
+        }
+        (expr, changed)
+    }
+}
+
+impl Expr {
+    /// Simplifies the formula with the rules as well, alternating both until neither
+    /// changes it.
+    pub fn simplify_with(&self, rules: &RuleSet) -> Expr {
+        let mut current = self.simplify();
+        for _ in 0..MAX_PASSES {
+            let next = rules.apply(&current).simplify();
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(sources: &[&str]) -> RuleSet {
+        let mut rules = RuleSet::new();
+        for source in sources {
+            rules.push(Rule::parse(source).unwrap());
+        }
+        rules
+    }
+
+    fn parse(source: &str) -> Expr {
+        Parser::new(source).parse().unwrap()
+    }
+
+    #[test]
+    fn rewrite_with_rules() {
+        let trigonometry = rules(&["sin(x)^2 + cos(x)^2 => 1", "ln(a * b) => ln(a) + ln(b)"]);
+        let simplify = |source: &str| parse(source).simplify_with(&trigonometry).to_string();
+        assert_eq!(simplify("2 + cos(a*b)^2 + sin(a*b)^2"), "3");
+        assert_eq!(simplify("ln(2 * y) - ln(y)"), "ln(2)");
+        assert_eq!(simplify("sin(a)^2 + cos(b)^2"), "cos(b)^2 + sin(a)^2");
+        assert_eq!(simplify("sqrt(sin(t)^2 + cos(t)^2)"), "sqrt(1)");
+        let doubling = rules(&["x + x => 2 * x"]);
+        assert_eq!(doubling.apply(&parse("f(y + y)")).to_string(), "f(2 * y)");
+        assert_eq!(doubling.apply(&parse("y + z")).to_string(), "y + z");
+        let endless = rules(&["a + b => b + a"]);
+        let swapped = endless.apply(&parse("1 + 2")).to_string();
+        assert!(swapped == "1 + 2" || swapped == "2 + 1");
+        assert!(matches!(
+            Rule::parse("x + 1").map_err(|error| error.kind),
+            Err(ParserErrorKind::UnexpectedEnd { expected: "=>" })
+        ));
+        assert_eq!(Rule::parse("x => (").unwrap_err().span.start, 6);
+    }
+}
@@ -0,0 +1,119 @@
+use std::cmp::Ordering;
+
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+
+/// How `round` and `roundsig` pick between the two nearest candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RoundingMode {
+    /// To the nearest, ties away from zero.
+    HalfUp,
+    /// To the nearest, ties towards zero.
+    HalfDown,
+    /// To the nearest, ties to an even last digit.
+    HalfEven,
+    /// Away from zero.
+    Up,
+    /// Towards zero.
+    Down,
+    Ceiling,
+    Floor,
+}
+
+impl RoundingMode {
+    pub(crate) fn from_name(name: &str) -> Result<RoundingMode, EvaluatorError> {
+        Ok(match name {
+            "half-up" => RoundingMode::HalfUp,
+            "half-down" => RoundingMode::HalfDown,
+            "half-even" => RoundingMode::HalfEven,
+            "up" => RoundingMode::Up,
+            "down" => RoundingMode::Down,
+            "ceiling" => RoundingMode::Ceiling,
+            "floor" => RoundingMode::Floor,
+            _ => {
+                return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                    format!(
+                        "Unknown rounding mode \"{}\", expected \"half-up\", \"half-down\", \
+                         \"half-even\", \"up\", \"down\", \"ceiling\" or \"floor\"",
+                        name
+                    ),
+                )))
+            }
+        })
+    }
+}
+
+/// Rounds `value` to a multiple of `10^last`, `last` being computed from the exponent of
+/// the leading digit of `value`. Rounding is decided on the shortest decimal of `value`,
+/// so `2.675` has a tie at two decimals although its binary value is slightly below.
+pub(crate) fn round_at(value: f64, mode: RoundingMode, last: impl FnOnce(i32) -> i32) -> f64 {
+    if !value.is_finite() || value == 0.0 {
+        return value;
+    }
+    let text = format!("{:e}", value.abs());
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let digits: Vec<u8> = mantissa.bytes().filter(u8::is_ascii_digit).collect();
+    // The number of leading digits kept, negative if the value is below the last digit.
+    let keep = exponent + 1 - last(exponent);
+    if keep >= digits.len() as i32 {
+        return value;
+    }
+    let (kept, rest) = digits.split_at(keep.max(0) as usize);
+    let kept = kept
+        .iter()
+        .fold(0u64, |kept, digit| kept * 10 + u64::from(digit - b'0'));
+    let half = match rest.first() {
+        _ if keep < 0 => Ordering::Less,
+        Some(b'5') if rest[1..].iter().all(|digit| *digit == b'0') => Ordering::Equal,
+        Some(digit) if *digit >= b'5' => Ordering::Greater,
+        _ => Ordering::Less,
+    };
+    let negative = value < 0.0;
+    let away = match mode {
+        RoundingMode::Up => true,
+        RoundingMode::Down => false,
+        RoundingMode::Ceiling => !negative,
+        RoundingMode::Floor => negative,
+        RoundingMode::HalfUp => half != Ordering::Less,
+        RoundingMode::HalfDown => half == Ordering::Greater,
+        RoundingMode::HalfEven => {
+            half == Ordering::Greater || half == Ordering::Equal && kept % 2 == 1
+        }
+    };
+    let rounded = kept + u64::from(away);
+    let sign = if negative { "-" } else { "" };
+    format!("{}{}e{}", sign, rounded, exponent + 1 - keep)
+        .parse()
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round(value: f64, decimals: i32, mode: RoundingMode) -> f64 {
+        round_at(value, mode, |_| -decimals)
+    }
+
+    #[test]
+    fn round_in_modes() {
+        assert_eq!(round(2.675, 2, RoundingMode::HalfUp), 2.68);
+        assert_eq!(round(2.675, 2, RoundingMode::HalfEven), 2.68);
+        assert_eq!(round(2.665, 2, RoundingMode::HalfEven), 2.66);
+        assert_eq!(round(2.665, 2, RoundingMode::HalfDown), 2.66);
+        assert_eq!(round(-2.5, 0, RoundingMode::HalfUp), -3.0);
+        assert_eq!(round(-2.5, 0, RoundingMode::HalfEven), -2.0);
+        assert_eq!(round(-2.1, 0, RoundingMode::Floor), -3.0);
+        assert_eq!(round(-2.9, 0, RoundingMode::Ceiling), -2.0);
+        assert_eq!(round(0.001, 1, RoundingMode::Up), 0.1);
+        assert_eq!(round(0.001, 1, RoundingMode::HalfUp), 0.0);
+        assert_eq!(round(1250.0, -2, RoundingMode::HalfEven), 1200.0);
+        assert_eq!(round(9.99, 1, RoundingMode::HalfUp), 10.0);
+        assert_eq!(round(1.5, 3, RoundingMode::Up), 1.5);
+        assert_eq!(
+            round_at(123456.0, RoundingMode::Down, |exponent| exponent - 1),
+            120000.0
+        );
+        assert!(RoundingMode::from_name("sideways").is_err());
+    }
+}
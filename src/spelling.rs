@@ -0,0 +1,65 @@
+//! Names close to unknown ones, suggested by errors as "did you mean 'sqrt'?".
+
+/// Names shorter than this are too short to tell a typo from another name.
+const MIN_LENGTH: usize = 3;
+
+/// The number of characters inserted, deleted, substituted or swapped with the next to
+/// turn `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // `rows[i][j]` is the distance between the first `i` characters of `a` and `j` of `b`.
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substituted = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut best = substituted.min(rows[i - 1][j] + 1).min(rows[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// The candidate closest to `name`, within an edit for each three characters of it; the
+/// first of those as close.
+pub(crate) fn closest<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let length = name.chars().count();
+    if length < MIN_LENGTH {
+        return None;
+    }
+    let most = (length / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= most)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn find_close_names() {
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("widht", "width"), 1);
+        assert_eq!(distance("", "abc"), 3);
+        let names = ["sin", "sqrt", "sum", "width"];
+        assert_eq!(closest("sqr", names).as_deref(), Some("sqrt"));
+        assert_eq!(closest("widht", names).as_deref(), Some("width"));
+        assert_eq!(closest("cos", names), None);
+        assert_eq!(closest("sn", names), None);
+    }
+}
@@ -0,0 +1,181 @@
+//! Scripts evaluated a statement at a time as they are read, for files too large to load:
+//! only the line being evaluated is kept, so memory is bounded by the longest line and
+//! the variables defined.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+use crate::document::{Document, Statement};
+use crate::error::Error;
+use crate::evaluator::Context;
+use crate::lexer::Span;
+use crate::parser::{Expr, ExprKind, ParserError, ParserErrorKind};
+use crate::value::Value;
+
+/// A statement evaluated by a [`Stream`], with its spans and those of its error within
+/// its line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluated {
+    /// The number of the line, from 1.
+    pub line: usize,
+    /// The text of the line, without its end.
+    pub text: String,
+    pub span: Span,
+    /// The variable or function assigned, for statements such as `f(x) = x^2`.
+    pub assigned: Option<String>,
+    /// The value of the formula, or the one assigned.
+    pub result: Result<Value, Error>,
+}
+
+/// The statements of a reader evaluated in order in a context, which keeps the
+/// assignments. Statements end at new lines, as in a [`Document`].
+pub struct Stream<'a, R> {
+    context: &'a mut Context,
+    reader: R,
+    line: usize,
+    text: String,
+    statements: VecDeque<Statement>,
+}
+
+impl Context {
+    /// Evaluates the statements read from `reader` as they are asked for, as `x = 2`,
+    /// `f(x) = x^2` or formulas.
+    pub fn stream<R: BufRead>(&mut self, reader: R) -> Stream<'_, R> {
+        Stream {
+            context: self,
+            reader,
+            line: 0,
+            text: String::new(),
+            statements: VecDeque::new(),
+        }
+    }
+}
+
+impl<R> Stream<'_, R> {
+    /// The context, with the assignments of the statements evaluated so far.
+    pub fn context(&self) -> &Context {
+        self.context
+    }
+}
+
+impl<R: BufRead> Iterator for Stream<'_, R> {
+    type Item = io::Result<Evaluated>;
+
+    fn next(&mut self) -> Option<io::Result<Evaluated>> {
+        while self.statements.is_empty() {
+            self.text.clear();
+            match self.reader.read_line(&mut self.text) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(error) => return Some(Err(error)),
+            }
+            self.line += 1;
+            let end = self.text.trim_end_matches(['\n', '\r']).len();
+            self.text.truncate(end);
+            self.statements = Document::new(&self.text).statements().to_vec().into();
+        }
+        let statement = self.statements.pop_front()?;
+        let (assigned, result) = execute(self.context, statement.target, statement.formula);
+        Some(Ok(Evaluated {
+            line: self.line,
+            text: self.text.clone(),
+            span: statement.span,
+            assigned,
+            result,
+        }))
+    }
+}
+
+fn execute(
+    context: &mut Context,
+    target: Option<Result<Expr, ParserError>>,
+    formula: Result<Expr, ParserError>,
+) -> (Option<String>, Result<Value, Error>) {
+    let Some(target) = target else {
+        let value = formula.map_err(Error::from);
+        return (
+            None,
+            value.and_then(|formula| Ok(context.evaluate(&formula)?)),
+        );
+    };
+    let mut target = match target {
+        Ok(target) => target,
+        Err(error) => return (None, Err(error.into())),
+    };
+    let kind = std::mem::replace(&mut target.kind, ExprKind::Number(0.0));
+    let (name, formula) = match (kind, formula) {
+        (_, Err(error)) => return (None, Err(error.into())),
+        (ExprKind::Variable(name), Ok(formula)) => (name, formula),
+        (ExprKind::Call(name, args), Ok(formula)) => {
+            let params: Option<Vec<String>> = args
+                .into_iter()
+                .map(|arg| match &arg.kind {
+                    ExprKind::Variable(param) => Some(param.clone()),
+                    _ => None,
+                })
+                .collect();
+            let Some(params) = params else {
+                return (None, Err(not_a_name(target.span)));
+            };
+            let span = formula.span;
+            let lambda = Expr::new(ExprKind::Lambda(params, Box::new(formula)), span);
+            (name, lambda)
+        }
+        _ => return (None, Err(not_a_name(target.span))),
+    };
+    match context.evaluate(&formula) {
+        Ok(value) => {
+            context.set_variable(&name, value.clone());
+            (Some(name), Ok(value))
+        }
+        Err(error) => (Some(name), Err(error.into())),
+    }
+}
+
+fn not_a_name(span: Span) -> Error {
+    Error::Parser(ParserError {
+        kind: ParserErrorKind::UnexpectedToken {
+            found: String::from("="),
+            expected: "a name or a function such as f(x)",
+        },
+        span,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evaluate_streams() {
+        let source = "r = 2 # radius\r\narea(r) = pi * r^2; round(area(r))\n\n2 *\nr\n";
+        let mut context = Context::new();
+        let evaluated: Vec<Evaluated> = context
+            .stream(source.as_bytes())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        let summary: Vec<_> = evaluated
+            .iter()
+            .map(|evaluated| {
+                let result = match &evaluated.result {
+                    Ok(value) => value.to_string(),
+                    Err(error) => error.code().to_string(),
+                };
+                (evaluated.line, evaluated.assigned.as_deref(), result)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, Some("r"), String::from("2")),
+                (2, Some("area"), String::from("r -> pi * r^2")),
+                (2, None, String::from("13")),
+                (4, None, String::from("E0102")),
+                (5, None, String::from("2")),
+            ]
+        );
+        assert_eq!(evaluated[2].text, "area(r) = pi * r^2; round(area(r))");
+        assert_eq!(evaluated[2].span, Span::new(20, 34));
+        assert_eq!(context.variable("r"), Some(&Value::Number(2.0)));
+    }
+}
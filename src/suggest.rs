@@ -0,0 +1,131 @@
+//! Completions at a cursor for editors and GUIs, by what may come there in the grammar.
+
+use crate::classify::TokenClass;
+use crate::evaluator::Context;
+use crate::lexer::{Lexer, Span, Token};
+
+/// A completion: `text` replaces the span of the source, which is the part of a name
+/// typed before the cursor, or empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub text: String,
+    /// `Function`, `Variable` or `Unit` for names, `Punctuation` for `)`.
+    pub kind: TokenClass,
+    pub replace: Span,
+}
+
+impl Context {
+    /// The completions at the byte `cursor` of `source`, a formula or script: the names
+    /// starting with what is typed where an operand may come, in the order of
+    /// `completions`, units only after a number or `in`, and `)` after an operand while
+    /// parentheses are open. There are none in strings and comments.
+    pub fn suggest(&self, source: &str, cursor: usize) -> Vec<Suggestion> {
+        let start = source[..cursor]
+            .rfind([';', '\n'])
+            .map_or(0, |index| index + 1);
+        let mut lexer = Lexer::new(&source[start..cursor]);
+        let mut tokens = vec![];
+        loop {
+            match lexer.next_spanned_token() {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => break,
+                Err(_) => return vec![],
+            }
+        }
+        let (prefix, replace) = match tokens.last() {
+            Some((Token::Identifier(name), span)) if start + span.end == cursor => {
+                let replace = Span::new(start + span.start, cursor);
+                let prefix = String::from_utf8_lossy(name).into_owned();
+                tokens.pop();
+                (prefix, replace)
+            }
+            _ => (String::new(), Span::new(cursor, cursor)),
+        };
+        let (units_only, operand) = match tokens.last().map(|(token, _)| token) {
+            Some(Token::Identifier(name)) if name == b"in" || name == b"of" => (true, false),
+            Some(Token::Number(_)) => (true, true),
+            Some(Token::Identifier(_) | Token::String(_) | Token::ClosedParenthesis) => {
+                (false, true)
+            }
+            _ => (false, false),
+        };
+        let mut suggestions = vec![];
+        let depth = tokens.iter().fold(0isize, |depth, (token, _)| match token {
+            Token::OpenParenthesis => depth + 1,
+            Token::ClosedParenthesis => depth - 1,
+            _ => depth,
+        });
+        if operand && prefix.is_empty() && depth > 0 {
+            suggestions.push(Suggestion {
+                text: String::from(")"),
+                kind: TokenClass::Punctuation,
+                replace,
+            });
+        }
+        if operand && !units_only && prefix.is_empty() {
+            return suggestions;
+        }
+        for name in self.completions(&prefix) {
+            let kind = self.class_of(&name, false);
+            if units_only && kind != TokenClass::Unit {
+                continue;
+            }
+            suggestions.push(Suggestion {
+                text: name,
+                kind,
+                replace,
+            });
+        }
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::value::Value;
+
+    #[test]
+    fn suggest_completions() {
+        let mut context = Context::new();
+        context.set_variable("sinus", Value::Number(2.0));
+        let suggest = |source: &str| {
+            let cursor = source.find('|').unwrap();
+            let source = source.replace('|', "");
+            context
+                .suggest(&source, cursor)
+                .into_iter()
+                .map(|suggestion| (suggestion.text, suggestion.kind, suggestion.replace))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            suggest("x = 1; 2 * sin|"),
+            [
+                (String::from("sin"), TokenClass::Function, Span::new(11, 14)),
+                (
+                    String::from("sinh"),
+                    TokenClass::Function,
+                    Span::new(11, 14)
+                ),
+                (
+                    String::from("sinus"),
+                    TokenClass::Variable,
+                    Span::new(11, 14)
+                ),
+            ]
+        );
+        assert_eq!(
+            suggest("sqrt(x |"),
+            [(String::from(")"), TokenClass::Punctuation, Span::new(7, 7))]
+        );
+        assert_eq!(
+            suggest("5 mi in f|"),
+            [(String::from("ft"), TokenClass::Unit, Span::new(8, 9))]
+        );
+        assert!(suggest("2 |")
+            .iter()
+            .all(|(_, kind, _)| *kind == TokenClass::Unit));
+        assert_eq!(suggest("(x + 1)|"), []);
+        assert_eq!(suggest("\"sin|"), []);
+    }
+}
@@ -0,0 +1,926 @@
+//! Symbolic simplification of formulas with free variables.
+
+use crate::evaluator::{Context, EvaluatorError, EvaluatorErrorKind};
+use crate::lexer::Span;
+use crate::parser::{BinaryOperator, Expr, ExprKind, Parser, UnaryOperator};
+use crate::value::Value;
+
+/// A power `base^exponent` of an expression which is not a sum, product or power with a
+/// constant exponent. Bases are identified by their text.
+#[derive(Debug, Clone)]
+struct Factor {
+    key: String,
+    base: Expr,
+    exponent: f64,
+    /// The terms of a base which is a sum, expanded again when the factor is added.
+    sum: Option<Sum>,
+}
+
+impl Factor {
+    /// Orders variables before other bases, as in `x * sin(x)`.
+    fn rank(&self) -> u8 {
+        match self.base.kind {
+            ExprKind::Variable(_) => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// `coefficient * factors`, with factors of distinct bases sorted by rank and text.
+#[derive(Debug, Clone)]
+struct Term {
+    coefficient: f64,
+    factors: Vec<Factor>,
+}
+
+impl Term {
+    fn constant(coefficient: f64) -> Term {
+        Term {
+            coefficient,
+            factors: vec![],
+        }
+    }
+
+    fn atom(base: Expr, sum: Option<Sum>) -> Term {
+        Term {
+            coefficient: 1.0,
+            factors: vec![Factor {
+                key: base.to_string(),
+                base,
+                exponent: 1.0,
+                sum,
+            }],
+        }
+    }
+
+    fn like(&self, other: &Term) -> bool {
+        self.factors.len() == other.factors.len()
+            && self
+                .factors
+                .iter()
+                .zip(&other.factors)
+                .all(|(a, b)| a.key == b.key && a.exponent == b.exponent)
+    }
+
+    fn multiply(mut self, other: Term) -> Term {
+        self.coefficient *= other.coefficient;
+        for factor in other.factors {
+            match self.factors.iter_mut().find(|own| own.key == factor.key) {
+                Some(own) => own.exponent += factor.exponent,
+                None => self.factors.push(factor),
+            }
+        }
+        self.factors.retain(|factor| factor.exponent != 0.0);
+        self.factors
+            .sort_by(|a, b| (a.rank(), &a.key).cmp(&(b.rank(), &b.key)));
+        self
+    }
+
+    fn power(mut self, exponent: f64) -> Term {
+        self.coefficient = self.coefficient.powf(exponent);
+        for factor in &mut self.factors {
+            factor.exponent *= exponent;
+        }
+        self.factors.retain(|factor| factor.exponent != 0.0);
+        self
+    }
+
+    fn degree(&self) -> f64 {
+        self.factors.iter().map(|factor| factor.exponent).sum()
+    }
+
+    /// The terms of `c * (a + b)`, `c (a + b)` itself being a single term.
+    fn expand(self) -> Vec<Term> {
+        match self.factors.as_slice() {
+            [Factor {
+                exponent,
+                sum: Some(sum),
+                ..
+            }] if *exponent == 1.0 => sum
+                .terms
+                .iter()
+                .map(|term| Term::constant(self.coefficient).multiply(term.clone()))
+                .collect(),
+            _ => vec![self],
+        }
+    }
+
+    /// The term as a product over a product, without its sign unless `signed`.
+    fn to_expr(&self, span: Span, signed: bool) -> Expr {
+        let product = |factors: Vec<Expr>| {
+            factors
+                .into_iter()
+                .reduce(|lhs, rhs| binary(BinaryOperator::Multiply, lhs, rhs, span))
+        };
+        let power = |factor: &Factor, exponent: f64| {
+            if exponent == 1.0 {
+                factor.base.clone()
+            } else {
+                let exponent = Expr::new(ExprKind::Number(exponent), span);
+                binary(BinaryOperator::Power, factor.base.clone(), exponent, span)
+            }
+        };
+        let negative = signed && self.coefficient < 0.0;
+        let magnitude = self.coefficient.abs();
+        let mut numerator = vec![];
+        let coefficient =
+            magnitude != 1.0 || self.factors.iter().all(|factor| factor.exponent < 0.0);
+        if coefficient {
+            let value = if negative { -magnitude } else { magnitude };
+            numerator.push(Expr::new(ExprKind::Number(value), span));
+        }
+        let mut denominator = vec![];
+        for factor in &self.factors {
+            if factor.exponent > 0.0 {
+                numerator.push(power(factor, factor.exponent));
+            } else {
+                denominator.push(power(factor, -factor.exponent));
+            }
+        }
+        let numerator =
+            product(numerator).unwrap_or_else(|| Expr::new(ExprKind::Number(1.0), span));
+        let term = match product(denominator) {
+            Some(denominator) => binary(BinaryOperator::Divide, numerator, denominator, span),
+            None => numerator,
+        };
+        if negative && !coefficient {
+            return Expr::new(ExprKind::Unary(UnaryOperator::Negate, Box::new(term)), span);
+        }
+        term
+    }
+}
+
+/// A sum of unlike terms with nonzero coefficients; zero without terms.
+#[derive(Debug, Clone)]
+struct Sum {
+    terms: Vec<Term>,
+}
+
+impl Sum {
+    fn term(term: Term) -> Sum {
+        let mut sum = Sum { terms: vec![] };
+        sum.push(term);
+        sum
+    }
+
+    fn push(&mut self, term: Term) {
+        match self.terms.iter().position(|own| own.like(&term)) {
+            Some(index) => {
+                self.terms[index].coefficient += term.coefficient;
+                if self.terms[index].coefficient == 0.0 {
+                    self.terms.remove(index);
+                }
+            }
+            None if term.coefficient != 0.0 => self.terms.push(term),
+            None => {}
+        }
+    }
+
+    fn add(self, other: Sum) -> Sum {
+        let mut sum = Sum { terms: vec![] };
+        for term in self.terms.into_iter().chain(other.terms) {
+            for term in term.expand() {
+                sum.push(term);
+            }
+        }
+        sum
+    }
+
+    fn negate(mut self) -> Sum {
+        for term in &mut self.terms {
+            term.coefficient = -term.coefficient;
+        }
+        self
+    }
+
+    /// The sum as a single term, a sum of several terms becoming a factor.
+    fn into_term(self, span: Span) -> Term {
+        match self.terms.len() {
+            0 => Term::constant(0.0),
+            1 => self.terms.into_iter().next().unwrap_or(Term::constant(0.0)),
+            _ => Term::atom(self.to_expr(span), Some(self)),
+        }
+    }
+
+    fn constant(&self) -> Option<f64> {
+        match self.terms.as_slice() {
+            [] => Some(0.0),
+            [term] if term.factors.is_empty() => Some(term.coefficient),
+            _ => None,
+        }
+    }
+
+    /// Terms of higher degree first, then by the text of their factors.
+    fn to_expr(&self, span: Span) -> Expr {
+        let mut terms: Vec<&Term> = self.terms.iter().collect();
+        terms.sort_by(|a, b| {
+            let keys = |term: &Term| -> Vec<String> {
+                term.factors
+                    .iter()
+                    .map(|factor| factor.key.clone())
+                    .collect()
+            };
+            b.degree()
+                .total_cmp(&a.degree())
+                .then_with(|| keys(a).cmp(&keys(b)))
+        });
+        let mut result: Option<Expr> = None;
+        for term in terms {
+            result = Some(match result {
+                None => term.to_expr(span, true),
+                Some(sum) => {
+                    let operator = if term.coefficient < 0.0 {
+                        BinaryOperator::Subtract
+                    } else {
+                        BinaryOperator::Add
+                    };
+                    binary(operator, sum, term.to_expr(span, false), span)
+                }
+            });
+        }
+        result.unwrap_or_else(|| Expr::new(ExprKind::Number(0.0), span))
+    }
+}
+
+fn binary(operator: BinaryOperator, lhs: Expr, rhs: Expr, span: Span) -> Expr {
+    Expr::new(
+        ExprKind::Binary(operator, Box::new(lhs), Box::new(rhs)),
+        span,
+    )
+}
+
+fn sum(expr: &Expr) -> Sum {
+    let span = expr.span;
+    let atom = |kind| Sum::term(Term::atom(Expr::new(kind, span), None));
+    match &expr.kind {
+        ExprKind::Number(value) | ExprKind::Value(Value::Number(value)) => {
+            Sum::term(Term::constant(*value))
+        }
+        ExprKind::Variable(_) => Sum::term(Term::atom(expr.clone(), None)),
+        ExprKind::Unary(UnaryOperator::Negate, operand) => sum(operand).negate(),
+        ExprKind::Unary(UnaryOperator::Percent, operand) => {
+            Sum::term(sum(operand).into_term(span).multiply(Term::constant(0.01)))
+        }
+        ExprKind::Binary(BinaryOperator::Add, lhs, rhs) => sum(lhs).add(sum(rhs)),
+        ExprKind::Binary(BinaryOperator::Subtract, lhs, rhs) => sum(lhs).add(sum(rhs).negate()),
+        ExprKind::Binary(BinaryOperator::Multiply, lhs, rhs) => {
+            Sum::term(sum(lhs).into_term(span).multiply(sum(rhs).into_term(span)))
+        }
+        ExprKind::Binary(BinaryOperator::Divide, lhs, rhs) => {
+            let (lhs, rhs) = (sum(lhs), sum(rhs));
+            if rhs.constant() == Some(0.0) {
+                return atom(ExprKind::Binary(
+                    BinaryOperator::Divide,
+                    Box::new(lhs.to_expr(span)),
+                    Box::new(rhs.to_expr(span)),
+                ));
+            }
+            Sum::term(
+                lhs.into_term(span)
+                    .multiply(rhs.into_term(span).power(-1.0)),
+            )
+        }
+        ExprKind::Binary(BinaryOperator::Power, base, exponent) => {
+            let (base, exponent) = (sum(base), sum(exponent));
+            let term = match exponent.constant() {
+                // Whole exponents keep `(x^2)^0.5`, which is `abs(x)`, from becoming `x`.
+                Some(exponent) if exponent.fract() == 0.0 && base.constant() != Some(0.0) => {
+                    Some(base.clone().into_term(span).power(exponent))
+                }
+                Some(exponent) if exponent > 0.0 && base.constant() == Some(0.0) => {
+                    Some(Term::constant(0.0))
+                }
+                Some(exponent) => base
+                    .constant()
+                    .filter(|base| *base > 0.0)
+                    .map(|base| Term::constant(base.powf(exponent))),
+                None => None,
+            };
+            match term {
+                Some(term) => Sum::term(term),
+                None => atom(ExprKind::Binary(
+                    BinaryOperator::Power,
+                    Box::new(base.to_expr(span)),
+                    Box::new(exponent.to_expr(span)),
+                )),
+            }
+        }
+        ExprKind::Binary(operator, lhs, rhs) => atom(ExprKind::Binary(
+            *operator,
+            Box::new(lhs.simplify()),
+            Box::new(rhs.simplify()),
+        )),
+        ExprKind::Call(name, args) => atom(ExprKind::Call(
+            name.clone(),
+            args.iter().map(Expr::simplify).collect(),
+        )),
+        ExprKind::Tuple(items) => atom(ExprKind::Tuple(items.iter().map(Expr::simplify).collect())),
+        ExprKind::Conditional(condition, then, otherwise) => atom(ExprKind::Conditional(
+            Box::new(condition.simplify()),
+            Box::new(then.simplify()),
+            Box::new(otherwise.simplify()),
+        )),
+        ExprKind::Lambda(params, body) => {
+            atom(ExprKind::Lambda(params.clone(), Box::new(body.simplify())))
+        }
+        ExprKind::String(_) | ExprKind::Value(_) => Sum::term(Term::atom(expr.clone(), None)),
+    }
+}
+
+/// Derivatives of the builtin functions of `u`.
+const DERIVATIVES: &[(&str, &str)] = &[
+    ("sqrt", "1 / (2 * sqrt(u))"),
+    ("abs", "u / abs(u)"),
+    ("sin", "cos(u)"),
+    ("cos", "-sin(u)"),
+    ("tan", "1 / cos(u)^2"),
+    ("asin", "1 / sqrt(1 - u^2)"),
+    ("acos", "-1 / sqrt(1 - u^2)"),
+    ("atan", "1 / (1 + u^2)"),
+    ("sinh", "cosh(u)"),
+    ("cosh", "sinh(u)"),
+    ("tanh", "1 - tanh(u)^2"),
+    ("asinh", "1 / sqrt(u^2 + 1)"),
+    ("acosh", "1 / sqrt(u^2 - 1)"),
+    ("atanh", "1 / (1 - u^2)"),
+    ("exp", "exp(u)"),
+    ("expm1", "exp(u)"),
+    ("ln", "1 / u"),
+    ("ln1p", "1 / (1 + u)"),
+    ("log10", "1 / (u * ln(10))"),
+    ("log2", "1 / (u * ln(2))"),
+];
+
+/// Antiderivatives of builtin functions of `u`, besides powers of `u`.
+const ANTIDERIVATIVES: &[(&str, &str)] = &[
+    ("sqrt", "2 * u * sqrt(u) / 3"),
+    ("sin", "-cos(u)"),
+    ("cos", "sin(u)"),
+    ("tan", "-ln(abs(cos(u)))"),
+    ("sinh", "cosh(u)"),
+    ("cosh", "sinh(u)"),
+    ("exp", "exp(u)"),
+    ("expm1", "exp(u) - u"),
+];
+
+fn template(table: &[(&str, &str)], name: &str) -> Option<Expr> {
+    let (_, template) = table.iter().find(|(function, _)| *function == name)?;
+    Parser::new(template).parse().ok()
+}
+
+/// Whether `expr` contains `variable` free.
+fn depends(expr: &Expr, variable: &str) -> bool {
+    match &expr.kind {
+        ExprKind::Variable(name) => name == variable,
+        ExprKind::Unary(_, operand) => depends(operand, variable),
+        ExprKind::Binary(_, lhs, rhs) => depends(lhs, variable) || depends(rhs, variable),
+        ExprKind::Call(name, args) => {
+            name == variable || args.iter().any(|arg| depends(arg, variable))
+        }
+        ExprKind::Tuple(items) => items.iter().any(|item| depends(item, variable)),
+        ExprKind::Conditional(condition, then, otherwise) => [condition, then, otherwise]
+            .iter()
+            .any(|expr| depends(expr, variable)),
+        ExprKind::Lambda(params, body) => {
+            !params.iter().any(|param| param == variable) && depends(body, variable)
+        }
+        ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Value(_) => false,
+    }
+}
+
+/// `template` with `u` replaced by `arg`, at the span of `arg`.
+fn substitute(template: &Expr, arg: &Expr) -> Expr {
+    let kind = match &template.kind {
+        ExprKind::Variable(name) if name == "u" => return arg.clone(),
+        ExprKind::Unary(operator, operand) => {
+            ExprKind::Unary(*operator, Box::new(substitute(operand, arg)))
+        }
+        ExprKind::Binary(operator, lhs, rhs) => ExprKind::Binary(
+            *operator,
+            Box::new(substitute(lhs, arg)),
+            Box::new(substitute(rhs, arg)),
+        ),
+        ExprKind::Call(name, args) => ExprKind::Call(
+            name.clone(),
+            args.iter()
+                .map(|template| substitute(template, arg))
+                .collect(),
+        ),
+        kind => kind.clone(),
+    };
+    Expr::new(kind, arg.span)
+}
+
+fn negate(expr: Expr, span: Span) -> Expr {
+    Expr::new(ExprKind::Unary(UnaryOperator::Negate, Box::new(expr)), span)
+}
+
+/// The coefficients of the powers of `variable` in `expr`, from the constant up to the
+/// highest nonzero one, if `expr` is a polynomial in `variable`.
+fn coefficients(expr: &Expr, variable: &str) -> Option<Vec<Sum>> {
+    let expanded = expr.expand().unwrap_or_else(|_| expr.clone());
+    let mut coefficients: Vec<Sum> = vec![];
+    for term in sum(&expanded).terms.into_iter().flat_map(Term::expand) {
+        let mut power = 0.0;
+        let mut rest = Term::constant(term.coefficient);
+        for factor in term.factors {
+            if matches!(&factor.base.kind, ExprKind::Variable(name) if name == variable) {
+                power = factor.exponent;
+            } else if depends(&factor.base, variable) {
+                return None;
+            } else {
+                rest.factors.push(factor);
+            }
+        }
+        if power < 0.0 || power.fract() != 0.0 {
+            return None;
+        }
+        let power = power as usize;
+        if coefficients.len() <= power {
+            coefficients.resize(power + 1, Sum { terms: vec![] });
+        }
+        coefficients[power].push(rest);
+    }
+    while coefficients.last().is_some_and(|sum| sum.terms.is_empty()) {
+        coefficients.pop();
+    }
+    Some(coefficients)
+}
+
+fn not_differentiable(expr: &Expr) -> EvaluatorError {
+    EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+        "Cannot differentiate {}",
+        expr
+    )))
+    .or_span(expr.span)
+}
+
+/// The derivative of `expr` with respect to `variable`, unsimplified.
+fn derivative(expr: &Expr, variable: &str) -> Result<Expr, EvaluatorError> {
+    let span = expr.span;
+    let number = |value| Expr::new(ExprKind::Number(value), span);
+    if !depends(expr, variable) {
+        return Ok(number(0.0));
+    }
+    let d = |expr: &Expr| derivative(expr, variable);
+    let product = |lhs, rhs| binary(BinaryOperator::Multiply, lhs, rhs, span);
+    let ln = |expr: &Expr| Expr::new(ExprKind::Call(String::from("ln"), vec![expr.clone()]), span);
+    Ok(match &expr.kind {
+        ExprKind::Variable(_) => number(1.0),
+        ExprKind::Unary(UnaryOperator::Negate, operand) => Expr::new(
+            ExprKind::Unary(UnaryOperator::Negate, Box::new(d(operand)?)),
+            span,
+        ),
+        ExprKind::Unary(UnaryOperator::Percent, operand) => product(number(0.01), d(operand)?),
+        ExprKind::Binary(operator @ (BinaryOperator::Add | BinaryOperator::Subtract), u, v) => {
+            binary(*operator, d(u)?, d(v)?, span)
+        }
+        ExprKind::Binary(BinaryOperator::Multiply, u, v) => binary(
+            BinaryOperator::Add,
+            product(d(u)?, v.as_ref().clone()),
+            product(u.as_ref().clone(), d(v)?),
+            span,
+        ),
+        ExprKind::Binary(BinaryOperator::Divide, u, v) => {
+            let numerator = binary(
+                BinaryOperator::Subtract,
+                product(d(u)?, v.as_ref().clone()),
+                product(u.as_ref().clone(), d(v)?),
+                span,
+            );
+            let square = binary(BinaryOperator::Power, v.as_ref().clone(), number(2.0), span);
+            binary(BinaryOperator::Divide, numerator, square, span)
+        }
+        ExprKind::Binary(BinaryOperator::Power, u, v) if !depends(v, variable) => {
+            let exponent = binary(
+                BinaryOperator::Subtract,
+                v.as_ref().clone(),
+                number(1.0),
+                span,
+            );
+            let power = binary(BinaryOperator::Power, u.as_ref().clone(), exponent, span);
+            product(product(v.as_ref().clone(), power), d(u)?)
+        }
+        // (u^v)' = u^v (v' ln(u) + v u' / u)
+        ExprKind::Binary(BinaryOperator::Power, u, v) => {
+            let quotient = binary(BinaryOperator::Divide, d(u)?, u.as_ref().clone(), span);
+            let inner = binary(
+                BinaryOperator::Add,
+                product(d(v)?, ln(u)),
+                product(v.as_ref().clone(), quotient),
+                span,
+            );
+            product(expr.clone(), inner)
+        }
+        ExprKind::Call(name, args) if args.len() == 1 => {
+            let template = template(DERIVATIVES, name).ok_or_else(|| not_differentiable(expr))?;
+            product(substitute(&template, &args[0]), d(&args[0])?)
+        }
+        ExprKind::Conditional(condition, then, otherwise) => Expr::new(
+            ExprKind::Conditional(
+                condition.clone(),
+                Box::new(d(then)?),
+                Box::new(d(otherwise)?),
+            ),
+            span,
+        ),
+        _ => return Err(not_differentiable(expr)),
+    })
+}
+
+/// The argument `u` of a factor and the antiderivative of the factor as a function of `u`.
+fn antiderivative(factor: &Factor, variable: &str, span: Span) -> (Expr, Expr) {
+    let number = |value| Expr::new(ExprKind::Number(value), span);
+    match &factor.base.kind {
+        ExprKind::Call(name, args) if factor.exponent == 1.0 && args.len() == 1 => {
+            if let Some(template) = template(ANTIDERIVATIVES, name) {
+                return (args[0].clone(), substitute(&template, &args[0]));
+            }
+        }
+        // a^u / ln(a)
+        ExprKind::Binary(BinaryOperator::Power, base, exponent)
+            if factor.exponent == 1.0 && !depends(base, variable) =>
+        {
+            let ln = Expr::new(
+                ExprKind::Call(String::from("ln"), vec![*base.clone()]),
+                span,
+            );
+            let divide = binary(BinaryOperator::Divide, factor.base.clone(), ln, span);
+            return (*exponent.clone(), divide);
+        }
+        _ => {}
+    }
+    let u = factor.base.clone();
+    if factor.exponent == -1.0 {
+        let abs = Expr::new(ExprKind::Call(String::from("abs"), vec![u.clone()]), span);
+        let ln = Expr::new(ExprKind::Call(String::from("ln"), vec![abs]), span);
+        return (u, ln);
+    }
+    let exponent = number(factor.exponent + 1.0);
+    let power = binary(BinaryOperator::Power, u.clone(), exponent.clone(), span);
+    (u, binary(BinaryOperator::Divide, power, exponent, span))
+}
+
+/// An antiderivative of the term, by substitution: a factor `f(u)` whose antiderivative
+/// `F(u)` is known, the other factors being a constant `k` times `u'`, gives `k F(u)`.
+fn integrate_term(term: &Term, variable: &str, span: Span) -> Result<Expr, EvaluatorError> {
+    let expr = term.to_expr(span, true);
+    let x = Expr::new(ExprKind::Variable(variable.to_string()), span);
+    if !depends(&expr, variable) {
+        return Ok(binary(BinaryOperator::Multiply, expr, x, span));
+    }
+    for (index, factor) in term.factors.iter().enumerate() {
+        if !depends(&factor.base, variable) {
+            continue;
+        }
+        let (u, antiderivative) = antiderivative(factor, variable, span);
+        let mut rest = term.clone();
+        rest.factors.remove(index);
+        let ratio = binary(
+            BinaryOperator::Divide,
+            rest.to_expr(span, true),
+            derivative(&u, variable)?,
+            span,
+        )
+        .simplify();
+        if !depends(&ratio, variable) {
+            return Ok(binary(
+                BinaryOperator::Multiply,
+                ratio,
+                antiderivative,
+                span,
+            ));
+        }
+    }
+    Err(
+        EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+            "No antiderivative found for {}",
+            expr
+        )))
+        .or_span(span),
+    )
+}
+
+impl Expr {
+    /// A simplified antiderivative with respect to `variable`, without constant: of
+    /// polynomials, `1/x`, `exp`, `sin`, `cos` and a few other functions, and of the
+    /// products `k u' f(u)` these give by substitution, as `x * exp(x^2)`. Fails rather
+    /// than guessing when no such form is found, as for `x * exp(x)`.
+    pub fn integrate_symbolic(&self, variable: &str) -> Result<Expr, EvaluatorError> {
+        let span = self.span;
+        let mut result = Expr::new(ExprKind::Number(0.0), span);
+        for term in sum(self).terms.into_iter().flat_map(Term::expand) {
+            let integral = integrate_term(&term, variable, span)?;
+            result = binary(BinaryOperator::Add, result, integral, span);
+        }
+        Ok(result.simplify())
+    }
+
+    /// The simplified derivative with respect to `variable`, by the sum, product, quotient,
+    /// power and chain rules. Conditionals are differentiated branch by branch. Fails on
+    /// functions without known derivative, and on comparisons and lists of `variable`.
+    pub fn derive(&self, variable: &str) -> Result<Expr, EvaluatorError> {
+        Ok(derivative(self, variable)?.simplify())
+    }
+
+    /// The Taylor expansion at `at` up to the power `order`, as `1 + x + 0.5 * x^2` for
+    /// `exp(x)` at 0 to order 2. The coefficients are the derivatives evaluated in
+    /// `context`, so the formula may use its variables.
+    pub fn series(
+        &self,
+        context: &Context,
+        variable: &str,
+        at: f64,
+        order: usize,
+    ) -> Result<Expr, EvaluatorError> {
+        let span = self.span;
+        let number = |value| Expr::new(ExprKind::Number(value), span);
+        let point = Expr::new(ExprKind::Value(Value::Number(at)), span);
+        let mut base = Expr::new(ExprKind::Variable(variable.to_string()), span);
+        if at != 0.0 {
+            let (operator, offset) = if at < 0.0 {
+                (BinaryOperator::Add, -at)
+            } else {
+                (BinaryOperator::Subtract, at)
+            };
+            base = binary(
+                operator,
+                base,
+                Expr::new(ExprKind::Number(offset), span),
+                span,
+            );
+        }
+        let mut derivative = self.clone();
+        let mut factorial = 1.0;
+        let mut result: Option<Expr> = None;
+        for power in 0..=order {
+            if power > 0 {
+                derivative = derivative.derive(variable)?;
+                factorial *= power as f64;
+            }
+            let value = context
+                .evaluate(&derivative.substitute(variable, &point))?
+                .as_number()?;
+            let coefficient = value / factorial;
+            if !coefficient.is_finite() {
+                return Err(
+                    EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(format!(
+                        "{} has no Taylor series at {} = {}",
+                        self, variable, at
+                    )))
+                    .or_span(span),
+                );
+            }
+            if coefficient == 0.0 {
+                continue;
+            }
+            let negative = coefficient < 0.0 && result.is_some();
+            let magnitude = if negative { -coefficient } else { coefficient };
+            let term = match power {
+                0 => number(magnitude),
+                1 => base.clone(),
+                _ => binary(
+                    BinaryOperator::Power,
+                    base.clone(),
+                    number(power as f64),
+                    span,
+                ),
+            };
+            let term = match magnitude {
+                _ if power == 0 || magnitude == 1.0 => term,
+                -1.0 => Expr::new(ExprKind::Unary(UnaryOperator::Negate, Box::new(term)), span),
+                _ => binary(BinaryOperator::Multiply, number(magnitude), term, span),
+            };
+            result = Some(match result {
+                None => term,
+                Some(sum) if negative => binary(BinaryOperator::Subtract, sum, term, span),
+                Some(sum) => binary(BinaryOperator::Add, sum, term, span),
+            });
+        }
+        Ok(result.unwrap_or_else(|| number(0.0)))
+    }
+
+    /// The exact solutions of the equation `lhs == rhs`, or of `expr == 0`, if it is linear
+    /// or quadratic in `variable`: `a*x^2 + b*x + c == 0` gives the quadratic formula.
+    /// Quadratics with constant coefficients have no, one or two solutions in increasing
+    /// order, square roots of numbers being kept unless exact.
+    pub fn solve_symbolic(&self, variable: &str) -> Result<Vec<Expr>, EvaluatorError> {
+        let span = self.span;
+        let number = |value| Expr::new(ExprKind::Number(value), span);
+        let equation = match &self.kind {
+            ExprKind::Binary(BinaryOperator::Equal, lhs, rhs) => binary(
+                BinaryOperator::Subtract,
+                (**lhs).clone(),
+                (**rhs).clone(),
+                span,
+            ),
+            _ => self.clone(),
+        };
+        let error = |message: String| {
+            Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(message)).or_span(span))
+        };
+        let Some(coefficients) = coefficients(&equation, variable) else {
+            return error(format!(
+                "{} is not a polynomial equation in {}",
+                self, variable
+            ));
+        };
+        let coefficient = |power: usize| coefficients[power].to_expr(span);
+        match coefficients.len() {
+            0 => error(format!("Every {} is a solution of {}", variable, self)),
+            1 if coefficients[0].constant().is_some() => Ok(vec![]),
+            1 => error(format!("{} does not depend on {}", self, variable)),
+            2 => {
+                let root = binary(
+                    BinaryOperator::Divide,
+                    negate(coefficient(0), span),
+                    coefficient(1),
+                    span,
+                );
+                Ok(vec![root.simplify()])
+            }
+            3 => {
+                let (a, b, c) = (coefficient(2), coefficient(1), coefficient(0));
+                let square = binary(BinaryOperator::Power, b.clone(), number(2.0), span);
+                let product = binary(
+                    BinaryOperator::Multiply,
+                    binary(BinaryOperator::Multiply, number(4.0), a.clone(), span),
+                    c,
+                    span,
+                );
+                let discriminant =
+                    binary(BinaryOperator::Subtract, square, product, span).simplify();
+                let deviation = match sum(&discriminant).constant() {
+                    Some(value) if value < 0.0 => return Ok(vec![]),
+                    Some(0.0) => None,
+                    Some(value) if value.sqrt() * value.sqrt() == value => {
+                        Some(number(value.sqrt()))
+                    }
+                    _ => Some(Expr::new(
+                        ExprKind::Call(String::from("sqrt"), vec![discriminant]),
+                        span,
+                    )),
+                };
+                let denominator =
+                    binary(BinaryOperator::Multiply, number(2.0), a.clone(), span).simplify();
+                // `-b ± deviation` over the denominator, divided term by term if it is a
+                // number.
+                let over = |numerator| {
+                    binary(BinaryOperator::Divide, numerator, denominator.clone(), span)
+                };
+                let root = |operator| {
+                    let vertex = negate(b.clone(), span);
+                    let Some(deviation) = deviation.clone() else {
+                        return over(vertex).simplify();
+                    };
+                    match sum(&denominator).constant() {
+                        Some(_) => binary(operator, over(vertex), over(deviation), span).simplify(),
+                        None => over(binary(operator, vertex, deviation, span).simplify()),
+                    }
+                };
+                if deviation.is_none() {
+                    return Ok(vec![root(BinaryOperator::Add)]);
+                }
+                let mut roots = vec![root(BinaryOperator::Subtract), root(BinaryOperator::Add)];
+                if sum(&a).constant().is_some_and(|a| a < 0.0) {
+                    roots.reverse();
+                }
+                Ok(roots)
+            }
+            degree => error(format!(
+                "Cannot solve {} symbolically, being of degree {} in {}",
+                self,
+                degree - 1,
+                variable
+            )),
+        }
+    }
+
+    /// Simplifies the formula without evaluating its variables: like terms are collected
+    /// and canceled, powers of the same base combined, and terms and factors sorted, so
+    /// `y*x + x*y - x*x^2/x` becomes `-x^2 + 2 * x * y`. Products of sums are not
+    /// expanded, and only whole powers of powers are combined.
+    pub fn simplify(&self) -> Expr {
+        sum(self).to_expr(self.span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn simplify(source: &str) -> String {
+        Parser::new(source).parse().unwrap().simplify().to_string()
+    }
+
+    #[test]
+    fn simplify_formulas() {
+        assert_eq!(simplify("x*x^2"), "x^3");
+        assert_eq!(simplify("x + 2*x - y + y"), "3 * x");
+        assert_eq!(simplify("y*x + x*y - x*x^2/x"), "-x^2 + 2 * x * y");
+        assert_eq!(simplify("x*y/y"), "x");
+        assert_eq!(simplify("x - x"), "0");
+        assert_eq!(simplify("1 + x^2 + x"), "x^2 + x + 1");
+        assert_eq!(simplify("2*(x + 1) - 2"), "2 * x");
+        assert_eq!(simplify("(x + 1)*(1 + x)"), "(x + 1)^2");
+        assert_eq!(simplify("3*(x + 1)/(x + 1)"), "3");
+        assert_eq!(simplify("sin(x + x) / x^2 / 2"), "0.5 * sin(2 * x) / x^2");
+        assert_eq!(simplify("(x^2)^0.5 + 4^0.5"), "(x^2)^0.5 + 2");
+        assert_eq!(simplify("x / 0"), "x / 0");
+    }
+
+    #[test]
+    fn differentiate_formulas() {
+        let derive = |source: &str| {
+            let expr = Parser::new(source).parse().unwrap();
+            expr.derive("x").map(|derivative| derivative.to_string())
+        };
+        assert_eq!(derive("x^3 + sin(x) - 4").unwrap(), "3 * x^2 + cos(x)");
+        assert_eq!(derive("x * exp(x)").unwrap(), "x * exp(x) + exp(x)");
+        assert_eq!(derive("1 / x").unwrap(), "-1 / x^2");
+        assert_eq!(derive("sin(2 * x)").unwrap(), "2 * cos(2 * x)");
+        assert_eq!(derive("a * x^2 + b").unwrap(), "2 * a * x");
+        assert_eq!(derive("2^x").unwrap(), "2^x * ln(2)");
+        assert_eq!(derive("x > 0 ? x^2 : -x").unwrap(), "x > 0 ? 2 * x : -1");
+        assert_eq!(derive("y").unwrap(), "0");
+        assert!(derive("frac(x)").is_err());
+        assert!(derive("x > 0").is_err());
+    }
+
+    #[test]
+    fn expand_in_series() {
+        let context = Context::new();
+        let series = |source: &str, at: f64, order: usize| {
+            let expr = Parser::new(source).parse().unwrap();
+            expr.series(&context, "x", at, order)
+                .map(|series| series.to_string())
+        };
+        assert_eq!(
+            series("exp(x)", 0.0, 3).unwrap(),
+            "1 + x + 0.5 * x^2 + 0.16666666666666666 * x^3"
+        );
+        assert_eq!(
+            series("sin(x)", 0.0, 5).unwrap(),
+            "x - 0.16666666666666666 * x^3 + 0.008333333333333333 * x^5"
+        );
+        assert_eq!(series("ln(x)", 1.0, 2).unwrap(), "x - 1 - 0.5 * (x - 1)^2");
+        assert_eq!(
+            series("x^2", -2.0, 4).unwrap(),
+            "4 - 4 * (x + 2) + (x + 2)^2"
+        );
+        assert_eq!(series("cos(x) - 1", 0.0, 1).unwrap(), "0");
+        assert!(series("ln(x)", 0.0, 2).is_err());
+    }
+
+    #[test]
+    fn solve_equations() {
+        let solve = |source: &str| {
+            let expr = Parser::new(source).parse().unwrap();
+            expr.solve_symbolic("x").map(|roots| {
+                roots
+                    .iter()
+                    .map(|root| root.to_string())
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(solve("2*x + 6 == 0").unwrap(), ["-3"]);
+        assert_eq!(solve("a*x == b - x").unwrap(), ["b / (a + 1)"]);
+        assert_eq!(solve("x^2 == 4").unwrap(), ["-2", "2"]);
+        assert_eq!(solve("(x - 1)^2").unwrap(), ["1"]);
+        assert_eq!(
+            solve("x^2 - x - 1").unwrap(),
+            ["-0.5 * sqrt(5) + 0.5", "0.5 * sqrt(5) + 0.5"]
+        );
+        assert_eq!(
+            solve("a*x^2 + b*x + c == 0").unwrap(),
+            [
+                "(-b - sqrt(-4 * a * c + b^2)) / (2 * a)",
+                "(-b + sqrt(-4 * a * c + b^2)) / (2 * a)"
+            ]
+        );
+        assert!(solve("x^2 + 1").unwrap().is_empty());
+        assert!(solve("x^3 == 1").is_err());
+        assert!(solve("sin(x) == 0").is_err());
+        assert!(solve("x == x").is_err());
+    }
+
+    #[test]
+    fn integrate_formulas() {
+        let integrate = |source: &str| {
+            let expr = Parser::new(source).parse().unwrap();
+            expr.integrate_symbolic("x")
+                .map(|integral| integral.to_string())
+        };
+        assert_eq!(integrate("3*x^2 + 2*x + 1").unwrap(), "x^3 + x^2 + x");
+        assert_eq!(integrate("1/x").unwrap(), "ln(abs(x))");
+        assert_eq!(integrate("sin(x) + cos(x)").unwrap(), "-cos(x) + sin(x)");
+        assert_eq!(integrate("exp(2*x + 1)").unwrap(), "0.5 * exp(2 * x + 1)");
+        assert_eq!(integrate("x * exp(x^2)").unwrap(), "0.5 * exp(x^2)");
+        assert_eq!(integrate("(2*x + 1)^3").unwrap(), "0.125 * (2 * x + 1)^4");
+        assert_eq!(integrate("ln(x) / x").unwrap(), "0.5 * ln(x)^2");
+        assert_eq!(integrate("2^x").unwrap(), "2^x / ln(2)");
+        assert_eq!(integrate("a").unwrap(), "a * x");
+        assert!(integrate("x * exp(x)").is_err());
+        assert!(integrate("exp(x^2)").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Tokens and syntax trees made from bytes, for fuzzing code that takes them and for
+//! property tests such as printing and parsing back, with the `testing` feature. The
+//! `Arbitrary` trait and `Unstructured` follow those of the arbitrary crate, which cannot
+//! be fetched where the crate is built now, so that fuzz targets read the same.
+
+use crate::lexer::{Span, Token};
+use crate::parser::{BinaryOperator, Expr, ExprKind, UnaryOperator};
+
+/// Bound on the nesting of the formulas made, well below that of the parser.
+const MAX_DEPTH: usize = 6;
+
+/// Names of variables, functions and parameters, none of them builtin.
+const NAMES: [&str; 6] = ["x", "y", "z", "rate", "f", "g_2"];
+
+const OPERATORS: [BinaryOperator; 14] = [
+    BinaryOperator::Add,
+    BinaryOperator::Subtract,
+    BinaryOperator::Multiply,
+    BinaryOperator::Divide,
+    BinaryOperator::Power,
+    BinaryOperator::Equal,
+    BinaryOperator::NotEqual,
+    BinaryOperator::Less,
+    BinaryOperator::LessEqual,
+    BinaryOperator::Greater,
+    BinaryOperator::GreaterEqual,
+    BinaryOperator::And,
+    BinaryOperator::Or,
+    BinaryOperator::PlusMinus,
+];
+
+/// The bytes values are made of, read from the front. Once they run out every byte
+/// reads as 0, which makes the smallest values, so that making them always ends.
+#[derive(Debug, Clone)]
+pub struct Unstructured<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Unstructured<'a> {
+    pub fn new(bytes: &'a [u8]) -> Unstructured<'a> {
+        Unstructured { bytes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn byte(&mut self) -> u8 {
+        let Some((&byte, rest)) = self.bytes.split_first() else {
+            return 0;
+        };
+        self.bytes = rest;
+        byte
+    }
+
+    pub fn boolean(&mut self) -> bool {
+        self.byte() & 1 == 1
+    }
+
+    /// A number below `count`, which must not be 0.
+    pub fn choose_index(&mut self, count: usize) -> usize {
+        self.byte() as usize % count
+    }
+
+    pub fn choose<'b, T>(&mut self, items: &'b [T]) -> &'b T {
+        &items[self.choose_index(items.len())]
+    }
+}
+
+/// A value which can be made from any bytes.
+pub trait Arbitrary: Sized {
+    fn arbitrary(u: &mut Unstructured) -> Self;
+}
+
+impl Arbitrary for Token {
+    /// Tokens as the lexer makes them, so that lists of them written with spaces between
+    /// lex back the same, apart from numbers run together.
+    fn arbitrary(u: &mut Unstructured) -> Token {
+        match u.choose_index(7) {
+            0 => Token::Number(number(u).to_string().into_bytes()),
+            1 => Token::Identifier(u.choose(&NAMES).as_bytes().to_vec()),
+            2 => Token::String(text(u).into_bytes()),
+            3 => Token::Operator(u.choose(&OPERATORS).symbol().as_bytes().to_vec()),
+            4 => Token::OpenParenthesis,
+            5 => Token::ClosedParenthesis,
+            _ => Token::Comma,
+        }
+    }
+}
+
+impl Arbitrary for Expr {
+    /// Formulas as the parser makes them, which print as formulas parsing back to them:
+    /// numbers are not negative, tuples have two items or more and there are no values.
+    /// Spans are all empty.
+    fn arbitrary(u: &mut Unstructured) -> Expr {
+        expr(u, MAX_DEPTH)
+    }
+}
+
+fn number(u: &mut Unstructured) -> f64 {
+    // Halves, quarters and eighths print exactly.
+    u16::from_le_bytes([u.byte(), u.byte()]) as f64 / 8.0
+}
+
+fn text(u: &mut Unstructured) -> String {
+    let length = u.choose_index(4);
+    (0..length)
+        .map(|_| *u.choose(b"abc xyz012") as char)
+        .collect()
+}
+
+fn name(u: &mut Unstructured) -> String {
+    u.choose(&NAMES).to_string()
+}
+
+fn exprs(u: &mut Unstructured, depth: usize, min: usize) -> Vec<Expr> {
+    let count = min + u.choose_index(3);
+    (0..count).map(|_| expr(u, depth - 1)).collect()
+}
+
+fn expr(u: &mut Unstructured, depth: usize) -> Expr {
+    let choices = if depth == 0 { 3 } else { 10 };
+    let kind = match u.choose_index(choices) {
+        0 => ExprKind::Number(number(u)),
+        1 => ExprKind::Variable(name(u)),
+        2 => ExprKind::String(text(u)),
+        3 => ExprKind::Unary(UnaryOperator::Negate, Box::new(expr(u, depth - 1))),
+        4 => ExprKind::Unary(UnaryOperator::Percent, Box::new(expr(u, depth - 1))),
+        5 | 6 => ExprKind::Binary(
+            *u.choose(&OPERATORS),
+            Box::new(expr(u, depth - 1)),
+            Box::new(expr(u, depth - 1)),
+        ),
+        7 => ExprKind::Call(name(u), exprs(u, depth, 0)),
+        8 => ExprKind::Conditional(
+            Box::new(expr(u, depth - 1)),
+            Box::new(expr(u, depth - 1)),
+            Box::new(expr(u, depth - 1)),
+        ),
+        _ if u.boolean() => ExprKind::Tuple(exprs(u, depth, 2)),
+        _ => {
+            let mut params = vec![name(u)];
+            if u.boolean() {
+                params.push(String::from("t"));
+            }
+            ExprKind::Lambda(params, Box::new(expr(u, depth - 1)))
+        }
+    };
+    Expr::new(kind, Span::default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn print_and_parse_back() {
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..2000 {
+            let bytes: Vec<u8> = (0..64)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            let expr = Expr::arbitrary(&mut Unstructured::new(&bytes));
+            let text = expr.to_string();
+            assert_eq!(Parser::new(&text).parse(), Ok(expr), "{}", text);
+        }
+        let mut u = Unstructured::new(&[]);
+        assert_eq!(Expr::arbitrary(&mut u), Expr::number(0.0));
+        assert_eq!(Token::arbitrary(&mut u), Token::Number(b"0".to_vec()));
+    }
+}
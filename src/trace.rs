@@ -0,0 +1,160 @@
+use crate::builtins::{self, BuiltinKind};
+use crate::evaluator::{Evaluator, EvaluatorError};
+use crate::parser::{BinaryOperator, Expr, ExprKind, UnaryOperator};
+
+/// Upper bound on the steps of a trace, in case a reduction fails to make progress.
+const MAX_TRACE_STEPS: usize = 10_000;
+
+impl<'a> Evaluator<'a> {
+    /// Evaluates `expr` one reduction at a time, leftmost innermost first, and returns every
+    /// intermediate expression: `2*3+4`, `6 + 4`, `10`. The first step is `expr` itself and
+    /// the last is its value. Calls of builtins taking unevaluated arguments, such as
+    /// `integrate`, and calls of lambdas are reduced in a single step.
+    pub fn trace(&mut self, expr: &Expr) -> Result<Vec<Expr>, EvaluatorError> {
+        let mut steps = vec![expr.clone()];
+        let mut current = expr.clone();
+        while steps.len() < MAX_TRACE_STEPS {
+            let Some(next) = self.reduce(&current)? else {
+                break;
+            };
+            steps.push(next.clone());
+            current = next;
+        }
+        Ok(steps)
+    }
+
+    /// Performs one reduction inside `expr`, or returns `None` if it is already a value or
+    /// a literal.
+    fn reduce(&mut self, expr: &Expr) -> Result<Option<Expr>, EvaluatorError> {
+        let rebuild = |kind| Ok(Some(Expr::new(kind, expr.span)));
+        match &expr.kind {
+            ExprKind::Value(_)
+            | ExprKind::Number(_)
+            | ExprKind::String(_)
+            | ExprKind::Lambda(_, _) => return Ok(None),
+            ExprKind::Unary(operator, operand) => {
+                if let Some(operand) = self.reduce(operand)? {
+                    return rebuild(ExprKind::Unary(*operator, Box::new(operand)));
+                }
+            }
+            ExprKind::Binary(operator, lhs, rhs) => {
+                if let Some(lhs) = self.reduce(lhs)? {
+                    return rebuild(ExprKind::Binary(*operator, Box::new(lhs), rhs.clone()));
+                }
+                if let BinaryOperator::And | BinaryOperator::Or = operator {
+                    // Short-circuits without reducing the right operand.
+                    let lhs = self.evaluate(lhs)?.is_true()?;
+                    if lhs == (*operator == BinaryOperator::Or) {
+                        return rebuild(ExprKind::Value(self.evaluate(expr)?));
+                    }
+                }
+                // The percentage of `200 + 10%` is reduced together with the addition.
+                let rhs_operand = match (&rhs.kind, operator) {
+                    (
+                        ExprKind::Unary(UnaryOperator::Percent, operand),
+                        BinaryOperator::Add | BinaryOperator::Subtract,
+                    ) => operand,
+                    _ => rhs,
+                };
+                if let Some(reduced) = self.reduce(rhs_operand)? {
+                    let rhs = if std::ptr::eq(rhs_operand, rhs) {
+                        reduced
+                    } else {
+                        Expr::new(
+                            ExprKind::Unary(UnaryOperator::Percent, Box::new(reduced)),
+                            rhs.span,
+                        )
+                    };
+                    return rebuild(ExprKind::Binary(*operator, lhs.clone(), Box::new(rhs)));
+                }
+            }
+            ExprKind::Conditional(condition, then, otherwise) => {
+                if let Some(condition) = self.reduce(condition)? {
+                    return rebuild(ExprKind::Conditional(
+                        Box::new(condition),
+                        then.clone(),
+                        otherwise.clone(),
+                    ));
+                }
+                let branch = if self.evaluate(condition)?.is_true()? {
+                    then
+                } else {
+                    otherwise
+                };
+                return Ok(Some(branch.as_ref().clone()));
+            }
+            ExprKind::Call(name, args) if !self.is_special(name) => {
+                for (index, arg) in args.iter().enumerate() {
+                    if let Some(arg) = self.reduce(arg)? {
+                        let mut args = args.clone();
+                        args[index] = arg;
+                        return rebuild(ExprKind::Call(name.clone(), args));
+                    }
+                }
+            }
+            ExprKind::Tuple(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    if let Some(item) = self.reduce(item)? {
+                        let mut items = items.clone();
+                        items[index] = item;
+                        return rebuild(ExprKind::Tuple(items));
+                    }
+                }
+            }
+            _ => {}
+        }
+        let value = self.evaluate(expr)?;
+        rebuild(ExprKind::Value(value))
+    }
+
+    fn is_special(&self, name: &str) -> bool {
+        self.context().variable(name).is_none()
+            && builtins::lookup(name)
+                .is_some_and(|builtin| matches!(builtin.kind, BuiltinKind::Special(_)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evaluator::Context;
+    use crate::parser::Parser;
+
+    fn trace(source: &str) -> Vec<String> {
+        let context = Context::new();
+        let expr = Parser::new(source).parse().unwrap();
+        let steps = context.trace(&expr).unwrap();
+        steps.iter().map(|step| step.to_string()).collect()
+    }
+
+    #[test]
+    fn trace_reductions() {
+        assert_eq!(trace("2*3+4"), ["2 * 3 + 4", "6 + 4", "10"]);
+        assert_eq!(
+            trace("sqrt(3^2 + 4^2) - pi"),
+            [
+                "sqrt(3^2 + 4^2) - pi",
+                "sqrt(9 + 4^2) - pi",
+                "sqrt(9 + 16) - pi",
+                "sqrt(25) - pi",
+                "5 - pi",
+                "5 - 3.141592653589793",
+                "1.8584073464102069"
+            ]
+        );
+        assert_eq!(
+            trace("200 + (5 + 5)%"),
+            ["200 + (5 + 5)%", "200 + 10%", "220"]
+        );
+        assert_eq!(trace("2 km in m"), ["convert(2 * km, m)", "2000 m"]);
+        assert_eq!(trace("7"), ["7"]);
+        assert_eq!(
+            trace("1 > 2 ? 1/0 : 0 && 1/0"),
+            [
+                "1 > 2 ? 1 / 0 : 0 && 1 / 0",
+                "false ? 1 / 0 : 0 && 1 / 0",
+                "0 && 1 / 0",
+                "false"
+            ]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use core::fmt;
+
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+use crate::numeric;
+use crate::parser::BinaryOperator;
+use crate::value::Value;
+
+/// A measurement `value ± sigma`, `sigma` being its standard deviation. Uncertainties are
+/// propagated to first order, assuming independent operands: `(1 ± 0.3) + (2 ± 0.4)` is
+/// `3 ± 0.5`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Uncertain {
+    pub value: f64,
+    pub sigma: f64,
+}
+
+impl Uncertain {
+    pub fn new(value: f64, sigma: f64) -> Uncertain {
+        Uncertain {
+            value,
+            sigma: sigma.abs(),
+        }
+    }
+
+    /// The image by `f`, of uncertainty `|f'(value)| sigma`.
+    pub fn map(self, f: fn(f64) -> f64) -> Uncertain {
+        let slope = numeric::differentiate(
+            |x| Ok::<f64, ()>(f(x)),
+            self.value,
+            1,
+            numeric::default_step(self.value),
+        )
+        .map_or(f64::NAN, |derivative| derivative.value);
+        Uncertain::new(f(self.value), slope * self.sigma)
+    }
+
+    pub(crate) fn negate(self) -> Uncertain {
+        Uncertain::new(-self.value, self.sigma)
+    }
+
+    pub(crate) fn scale(self, factor: f64) -> Uncertain {
+        Uncertain::new(self.value * factor, self.sigma * factor)
+    }
+
+    /// Applies `operator` to numbers and uncertain numbers, `±` turning numbers into
+    /// uncertain ones. Comparisons are not defined.
+    pub(crate) fn binary(
+        operator: BinaryOperator,
+        lhs: &Value,
+        rhs: &Value,
+    ) -> Result<Value, EvaluatorError> {
+        let a = Uncertain::from_value(lhs)?;
+        if operator == BinaryOperator::PlusMinus {
+            let sigma = rhs.as_number()?;
+            return Ok(Value::Uncertain(Uncertain::new(
+                a.value,
+                a.sigma.hypot(sigma),
+            )));
+        }
+        let b = Uncertain::from_value(rhs)?;
+        let result = match operator {
+            BinaryOperator::Add => Uncertain::new(a.value + b.value, a.sigma.hypot(b.sigma)),
+            BinaryOperator::Subtract => Uncertain::new(a.value - b.value, a.sigma.hypot(b.sigma)),
+            BinaryOperator::Multiply => Uncertain::new(
+                a.value * b.value,
+                (b.value * a.sigma).hypot(a.value * b.sigma),
+            ),
+            BinaryOperator::Divide => {
+                if b.value == 0.0 {
+                    return Err(EvaluatorError::new(EvaluatorErrorKind::DivisionByZero));
+                }
+                let value = a.value / b.value;
+                Uncertain::new(value, (a.sigma / b.value).hypot(value * b.sigma / b.value))
+            }
+            BinaryOperator::Power => {
+                let value = a.value.powf(b.value);
+                let base_term = b.value * a.value.powf(b.value - 1.0) * a.sigma;
+                // Omitted for exact exponents, where the logarithm of a negative base would
+                // make the result NaN.
+                let exponent_term = if b.sigma == 0.0 {
+                    0.0
+                } else {
+                    value * a.value.ln() * b.sigma
+                };
+                Uncertain::new(value, base_term.hypot(exponent_term))
+            }
+            _ => {
+                return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                    format!("Cannot apply '{}' to uncertain numbers", operator.symbol()),
+                )))
+            }
+        };
+        Ok(Value::Uncertain(result))
+    }
+
+    fn from_value(value: &Value) -> Result<Uncertain, EvaluatorError> {
+        match value {
+            Value::Uncertain(uncertain) => Ok(*uncertain),
+            _ => Ok(Uncertain::new(value.as_number()?, 0.0)),
+        }
+    }
+}
+
+impl fmt::Display for Uncertain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ± {}", self.value, self.sigma)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::Context;
+    use crate::parser::Parser;
+
+    fn evaluate(source: &str) -> Result<Uncertain, EvaluatorError> {
+        let expr = Parser::new(source).parse().unwrap();
+        match Context::new().evaluate(&expr)? {
+            Value::Uncertain(uncertain) => Ok(uncertain),
+            value => panic!("{} is certain", value),
+        }
+    }
+
+    fn assert_near(found: Uncertain, value: f64, sigma: f64) {
+        assert!((found.value - value).abs() < 1e-9, "{}", found);
+        assert!((found.sigma - sigma).abs() < 1e-9, "{}", found);
+    }
+
+    #[test]
+    fn propagate_uncertainties() {
+        assert_near(evaluate("1 ± 0.3 + 2 +/- 0.4").unwrap(), 3.0, 0.5);
+        assert_near(evaluate("(1 ± 0.3) + (2 ± 0.4)").unwrap(), 3.0, 0.5);
+        assert_near(evaluate("2 * (9.81 ± 0.02)").unwrap(), 19.62, 0.04);
+        assert_near(
+            evaluate("(3 ± 0.3) * (4 ± 0.4)").unwrap(),
+            12.0,
+            12.0 * 0.02f64.sqrt(),
+        );
+        assert_near(evaluate("(2 ± 0.1)^2").unwrap(), 4.0, 0.4);
+        assert_near(evaluate("-sqrt(4 ± 0.4)").unwrap(), -2.0, 0.1);
+        assert_near(evaluate("sin(0 ± 0.01)").unwrap(), 0.0, 0.01);
+        assert_eq!(Uncertain::new(9.81, -0.02).to_string(), "9.81 ± 0.02");
+        assert!(evaluate("(1 ± 0.1) > 0").is_err());
+        assert!(evaluate("1 ± (1 ± 0.1)").is_err());
+    }
+}
@@ -0,0 +1,671 @@
+use core::fmt;
+use std::error::Error;
+
+use crate::currency::CURRENCIES;
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+use crate::parser::BinaryOperator;
+
+const BASE_DIMENSIONS: usize = 7;
+const BASE_SYMBOLS: [&str; BASE_DIMENSIONS] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+/// Exponents of the SI base quantities: length, mass, time, current, temperature, amount
+/// of substance and luminous intensity. Each currency is a base quantity of its own, so
+/// amounts in different currencies only mix through an exchange rate.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Dimension {
+    pub base: [i32; BASE_DIMENSIONS],
+    /// Exponents of currencies, sorted by code.
+    pub currencies: Vec<(String, i32)>,
+}
+
+impl Dimension {
+    pub fn new(base: [i32; BASE_DIMENSIONS]) -> Dimension {
+        Dimension {
+            base,
+            currencies: vec![],
+        }
+    }
+
+    pub fn currency(code: &str) -> Dimension {
+        Dimension {
+            base: [0; BASE_DIMENSIONS],
+            currencies: vec![(code.to_string(), 1)],
+        }
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.base.iter().all(|exponent| *exponent == 0) && self.currencies.is_empty()
+    }
+
+    fn multiply(&self, other: &Dimension, power: i32) -> Dimension {
+        let mut result = self.clone();
+        for (exponent, other) in result.base.iter_mut().zip(other.base) {
+            *exponent += other * power;
+        }
+        for (code, exponent) in &other.currencies {
+            match result.currencies.binary_search_by(|(c, _)| c.cmp(code)) {
+                Ok(index) => result.currencies[index].1 += exponent * power,
+                Err(index) => result
+                    .currencies
+                    .insert(index, (code.clone(), exponent * power)),
+            }
+        }
+        result.currencies.retain(|(_, exponent)| *exponent != 0);
+        result
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        if !self.currencies.is_empty() {
+            let single = self.currencies.len() == 1 && self.currencies[0].1 == 1;
+            return (single && self.base == [0; BASE_DIMENSIONS]).then_some("currency");
+        }
+        DIMENSION_NAMES
+            .iter()
+            .find(|(dimension, _)| dimension == &self.base)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = self.name() {
+            return write!(f, "{}", name);
+        }
+        let base = BASE_SYMBOLS.iter().copied().zip(self.base);
+        let currencies = self
+            .currencies
+            .iter()
+            .map(|(code, exponent)| (code.as_str(), *exponent));
+        let mut first = true;
+        for (symbol, exponent) in base.chain(currencies) {
+            if exponent == 0 {
+                continue;
+            }
+            if !first {
+                write!(f, "*")?;
+            }
+            first = false;
+            write!(f, "{}", symbol)?;
+            if exponent != 1 {
+                write!(f, "^{}", exponent)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+const DIMENSION_NAMES: &[([i32; BASE_DIMENSIONS], &str)] = &[
+    ([0, 0, 0, 0, 0, 0, 0], "dimensionless"),
+    ([1, 0, 0, 0, 0, 0, 0], "length"),
+    ([0, 1, 0, 0, 0, 0, 0], "mass"),
+    ([0, 0, 1, 0, 0, 0, 0], "time"),
+    ([0, 0, 0, 1, 0, 0, 0], "current"),
+    ([0, 0, 0, 0, 1, 0, 0], "temperature"),
+    ([0, 0, 0, 0, 0, 1, 0], "amount of substance"),
+    ([0, 0, 0, 0, 0, 0, 1], "luminous intensity"),
+    ([2, 0, 0, 0, 0, 0, 0], "area"),
+    ([3, 0, 0, 0, 0, 0, 0], "volume"),
+    ([1, 0, -1, 0, 0, 0, 0], "velocity"),
+    ([1, 0, -2, 0, 0, 0, 0], "acceleration"),
+    ([0, 0, -1, 0, 0, 0, 0], "frequency"),
+    ([1, 1, -2, 0, 0, 0, 0], "force"),
+    ([2, 1, -2, 0, 0, 0, 0], "energy"),
+    ([2, 1, -3, 0, 0, 0, 0], "power"),
+    ([-1, 1, -2, 0, 0, 0, 0], "pressure"),
+    ([0, 0, 1, 1, 0, 0, 0], "charge"),
+    ([2, 1, -3, -1, 0, 0, 0], "voltage"),
+];
+
+struct UnitDefinition {
+    symbol: &'static str,
+    factor: f64,
+    dimension: [i32; BASE_DIMENSIONS],
+    prefixable: bool,
+}
+
+const fn unit(
+    symbol: &'static str,
+    factor: f64,
+    dimension: [i32; BASE_DIMENSIONS],
+    prefixable: bool,
+) -> UnitDefinition {
+    UnitDefinition {
+        symbol,
+        factor,
+        dimension,
+        prefixable,
+    }
+}
+
+const UNITS: &[UnitDefinition] = &[
+    unit("m", 1.0, [1, 0, 0, 0, 0, 0, 0], true),
+    unit("g", 1e-3, [0, 1, 0, 0, 0, 0, 0], true),
+    unit("s", 1.0, [0, 0, 1, 0, 0, 0, 0], true),
+    unit("A", 1.0, [0, 0, 0, 1, 0, 0, 0], true),
+    unit("K", 1.0, [0, 0, 0, 0, 1, 0, 0], true),
+    unit("mol", 1.0, [0, 0, 0, 0, 0, 1, 0], true),
+    unit("cd", 1.0, [0, 0, 0, 0, 0, 0, 1], true),
+    unit("min", 60.0, [0, 0, 1, 0, 0, 0, 0], false),
+    unit("h", 3600.0, [0, 0, 1, 0, 0, 0, 0], false),
+    unit("day", 86400.0, [0, 0, 1, 0, 0, 0, 0], false),
+    unit("days", 86400.0, [0, 0, 1, 0, 0, 0, 0], false),
+    unit("week", 604800.0, [0, 0, 1, 0, 0, 0, 0], false),
+    unit("weeks", 604800.0, [0, 0, 1, 0, 0, 0, 0], false),
+    unit("inch", 0.0254, [1, 0, 0, 0, 0, 0, 0], false),
+    unit("ft", 0.3048, [1, 0, 0, 0, 0, 0, 0], false),
+    unit("yd", 0.9144, [1, 0, 0, 0, 0, 0, 0], false),
+    unit("mi", 1609.344, [1, 0, 0, 0, 0, 0, 0], false),
+    unit("L", 1e-3, [3, 0, 0, 0, 0, 0, 0], true),
+    unit("lb", 0.45359237, [0, 1, 0, 0, 0, 0, 0], false),
+    unit("oz", 0.028349523125, [0, 1, 0, 0, 0, 0, 0], false),
+    unit("Hz", 1.0, [0, 0, -1, 0, 0, 0, 0], true),
+    unit("N", 1.0, [1, 1, -2, 0, 0, 0, 0], true),
+    unit("J", 1.0, [2, 1, -2, 0, 0, 0, 0], true),
+    unit("W", 1.0, [2, 1, -3, 0, 0, 0, 0], true),
+    unit("Pa", 1.0, [-1, 1, -2, 0, 0, 0, 0], true),
+    unit("C", 1.0, [0, 0, 1, 1, 0, 0, 0], true),
+    unit("V", 1.0, [2, 1, -3, -1, 0, 0, 0], true),
+];
+
+const PREFIXES: &[(&str, f64)] = &[
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+];
+
+/// A single named unit, possibly prefixed, such as `km` or `N`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct NamedUnit {
+    pub symbol: String,
+    /// Size of the unit in SI base units.
+    pub factor: f64,
+    pub dimension: Dimension,
+}
+
+impl NamedUnit {
+    fn from_definition(symbol: &str, definition: &UnitDefinition, prefix: f64) -> NamedUnit {
+        NamedUnit {
+            symbol: symbol.to_string(),
+            factor: definition.factor * prefix,
+            dimension: Dimension::new(definition.dimension),
+        }
+    }
+
+    fn currency(code: &str) -> NamedUnit {
+        NamedUnit {
+            symbol: code.to_string(),
+            factor: 1.0,
+            dimension: Dimension::currency(code),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitDefinitionError {
+    /// Line of the definition file, when loading one.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl UnitDefinitionError {
+    pub fn new(message: String) -> UnitDefinitionError {
+        UnitDefinitionError {
+            line: None,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for UnitDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+impl Error for UnitDefinitionError {}
+
+fn check_symbol(symbol: &str) -> Result<(), UnitDefinitionError> {
+    let mut chars = symbol.bytes();
+    let valid = chars
+        .next()
+        .is_some_and(|char| char.is_ascii_alphabetic() || char == b'_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == b'_');
+    if !valid {
+        return Err(UnitDefinitionError::new(format!(
+            "'{}' is not a valid unit or prefix symbol",
+            symbol
+        )));
+    }
+    Ok(())
+}
+
+/// The units known to a context: the built-in SI and common units, extended by custom
+/// units and prefixes. Custom units take precedence and accept every prefix.
+#[derive(Debug, Clone, Default)]
+pub struct UnitTable {
+    units: Vec<NamedUnit>,
+    prefixes: Vec<(String, f64)>,
+}
+
+impl UnitTable {
+    pub fn define_unit(
+        &mut self,
+        symbol: &str,
+        factor: f64,
+        dimension: Dimension,
+    ) -> Result<(), UnitDefinitionError> {
+        check_symbol(symbol)?;
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(UnitDefinitionError::new(format!(
+                "The size of unit '{}' must be positive and finite",
+                symbol
+            )));
+        }
+        self.units.retain(|unit| unit.symbol != symbol);
+        self.units.push(NamedUnit {
+            symbol: symbol.to_string(),
+            factor,
+            dimension,
+        });
+        Ok(())
+    }
+
+    /// Defines `code` as a currency, incommensurable with every other until an exchange
+    /// rate is given.
+    pub fn define_currency(&mut self, code: &str) -> Result<(), UnitDefinitionError> {
+        check_symbol(code)?;
+        self.units.retain(|unit| unit.symbol != code);
+        self.units.push(NamedUnit::currency(code));
+        Ok(())
+    }
+
+    pub fn define_prefix(&mut self, symbol: &str, factor: f64) -> Result<(), UnitDefinitionError> {
+        check_symbol(symbol)?;
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(UnitDefinitionError::new(format!(
+                "The factor of prefix '{}' must be positive and finite",
+                symbol
+            )));
+        }
+        self.prefixes.retain(|(prefix, _)| prefix != symbol);
+        self.prefixes.push((symbol.to_string(), factor));
+        Ok(())
+    }
+
+    fn lookup_unprefixed(&self, symbol: &str, prefixed: bool) -> Option<NamedUnit> {
+        if let Some(unit) = self.units.iter().find(|unit| unit.symbol == symbol) {
+            return Some(unit.clone());
+        }
+        if !prefixed && CURRENCIES.contains(&symbol) {
+            return Some(NamedUnit::currency(symbol));
+        }
+        UNITS
+            .iter()
+            .find(|unit| unit.symbol == symbol && (unit.prefixable || !prefixed))
+            .map(|definition| NamedUnit::from_definition(symbol, definition, 1.0))
+    }
+
+    /// The symbols of the units and currencies without prefixes.
+    pub fn symbols(&self) -> Vec<String> {
+        let custom = self.units.iter().map(|unit| unit.symbol.as_str());
+        let builtin = UNITS.iter().map(|definition| definition.symbol);
+        let mut symbols: Vec<String> = custom
+            .chain(builtin)
+            .chain(CURRENCIES.iter().copied())
+            .map(String::from)
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    pub fn lookup(&self, symbol: &str) -> Option<NamedUnit> {
+        if let Some(unit) = self.lookup_unprefixed(symbol, false) {
+            return Some(unit);
+        }
+        let custom = self
+            .prefixes
+            .iter()
+            .map(|(prefix, factor)| (prefix.as_str(), *factor));
+        custom
+            .chain(PREFIXES.iter().copied())
+            .find_map(|(prefix, factor)| {
+                let unit = self.lookup_unprefixed(symbol.strip_prefix(prefix)?, true)?;
+                Some(NamedUnit {
+                    symbol: symbol.to_string(),
+                    factor: unit.factor * factor,
+                    dimension: unit.dimension,
+                })
+            })
+    }
+}
+
+/// A product of powers of named units, such as `kg*m/s^2`.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Unit {
+    pub components: Vec<(NamedUnit, i32)>,
+}
+
+impl Unit {
+    pub fn named(unit: NamedUnit) -> Unit {
+        Unit {
+            components: vec![(unit, 1)],
+        }
+    }
+
+    pub fn factor(&self) -> f64 {
+        self.components
+            .iter()
+            .map(|(unit, power)| unit.factor.powi(*power))
+            .product()
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.components
+            .iter()
+            .fold(Dimension::default(), |dimension, (unit, power)| {
+                dimension.multiply(&unit.dimension, *power)
+            })
+    }
+
+    /// Multiplies by `other^power`, reusing the units already present for components of the
+    /// same dimension (so `m * cm` becomes `m^2`). Returns the product together with the
+    /// factor the numeric value must be scaled by.
+    fn multiply(&self, other: &Unit, power: i32) -> (Unit, f64) {
+        let mut result = self.clone();
+        let mut scale = 1.0;
+        for (unit, exponent) in &other.components {
+            let exponent = exponent * power;
+            let existing = result
+                .components
+                .iter_mut()
+                .find(|(existing, _)| existing.dimension == unit.dimension);
+            match existing {
+                Some((existing, existing_exponent)) => {
+                    scale *= (unit.factor / existing.factor).powi(exponent);
+                    *existing_exponent += exponent;
+                }
+                None => result.components.push((unit.clone(), exponent)),
+            }
+        }
+        result.components.retain(|(_, exponent)| *exponent != 0);
+        (result, scale)
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        format!("{} ({})", self, self.dimension())
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let write_component =
+            |f: &mut fmt::Formatter, unit: &NamedUnit, exponent: i32| -> fmt::Result {
+                write!(f, "{}", unit.symbol)?;
+                if exponent != 1 {
+                    write!(f, "^{}", exponent)?;
+                }
+                Ok(())
+            };
+        let numerator: Vec<_> = self.components.iter().filter(|(_, e)| *e > 0).collect();
+        let denominator: Vec<_> = self.components.iter().filter(|(_, e)| *e < 0).collect();
+        if numerator.is_empty() {
+            for (index, (unit, exponent)) in denominator.iter().enumerate() {
+                if index > 0 {
+                    write!(f, "*")?;
+                }
+                write_component(f, unit, *exponent)?;
+            }
+            return Ok(());
+        }
+        for (index, (unit, exponent)) in numerator.iter().enumerate() {
+            if index > 0 {
+                write!(f, "*")?;
+            }
+            write_component(f, unit, *exponent)?;
+        }
+        for (unit, exponent) in denominator {
+            write!(f, "/")?;
+            write_component(f, unit, -exponent)?;
+        }
+        Ok(())
+    }
+}
+
+/// A numeric value expressed in a unit. Operations on quantities whose result is
+/// dimensionless produce plain numbers instead.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+/// The result of an operation on quantities.
+pub enum Magnitude {
+    Number(f64),
+    Quantity(Quantity),
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: Unit) -> Quantity {
+        Quantity { value, unit }
+    }
+
+    pub fn dimensionless(value: f64) -> Quantity {
+        Quantity::new(value, Unit::default())
+    }
+
+    /// The value in SI base units.
+    pub fn base_value(&self) -> f64 {
+        self.value * self.unit.factor()
+    }
+
+    fn simplify(self) -> Magnitude {
+        if self.unit.dimension().is_dimensionless() {
+            Magnitude::Number(self.base_value())
+        } else {
+            Magnitude::Quantity(self)
+        }
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        if self.unit.components.is_empty() {
+            String::from("a dimensionless number")
+        } else {
+            self.unit.describe()
+        }
+    }
+
+    pub(crate) fn check_compatible(
+        &self,
+        other: &Quantity,
+        operation: &'static str,
+    ) -> Result<(), EvaluatorError> {
+        if self.unit.dimension() != other.unit.dimension() {
+            return Err(EvaluatorError::new(EvaluatorErrorKind::DimensionMismatch {
+                operation,
+                lhs: self.describe(),
+                rhs: other.describe(),
+            }));
+        }
+        Ok(())
+    }
+
+    fn in_unit_of(&self, other: &Quantity) -> f64 {
+        if self.unit.components.is_empty() {
+            return self.value;
+        }
+        self.base_value() / other.unit.factor()
+    }
+
+    /// Expresses the quantity in `unit`, which must have the same dimension.
+    pub fn convert(&self, unit: &Unit) -> Result<Quantity, EvaluatorError> {
+        let target = Quantity::new(1.0, unit.clone());
+        self.check_compatible(&target, "convert")?;
+        Ok(Quantity::new(
+            self.base_value() / unit.factor(),
+            unit.clone(),
+        ))
+    }
+
+    pub fn powi(&self, exponent: f64) -> Result<Magnitude, EvaluatorError> {
+        let mut unit = self.unit.clone();
+        for (_, power) in unit.components.iter_mut() {
+            let raised = *power as f64 * exponent;
+            if raised.fract() != 0.0 {
+                return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                    format!(
+                        "Cannot raise {} to the non-integer power {}",
+                        self.unit, exponent
+                    ),
+                )));
+            }
+            *power = raised as i32;
+        }
+        Ok(Quantity::new(self.value.powf(exponent), unit).simplify())
+    }
+
+    pub fn binary(
+        operator: BinaryOperator,
+        lhs: &Quantity,
+        rhs: &Quantity,
+    ) -> Result<Magnitude, EvaluatorError> {
+        let compare = |lhs: &Quantity, rhs: &Quantity, operation| {
+            lhs.check_compatible(rhs, operation)?;
+            Ok::<(f64, f64), EvaluatorError>((lhs.base_value(), rhs.base_value()))
+        };
+        let truth = |condition: bool| Magnitude::Number(if condition { 1.0 } else { 0.0 });
+        match operator {
+            BinaryOperator::Add | BinaryOperator::Subtract => {
+                let (operation, sign) = if operator == BinaryOperator::Add {
+                    ("add", 1.0)
+                } else {
+                    ("subtract", -1.0)
+                };
+                lhs.check_compatible(rhs, operation)?;
+                let value = lhs.value + sign * rhs.in_unit_of(lhs);
+                Ok(Quantity::new(value, lhs.unit.clone()).simplify())
+            }
+            BinaryOperator::Multiply | BinaryOperator::Divide => {
+                let power = if operator == BinaryOperator::Multiply {
+                    1
+                } else {
+                    -1
+                };
+                if power == -1 && rhs.value == 0.0 {
+                    return Err(EvaluatorError::new(EvaluatorErrorKind::DivisionByZero));
+                }
+                let (unit, scale) = lhs.unit.multiply(&rhs.unit, power);
+                let value = lhs.value * rhs.value.powi(power) * scale;
+                Ok(Quantity::new(value, unit).simplify())
+            }
+            BinaryOperator::Power => {
+                if !rhs.unit.components.is_empty() {
+                    return Err(EvaluatorError::new(EvaluatorErrorKind::InvalidArgument(
+                        format!("Cannot raise to the power of {}", rhs.unit.describe()),
+                    )));
+                }
+                lhs.powi(rhs.value)
+            }
+            BinaryOperator::Equal => {
+                let (lhs, rhs) = compare(lhs, rhs, "compare")?;
+                Ok(truth(lhs == rhs))
+            }
+            BinaryOperator::NotEqual => {
+                let (lhs, rhs) = compare(lhs, rhs, "compare")?;
+                Ok(truth(lhs != rhs))
+            }
+            BinaryOperator::Less => {
+                let (lhs, rhs) = compare(lhs, rhs, "compare")?;
+                Ok(truth(lhs < rhs))
+            }
+            BinaryOperator::LessEqual => {
+                let (lhs, rhs) = compare(lhs, rhs, "compare")?;
+                Ok(truth(lhs <= rhs))
+            }
+            BinaryOperator::Greater => {
+                let (lhs, rhs) = compare(lhs, rhs, "compare")?;
+                Ok(truth(lhs > rhs))
+            }
+            BinaryOperator::GreaterEqual => {
+                let (lhs, rhs) = compare(lhs, rhs, "compare")?;
+                Ok(truth(lhs >= rhs))
+            }
+            BinaryOperator::And | BinaryOperator::Or | BinaryOperator::PlusMinus => {
+                Err(EvaluatorError::new(EvaluatorErrorKind::TypeMismatch {
+                    expected: "number",
+                    found: "quantity",
+                }))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lookup(symbol: &str) -> Unit {
+        Unit::named(UnitTable::default().lookup(symbol).unwrap())
+    }
+
+    #[test]
+    fn lookup_prefixed_and_derived_units() {
+        let table = UnitTable::default();
+        let kilometre = table.lookup("km").unwrap();
+        assert_eq!(kilometre.factor, 1e3);
+        assert_eq!(kilometre.dimension.name(), Some("length"));
+        assert_eq!(table.lookup("min").unwrap().factor, 60.0);
+        assert_eq!(table.lookup("kJ").unwrap().dimension.name(), Some("energy"));
+        assert_eq!(table.lookup("kmin"), None);
+        assert_eq!(table.lookup("x"), None);
+    }
+
+    #[test]
+    fn lookup_custom_units_and_prefixes() {
+        let mut table = UnitTable::default();
+        let length = table.lookup("m").unwrap().dimension;
+        table
+            .define_unit("pt", 0.0254 / 72.0, length.clone())
+            .unwrap();
+        table.define_prefix("Ki", 1024.0).unwrap();
+        assert_eq!(table.lookup("kpt").unwrap().factor, 1000.0 * 0.0254 / 72.0);
+        assert_eq!(table.lookup("Kim").unwrap().factor, 1024.0);
+        assert!(table.lookup("Kimin").is_none());
+        assert!(table.define_unit("2x", 1.0, length).is_err());
+        assert!(table.define_prefix("z", 0.0).is_err());
+    }
+
+    #[test]
+    fn format_compound_units() {
+        let metre = lookup("m");
+        let second = lookup("s");
+        let kilogram = lookup("kg");
+        let (velocity, _) = metre.multiply(&second, -1);
+        assert_eq!(velocity.to_string(), "m/s");
+        let (force, _) = kilogram.multiply(&velocity, 1);
+        let (force, _) = force.multiply(&second, -1);
+        assert_eq!(force.to_string(), "kg*m/s^2");
+        assert_eq!(force.dimension().name(), Some("force"));
+        let (frequency, _) = Unit::default().multiply(&second, -1);
+        assert_eq!(frequency.to_string(), "s^-1");
+        let (area, scale) = metre.multiply(&lookup("cm"), 1);
+        assert_eq!((area.to_string(), scale), (String::from("m^2"), 0.01));
+    }
+}
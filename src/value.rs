@@ -0,0 +1,204 @@
+use core::fmt;
+
+use crate::date::Date;
+use crate::evaluator::{EvaluatorError, EvaluatorErrorKind};
+use crate::parser::{BinaryOperator, Expr, UnaryOperator};
+use crate::uncertain::Uncertain;
+use crate::units::{Magnitude, Quantity};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Function {
+    Builtin(&'static str),
+    Lambda(Vec<String>, Box<Expr>),
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Function::Builtin(name) => write!(f, "{}", name),
+            Function::Lambda(params, body) => {
+                if params.len() == 1 {
+                    write!(f, "{} -> {}", params[0], body)
+                } else {
+                    write!(f, "({}) -> {}", params.join(", "), body)
+                }
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    Function(Function),
+    List(Vec<Value>),
+    Quantity(Quantity),
+    String(String),
+    Date(Date),
+    Uncertain(Uncertain),
+    /// Result of comparisons and logic, behaving as 1 and 0 in arithmetic.
+    Bool(bool),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Function(_) => "function",
+            Value::List(_) => "list",
+            Value::Quantity(_) => "quantity",
+            Value::String(_) => "string",
+            Value::Date(_) => "date",
+            Value::Uncertain(_) => "uncertain number",
+            Value::Bool(_) => "boolean",
+        }
+    }
+
+    pub fn as_number(&self) -> Result<f64, EvaluatorError> {
+        match self {
+            Value::Number(value) => Ok(*value),
+            Value::Bool(value) => Ok(truth(*value)),
+            _ => Err(EvaluatorError::new(EvaluatorErrorKind::TypeMismatch {
+                expected: "number",
+                found: self.type_name(),
+            })),
+        }
+    }
+
+    /// The truth value of a condition: booleans are themselves, and any number other than
+    /// zero (and NaN) is true. Other values are not conditions.
+    pub fn is_true(&self) -> Result<bool, EvaluatorError> {
+        match self {
+            Value::Bool(value) => Ok(*value),
+            _ => Ok(is_true(self.as_number()?)),
+        }
+    }
+
+    pub fn unary(operator: UnaryOperator, operand: &Value) -> Result<Value, EvaluatorError> {
+        match (operator, operand) {
+            (UnaryOperator::Negate, Value::Quantity(quantity)) => Ok(Value::Quantity(
+                Quantity::new(-quantity.value, quantity.unit.clone()),
+            )),
+            (UnaryOperator::Negate, Value::Uncertain(uncertain)) => {
+                Ok(Value::Uncertain(uncertain.negate()))
+            }
+            (UnaryOperator::Negate, _) => Ok(Value::Number(-operand.as_number()?)),
+            (UnaryOperator::Percent, Value::Quantity(quantity)) => Ok(Value::Quantity(
+                Quantity::new(quantity.value / 100.0, quantity.unit.clone()),
+            )),
+            (UnaryOperator::Percent, Value::Uncertain(uncertain)) => {
+                Ok(Value::Uncertain(uncertain.scale(0.01)))
+            }
+            (UnaryOperator::Percent, _) => Ok(Value::Number(operand.as_number()? / 100.0)),
+        }
+    }
+
+    pub fn binary(
+        operator: BinaryOperator,
+        lhs: &Value,
+        rhs: &Value,
+    ) -> Result<Value, EvaluatorError> {
+        if let (Value::Date(_), _) | (_, Value::Date(_)) = (lhs, rhs) {
+            return Date::binary(operator, lhs, rhs);
+        }
+        if let (BinaryOperator::PlusMinus, _, _)
+        | (_, Value::Uncertain(_), _)
+        | (_, _, Value::Uncertain(_)) = (operator, lhs, rhs)
+        {
+            return Uncertain::binary(operator, lhs, rhs);
+        }
+        if let Value::Quantity(_) = lhs {
+            return Value::quantity_binary(operator, lhs, rhs);
+        }
+        if let Value::Quantity(_) = rhs {
+            return Value::quantity_binary(operator, lhs, rhs);
+        }
+        if operator.is_comparison() || matches!(operator, BinaryOperator::And | BinaryOperator::Or)
+        {
+            let (a, b) = (lhs.as_number()?, rhs.as_number()?);
+            return Ok(Value::Bool(match operator {
+                BinaryOperator::Equal => a == b,
+                BinaryOperator::NotEqual => a != b,
+                BinaryOperator::Less => a < b,
+                BinaryOperator::LessEqual => a <= b,
+                BinaryOperator::Greater => a > b,
+                BinaryOperator::GreaterEqual => a >= b,
+                BinaryOperator::And => lhs.is_true()? && rhs.is_true()?,
+                _ => lhs.is_true()? || rhs.is_true()?,
+            }));
+        }
+        let (lhs, rhs) = (lhs.as_number()?, rhs.as_number()?);
+        let result = match operator {
+            BinaryOperator::Add => lhs + rhs,
+            BinaryOperator::Subtract => lhs - rhs,
+            BinaryOperator::Multiply => lhs * rhs,
+            BinaryOperator::Divide => {
+                if rhs == 0.0 {
+                    return Err(EvaluatorError::new(EvaluatorErrorKind::DivisionByZero));
+                }
+                lhs / rhs
+            }
+            BinaryOperator::Power => lhs.powf(rhs),
+            BinaryOperator::PlusMinus => unreachable!("handled by Uncertain::binary"),
+            _ => unreachable!("comparisons and logic are handled above"),
+        };
+        Ok(Value::Number(result))
+    }
+}
+
+impl Value {
+    fn as_quantity(&self) -> Result<Quantity, EvaluatorError> {
+        match self {
+            Value::Quantity(quantity) => Ok(quantity.clone()),
+            _ => Ok(Quantity::dimensionless(self.as_number()?)),
+        }
+    }
+
+    fn quantity_binary(
+        operator: BinaryOperator,
+        lhs: &Value,
+        rhs: &Value,
+    ) -> Result<Value, EvaluatorError> {
+        match Quantity::binary(operator, &lhs.as_quantity()?, &rhs.as_quantity()?)? {
+            Magnitude::Number(value) if operator.is_comparison() => Ok(Value::Bool(is_true(value))),
+            Magnitude::Number(value) => Ok(Value::Number(value)),
+            Magnitude::Quantity(quantity) => Ok(Value::Quantity(quantity)),
+        }
+    }
+}
+
+pub(crate) fn is_true(value: f64) -> bool {
+    value != 0.0 && !value.is_nan()
+}
+
+pub(crate) fn truth(condition: bool) -> f64 {
+    if condition {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Function(function) => write!(f, "{}", function),
+            Value::Quantity(quantity) => write!(f, "{}", quantity),
+            Value::String(string) => write!(f, "{}", string),
+            Value::Date(date) => write!(f, "{}", date),
+            Value::Uncertain(uncertain) => write!(f, "{}", uncertain),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}